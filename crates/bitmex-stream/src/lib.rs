@@ -1,4 +1,3 @@
-use crate::tungstenite::http::Method;
 use anyhow::Context;
 use async_stream::stream;
 use futures::SinkExt;
@@ -13,9 +12,10 @@ use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
 use tokio_tungstenite::tungstenite;
 use tracing::Instrument;
-use url::Url;
 
 pub use anyhow::Error;
+pub use tokio_tungstenite::tungstenite::http::Method;
+pub use url::Url;
 
 /// Connects to the BitMex websocket API
 ///
@@ -180,7 +180,10 @@ impl Credentials {
         }
     }
 
-    fn sign(&self, method: Method, expires: u64, url: &Url, body: &str) -> Signature {
+    /// Sign a request to the BitMex API which expires at the unix timestamp `expires`
+    ///
+    /// The same signature authenticates websocket connections and REST requests.
+    pub fn sign(&self, method: Method, expires: u64, url: &Url, body: &str) -> Signature {
         let signed_key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, self.secret.as_bytes());
         let sign_message = match url.query() {
             Some(query) => format!(
@@ -203,6 +206,20 @@ impl Credentials {
     }
 }
 
+impl Signature {
+    pub fn api_key(&self) -> &str {
+        &self.api_key
+    }
+
+    pub fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    pub fn expires(&self) -> u64 {
+        self.expires
+    }
+}
+
 impl From<Signature> for Command {
     fn from(sig: Signature) -> Self {
         Command::Authenticate(sig)
@@ -0,0 +1,449 @@
+use daemon::bdk::bitcoin::Amount;
+use daemon::bdk::bitcoin::Transaction;
+use daemon::bdk::bitcoin::Txid;
+use daemon::bdk::miniscript::DescriptorTrait;
+use model::long_and_short_leverage;
+use model::CfdEvent;
+use model::Dlc;
+use model::EventKind;
+use model::FeeAccount;
+use model::FundingFee;
+use std::fmt;
+
+/// A broken invariant in the event log of a CFD.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Position of the offending event in the event log, starting at 1.
+    pub event: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.event {
+            Some(event) => write!(f, "event {event}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Check the event log of a CFD against the invariants of the protocol.
+///
+/// We verify that
+/// - every rollover extends the DLC chain: it keeps the lock transaction, revokes the previous
+///   commit transaction and does not move the settlement event backwards,
+/// - the complete fee recorded on rollover matches the fees accumulated from the opening fee and
+///   the funding fees,
+/// - every transaction we recorded spends the output it is supposed to and does not pay out more
+///   than it spends, and
+/// - every confirmation refers to a transaction we recorded before.
+pub fn check(cfd: &sqlite_db::Cfd, events: &[CfdEvent]) -> Vec<Violation> {
+    let mut checker = Checker::new(cfd);
+
+    for (index, event) in events.iter().enumerate() {
+        checker.event = Some(index + 1);
+        checker.check_event(&event.event);
+    }
+
+    checker.violations
+}
+
+struct Checker {
+    fee_account: FeeAccount,
+    latest_dlc: Option<Dlc>,
+    collab_settlement_tx: Option<Transaction>,
+    cet: Option<Transaction>,
+    refund_tx: Option<Transaction>,
+    event: Option<usize>,
+    violations: Vec<Violation>,
+}
+
+impl Checker {
+    fn new(cfd: &sqlite_db::Cfd) -> Self {
+        let mut checker = Self {
+            fee_account: FeeAccount::new(cfd.position, cfd.role).add_opening_fee(cfd.opening_fee),
+            latest_dlc: None,
+            collab_settlement_tx: None,
+            cet: None,
+            refund_tx: None,
+            event: None,
+            violations: Vec::new(),
+        };
+
+        let (long_leverage, short_leverage) =
+            long_and_short_leverage(cfd.taker_leverage, cfd.role, cfd.position);
+
+        match FundingFee::calculate(
+            cfd.initial_price,
+            cfd.quantity,
+            long_leverage,
+            short_leverage,
+            cfd.initial_funding_rate,
+            cfd.contract_type
+                .initial_funding_hours(cfd.settlement_interval),
+            cfd.contract_symbol,
+        ) {
+            Ok(initial_funding_fee) => {
+                checker.fee_account = checker.fee_account.add_funding_fee(initial_funding_fee)
+            }
+            Err(e) => checker.violation(format!("Cannot calculate initial funding fee: {e:#}")),
+        }
+
+        checker
+    }
+
+    fn check_event(&mut self, event: &EventKind) {
+        use EventKind::*;
+
+        match event {
+            ContractSetupCompleted { dlc: Some(dlc) } => {
+                if self.latest_dlc.is_some() {
+                    self.violation("Contract setup completed more than once");
+                }
+
+                self.check_dlc(dlc);
+                self.latest_dlc = Some(dlc.clone());
+            }
+            ContractSetupCompleted { dlc: None } => {
+                self.violation("Contract setup completed without a DLC");
+            }
+            RolloverCompleted {
+                dlc: Some(dlc),
+                funding_fee,
+                complete_fee,
+            } => {
+                self.check_rollover(dlc);
+                self.check_dlc(dlc);
+                self.latest_dlc = Some(dlc.clone());
+
+                self.fee_account = self.fee_account.add_funding_fee(*funding_fee);
+
+                if let Some(complete_fee) = complete_fee {
+                    let accumulated = self.fee_account.settle();
+
+                    if accumulated != *complete_fee {
+                        self.violation(format!(
+                            "Complete fee {complete_fee:?} does not match accumulated fees {accumulated:?}"
+                        ));
+                    }
+
+                    // Continue from the recorded fee, just like the projection does
+                    self.fee_account = self.fee_account.from_complete_fee(*complete_fee);
+                }
+            }
+            RolloverCompleted { dlc: None, .. } => {
+                self.violation("Rollover completed without a DLC");
+            }
+            CollaborativeSettlementCompleted { spend_tx, .. } => {
+                if let Some(lock_txid) = self.latest_dlc.as_ref().map(|dlc| dlc.lock.0.txid()) {
+                    self.check_spends("Collaborative settlement transaction", spend_tx, lock_txid);
+                }
+                self.check_payout("Collaborative settlement transaction", spend_tx);
+
+                self.collab_settlement_tx = Some(spend_tx.clone());
+            }
+            OracleAttestedPriorCetTimelock {
+                timelocked_cet: cet,
+                ..
+            }
+            | OracleAttestedPostCetTimelock { cet, .. }
+            | CetTimelockExpiredPostOracleAttestation { cet } => {
+                if let Some(commit_txid) = self.latest_commit_txid() {
+                    self.check_spends("CET", cet, commit_txid);
+                }
+                self.check_payout("CET", cet);
+
+                self.cet = Some(cet.clone());
+            }
+            RefundTimelockExpired { refund_tx } => {
+                if let Some(commit_txid) = self.latest_commit_txid() {
+                    self.check_spends("Refund transaction", refund_tx, commit_txid);
+                }
+                self.check_payout("Refund transaction", refund_tx);
+
+                self.refund_tx = Some(refund_tx.clone());
+            }
+            ManualCommit { tx } => {
+                if let Some(commit_txid) = self.latest_commit_txid() {
+                    if tx.txid() != commit_txid {
+                        self.violation(format!(
+                            "Published commit transaction {} is not the latest one {commit_txid}",
+                            tx.txid()
+                        ));
+                    }
+                }
+            }
+            LockConfirmed | CommitConfirmed | RevokeConfirmed => {
+                self.require(self.latest_dlc.is_some(), "a DLC");
+            }
+            CetConfirmed => {
+                self.require(self.cet.is_some(), "a CET");
+            }
+            RefundConfirmed => {
+                self.require(self.refund_tx.is_some(), "a refund transaction");
+            }
+            CollaborativeSettlementConfirmed => {
+                self.require(
+                    self.collab_settlement_tx.is_some(),
+                    "a collaborative settlement transaction",
+                );
+            }
+            ContractSetupStarted
+            | ContractSetupFailed
+            | OfferRejected
+            | RolloverStarted
+            | RolloverAccepted
+            | RolloverRejected
+            | RolloverFailed
+            | CollaborativeSettlementStarted { .. }
+            | CollaborativeSettlementProposalAccepted
+            | CollaborativeSettlementRejected
+            | CollaborativeSettlementFailed
+            | LockConfirmedAfterFinality
+            | CetTimelockExpiredPriorOracleAttestation => {}
+        }
+    }
+
+    /// Verify that the DLC produced by a rollover extends the previous one.
+    fn check_rollover(&mut self, dlc: &Dlc) {
+        let previous = match &self.latest_dlc {
+            Some(previous) => previous.clone(),
+            None => {
+                self.violation("Rollover completed before contract setup");
+                return;
+            }
+        };
+
+        if dlc.lock.0.txid() != previous.lock.0.txid() {
+            self.violation(format!(
+                "Rollover changed the lock transaction from {} to {}",
+                previous.lock.0.txid(),
+                dlc.lock.0.txid()
+            ));
+        }
+
+        let previous_commit_txid = previous.commit.0.txid();
+        if !dlc
+            .revoked_commit
+            .iter()
+            .any(|revoked| revoked.txid == previous_commit_txid)
+        {
+            self.violation(format!(
+                "Rollover did not revoke the previous commit transaction {previous_commit_txid}"
+            ));
+        }
+
+        if dlc.settlement_event_id.timestamp() < previous.settlement_event_id.timestamp() {
+            self.violation(format!(
+                "Rollover moved the settlement event backwards from {} to {}",
+                previous.settlement_event_id, dlc.settlement_event_id
+            ));
+        }
+    }
+
+    /// Verify the transactions of a DLC against each other.
+    fn check_dlc(&mut self, dlc: &Dlc) {
+        let lock_txid = dlc.lock.0.txid();
+        let lock_script = dlc.lock.1.script_pubkey();
+
+        match dlc
+            .lock
+            .0
+            .output
+            .iter()
+            .find(|output| output.script_pubkey == lock_script)
+        {
+            Some(output) => {
+                let margins = dlc.maker_lock_amount + dlc.taker_lock_amount;
+
+                if Amount::from_sat(output.value) != margins {
+                    self.violation(format!(
+                        "Lock output of {} does not match the locked margins of {margins}",
+                        Amount::from_sat(output.value)
+                    ));
+                }
+            }
+            None => self.violation(format!(
+                "Lock transaction {lock_txid} does not pay to the lock descriptor"
+            )),
+        }
+
+        let commit_tx = &dlc.commit.0;
+        self.check_spends("Commit transaction", commit_tx, lock_txid);
+
+        let commit_txid = commit_tx.txid();
+        let commit_amount = commit_tx
+            .output
+            .first()
+            .map(|output| Amount::from_sat(output.value))
+            .unwrap_or(Amount::ZERO);
+
+        self.check_spends("Refund transaction", &dlc.refund.0, commit_txid);
+
+        for cets in dlc.cets.values() {
+            for cet in cets {
+                let payout = cet.maker_amount + cet.taker_amount;
+
+                if payout > commit_amount {
+                    self.violation(format!(
+                        "CET {} pays out {payout}, more than the commit output of {commit_amount}",
+                        cet.txid
+                    ));
+                }
+            }
+        }
+    }
+
+    fn check_spends(&mut self, name: &str, tx: &Transaction, txid: Txid) {
+        if !tx
+            .input
+            .iter()
+            .any(|input| input.previous_output.txid == txid)
+        {
+            self.violation(format!("{name} {} does not spend {txid}", tx.txid()));
+        }
+    }
+
+    /// Verify that a transaction spending from the DLC does not pay out more than the DLC holds.
+    fn check_payout(&mut self, name: &str, tx: &Transaction) {
+        let dlc = match &self.latest_dlc {
+            Some(dlc) => dlc,
+            None => {
+                self.violation(format!("{name} {} recorded without a DLC", tx.txid()));
+                return;
+            }
+        };
+
+        let locked = dlc.maker_lock_amount + dlc.taker_lock_amount;
+        let payout = Amount::from_sat(tx.output.iter().map(|output| output.value).sum());
+
+        if payout > locked {
+            self.violation(format!(
+                "{name} {} pays out {payout}, more than the locked {locked}",
+                tx.txid()
+            ));
+        }
+    }
+
+    fn require(&mut self, condition: bool, what: &str) {
+        if !condition {
+            self.violation(format!("Confirmation recorded without {what}"));
+        }
+    }
+
+    fn latest_commit_txid(&mut self) -> Option<Txid> {
+        let txid = self.latest_dlc.as_ref().map(|dlc| dlc.commit.0.txid());
+
+        if txid.is_none() {
+            self.violation("Transaction recorded without a DLC");
+        }
+
+        txid
+    }
+
+    fn violation(&mut self, message: impl Into<String>) {
+        self.violations.push(Violation {
+            event: self.event,
+            message: message.into(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::Timestamp;
+    use model::DEFAULT_SETTLEMENT_INTERVAL;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn confirmation_without_transaction_is_a_violation() {
+        let cfd = dummy_cfd();
+
+        let violations = check(
+            &cfd,
+            &[
+                event(&cfd, EventKind::ContractSetupStarted),
+                event(&cfd, EventKind::CetConfirmed),
+            ],
+        );
+
+        assert_eq!(
+            violations,
+            vec![Violation {
+                event: Some(2),
+                message: "Confirmation recorded without a CET".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn rollover_before_contract_setup_is_a_violation() {
+        let cfd = dummy_cfd();
+
+        let violations = check(
+            &cfd,
+            &[event(
+                &cfd,
+                EventKind::RolloverCompleted {
+                    dlc: None,
+                    funding_fee: FundingFee {
+                        fee: Amount::ZERO,
+                        rate: model::FundingRate::default(),
+                    },
+                    complete_fee: None,
+                },
+            )],
+        );
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].event, Some(1));
+    }
+
+    #[test]
+    fn failed_setup_has_no_violations() {
+        let cfd = dummy_cfd();
+
+        let violations = check(
+            &cfd,
+            &[
+                event(&cfd, EventKind::ContractSetupStarted),
+                event(&cfd, EventKind::ContractSetupFailed),
+            ],
+        );
+
+        assert!(violations.is_empty());
+    }
+
+    fn event(cfd: &sqlite_db::Cfd, event: EventKind) -> CfdEvent {
+        CfdEvent {
+            timestamp: Timestamp::now(),
+            id: cfd.id,
+            event,
+        }
+    }
+
+    fn dummy_cfd() -> sqlite_db::Cfd {
+        sqlite_db::Cfd {
+            id: model::OrderId::default(),
+            offer_id: model::OfferId::default(),
+            position: model::Position::Long,
+            initial_price: model::Price::new(dec!(41_772)).unwrap(),
+            taker_leverage: model::Leverage::TWO,
+            settlement_interval: DEFAULT_SETTLEMENT_INTERVAL,
+            quantity: model::Contracts::new(100),
+            counterparty_network_identity:
+                "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"
+                    .parse()
+                    .unwrap(),
+            counterparty_peer_id: None,
+            role: model::Role::Taker,
+            opening_fee: model::OpeningFee::new(Amount::from_sat(2)),
+            initial_funding_rate: model::FundingRate::default(),
+            initial_tx_fee_rate: model::TxFeeRate::default(),
+            contract_symbol: model::ContractSymbol::BtcUsd,
+            contract_type: model::ContractType::Perpetual,
+            granularity: model::PayoutGranularity::default(),
+        }
+    }
+}
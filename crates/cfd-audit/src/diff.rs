@@ -0,0 +1,293 @@
+use crate::NETWORK;
+use daemon::bdk::bitcoin::SignedAmount;
+use daemon::bdk::bitcoin::Txid;
+use daemon::projection;
+use daemon::projection::CfdState;
+use model::olivia::BitMexPriceEventId;
+use model::CfdEvent;
+use model::ContractSymbol;
+use model::Contracts;
+use model::EventKind;
+use model::Leverage;
+use model::Position;
+use model::Price;
+use model::Role;
+use sqlite_db::CfdAggregate;
+use std::fmt;
+
+/// The parts of a CFD both parties have to agree on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub role: Role,
+    pub position: Position,
+    pub quantity: Contracts,
+    pub initial_price: Price,
+    pub leverage_taker: Leverage,
+    pub contract_symbol: ContractSymbol,
+    pub state: CfdState,
+    pub accumulated_fees: SignedAmount,
+    pub lock_txid: Option<Txid>,
+    pub commit_txid: Option<Txid>,
+    pub settlement_event_id: Option<BitMexPriceEventId>,
+    /// Names of the events that conclude a protocol run with the counterparty.
+    ///
+    /// All other events are recorded independently by each party and may legitimately differ,
+    /// e.g. because one party saw a confirmation earlier than the other.
+    pub protocol_events: Vec<String>,
+}
+
+impl Snapshot {
+    pub fn new(cfd: sqlite_db::Cfd, events: &[CfdEvent]) -> Self {
+        let protocol_events = events
+            .iter()
+            .filter(|event| {
+                matches!(
+                    event.event,
+                    EventKind::ContractSetupCompleted { .. }
+                        | EventKind::RolloverCompleted { .. }
+                        | EventKind::CollaborativeSettlementCompleted { .. }
+                )
+            })
+            .map(|event| event.event.to_string())
+            .collect();
+
+        let cfd = events.iter().cloned().fold(
+            <projection::Cfd as CfdAggregate>::new(NETWORK, cfd),
+            CfdAggregate::apply,
+        );
+        let dlc = cfd.aggregated().latest_dlc();
+
+        Self {
+            role: cfd.role,
+            position: cfd.position,
+            quantity: cfd.quantity,
+            initial_price: cfd.initial_price,
+            leverage_taker: cfd.leverage_taker,
+            contract_symbol: cfd.contract_symbol,
+            state: cfd.state,
+            accumulated_fees: cfd.accumulated_fees,
+            lock_txid: dlc.as_ref().map(|dlc| dlc.lock.0.txid()),
+            commit_txid: dlc.as_ref().map(|dlc| dlc.commit.0.txid()),
+            settlement_event_id: dlc.as_ref().map(|dlc| dlc.settlement_event_id),
+            protocol_events,
+        }
+    }
+}
+
+/// A disagreement between two views of the same CFD.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    pub field: &'static str,
+    pub ours: String,
+    pub theirs: String,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: ours={} theirs={}",
+            self.field, self.ours, self.theirs
+        )
+    }
+}
+
+/// Compare our view of a CFD with the counterparty's.
+///
+/// Role, position and fees are expected to mirror each other, everything else has to be equal.
+pub fn diff(ours: &Snapshot, theirs: &Snapshot) -> Vec<Difference> {
+    let mut differences = Vec::new();
+
+    let mut compare = |field, consistent: bool, ours: String, theirs: String| {
+        if !consistent {
+            differences.push(Difference {
+                field,
+                ours,
+                theirs,
+            });
+        }
+    };
+
+    compare(
+        "role",
+        ours.role != theirs.role,
+        format!("{:?}", ours.role),
+        format!("{:?}", theirs.role),
+    );
+    compare(
+        "position",
+        ours.position != theirs.position,
+        format!("{:?}", ours.position),
+        format!("{:?}", theirs.position),
+    );
+    compare(
+        "quantity",
+        ours.quantity == theirs.quantity,
+        ours.quantity.to_string(),
+        theirs.quantity.to_string(),
+    );
+    compare(
+        "initial_price",
+        ours.initial_price == theirs.initial_price,
+        ours.initial_price.to_string(),
+        theirs.initial_price.to_string(),
+    );
+    compare(
+        "leverage_taker",
+        ours.leverage_taker == theirs.leverage_taker,
+        ours.leverage_taker.to_string(),
+        theirs.leverage_taker.to_string(),
+    );
+    compare(
+        "contract_symbol",
+        ours.contract_symbol == theirs.contract_symbol,
+        ours.contract_symbol.to_string(),
+        theirs.contract_symbol.to_string(),
+    );
+    compare(
+        "state",
+        ours.state == theirs.state,
+        format!("{:?}", ours.state),
+        format!("{:?}", theirs.state),
+    );
+    compare(
+        "accumulated_fees",
+        ours.accumulated_fees == -theirs.accumulated_fees,
+        ours.accumulated_fees.to_string(),
+        theirs.accumulated_fees.to_string(),
+    );
+    compare(
+        "lock_txid",
+        ours.lock_txid == theirs.lock_txid,
+        format_option(ours.lock_txid),
+        format_option(theirs.lock_txid),
+    );
+    compare(
+        "commit_txid",
+        ours.commit_txid == theirs.commit_txid,
+        format_option(ours.commit_txid),
+        format_option(theirs.commit_txid),
+    );
+    compare(
+        "settlement_event_id",
+        ours.settlement_event_id == theirs.settlement_event_id,
+        format_option(ours.settlement_event_id),
+        format_option(theirs.settlement_event_id),
+    );
+
+    if ours.protocol_events != theirs.protocol_events {
+        let index = ours
+            .protocol_events
+            .iter()
+            .zip(theirs.protocol_events.iter())
+            .position(|(ours, theirs)| ours != theirs)
+            .unwrap_or_else(|| ours.protocol_events.len().min(theirs.protocol_events.len()));
+
+        compare(
+            "protocol_events",
+            false,
+            format!(
+                "{} from #{}",
+                format_option(ours.protocol_events.get(index)),
+                index + 1
+            ),
+            format!(
+                "{} from #{}",
+                format_option(theirs.protocol_events.get(index)),
+                index + 1
+            ),
+        );
+    }
+
+    differences
+}
+
+fn format_option<T>(value: Option<T>) -> String
+where
+    T: fmt::Display,
+{
+    value
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| "none".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn mirrored_snapshots_agree() {
+        let maker = dummy_snapshot(Role::Maker, Position::Short);
+        let taker = dummy_snapshot(Role::Taker, Position::Long);
+
+        assert_eq!(diff(&maker, &taker), vec![]);
+    }
+
+    #[test]
+    fn fees_that_do_not_mirror_each_other_are_reported() {
+        let maker = dummy_snapshot(Role::Maker, Position::Short);
+        let mut taker = dummy_snapshot(Role::Taker, Position::Long);
+        taker.accumulated_fees = SignedAmount::from_sat(1_000);
+
+        let differences = diff(&maker, &taker);
+
+        assert_eq!(differences.len(), 1);
+        assert_eq!(differences[0].field, "accumulated_fees");
+    }
+
+    #[test]
+    fn missing_rollover_is_reported_at_first_divergence() {
+        let maker = dummy_snapshot(Role::Maker, Position::Short);
+        let mut taker = dummy_snapshot(Role::Taker, Position::Long);
+        taker.protocol_events.pop();
+
+        let differences = diff(&maker, &taker);
+
+        assert_eq!(
+            differences,
+            vec![Difference {
+                field: "protocol_events",
+                ours: "RolloverCompleted from #2".to_owned(),
+                theirs: "none from #2".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn same_role_is_reported() {
+        let maker = dummy_snapshot(Role::Maker, Position::Short);
+        let other_maker = dummy_snapshot(Role::Maker, Position::Long);
+
+        let differences = diff(&maker, &other_maker);
+
+        assert_eq!(differences.len(), 2);
+        assert_eq!(differences[0].field, "role");
+        assert_eq!(differences[1].field, "accumulated_fees");
+    }
+
+    fn dummy_snapshot(role: Role, position: Position) -> Snapshot {
+        let accumulated_fees = match role {
+            Role::Maker => SignedAmount::from_sat(-500),
+            Role::Taker => SignedAmount::from_sat(500),
+        };
+
+        Snapshot {
+            role,
+            position,
+            quantity: Contracts::new(100),
+            initial_price: Price::new(dec!(41_772)).unwrap(),
+            leverage_taker: Leverage::TWO,
+            contract_symbol: ContractSymbol::BtcUsd,
+            state: CfdState::Open,
+            accumulated_fees,
+            lock_txid: None,
+            commit_txid: None,
+            settlement_event_id: None,
+            protocol_events: vec![
+                "ContractSetupCompleted".to_owned(),
+                "RolloverCompleted".to_owned(),
+            ],
+        }
+    }
+}
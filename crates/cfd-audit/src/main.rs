@@ -0,0 +1,145 @@
+use anyhow::bail;
+use anyhow::Result;
+use clap::Parser;
+use clap::Subcommand;
+use daemon::bdk::bitcoin::Network;
+use model::CfdEvent;
+use model::OrderId;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+mod check;
+mod diff;
+mod replay;
+
+/// Network the projection is built for.
+///
+/// The network is only used to render block explorer links, which we never print.
+const NETWORK: Network = Network::Bitcoin;
+
+/// Inspect, replay and audit the CFD event log of a maker or taker database.
+///
+/// Databases are opened read-only, it is safe to point this tool at the database of a running
+/// daemon.
+#[derive(Parser)]
+struct Opts {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List all CFDs in the database.
+    List {
+        /// Path to the maker or taker database.
+        db: PathBuf,
+    },
+    /// Replay the events of a CFD, printing its state after every event.
+    Replay {
+        /// Path to the maker or taker database.
+        db: PathBuf,
+        order_id: Uuid,
+    },
+    /// Check the event log of a CFD against the invariants of the protocol.
+    ///
+    /// Checks all CFDs with an event log if no order ID is given.
+    Check {
+        /// Path to the maker or taker database.
+        db: PathBuf,
+        order_id: Option<Uuid>,
+    },
+    /// Compare two databases' view of the same CFD, e.g. the maker's and the taker's.
+    Diff {
+        /// Path to the first database.
+        ours: PathBuf,
+        /// Path to the second database.
+        theirs: PathBuf,
+        order_id: Uuid,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let opts = Opts::parse();
+
+    match opts.command {
+        Command::List { db } => {
+            let db = sqlite_db::connect_read_only(db).await?;
+            replay::list(&db).await?;
+        }
+        Command::Replay { db, order_id } => {
+            let db = sqlite_db::connect_read_only(db).await?;
+            replay::replay(&db, order_id.into()).await?;
+        }
+        Command::Check { db, order_id } => {
+            let db = sqlite_db::connect_read_only(db).await?;
+
+            let order_ids = match order_id {
+                Some(order_id) => vec![order_id.into()],
+                None => db.load_open_cfd_ids().await?,
+            };
+
+            let mut num_violations = 0;
+            for order_id in order_ids {
+                let (cfd, events) = load_event_log(&db, order_id).await?;
+                let violations = check::check(&cfd, &events);
+
+                for violation in violations.iter() {
+                    println!("{order_id}: {violation}");
+                }
+                if violations.is_empty() {
+                    println!("{order_id}: ok");
+                }
+
+                num_violations += violations.len();
+            }
+
+            if num_violations > 0 {
+                bail!("Found {num_violations} invariant violations");
+            }
+        }
+        Command::Diff {
+            ours,
+            theirs,
+            order_id,
+        } => {
+            let order_id = OrderId::from(order_id);
+
+            let ours = sqlite_db::connect_read_only(ours).await?;
+            let theirs = sqlite_db::connect_read_only(theirs).await?;
+
+            let (ours_cfd, ours_events) = load_event_log(&ours, order_id).await?;
+            let (theirs_cfd, theirs_events) = load_event_log(&theirs, order_id).await?;
+
+            let differences = diff::diff(
+                &diff::Snapshot::new(ours_cfd, &ours_events),
+                &diff::Snapshot::new(theirs_cfd, &theirs_events),
+            );
+
+            for difference in differences.iter() {
+                println!("{difference}");
+            }
+
+            if !differences.is_empty() {
+                bail!("Found {} differences", differences.len());
+            }
+
+            println!("{order_id}: both databases agree");
+        }
+    }
+
+    Ok(())
+}
+
+async fn load_event_log(
+    db: &sqlite_db::Connection,
+    order_id: OrderId,
+) -> Result<(sqlite_db::Cfd, Vec<CfdEvent>)> {
+    match db.load_cfd_with_events(order_id).await {
+        Ok(event_log) => Ok(event_log),
+        Err(sqlite_db::Error::OpenCfdNotFound) => bail!(
+            "No event log for CFD {order_id}, closed and failed CFDs are only kept as a summary"
+        ),
+        Err(e) => Err(e.into()),
+    }
+}
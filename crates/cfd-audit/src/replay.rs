@@ -0,0 +1,122 @@
+use crate::load_event_log;
+use crate::NETWORK;
+use anyhow::Result;
+use daemon::projection;
+use model::CfdEvent;
+use model::OrderId;
+use model::Timestamp;
+use sqlite_db::CfdAggregate;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// Print one line per CFD in the database.
+pub async fn list(db: &sqlite_db::Connection) -> Result<()> {
+    println!(
+        "{:<36}  {:<5}  {:<5}  {:>10}  {:<7}  {:>6}  STATE",
+        "ORDER ID", "ROLE", "POS", "QUANTITY", "SYMBOL", "EVENTS"
+    );
+
+    for order_id in db.load_open_cfd_ids().await? {
+        let (cfd, events) = load_event_log(db, order_id).await?;
+        let num_events = events.len().to_string();
+
+        print_row(&aggregate(cfd, events), &num_events);
+    }
+
+    for order_id in db.load_closed_cfd_ids().await? {
+        let cfd = db
+            .load_closed_cfd::<projection::Cfd>(order_id, NETWORK)
+            .await?;
+
+        print_row(&cfd, "-");
+    }
+
+    for order_id in db.load_failed_cfd_ids().await? {
+        let cfd = db
+            .load_failed_cfd::<projection::Cfd>(order_id, NETWORK)
+            .await?;
+
+        print_row(&cfd, "-");
+    }
+
+    Ok(())
+}
+
+/// Replay the events of a CFD one by one and print the state after every `apply`.
+pub async fn replay(db: &sqlite_db::Connection, order_id: OrderId) -> Result<()> {
+    let (row, events) = load_event_log(db, order_id).await?;
+
+    println!(
+        "{order_id}: {:?} {:?} {} {} at {} with leverage {}",
+        row.role,
+        row.position,
+        row.quantity,
+        row.contract_symbol,
+        row.initial_price,
+        row.taker_leverage
+    );
+
+    let mut cfd = <projection::Cfd as CfdAggregate>::new(NETWORK, row);
+    println!("  initial state: {}", summary(&cfd));
+
+    for (index, event) in events.into_iter().enumerate() {
+        let name = event.event.to_string();
+        let timestamp = format_timestamp(event.timestamp);
+
+        cfd = CfdAggregate::apply(cfd, event);
+
+        println!("{:>4} {timestamp} {name}", index + 1);
+        println!("       {}", summary(&cfd));
+    }
+
+    Ok(())
+}
+
+fn aggregate(cfd: sqlite_db::Cfd, events: Vec<CfdEvent>) -> projection::Cfd {
+    events.into_iter().fold(
+        <projection::Cfd as CfdAggregate>::new(NETWORK, cfd),
+        CfdAggregate::apply,
+    )
+}
+
+fn print_row(cfd: &projection::Cfd, num_events: &str) {
+    println!(
+        "{:<36}  {:<5}  {:<5}  {:>10}  {:<7}  {:>6}  {:?}",
+        cfd.order_id.to_string(),
+        format!("{:?}", cfd.role),
+        format!("{:?}", cfd.position),
+        cfd.quantity.to_string(),
+        cfd.contract_symbol.to_string(),
+        num_events,
+        cfd.state
+    );
+}
+
+fn summary(cfd: &projection::Cfd) -> String {
+    let mut summary = format!(
+        "state={:?} accumulated_fees={}",
+        cfd.state, cfd.accumulated_fees
+    );
+
+    if let Some(dlc) = cfd.aggregated().latest_dlc() {
+        summary.push_str(&format!(
+            " commit={} settlement_event={} revoked_commits={}",
+            dlc.commit.0.txid(),
+            dlc.settlement_event_id,
+            dlc.revoked_commit.len()
+        ));
+    }
+
+    if let Some(payout) = cfd.payout {
+        summary.push_str(&format!(" payout={payout}"));
+    }
+
+    summary
+}
+
+fn format_timestamp(timestamp: Timestamp) -> String {
+    OffsetDateTime::from_unix_timestamp(timestamp.seconds())
+        .ok()
+        .and_then(|timestamp| timestamp.format(&Rfc3339).ok())
+        .unwrap_or_else(|| timestamp.seconds().to_string())
+}
@@ -0,0 +1,90 @@
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use criterion::Throughput;
+use daemon_tests::open_cfd;
+use daemon_tests::simulation::network::Network;
+use daemon_tests::Maker;
+use daemon_tests::MakerConfig;
+use daemon_tests::OpenCfdArgs;
+use daemon_tests::Taker;
+use daemon_tests::TakerConfig;
+use model::PayoutGranularity;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Measure the wall time and the bytes exchanged of a contract setup for different payout
+/// granularities.
+///
+/// Every iteration starts a fresh maker and taker talking over a fault-free simulated network, only
+/// opening the CFD is measured. The bytes exchanged are reported as the throughput of each
+/// benchmark.
+fn contract_setup(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    let mut group = c.benchmark_group("contract_setup");
+    group.sample_size(10);
+
+    for n_payouts in [50, 200, 1000] {
+        let granularity = PayoutGranularity {
+            n_payouts,
+            ..PayoutGranularity::default()
+        };
+
+        let (_, bytes) = runtime.block_on(measure_contract_setup(granularity));
+        println!("contract_setup/n_payouts/{n_payouts}: {bytes} bytes exchanged");
+        group.throughput(Throughput::Bytes(bytes));
+
+        group.bench_with_input(
+            BenchmarkId::new("n_payouts", n_payouts),
+            &granularity,
+            |b, granularity| {
+                b.iter_custom(|iters| {
+                    runtime.block_on(async {
+                        let mut elapsed = Duration::ZERO;
+
+                        for _ in 0..iters {
+                            elapsed += measure_contract_setup(*granularity).await.0;
+                        }
+
+                        elapsed
+                    })
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// Open a CFD between a fresh maker and taker, returning the time it took and the bytes written
+/// to the network by both parties.
+async fn measure_contract_setup(granularity: PayoutGranularity) -> (Duration, u64) {
+    let network = Network::new(0);
+    let mut maker = Maker::start(&MakerConfig::default().with_network(network.clone())).await;
+    let mut taker = Taker::start(
+        &TakerConfig::default().with_network(network.clone()),
+        maker.identity,
+        maker.connect_addr.clone(),
+    )
+    .await;
+    let bytes_before = network.bytes_written();
+
+    let started = Instant::now();
+    open_cfd(
+        &mut taker,
+        &mut maker,
+        OpenCfdArgs {
+            granularity,
+            ..Default::default()
+        },
+    )
+    .await;
+    let elapsed = started.elapsed();
+
+    (elapsed, network.bytes_written() - bytes_before)
+}
+
+criterion_group!(benches, contract_setup);
+criterion_main!(benches);
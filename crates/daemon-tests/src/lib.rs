@@ -10,9 +10,9 @@ use crate::mocks::wallet::WalletActor;
 use anyhow::Context;
 use daemon::auto_rollover;
 use daemon::bdk::bitcoin::Amount;
-use daemon::bdk::bitcoin::Network;
 use daemon::bdk::bitcoin::SignedAmount;
 use daemon::bdk::bitcoin::Txid;
+use daemon::block_explorer::BlockExplorer;
 use daemon::libp2p_utils::create_connect_multiaddr;
 use daemon::maia_core::secp256k1_zkp::XOnlyPublicKey;
 use daemon::online_status::ConnectionStatus;
@@ -25,7 +25,8 @@ use daemon::projection::MakerOffers;
 use daemon::seed::RandomSeed;
 use daemon::seed::Seed;
 use daemon::Environment;
-use daemon::N_PAYOUTS;
+use futures::AsyncRead;
+use futures::AsyncWrite;
 use maia::olivia::btc_example_0;
 use maia::OliviaData;
 use maker::cfd::OfferParams;
@@ -35,6 +36,7 @@ use model::olivia::BitMexPriceEventId;
 use model::CfdEvent;
 use model::CompleteFee;
 use model::ContractSymbol;
+use model::ContractType;
 use model::Contracts;
 use model::Dlc;
 use model::EventKind;
@@ -46,11 +48,12 @@ use model::Leverage;
 use model::LotSize;
 use model::OpeningFee;
 use model::OrderId;
+use model::PayoutGranularity;
 use model::Position;
 use model::Price;
 use model::Role;
 use model::TxFeeRate;
-use model::SETTLEMENT_INTERVAL;
+use model::DEFAULT_SETTLEMENT_INTERVAL;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 use std::collections::HashSet;
@@ -60,7 +63,6 @@ use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
-use time::OffsetDateTime;
 use tokio::sync::watch;
 use tokio_extras::Tasks;
 use tracing::instrument;
@@ -68,12 +70,15 @@ use xtra::Actor;
 use xtra_bitmex_price_feed::LatestQuotes;
 use xtra_bitmex_price_feed::Quote;
 use xtra_libp2p::libp2p::Multiaddr;
+use xtra_libp2p::libp2p::Transport;
 use xtra_libp2p::multiaddress_ext::MultiaddrExt;
 
 pub mod flow;
 pub mod maia;
 pub mod mocks;
+pub mod regtest;
 pub mod rollover;
+pub mod simulation;
 
 #[macro_export]
 macro_rules! confirm {
@@ -248,6 +253,7 @@ pub struct OpenCfdArgs {
     pub quantity: Contracts,
     pub taker_leverage: Leverage,
     pub oracle_data: OliviaData,
+    pub granularity: PayoutGranularity,
 }
 
 pub fn initial_price_for(symbol: ContractSymbol) -> Price {
@@ -270,6 +276,7 @@ impl OpenCfdArgs {
     fn offer_params(&self) -> OfferParams {
         OfferParamsBuilder::new(self.contract_symbol)
             .price(self.initial_price)
+            .granularity(self.granularity)
             .build()
     }
 
@@ -299,6 +306,7 @@ impl Default for OpenCfdArgs {
             quantity: Contracts::new(100),
             taker_leverage: Leverage::TWO,
             oracle_data: btc_example_0(),
+            granularity: PayoutGranularity::default(),
         }
     }
 }
@@ -421,6 +429,10 @@ impl FeeCalculator {
         taker_leverage: Leverage,
         maker_position: Position,
     ) -> Self {
+        let hours_to_charge = offer_params
+            .settlement_interval
+            .unwrap_or(DEFAULT_SETTLEMENT_INTERVAL)
+            .whole_hours();
         let initial_funding_fee = match maker_position {
             Position::Long => FundingFee::calculate(
                 offer_params.price_long.unwrap(),
@@ -428,7 +440,7 @@ impl FeeCalculator {
                 Leverage::ONE,
                 taker_leverage,
                 offer_params.funding_rate_long,
-                SETTLEMENT_INTERVAL.whole_hours(),
+                hours_to_charge,
                 contract_symbol,
             )
             .unwrap(),
@@ -438,7 +450,7 @@ impl FeeCalculator {
                 taker_leverage,
                 Leverage::ONE,
                 offer_params.funding_rate_short,
-                SETTLEMENT_INTERVAL.whole_hours(),
+                hours_to_charge,
                 contract_symbol,
             )
             .unwrap(),
@@ -562,9 +574,26 @@ pub async fn start_both() -> (Maker, Taker) {
 pub struct MakerConfig {
     oracle_pk: XOnlyPublicKey,
     seed: RandomSeed,
-    n_payouts: usize,
     libp2p_port: u16,
     blocked_peers: HashSet<xtra_libp2p::libp2p::PeerId>,
+    /// Network and address to listen on if the maker is part of a simulation.
+    simulated_network: Option<(simulation::network::Network, Multiaddr)>,
+}
+
+impl MakerConfig {
+    pub fn with_seed(self, seed: RandomSeed) -> Self {
+        Self { seed, ..self }
+    }
+
+    /// Connect the maker to a simulated network instead of listening on TCP.
+    pub fn with_network(self, network: simulation::network::Network) -> Self {
+        let listen_address = network.memory_address();
+
+        Self {
+            simulated_network: Some((network, listen_address)),
+            ..self
+        }
+    }
 }
 
 impl Default for MakerConfig {
@@ -572,18 +601,32 @@ impl Default for MakerConfig {
         Self {
             oracle_pk: oracle_pk(),
             seed: RandomSeed::default(),
-            n_payouts: N_PAYOUTS,
             libp2p_port: portpicker::pick_unused_port().expect("to be able to find a free port"),
             blocked_peers: HashSet::new(),
+            simulated_network: None,
         }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct TakerConfig {
     oracle_pk: XOnlyPublicKey,
     seed: RandomSeed,
-    n_payouts: usize,
+    simulated_network: Option<simulation::network::Network>,
+}
+
+impl TakerConfig {
+    pub fn with_seed(self, seed: RandomSeed) -> Self {
+        Self { seed, ..self }
+    }
+
+    /// Connect the taker to a simulated network instead of dialing via TCP.
+    pub fn with_network(self, network: simulation::network::Network) -> Self {
+        Self {
+            simulated_network: Some(network),
+            ..self
+        }
+    }
 }
 
 impl Default for TakerConfig {
@@ -591,7 +634,7 @@ impl Default for TakerConfig {
         Self {
             oracle_pk: oracle_pk(),
             seed: RandomSeed::default(),
-            n_payouts: N_PAYOUTS,
+            simulated_network: None,
         }
     }
 }
@@ -660,6 +703,48 @@ impl Maker {
     pub async fn start(config: &MakerConfig) -> Self {
         let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), config.libp2p_port);
 
+        match &config.simulated_network {
+            Some((network, listen_address)) => {
+                Self::start_with_transport(
+                    config,
+                    address,
+                    listen_address.clone(),
+                    network.transport("maker"),
+                )
+                .await
+            }
+            None => {
+                let endpoint_listen = daemon::libp2p_utils::create_listen_tcp_multiaddr(
+                    &address.ip(),
+                    address.port(),
+                )
+                .expect("to parse properly");
+
+                Self::start_with_transport(
+                    config,
+                    address,
+                    endpoint_listen,
+                    Box::new(daemon::libp2p_utils::tcp_transport),
+                )
+                .await
+            }
+        }
+    }
+
+    async fn start_with_transport<T>(
+        config: &MakerConfig,
+        address: SocketAddr,
+        endpoint_listen: Multiaddr,
+        transport: Box<dyn Fn() -> T + Send + 'static>,
+    ) -> Self
+    where
+        T: Transport + Send + Sync + 'static,
+        T::Output: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        T::Error: Send + Sync,
+        T::Listener: Send + 'static,
+        T::Dial: Send + 'static,
+        T::ListenerUpgrade: Send + 'static,
+    {
         let db = sqlite_db::memory().await.unwrap();
 
         let (wallet, wallet_mock) = WalletActor::new();
@@ -674,7 +759,7 @@ impl Maker {
             let _ = price_feed_fut.await;
         });
 
-        let settlement_interval = SETTLEMENT_INTERVAL;
+        let settlement_interval = DEFAULT_SETTLEMENT_INTERVAL;
 
         let identities = config.seed.derive_identities();
 
@@ -683,10 +768,6 @@ impl Maker {
         let mut monitor_mock = None;
         let mut oracle_mock = None;
 
-        let endpoint_listen =
-            daemon::libp2p_utils::create_listen_tcp_multiaddr(&address.ip(), address.port())
-                .expect("to parse properly");
-
         let maker = maker::ActorSystem::new(
             db.clone(),
             wallet_addr,
@@ -704,11 +785,13 @@ impl Maker {
                 Ok(monitor)
             },
             settlement_interval,
-            config.n_payouts,
             projection_actor,
             identities.clone(),
             endpoint_listen.clone(),
+            transport,
             config.blocked_peers.clone(),
+            None,
+            None,
         )
         .unwrap();
 
@@ -723,7 +806,7 @@ impl Maker {
         let feed_senders = Arc::new(feed_senders);
         let proj_actor = projection::Actor::new(
             db,
-            Network::Testnet,
+            BlockExplorer::None,
             price_feed_addr.into(),
             Role::Maker,
             feed_senders,
@@ -755,6 +838,9 @@ impl Maker {
             leverage_choices,
             contract_symbol,
             lot_size,
+            settlement_interval,
+            contract_type,
+            granularity,
         } = offer_params;
         self.system
             .set_offer_params(
@@ -769,6 +855,9 @@ impl Maker {
                 leverage_choices,
                 contract_symbol,
                 lot_size,
+                settlement_interval,
+                contract_type,
+                granularity,
             )
             .await
             .unwrap();
@@ -862,6 +951,42 @@ impl Taker {
         maker_identity: Identity,
         maker_multiaddr: Multiaddr,
     ) -> Self {
+        match &config.simulated_network {
+            Some(network) => {
+                Self::start_with_transport(
+                    config,
+                    maker_identity,
+                    maker_multiaddr,
+                    network.transport("taker"),
+                )
+                .await
+            }
+            None => {
+                Self::start_with_transport(
+                    config,
+                    maker_identity,
+                    maker_multiaddr,
+                    Box::new(daemon::libp2p_utils::tcp_transport),
+                )
+                .await
+            }
+        }
+    }
+
+    async fn start_with_transport<T>(
+        config: &TakerConfig,
+        maker_identity: Identity,
+        maker_multiaddr: Multiaddr,
+        transport: Box<dyn Fn() -> T + Send + 'static>,
+    ) -> Self
+    where
+        T: Transport + Send + Sync + 'static,
+        T::Output: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        T::Error: Send + Sync,
+        T::Listener: Send + 'static,
+        T::Dial: Send + 'static,
+        T::ListenerUpgrade: Send + 'static,
+    {
         let identities = config.seed.derive_identities();
 
         let db = sqlite_db::memory().await.unwrap();
@@ -901,12 +1026,13 @@ impl Taker {
                 Ok(monitor)
             },
             price_feed_addr,
-            config.n_payouts,
             Duration::from_secs(10),
             projection_actor,
             maker_identity,
             maker_multiaddr.clone(),
+            transport,
             Environment::Test,
+            None,
         )
         .unwrap();
 
@@ -921,7 +1047,7 @@ impl Taker {
         let feed_senders = Arc::new(feed_senders);
         let proj_actor = projection::Actor::new(
             db.clone(),
-            Network::Testnet,
+            BlockExplorer::None,
             taker.price_feed_actor.clone().into(),
             Role::Taker,
             feed_senders,
@@ -1011,7 +1137,7 @@ pub fn dummy_latest_quotes() -> LatestQuotes {
 
 fn dummy_btc_quote() -> Quote {
     Quote {
-        timestamp: OffsetDateTime::now_utc(),
+        timestamp: tokio_extras::time::now_utc(),
         bid: dummy_btc_price(),
         ask: dummy_btc_price(),
         symbol: xtra_bitmex_price_feed::ContractSymbol::BtcUsd,
@@ -1020,7 +1146,7 @@ fn dummy_btc_quote() -> Quote {
 
 fn dummy_eth_quote() -> Quote {
     Quote {
-        timestamp: OffsetDateTime::now_utc(),
+        timestamp: tokio_extras::time::now_utc(),
         bid: dummy_eth_price(),
         ask: dummy_eth_price(),
         symbol: xtra_bitmex_price_feed::ContractSymbol::EthUsd,
@@ -1046,6 +1172,9 @@ impl OfferParamsBuilder {
             leverage_choices: vec![Leverage::TWO],
             contract_symbol: symbol,
             lot_size: lot_size_for(symbol),
+            settlement_interval: None,
+            contract_type: ContractType::Perpetual,
+            granularity: PayoutGranularity::default(),
         })
     }
 
@@ -1062,6 +1191,18 @@ impl OfferParamsBuilder {
         self
     }
 
+    pub fn settlement_interval(mut self, settlement_interval: time::Duration) -> Self {
+        self.0.settlement_interval = Some(settlement_interval);
+
+        self
+    }
+
+    pub fn granularity(mut self, granularity: PayoutGranularity) -> Self {
+        self.0.granularity = granularity;
+
+        self
+    }
+
     pub fn build(self) -> OfferParams {
         self.0
     }
@@ -2,11 +2,12 @@ use crate::maia::olivia::btc_example_0;
 use async_trait::async_trait;
 use daemon::command;
 use daemon::oracle;
+use futures::future;
+use futures::FutureExt;
 use model::olivia;
 use model::olivia::BitMexPriceEventId;
 use model::OrderId;
 use std::sync::Arc;
-use time::OffsetDateTime;
 use tokio::sync::Mutex;
 use xtra_productivity::xtra_productivity;
 
@@ -38,17 +39,16 @@ impl OracleActor {
     ///
     /// We _ignore_ the message that is supposed to indicate which `Announcement`s the sender is
     /// requesting. Instead, we return whatever `Announcement`s we have mocked beforehand.
-    async fn handle(
-        &mut self,
-        _: oracle::GetAnnouncements,
-    ) -> Result<Vec<olivia::Announcement>, oracle::NoAnnouncement> {
-        Ok(self
+    async fn handle(&mut self, _: oracle::GetAnnouncements) -> oracle::PendingAnnouncements {
+        let announcements = self
             .mock
             .lock()
             .await
             .announcements
             .clone()
-            .expect("To have mocked announcements"))
+            .expect("To have mocked announcements");
+
+        future::ready(Ok(announcements)).boxed()
     }
 
     async fn handle(&mut self, _msg: oracle::MonitorAttestations) {}
@@ -91,7 +91,7 @@ pub fn dummy_wrong_attestation() -> oracle::Attestation {
         btc_example_0().attestations()[0].clone().into_inner();
 
     oracle::Attestation::new(olivia::Attestation {
-        id: BitMexPriceEventId::with_20_digits(OffsetDateTime::now_utc(), id.index_price()),
+        id: BitMexPriceEventId::with_20_digits(tokio_extras::time::now_utc(), id.index_price()),
         price,
         scalars,
     })
@@ -10,6 +10,8 @@ use daemon::bdk::FeeRate;
 use daemon::maia_core::PartyParams;
 use daemon::maia_core::TxBuilderExt;
 use daemon::wallet;
+use futures::future;
+use futures::FutureExt;
 use mockall::*;
 use rand::thread_rng;
 use std::sync::Arc;
@@ -43,11 +45,26 @@ impl WalletActor {
     async fn handle(&mut self, msg: wallet::BuildPartyParams) -> Result<PartyParams> {
         self.mock.lock().await.build_party_params(msg)
     }
-    async fn handle(&mut self, msg: wallet::Sign) -> Result<PartiallySignedTransaction> {
-        self.mock.lock().await.sign(msg)
+    async fn handle(&mut self, msg: wallet::Sign) -> wallet::PendingSignature {
+        future::ready(self.mock.lock().await.sign(msg)).boxed()
     }
-    async fn handle(&mut self, msg: wallet::Withdraw) -> Result<Txid> {
-        self.mock.lock().await.withdraw(msg)
+    async fn handle(&mut self, msg: wallet::Withdraw) -> wallet::PendingTxid {
+        future::ready(self.mock.lock().await.withdraw(msg)).boxed()
+    }
+    async fn handle(&mut self, msg: wallet::PreviewWithdraw) -> Result<wallet::WithdrawPreview> {
+        self.mock.lock().await.preview_withdraw(msg)
+    }
+    async fn handle(&mut self, msg: wallet::BumpFee) -> wallet::PendingTxid {
+        future::ready(self.mock.lock().await.bump_fee(msg)).boxed()
+    }
+    async fn handle(&mut self, msg: wallet::EstimateFeeRates) -> Result<Vec<wallet::FeeEstimate>> {
+        self.mock.lock().await.estimate_fee_rates(msg)
+    }
+    async fn handle(&mut self, msg: wallet::ListUtxos) -> Result<Vec<wallet::Utxo>> {
+        self.mock.lock().await.list_utxos(msg)
+    }
+    async fn handle(&mut self, msg: wallet::UpdateUtxo) -> Result<()> {
+        self.mock.lock().await.update_utxo(msg)
     }
     async fn handle(&mut self, msg: wallet::Sync) {
         self.mock.lock().await.sync(msg)
@@ -68,6 +85,32 @@ pub trait Wallet {
         unreachable!("mockall will reimplement this method")
     }
 
+    fn preview_withdraw(
+        &mut self,
+        _msg: wallet::PreviewWithdraw,
+    ) -> Result<wallet::WithdrawPreview> {
+        unreachable!("mockall will reimplement this method")
+    }
+
+    fn bump_fee(&mut self, _msg: wallet::BumpFee) -> Result<Txid> {
+        unreachable!("mockall will reimplement this method")
+    }
+
+    fn estimate_fee_rates(
+        &mut self,
+        _msg: wallet::EstimateFeeRates,
+    ) -> Result<Vec<wallet::FeeEstimate>> {
+        unreachable!("mockall will reimplement this method")
+    }
+
+    fn list_utxos(&mut self, _msg: wallet::ListUtxos) -> Result<Vec<wallet::Utxo>> {
+        unreachable!("mockall will reimplement this method")
+    }
+
+    fn update_utxo(&mut self, _msg: wallet::UpdateUtxo) -> Result<()> {
+        unreachable!("mockall will reimplement this method")
+    }
+
     fn sync(&mut self, _msg: wallet::Sync) {
         unreachable!("mockall will reimplement this method")
     }
@@ -0,0 +1,497 @@
+use crate::dummy_latest_quotes;
+use crate::flow::ensure_null_next_offers;
+use crate::flow::next_maker_offers;
+use crate::flow::next_with;
+use crate::flow::one_cfd_with_state;
+use crate::mocks::price_feed::MockPriceFeed;
+use crate::mocks::price_feed::PriceFeedActor;
+use crate::regtest::bitcoind::Bitcoind;
+use crate::regtest::electrs::Electrs;
+use crate::regtest::oracle::Attestor;
+use crate::regtest::oracle::OracleActor;
+use crate::regtest::oracle::TestOracle;
+use crate::wait_next_state;
+use crate::OfferParamsBuilder;
+use anyhow::Context;
+use anyhow::Result;
+use daemon::auto_rollover;
+use daemon::bdk::bitcoin::Amount;
+use daemon::bdk::bitcoin::Network;
+use daemon::bdk::blockchain::ElectrumBlockchain;
+use daemon::bdk::sled;
+use daemon::block_explorer::BlockExplorer;
+use daemon::libp2p_utils::create_connect_multiaddr;
+use daemon::libp2p_utils::create_listen_tcp_multiaddr;
+use daemon::libp2p_utils::tcp_transport;
+use daemon::monitor;
+use daemon::projection;
+use daemon::projection::Cfd;
+use daemon::projection::CfdState;
+use daemon::projection::FeedReceivers;
+use daemon::projection::MakerOffers;
+use daemon::seed::RandomSeed;
+use daemon::seed::Seed;
+use daemon::wallet;
+use daemon::Environment;
+use model::libp2p::PeerId;
+use model::ContractSymbol;
+use model::Contracts;
+use model::Dlc;
+use model::Identity;
+use model::Leverage;
+use model::OrderId;
+use model::Position;
+use model::Price;
+use model::Role;
+use model::WalletInfo;
+use model::DEFAULT_SETTLEMENT_INTERVAL;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::watch;
+use tokio::sync::Mutex;
+use tokio_extras::Tasks;
+use xtra::Actor;
+use xtra_libp2p::libp2p::Multiaddr;
+use xtra_libp2p::multiaddress_ext::MultiaddrExt;
+
+pub mod bitcoind;
+pub mod electrs;
+pub mod oracle;
+
+type WalletActor = wallet::Actor<ElectrumBlockchain, sled::Tree>;
+
+/// Amount each party's wallet is funded with on start.
+const INITIAL_FUNDING_SAT: u64 = 10_000_000;
+
+/// How often we poll the wallet until it reflects the funding transaction.
+const WALLET_SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A local regtest network for end-to-end tests against real transactions.
+///
+/// Spins up `bitcoind` and `electrs`; maker and taker started on top of it run the real wallet and
+/// monitor actors. Only the oracle is replaced by a [`TestOracle`] and the price feed is mocked.
+pub struct Regtest {
+    pub bitcoind: Bitcoind,
+    pub electrs: Electrs,
+    pub oracle: TestOracle,
+}
+
+impl Regtest {
+    pub async fn start() -> Result<Self> {
+        let bitcoind = Bitcoind::start().await?;
+        let electrs = Electrs::start(&bitcoind).await?;
+
+        Ok(Self {
+            bitcoind,
+            electrs,
+            oracle: TestOracle::default(),
+        })
+    }
+
+    /// Start a maker and a taker connected to it, both with funded wallets.
+    pub async fn start_both(&self) -> Result<(Maker, Taker)> {
+        let mut maker = Maker::start(self).await?;
+        let mut taker = Taker::start(self, maker.identity, maker.connect_addr.clone()).await?;
+
+        self.fund(&maker.wallet, &mut maker.wallet_feed).await?;
+        self.fund(&taker.wallet, &mut taker.wallet_feed).await?;
+
+        Ok((maker, taker))
+    }
+
+    pub async fn mine(&self, blocks: u32) -> Result<()> {
+        self.bitcoind.mine(blocks).await
+    }
+
+    async fn fund(
+        &self,
+        wallet: &xtra::Address<WalletActor>,
+        wallet_feed: &mut watch::Receiver<Option<WalletInfo>>,
+    ) -> Result<()> {
+        wallet.send(wallet::Sync).await?;
+        let address = next_with(wallet_feed, |info| info)
+            .await
+            .context("Wallet did not sync")?
+            .address;
+        let amount = Amount::from_sat(INITIAL_FUNDING_SAT);
+
+        self.bitcoind.fund(&address, amount).await?;
+
+        loop {
+            wallet.send(wallet::Sync).await?;
+
+            let balance = wallet_feed
+                .borrow()
+                .as_ref()
+                .map(|info| info.balance)
+                .unwrap_or_default();
+            if balance >= amount {
+                return Ok(());
+            }
+
+            tokio_extras::time::sleep(WALLET_SYNC_INTERVAL).await;
+        }
+    }
+}
+
+/// Maker running against a [`Regtest`] network.
+pub struct Maker {
+    pub system: maker::ActorSystem<OracleActor, WalletActor>,
+    pub feeds: FeedReceivers,
+    pub identity: Identity,
+    pub connect_addr: Multiaddr,
+    pub attestor: Attestor,
+    pub price_feed: Arc<Mutex<MockPriceFeed>>,
+    wallet: xtra::Address<WalletActor>,
+    wallet_feed: watch::Receiver<Option<WalletInfo>>,
+    _data_dir: TempDir,
+    _tasks: Tasks,
+}
+
+impl Maker {
+    async fn start(regtest: &Regtest) -> Result<Self> {
+        let seed = RandomSeed::default();
+        let identities = seed.derive_identities();
+        let data_dir = TempDir::new()?;
+        let db = sqlite_db::memory().await?;
+        let electrum_url = regtest.electrs.electrum_url();
+
+        let mut tasks = Tasks::default();
+
+        let (wallet, wallet_feed) = WalletActor::spawn(
+            &electrum_url,
+            Network::Regtest,
+            wallet::WalletKeys::Hot(seed.derive_extended_priv_key(Network::Regtest)?),
+            data_dir.path().join("maker-wallet"),
+        )?;
+
+        let (price_feed, price_feed_mock) = PriceFeedActor::new();
+        let (price_feed_addr, price_feed_fut) = price_feed.create(None).run();
+        tasks.add(async move {
+            let _ = price_feed_fut.await;
+        });
+
+        let port = portpicker::pick_unused_port().context("No free port for the maker")?;
+        let endpoint_listen = create_listen_tcp_multiaddr(&IpAddr::V4(Ipv4Addr::LOCALHOST), port)?;
+
+        let (projection_actor, projection_context) = xtra::Context::new(None);
+
+        let mut attestor = None;
+
+        let system = maker::ActorSystem::new(
+            db.clone(),
+            wallet.clone(),
+            regtest.oracle.public_key(),
+            |executor| {
+                let (oracle, oracle_attestor) = OracleActor::new(regtest.oracle, executor);
+                attestor = Some(oracle_attestor);
+
+                oracle
+            },
+            |executor| monitor::Actor::new(db.clone(), electrum_url.clone(), executor),
+            DEFAULT_SETTLEMENT_INTERVAL,
+            projection_actor,
+            identities.clone(),
+            endpoint_listen.clone(),
+            Box::new(tcp_transport),
+            HashSet::new(),
+            None,
+            None,
+        )?;
+
+        let (feed_senders, feeds) = projection::feeds();
+        let projection = projection::Actor::new(
+            db,
+            BlockExplorer::None,
+            price_feed_addr.into(),
+            Role::Maker,
+            Arc::new(feed_senders),
+        );
+        tasks.add(projection_context.run(projection));
+
+        Ok(Self {
+            system,
+            feeds,
+            identity: Identity::new(identities.identity_pk),
+            connect_addr: create_connect_multiaddr(
+                &endpoint_listen,
+                &identities.peer_id().inner(),
+            )?,
+            attestor: attestor.expect("oracle to be constructed"),
+            price_feed: price_feed_mock,
+            wallet,
+            wallet_feed,
+            _data_dir: data_dir,
+            _tasks: tasks,
+        })
+    }
+
+    pub fn cfd_feed(&mut self) -> &mut watch::Receiver<Option<Vec<Cfd>>> {
+        &mut self.feeds.cfds
+    }
+
+    pub fn offers_feed(&mut self) -> &mut watch::Receiver<MakerOffers> {
+        &mut self.feeds.offers
+    }
+
+    pub fn first_cfd(&mut self) -> Cfd {
+        self.cfd_feed()
+            .borrow()
+            .as_ref()
+            .and_then(|cfds| cfds.first().cloned())
+            .expect("maker to have a CFD")
+    }
+
+    pub fn latest_dlc(&mut self) -> Dlc {
+        self.first_cfd()
+            .aggregated()
+            .latest_dlc()
+            .clone()
+            .expect("maker to have a DLC")
+    }
+
+    async fn set_offer_params(&mut self, offer_params: maker::cfd::OfferParams) -> Result<()> {
+        let maker::cfd::OfferParams {
+            price_long,
+            price_short,
+            min_quantity,
+            max_quantity,
+            tx_fee_rate,
+            funding_rate_long,
+            funding_rate_short,
+            opening_fee,
+            leverage_choices,
+            contract_symbol,
+            lot_size,
+            settlement_interval,
+            contract_type,
+            granularity,
+        } = offer_params;
+
+        self.system
+            .set_offer_params(
+                price_long,
+                price_short,
+                min_quantity,
+                max_quantity,
+                tx_fee_rate,
+                funding_rate_long,
+                funding_rate_short,
+                opening_fee,
+                leverage_choices,
+                contract_symbol,
+                lot_size,
+                settlement_interval,
+                contract_type,
+                granularity,
+            )
+            .await
+    }
+}
+
+/// Taker running against a [`Regtest`] network.
+pub struct Taker {
+    pub system: daemon::TakerActorSystem<OracleActor, WalletActor, PriceFeedActor>,
+    pub feeds: FeedReceivers,
+    pub maker_peer_id: PeerId,
+    pub attestor: Attestor,
+    pub price_feed: Arc<Mutex<MockPriceFeed>>,
+    wallet: xtra::Address<WalletActor>,
+    wallet_feed: watch::Receiver<Option<WalletInfo>>,
+    _data_dir: TempDir,
+    _tasks: Tasks,
+}
+
+impl Taker {
+    async fn start(
+        regtest: &Regtest,
+        maker_identity: Identity,
+        maker_multiaddr: Multiaddr,
+    ) -> Result<Self> {
+        let seed = RandomSeed::default();
+        let identities = seed.derive_identities();
+        let data_dir = TempDir::new()?;
+        let db = sqlite_db::memory().await?;
+        let electrum_url = regtest.electrs.electrum_url();
+
+        let mut tasks = Tasks::default();
+
+        let (wallet, wallet_feed) = WalletActor::spawn(
+            &electrum_url,
+            Network::Regtest,
+            wallet::WalletKeys::Hot(seed.derive_extended_priv_key(Network::Regtest)?),
+            data_dir.path().join("taker-wallet"),
+        )?;
+
+        let (price_feed, price_feed_mock) = PriceFeedActor::new();
+        let (price_feed_addr, price_feed_fut) = price_feed.create(None).run();
+        tasks.add(async move {
+            let _ = price_feed_fut.await;
+        });
+
+        let (projection_actor, projection_context) = xtra::Context::new(None);
+
+        let mut attestor = None;
+
+        let system = daemon::TakerActorSystem::new(
+            db.clone(),
+            wallet.clone(),
+            regtest.oracle.public_key(),
+            identities,
+            |executor| {
+                let (oracle, oracle_attestor) = OracleActor::new(regtest.oracle, executor);
+                attestor = Some(oracle_attestor);
+
+                oracle
+            },
+            |executor| monitor::Actor::new(db.clone(), electrum_url.clone(), executor),
+            price_feed_addr,
+            Duration::from_secs(10),
+            projection_actor,
+            maker_identity,
+            maker_multiaddr.clone(),
+            Box::new(tcp_transport),
+            Environment::Test,
+            None,
+        )?;
+
+        let (feed_senders, feeds) = projection::feeds();
+        let projection = projection::Actor::new(
+            db,
+            BlockExplorer::None,
+            system.price_feed_actor.clone().into(),
+            Role::Taker,
+            Arc::new(feed_senders),
+        );
+        tasks.add(projection_context.run(projection));
+
+        Ok(Self {
+            system,
+            feeds,
+            maker_peer_id: maker_multiaddr
+                .extract_peer_id()
+                .context("Maker address without peer id")?
+                .into(),
+            attestor: attestor.expect("oracle to be constructed"),
+            price_feed: price_feed_mock,
+            wallet,
+            wallet_feed,
+            _data_dir: data_dir,
+            _tasks: tasks,
+        })
+    }
+
+    pub fn cfd_feed(&mut self) -> &mut watch::Receiver<Option<Vec<Cfd>>> {
+        &mut self.feeds.cfds
+    }
+
+    pub fn offers_feed(&mut self) -> &mut watch::Receiver<MakerOffers> {
+        &mut self.feeds.offers
+    }
+
+    pub fn quote_feed(&mut self) -> &mut watch::Receiver<projection::LatestQuotes> {
+        &mut self.feeds.quote
+    }
+
+    pub fn first_cfd(&mut self) -> Cfd {
+        self.cfd_feed()
+            .borrow()
+            .as_ref()
+            .and_then(|cfds| cfds.first().cloned())
+            .expect("taker to have a CFD")
+    }
+
+    pub fn latest_dlc(&mut self) -> Dlc {
+        self.first_cfd()
+            .aggregated()
+            .latest_dlc()
+            .clone()
+            .expect("taker to have a DLC")
+    }
+
+    pub async fn trigger_rollover(&mut self, order_id: OrderId) -> Result<()> {
+        let latest_dlc = self.latest_dlc();
+
+        self.system
+            .auto_rollover_actor
+            .send(auto_rollover::Rollover {
+                order_id,
+                maker_peer_id: Some(self.maker_peer_id),
+                from_commit_txid: latest_dlc.commit.0.txid(),
+                from_settlement_event_id: latest_dlc.settlement_event_id,
+            })
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Open a CFD with real lock transactions and wait until it is confirmed.
+pub async fn open_cfd(
+    regtest: &Regtest,
+    maker: &mut Maker,
+    taker: &mut Taker,
+    position_maker: Position,
+    quantity: Contracts,
+) -> Result<OrderId> {
+    let contract_symbol = ContractSymbol::BtcUsd;
+
+    ensure_null_next_offers(taker.offers_feed()).await?;
+
+    let offer_params = OfferParamsBuilder::new(contract_symbol)
+        .price(initial_price())
+        .build();
+    maker.set_offer_params(offer_params).await?;
+
+    let (_, offers) =
+        next_maker_offers(maker.offers_feed(), taker.offers_feed(), &contract_symbol).await?;
+    let offer = match position_maker {
+        Position::Long => offers.btcusd_long,
+        Position::Short => offers.btcusd_short,
+    }
+    .context("No offer for the maker's position")?;
+
+    let order_id = taker
+        .system
+        .place_order(offer.id, quantity, Leverage::TWO)
+        .await?;
+    wait_next_state!(order_id, maker, taker, CfdState::PendingSetup);
+
+    maker.system.accept_order(order_id).await?;
+    wait_next_state!(order_id, maker, taker, CfdState::PendingOpen);
+
+    regtest.mine(1).await?;
+    wait_next_state!(order_id, maker, taker, CfdState::Open);
+
+    Ok(order_id)
+}
+
+/// Make quotes available to both parties, required for collaborative settlement.
+pub async fn mock_quotes(maker: &mut Maker, taker: &mut Taker) -> Result<()> {
+    maker
+        .price_feed
+        .lock()
+        .await
+        .set_latest_quotes(dummy_latest_quotes());
+    taker
+        .price_feed
+        .lock()
+        .await
+        .set_latest_quotes(dummy_latest_quotes());
+
+    let mut quote_feed = taker.quote_feed().clone();
+    next_with(&mut quote_feed, |quotes: projection::LatestQuotes| {
+        quotes.get(&ContractSymbol::BtcUsd).copied()
+    })
+    .await?;
+
+    Ok(())
+}
+
+pub fn initial_price() -> Price {
+    Price::new(crate::dummy_btc_price()).expect("static price to be valid")
+}
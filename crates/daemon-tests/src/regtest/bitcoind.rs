@@ -0,0 +1,165 @@
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use daemon::bdk::bitcoin::consensus::encode::serialize_hex;
+use daemon::bdk::bitcoin::Address;
+use daemon::bdk::bitcoin::Amount;
+use daemon::bdk::bitcoin::Transaction;
+use daemon::bdk::bitcoin::Txid;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use serde_json::Value;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::process::Child;
+use tokio::process::Command;
+
+/// Environment variable pointing to the `bitcoind` binary, defaults to `bitcoind` on the `PATH`.
+const BITCOIND_EXE: &str = "BITCOIND_EXE";
+
+const RPC_USER: &str = "itchysats";
+const RPC_PASSWORD: &str = "itchysats";
+
+/// Number of blocks after which coinbase outputs can be spent.
+const COINBASE_MATURITY: u32 = 100;
+
+/// A `bitcoind` running in regtest mode, killed on drop.
+pub struct Bitcoind {
+    client: reqwest::Client,
+    rpc_port: u16,
+    p2p_port: u16,
+    data_dir: TempDir,
+    _process: Child,
+}
+
+impl Bitcoind {
+    pub async fn start() -> Result<Self> {
+        let exe = std::env::var(BITCOIND_EXE).unwrap_or_else(|_| "bitcoind".to_owned());
+        let data_dir = TempDir::new()?;
+        let rpc_port = portpicker::pick_unused_port().context("No free port for bitcoind RPC")?;
+        let p2p_port = portpicker::pick_unused_port().context("No free port for bitcoind P2P")?;
+
+        let process = Command::new(&exe)
+            .arg("-regtest")
+            .arg("-server")
+            .arg("-txindex")
+            .arg("-fallbackfee=0.0001")
+            .arg(format!("-datadir={}", data_dir.path().display()))
+            .arg(format!("-rpcport={rpc_port}"))
+            .arg(format!("-port={p2p_port}"))
+            .arg(format!("-rpcuser={RPC_USER}"))
+            .arg(format!("-rpcpassword={RPC_PASSWORD}"))
+            .stdout(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to spawn {exe}, set {BITCOIND_EXE} to its path"))?;
+
+        let bitcoind = Self {
+            client: reqwest::Client::new(),
+            rpc_port,
+            p2p_port,
+            data_dir,
+            _process: process,
+        };
+
+        bitcoind.wait_until_ready().await?;
+        bitcoind
+            .rpc::<Value>("createwallet", json!(["itchysats"]))
+            .await?;
+        bitcoind.mine(COINBASE_MATURITY + 1).await?;
+
+        Ok(bitcoind)
+    }
+
+    pub fn rpc_port(&self) -> u16 {
+        self.rpc_port
+    }
+
+    pub fn p2p_port(&self) -> u16 {
+        self.p2p_port
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        self.data_dir.path()
+    }
+
+    pub fn auth(&self) -> String {
+        format!("{RPC_USER}:{RPC_PASSWORD}")
+    }
+
+    pub async fn height(&self) -> Result<u32> {
+        self.rpc("getblockcount", json!([])).await
+    }
+
+    /// Mine `blocks` blocks, paying the rewards to the node's own wallet.
+    pub async fn mine(&self, blocks: u32) -> Result<()> {
+        let address = self.rpc::<String>("getnewaddress", json!([])).await?;
+        self.rpc::<Vec<String>>("generatetoaddress", json!([blocks, address]))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Send `amount` to `address` and confirm the transaction.
+    pub async fn fund(&self, address: &Address, amount: Amount) -> Result<Txid> {
+        let txid = self
+            .rpc(
+                "sendtoaddress",
+                json!([address.to_string(), amount.to_btc()]),
+            )
+            .await?;
+        self.mine(1).await?;
+
+        Ok(txid)
+    }
+
+    /// Publish a transaction bypassing the wallets of the parties.
+    pub async fn broadcast(&self, transaction: &Transaction) -> Result<Txid> {
+        self.rpc("sendrawtransaction", json!([serialize_hex(transaction)]))
+            .await
+    }
+
+    async fn wait_until_ready(&self) -> Result<()> {
+        for _ in 0..100 {
+            if self.height().await.is_ok() {
+                return Ok(());
+            }
+
+            tokio_extras::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        bail!("bitcoind did not start in time")
+    }
+
+    async fn rpc<T>(&self, method: &str, params: Value) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let response = self
+            .client
+            .post(format!("http://127.0.0.1:{}/", self.rpc_port))
+            .basic_auth(RPC_USER, Some(RPC_PASSWORD))
+            .json(&json!({
+                "jsonrpc": "1.0",
+                "id": method,
+                "method": method,
+                "params": params,
+            }))
+            .send()
+            .await
+            .with_context(|| format!("Failed to call {method}"))?
+            .json::<Value>()
+            .await?;
+
+        if !response["error"].is_null() {
+            bail!("{method} failed: {}", response["error"]);
+        }
+
+        let result = serde_json::from_value(response["result"].clone())
+            .with_context(|| format!("Unexpected response to {method}"))?;
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,79 @@
+use crate::regtest::bitcoind::Bitcoind;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use std::process::Stdio;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::net::TcpStream;
+use tokio::process::Child;
+use tokio::process::Command;
+
+/// Environment variable pointing to the `electrs` binary, defaults to `electrs` on the `PATH`.
+const ELECTRS_EXE: &str = "ELECTRS_EXE";
+
+/// An `electrs` instance indexing a regtest [`Bitcoind`], killed on drop.
+pub struct Electrs {
+    electrum_port: u16,
+    _db_dir: TempDir,
+    _process: Child,
+}
+
+impl Electrs {
+    pub async fn start(bitcoind: &Bitcoind) -> Result<Self> {
+        let exe = std::env::var(ELECTRS_EXE).unwrap_or_else(|_| "electrs".to_owned());
+        let db_dir = TempDir::new()?;
+        let electrum_port =
+            portpicker::pick_unused_port().context("No free port for electrs RPC")?;
+
+        let process = Command::new(&exe)
+            .arg("--network=regtest")
+            .arg(format!("--daemon-dir={}", bitcoind.data_dir().display()))
+            .arg(format!("--db-dir={}", db_dir.path().display()))
+            .arg(format!("--auth={}", bitcoind.auth()))
+            .arg(format!(
+                "--daemon-rpc-addr=127.0.0.1:{}",
+                bitcoind.rpc_port()
+            ))
+            .arg(format!(
+                "--daemon-p2p-addr=127.0.0.1:{}",
+                bitcoind.p2p_port()
+            ))
+            .arg(format!("--electrum-rpc-addr=127.0.0.1:{electrum_port}"))
+            .stdout(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to spawn {exe}, set {ELECTRS_EXE} to its path"))?;
+
+        let electrs = Self {
+            electrum_port,
+            _db_dir: db_dir,
+            _process: process,
+        };
+
+        electrs.wait_until_ready().await?;
+
+        Ok(electrs)
+    }
+
+    /// The URL under which the wallet and the monitor reach electrs.
+    pub fn electrum_url(&self) -> String {
+        format!("tcp://127.0.0.1:{}", self.electrum_port)
+    }
+
+    async fn wait_until_ready(&self) -> Result<()> {
+        // electrs only opens the RPC port once the initial sync with bitcoind completed
+        for _ in 0..300 {
+            if TcpStream::connect(("127.0.0.1", self.electrum_port))
+                .await
+                .is_ok()
+            {
+                return Ok(());
+            }
+
+            tokio_extras::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        bail!("electrs did not start in time")
+    }
+}
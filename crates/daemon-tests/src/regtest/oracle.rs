@@ -0,0 +1,153 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use daemon::bdk::bitcoin::hashes::sha256;
+use daemon::bdk::bitcoin::hashes::Hash;
+use daemon::bdk::bitcoin::hashes::HashEngine;
+use daemon::command;
+use daemon::maia_core::secp256k1_zkp::KeyPair;
+use daemon::maia_core::secp256k1_zkp::SecretKey;
+use daemon::maia_core::secp256k1_zkp::XOnlyPublicKey;
+use daemon::maia_core::secp256k1_zkp::SECP256K1;
+use daemon::oracle;
+use futures::future;
+use futures::FutureExt;
+use model::olivia;
+use model::olivia::BitMexPriceEventId;
+use model::OrderId;
+use xtra_productivity::xtra_productivity;
+
+/// Number of binary digits the price is decomposed into, matching olivia's `n=20` events.
+const N_DIGITS: usize = 20;
+
+/// An olivia-compatible oracle signing with a test key.
+///
+/// Nonces are derived from the key and the event id, so every party serving the same
+/// [`TestOracle`] announces the same nonces for an event and the oracle can attest to any event
+/// without keeping state.
+#[derive(Clone, Copy)]
+pub struct TestOracle {
+    secret_key: SecretKey,
+    keypair: KeyPair,
+}
+
+impl TestOracle {
+    pub fn new(secret_key: SecretKey) -> Self {
+        Self {
+            secret_key,
+            keypair: KeyPair::from_secret_key(SECP256K1, secret_key),
+        }
+    }
+
+    pub fn public_key(&self) -> XOnlyPublicKey {
+        XOnlyPublicKey::from_keypair(&self.keypair)
+    }
+
+    pub fn announce(&self, id: BitMexPriceEventId) -> olivia::Announcement {
+        olivia::Announcement {
+            id,
+            expected_outcome_time: id.timestamp(),
+            nonce_pks: (0..N_DIGITS)
+                .map(|index| XOnlyPublicKey::from_keypair(&self.nonce(id, index)))
+                .collect(),
+        }
+    }
+
+    pub fn attest(&self, id: BitMexPriceEventId, price: u64) -> oracle::Attestation {
+        let scalars = digits(price)
+            .enumerate()
+            .map(|(index, digit)| {
+                daemon::maia_core::oracle::attest(&self.keypair, &self.nonce(id, index), digit)
+            })
+            .collect();
+
+        oracle::Attestation::new(olivia::Attestation { id, price, scalars })
+    }
+
+    fn nonce(&self, id: BitMexPriceEventId, index: usize) -> KeyPair {
+        let mut engine = sha256::Hash::engine();
+        engine.input(&self.secret_key[..]);
+        engine.input(id.to_string().as_bytes());
+        engine.input(&index.to_be_bytes());
+        let hash = sha256::Hash::from_engine(engine);
+
+        let secret_key =
+            SecretKey::from_slice(&hash.into_inner()).expect("hash to be a valid secret key");
+
+        KeyPair::from_secret_key(SECP256K1, secret_key)
+    }
+}
+
+impl Default for TestOracle {
+    fn default() -> Self {
+        Self::new(SecretKey::from_slice(&[1; 32]).expect("static key to be valid"))
+    }
+}
+
+/// The binary digits of the price, most significant first.
+fn digits(price: u64) -> impl Iterator<Item = u8> {
+    (0..N_DIGITS).rev().map(move |i| ((price >> i) & 1) as u8)
+}
+
+/// Oracle actor serving announcements of a [`TestOracle`].
+///
+/// Attestations are not published on a schedule but delivered on demand through the
+/// [`Attestor`] returned on construction.
+pub struct OracleActor {
+    oracle: TestOracle,
+}
+
+impl OracleActor {
+    pub fn new(oracle: TestOracle, executor: command::Executor) -> (Self, Attestor) {
+        (Self { oracle }, Attestor { oracle, executor })
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for OracleActor {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[xtra_productivity]
+impl OracleActor {
+    async fn handle(&mut self, msg: oracle::GetAnnouncements) -> oracle::PendingAnnouncements {
+        let announcements = msg
+            .0
+            .into_iter()
+            .map(|id| self.oracle.announce(id))
+            .collect();
+
+        future::ready(Ok(announcements)).boxed()
+    }
+
+    async fn handle(&mut self, _msg: oracle::MonitorAttestations) {}
+
+    async fn handle(&mut self, _msg: oracle::SyncAnnouncements) {}
+
+    async fn handle(&mut self, _msg: oracle::SyncAttestations) {}
+}
+
+/// Hands attestations of the [`TestOracle`] to the CFDs of one party.
+#[derive(Clone)]
+pub struct Attestor {
+    oracle: TestOracle,
+    executor: command::Executor,
+}
+
+impl Attestor {
+    pub async fn attest(
+        &self,
+        order_id: OrderId,
+        event_id: BitMexPriceEventId,
+        price: u64,
+    ) -> Result<()> {
+        let attestation = self.oracle.attest(event_id, price);
+
+        self.executor
+            .execute(order_id, |cfd| cfd.decrypt_cet(attestation.as_inner()))
+            .await?;
+
+        Ok(())
+    }
+}
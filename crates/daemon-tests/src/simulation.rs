@@ -0,0 +1,339 @@
+use crate::flow::next_with;
+use crate::flow::one_cfd_with_state;
+use crate::initial_price_for;
+use crate::maia::olivia::btc_example_0;
+use crate::maia::olivia::eth_example_0;
+use crate::mock_quotes;
+use crate::mocks::oracle::dummy_wrong_attestation;
+use crate::open_cfd;
+use crate::simulate_attestation;
+use crate::simulation::chain::Chain;
+use crate::simulation::chain::ChainEvent;
+use crate::simulation::network::Faults;
+use crate::simulation::network::Network;
+use crate::wait_next_state;
+use crate::Maker;
+use crate::MakerConfig;
+use crate::OpenCfdArgs;
+use crate::Taker;
+use crate::TakerConfig;
+use daemon::projection::CfdState;
+use daemon::seed::RandomSeed;
+use model::ContractSymbol;
+use model::Contracts;
+use model::Leverage;
+use model::OrderId;
+use model::PayoutGranularity;
+use model::Position;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+pub mod chain;
+pub mod network;
+
+/// Unix timestamp at which the virtual clock of every simulation starts.
+const START_TIMESTAMP: i64 = 1_664_798_400;
+
+/// Deterministic simulation of a maker and a taker trading with each other.
+///
+/// A simulation runs on a virtual clock and therefore has to be driven by a single-threaded runtime
+/// with paused time, i.e. `#[tokio::test(start_paused = true)]`. Wall-clock time
+/// ([`tokio_extras::time::now_utc`]), sleeps, timeouts and intervals then only advance once all
+/// tasks are idle, so scenarios spanning days of protocol time complete in seconds.
+///
+/// The parties talk to each other over a simulated [`Network`] and observe a scripted [`Chain`].
+/// All randomness is derived from the seed: a failing scenario can be replayed by running it again
+/// with the same seed.
+pub struct Simulation {
+    seed: u64,
+    rng: StdRng,
+    pub network: Network,
+    pub chain: Chain,
+}
+
+impl Simulation {
+    pub fn new(seed: u64) -> Self {
+        let start =
+            OffsetDateTime::from_unix_timestamp(START_TIMESTAMP).expect("valid unix timestamp");
+        tokio_extras::time::start_simulated_clock(start);
+
+        tracing::info!(%seed, "Starting simulation");
+
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            network: Network::new(seed),
+            chain: Chain::default(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    pub fn maker_config(&mut self) -> MakerConfig {
+        MakerConfig::default()
+            .with_seed(self.random_seed())
+            .with_network(self.network.clone())
+    }
+
+    pub fn taker_config(&mut self) -> TakerConfig {
+        TakerConfig::default()
+            .with_seed(self.random_seed())
+            .with_network(self.network.clone())
+    }
+
+    pub async fn start_both(&mut self) -> (Maker, Taker) {
+        let maker = Maker::start(&self.maker_config()).await;
+        let taker = Taker::start(
+            &self.taker_config(),
+            maker.identity,
+            maker.connect_addr.clone(),
+        )
+        .await;
+
+        (maker, taker)
+    }
+
+    /// Run a scenario generated from the seed of the simulation.
+    pub async fn run_random_scenario(&mut self) {
+        let scenario = Scenario::random(&mut self.rng);
+        self.run(scenario).await;
+    }
+
+    /// Open a CFD and take it through to the end of its lifecycle as described by the scenario.
+    pub async fn run(&mut self, scenario: Scenario) {
+        tracing::info!(seed = %self.seed, ?scenario, "Running scenario");
+
+        // Dropping a connection aborts the protocols running over it, hence connections are only
+        // dropped once the parties merely wait for the chain, see `mine_all`
+        self.network.set_faults(scenario.faults.without_drops());
+
+        let (mut maker, mut taker) = self.start_both().await;
+        let (maker, taker) = (&mut maker, &mut taker);
+
+        let order_id = open_cfd(taker, maker, scenario.open_cfd_args.clone()).await;
+
+        match scenario.outcome {
+            Outcome::CollaborativeSettlement => {
+                self.settle_collaboratively(maker, taker, &scenario, order_id)
+                    .await
+            }
+            Outcome::NonCollaborativeSettlement => {
+                self.settle_non_collaboratively(maker, taker, &scenario, order_id)
+                    .await
+            }
+            Outcome::Refund => self.refund(maker, taker, &scenario, order_id).await,
+        }
+    }
+
+    async fn settle_collaboratively(
+        &mut self,
+        maker: &mut Maker,
+        taker: &mut Taker,
+        scenario: &Scenario,
+        order_id: OrderId,
+    ) {
+        mock_quotes(maker, taker, scenario.open_cfd_args.contract_symbol).await;
+
+        taker.system.propose_settlement(order_id).await.unwrap();
+        wait_next_state!(
+            order_id,
+            maker,
+            taker,
+            CfdState::IncomingSettlementProposal,
+            CfdState::OutgoingSettlementProposal
+        );
+
+        maker.system.accept_settlement(order_id).await.unwrap();
+        wait_next_state!(order_id, maker, taker, CfdState::PendingClose);
+
+        self.chain.schedule(
+            scenario.confirmation_blocks,
+            order_id,
+            ChainEvent::CloseConfirmed,
+        );
+        self.mine_all(maker, taker, scenario).await;
+        wait_next_state!(order_id, maker, taker, CfdState::Closed);
+    }
+
+    async fn settle_non_collaboratively(
+        &mut self,
+        maker: &mut Maker,
+        taker: &mut Taker,
+        scenario: &Scenario,
+        order_id: OrderId,
+    ) {
+        self.chain.schedule(
+            scenario.confirmation_blocks,
+            order_id,
+            ChainEvent::CommitConfirmed,
+        );
+        self.mine_all(maker, taker, scenario).await;
+        wait_next_state!(order_id, maker, taker, CfdState::OpenCommitted);
+
+        self.chain.schedule(
+            CET_TIMELOCK_BLOCKS,
+            order_id,
+            ChainEvent::CetTimelockExpired,
+        );
+        self.mine_all(maker, taker, scenario).await;
+
+        simulate_attestation!(taker, maker, order_id, &dummy_wrong_attestation());
+        wait_next_state!(order_id, maker, taker, CfdState::OpenCommitted);
+
+        let attestation = scenario.open_cfd_args.oracle_data.settlement_attestation();
+        simulate_attestation!(taker, maker, order_id, &attestation);
+        wait_next_state!(order_id, maker, taker, CfdState::PendingCet);
+
+        self.chain.schedule(
+            scenario.confirmation_blocks,
+            order_id,
+            ChainEvent::CetConfirmed,
+        );
+        self.mine_all(maker, taker, scenario).await;
+        wait_next_state!(order_id, maker, taker, CfdState::Closed);
+    }
+
+    async fn refund(
+        &mut self,
+        maker: &mut Maker,
+        taker: &mut Taker,
+        scenario: &Scenario,
+        order_id: OrderId,
+    ) {
+        self.chain.schedule(
+            scenario.confirmation_blocks,
+            order_id,
+            ChainEvent::CommitConfirmed,
+        );
+        self.mine_all(maker, taker, scenario).await;
+        wait_next_state!(order_id, maker, taker, CfdState::OpenCommitted);
+
+        self.chain.schedule(
+            REFUND_TIMELOCK_BLOCKS,
+            order_id,
+            ChainEvent::RefundTimelockExpired,
+        );
+        self.mine_all(maker, taker, scenario).await;
+        wait_next_state!(order_id, maker, taker, CfdState::PendingRefund);
+
+        self.chain.schedule(
+            scenario.confirmation_blocks,
+            order_id,
+            ChainEvent::RefundConfirmed,
+        );
+        self.mine_all(maker, taker, scenario).await;
+        wait_next_state!(order_id, maker, taker, CfdState::Refunded);
+    }
+
+    /// Mine all scheduled blocks while injecting all faults of the `scenario`.
+    ///
+    /// The taker reconnects to the maker in the background if a connection is dropped.
+    async fn mine_all(&mut self, maker: &mut Maker, taker: &mut Taker, scenario: &Scenario) {
+        self.network.set_faults(scenario.faults);
+        self.chain.mine_all(maker, taker).await;
+    }
+
+    fn random_seed(&mut self) -> RandomSeed {
+        let mut seed = [0u8; 256];
+        self.rng.fill(&mut seed[..]);
+
+        RandomSeed::from(seed)
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        tokio_extras::time::stop_simulated_clock();
+    }
+}
+
+/// Number of blocks after which the CET timelock expires on the simulated chain.
+const CET_TIMELOCK_BLOCKS: u32 = 12;
+
+/// Number of blocks after which the refund timelock expires on the simulated chain.
+const REFUND_TIMELOCK_BLOCKS: u32 = 24 * 6;
+
+/// Upper bound for the probability with which a write severs a connection in a random scenario.
+const MAX_DROP_PROBABILITY: f64 = 0.05;
+
+/// How a CFD in a [`Scenario`] reaches the end of its lifecycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    CollaborativeSettlement,
+    NonCollaborativeSettlement,
+    Refund,
+}
+
+#[derive(Clone)]
+pub struct Scenario {
+    pub open_cfd_args: OpenCfdArgs,
+    pub outcome: Outcome,
+    pub faults: Faults,
+    /// Number of blocks it takes for a transaction to be confirmed.
+    pub confirmation_blocks: u32,
+}
+
+impl Scenario {
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let contract_symbol = if rng.gen() {
+            ContractSymbol::BtcUsd
+        } else {
+            ContractSymbol::EthUsd
+        };
+        let oracle_data = match contract_symbol {
+            ContractSymbol::BtcUsd => btc_example_0(),
+            ContractSymbol::EthUsd => eth_example_0(),
+        };
+        let position_maker = if rng.gen() {
+            Position::Long
+        } else {
+            Position::Short
+        };
+        let outcome = match rng.gen_range(0, 3) {
+            0 => Outcome::CollaborativeSettlement,
+            1 => Outcome::NonCollaborativeSettlement,
+            _ => Outcome::Refund,
+        };
+
+        Self {
+            open_cfd_args: OpenCfdArgs {
+                contract_symbol,
+                position_maker,
+                initial_price: initial_price_for(contract_symbol),
+                // Offers are created with a minimum quantity of 100 and a maximum of 1000
+                quantity: Contracts::new(rng.gen_range(1, 11) * 100),
+                taker_leverage: Leverage::TWO,
+                oracle_data,
+                granularity: PayoutGranularity::default(),
+            },
+            outcome,
+            faults: Faults {
+                max_latency: Duration::from_millis(rng.gen_range(0, 2_000)),
+                drop_probability: rng.gen_range(0.0, MAX_DROP_PROBABILITY),
+            },
+            confirmation_blocks: rng.gen_range(1, 7),
+        }
+    }
+}
+
+impl std::fmt::Debug for Scenario {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scenario")
+            .field("contract_symbol", &self.open_cfd_args.contract_symbol)
+            .field("position_maker", &self.open_cfd_args.position_maker)
+            .field("quantity", &self.open_cfd_args.quantity)
+            .field("outcome", &self.outcome)
+            .field("faults", &self.faults)
+            .field("confirmation_blocks", &self.confirmation_blocks)
+            .finish()
+    }
+}
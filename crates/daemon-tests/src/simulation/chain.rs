@@ -0,0 +1,88 @@
+use crate::mocks::Mocks;
+use crate::Maker;
+use crate::Taker;
+use model::OrderId;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Average time between two blocks on the simulated chain.
+pub const BLOCK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Something that happens to a CFD on chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChainEvent {
+    LockConfirmed,
+    CommitConfirmed,
+    CetTimelockExpired,
+    CetConfirmed,
+    RefundTimelockExpired,
+    RefundConfirmed,
+    CloseConfirmed,
+}
+
+impl ChainEvent {
+    async fn deliver(self, order_id: OrderId, mocks: &mut Mocks) {
+        let mut monitor = mocks.monitor().await;
+
+        match self {
+            ChainEvent::LockConfirmed => monitor.confirm_lock_transaction(order_id).await,
+            ChainEvent::CommitConfirmed => monitor.confirm_commit_transaction(order_id).await,
+            ChainEvent::CetTimelockExpired => monitor.expire_cet_timelock(order_id).await,
+            ChainEvent::CetConfirmed => monitor.confirm_cet(order_id).await,
+            ChainEvent::RefundTimelockExpired => monitor.expire_refund_timelock(order_id).await,
+            ChainEvent::RefundConfirmed => monitor.confirm_refund_transaction(order_id).await,
+            ChainEvent::CloseConfirmed => monitor.confirm_close_transaction(order_id).await,
+        }
+    }
+}
+
+/// A scripted blockchain.
+///
+/// Instead of watching a real chain, events are scheduled at a block height and delivered to the
+/// monitors of both parties once that block is mined.
+#[derive(Debug, Default)]
+pub struct Chain {
+    height: u32,
+    script: BTreeMap<u32, Vec<(OrderId, ChainEvent)>>,
+}
+
+impl Chain {
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Schedule an event to happen `blocks` blocks from now.
+    pub fn schedule(&mut self, blocks: u32, order_id: OrderId, event: ChainEvent) {
+        self.script
+            .entry(self.height + blocks)
+            .or_default()
+            .push((order_id, event));
+    }
+
+    /// Whether there are still events waiting to be mined.
+    pub fn has_pending_events(&self) -> bool {
+        !self.script.is_empty()
+    }
+
+    /// Wait for the next block and deliver the events scheduled for it.
+    pub async fn mine(&mut self, maker: &mut Maker, taker: &mut Taker) {
+        tokio_extras::time::sleep_silent(BLOCK_INTERVAL).await;
+        self.height += 1;
+
+        let events = self.script.remove(&self.height).unwrap_or_default();
+
+        for (order_id, event) in events {
+            tracing::debug!(height = %self.height, %order_id, ?event, "Delivering chain event");
+
+            event.deliver(order_id, &mut maker.mocks).await;
+            event.deliver(order_id, &mut taker.mocks).await;
+        }
+    }
+
+    /// Mine blocks until all scheduled events have been delivered.
+    pub async fn mine_all(&mut self, maker: &mut Maker, taker: &mut Taker) {
+        while self.has_pending_events() {
+            self.mine(maker, taker).await;
+        }
+    }
+}
@@ -0,0 +1,493 @@
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::task::AtomicWaker;
+use futures::AsyncRead;
+use futures::AsyncReadExt;
+use futures::AsyncWrite;
+use futures::AsyncWriteExt;
+use futures::FutureExt;
+use futures::StreamExt;
+use futures::TryFutureExt;
+use futures::TryStreamExt;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+use xtra_libp2p::libp2p::multiaddr::Protocol;
+use xtra_libp2p::libp2p::transport::memory::Channel;
+use xtra_libp2p::libp2p::transport::ListenerEvent;
+use xtra_libp2p::libp2p::transport::MemoryTransport;
+use xtra_libp2p::libp2p::transport::TransportError;
+use xtra_libp2p::libp2p::Multiaddr;
+use xtra_libp2p::libp2p::Transport;
+
+/// Name of a node in the simulated network, e.g. `"maker"`.
+pub type Node = &'static str;
+
+/// Faults injected into every connection of the simulated network.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Faults {
+    /// Upper bound for the latency added to every write.
+    ///
+    /// The actual latency is drawn uniformly from `[0, max_latency]`.
+    pub max_latency: Duration,
+    /// Probability with which a write severs the connection.
+    pub drop_probability: f64,
+}
+
+impl Faults {
+    /// The same faults, except that connections are never dropped.
+    pub fn without_drops(self) -> Self {
+        Self {
+            drop_probability: 0.0,
+            ..self
+        }
+    }
+}
+
+/// An in-memory network between simulated nodes which allows for injecting faults.
+///
+/// Nodes talk to each other over [`SimulatedTransport`]s obtained through
+/// [`Network::transport`]. All randomness is drawn from a single RNG seeded on construction, which
+/// makes the injected faults reproducible as long as the nodes are polled in a deterministic order,
+/// i.e. on a single-threaded runtime.
+#[derive(Clone)]
+pub struct Network {
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    rng: StdRng,
+    faults: Faults,
+    partitions: HashSet<(Node, Node)>,
+    nodes: HashSet<Node>,
+    listeners: HashMap<Multiaddr, Node>,
+    links: Vec<Weak<Link>>,
+    bytes_written: u64,
+}
+
+impl Network {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                rng: StdRng::seed_from_u64(seed),
+                faults: Faults::default(),
+                partitions: HashSet::new(),
+                nodes: HashSet::new(),
+                listeners: HashMap::new(),
+                links: Vec::new(),
+                bytes_written: 0,
+            })),
+        }
+    }
+
+    /// Construct the transport for the given node, to be handed to an
+    /// [`Endpoint`](xtra_libp2p::Endpoint).
+    pub fn transport(&self, node: Node) -> Box<dyn Fn() -> SimulatedTransport + Send + 'static> {
+        self.lock().nodes.insert(node);
+        let network = self.clone();
+
+        Box::new(move || SimulatedTransport {
+            node,
+            network: network.clone(),
+            inner: MemoryTransport::default(),
+        })
+    }
+
+    /// Pick a fresh in-memory address for a node to listen on.
+    pub fn memory_address(&self) -> Multiaddr {
+        let port = self.lock().rng.gen_range(1, u64::MAX);
+
+        Multiaddr::empty().with(Protocol::Memory(port))
+    }
+
+    pub fn set_faults(&self, faults: Faults) {
+        self.lock().faults = faults;
+    }
+
+    /// Cut the network between two nodes.
+    ///
+    /// Existing connections between the nodes are severed and new ones are refused until the
+    /// partition is [healed](Network::heal).
+    pub fn partition(&self, a: Node, b: Node) {
+        let mut inner = self.lock();
+
+        inner.partitions.insert(ordered(a, b));
+
+        for link in inner.links.iter().filter_map(Weak::upgrade) {
+            if link.connects(a, b) {
+                link.sever();
+            }
+        }
+    }
+
+    pub fn heal(&self, a: Node, b: Node) {
+        self.lock().partitions.remove(&ordered(a, b));
+    }
+
+    /// Total number of bytes written to any connection of the network so far.
+    pub fn bytes_written(&self) -> u64 {
+        self.lock().bytes_written
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().expect("network lock not to be poisoned")
+    }
+
+    fn is_partitioned(&self, a: Node, b: Node) -> bool {
+        self.lock().partitions.contains(&ordered(a, b))
+    }
+
+    fn add_listener(&self, address: Multiaddr, node: Node) {
+        self.lock().listeners.insert(address, node);
+    }
+
+    fn listener(&self, address: &Multiaddr) -> Option<Node> {
+        let address = address
+            .iter()
+            .filter(|protocol| !matches!(protocol, Protocol::P2p(_)))
+            .collect::<Multiaddr>();
+
+        self.lock().listeners.get(&address).copied()
+    }
+
+    fn node(&self, name: &[u8]) -> Option<Node> {
+        self.lock()
+            .nodes
+            .iter()
+            .copied()
+            .find(|node| node.as_bytes() == name)
+    }
+
+    fn ensure_not_partitioned(&self, a: Node, b: Node) -> io::Result<()> {
+        if self.is_partitioned(a, b) {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("{a} is partitioned from {b}"),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn latency(&self) -> Duration {
+        let mut inner = self.lock();
+        let max_latency = inner.faults.max_latency.as_millis() as u64;
+
+        if max_latency == 0 {
+            return Duration::ZERO;
+        }
+
+        Duration::from_millis(inner.rng.gen_range(0, max_latency + 1))
+    }
+
+    fn should_drop(&self) -> bool {
+        let mut inner = self.lock();
+        let drop_probability = inner.faults.drop_probability;
+
+        drop_probability > 0.0 && inner.rng.gen_bool(drop_probability)
+    }
+
+    fn record_written(&self, n: usize) {
+        self.lock().bytes_written += n as u64;
+    }
+
+    fn connect(&self, local: Node, remote: Node, channel: Channel<Vec<u8>>) -> Connection {
+        let link = Arc::new(Link {
+            local,
+            remote,
+            severed: AtomicBool::new(false),
+            waker: AtomicWaker::new(),
+        });
+
+        let mut inner = self.lock();
+        inner.links.retain(|link| link.strong_count() > 0);
+        inner.links.push(Arc::downgrade(&link));
+
+        Connection {
+            channel,
+            link,
+            network: self.clone(),
+            write_state: WriteState::Idle,
+        }
+    }
+}
+
+impl fmt::Debug for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Network").finish_non_exhaustive()
+    }
+}
+
+fn ordered(a: Node, b: Node) -> (Node, Node) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// An in-memory [`Transport`] of a node in the simulated [`Network`].
+pub struct SimulatedTransport {
+    node: Node,
+    network: Network,
+    inner: MemoryTransport,
+}
+
+impl SimulatedTransport {
+    fn dial_simulated(
+        &mut self,
+        addr: Multiaddr,
+    ) -> Result<BoxFuture<'static, Result<Connection, io::Error>>, TransportError<io::Error>> {
+        let node = self.node;
+        let remote = self.network.listener(&addr).ok_or_else(|| {
+            TransportError::Other(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("No node is listening on {addr}"),
+            ))
+        })?;
+
+        self.network
+            .ensure_not_partitioned(node, remote)
+            .map_err(TransportError::Other)?;
+
+        let dial = self.inner.dial(addr).map_err(|e| e.map(to_io_error))?;
+        let latency = self.network.latency();
+        let network = self.network.clone();
+
+        let dial = async move {
+            tokio_extras::time::sleep_silent(latency).await;
+            let mut channel = dial.await.map_err(to_io_error)?;
+
+            // Introduce ourselves, so that the listener knows who is connecting to it
+            channel.write_all(&[node.len() as u8]).await?;
+            channel.write_all(node.as_bytes()).await?;
+
+            Ok(network.connect(node, remote, channel))
+        };
+
+        Ok(dial.boxed())
+    }
+}
+
+impl Transport for SimulatedTransport {
+    type Output = Connection;
+    type Error = io::Error;
+    #[allow(clippy::type_complexity)]
+    type Listener =
+        BoxStream<'static, Result<ListenerEvent<Self::ListenerUpgrade, Self::Error>, Self::Error>>;
+    type ListenerUpgrade = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+    type Dial = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn listen_on(&mut self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>>
+    where
+        Self: Sized,
+    {
+        let node = self.node;
+        let network = self.network.clone();
+
+        let listener = self
+            .inner
+            .listen_on(addr)
+            .map_err(|e| e.map(to_io_error))?
+            .map_err(to_io_error)
+            .map_ok(move |event| {
+                if let ListenerEvent::NewAddress(address) = &event {
+                    network.add_listener(address.clone(), node);
+                }
+
+                let network = network.clone();
+                event
+                    .map(move |upgrade| {
+                        upgrade
+                            .map_err(to_io_error)
+                            .and_then(move |channel| accept(network, node, channel))
+                            .boxed()
+                    })
+                    .map_err(to_io_error)
+            })
+            .boxed();
+
+        Ok(listener)
+    }
+
+    fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>>
+    where
+        Self: Sized,
+    {
+        self.dial_simulated(addr)
+    }
+
+    fn dial_as_listener(
+        &mut self,
+        addr: Multiaddr,
+    ) -> Result<Self::Dial, TransportError<Self::Error>>
+    where
+        Self: Sized,
+    {
+        self.dial_simulated(addr)
+    }
+
+    fn address_translation(&self, _: &Multiaddr, _: &Multiaddr) -> Option<Multiaddr> {
+        None
+    }
+}
+
+/// Accept an inbound connection once the dialer introduced itself.
+async fn accept(
+    network: Network,
+    node: Node,
+    mut channel: Channel<Vec<u8>>,
+) -> io::Result<Connection> {
+    let mut len = [0u8; 1];
+    channel.read_exact(&mut len).await?;
+    let mut name = vec![0u8; len[0] as usize];
+    channel.read_exact(&mut name).await?;
+
+    let remote = network.node(&name).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Inbound connection from unknown node",
+        )
+    })?;
+    network.ensure_not_partitioned(node, remote)?;
+
+    Ok(network.connect(node, remote, channel))
+}
+
+fn to_io_error<E>(e: E) -> io::Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+struct Link {
+    local: Node,
+    remote: Node,
+    severed: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl Link {
+    fn connects(&self, a: Node, b: Node) -> bool {
+        ordered(self.local, self.remote) == ordered(a, b)
+    }
+
+    fn sever(&self) {
+        self.severed.store(true, Ordering::SeqCst);
+        self.waker.wake();
+    }
+
+    fn poll_severed(&self, cx: &mut Context<'_>) -> io::Result<()> {
+        self.waker.register(cx.waker());
+
+        if self.severed.load(Ordering::SeqCst) {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionReset,
+                "Connection severed by simulated network",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+enum WriteState {
+    Idle,
+    Delaying(BoxFuture<'static, ()>),
+    Writing,
+}
+
+/// A connection between two nodes in the simulated [`Network`].
+pub struct Connection {
+    channel: Channel<Vec<u8>>,
+    link: Arc<Link>,
+    network: Network,
+    write_state: WriteState,
+}
+
+impl AsyncRead for Connection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if let Err(e) = this.link.poll_severed(cx) {
+            return Poll::Ready(Err(e));
+        }
+
+        Pin::new(&mut this.channel).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Connection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Err(e) = this.link.poll_severed(cx) {
+                return Poll::Ready(Err(e));
+            }
+
+            match &mut this.write_state {
+                WriteState::Idle => {
+                    if this.network.should_drop() {
+                        this.link.sever();
+                        continue;
+                    }
+
+                    let latency = this.network.latency();
+                    this.write_state =
+                        WriteState::Delaying(tokio_extras::time::sleep_silent(latency).boxed());
+                }
+                WriteState::Delaying(delay) => {
+                    futures::ready!(delay.poll_unpin(cx));
+                    this.write_state = WriteState::Writing;
+                }
+                WriteState::Writing => {
+                    let written = futures::ready!(Pin::new(&mut this.channel).poll_write(cx, buf));
+                    this.write_state = WriteState::Idle;
+
+                    if let Ok(n) = written {
+                        this.network.record_written(n);
+                    }
+
+                    return Poll::Ready(written);
+                }
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if let Err(e) = this.link.poll_severed(cx) {
+            return Poll::Ready(Err(e));
+        }
+
+        Pin::new(&mut this.channel).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().channel).poll_close(cx)
+    }
+}
@@ -17,6 +17,7 @@ use daemon_tests::Taker;
 use model::ContractSymbol;
 use model::Contracts;
 use model::Leverage;
+use model::PayoutGranularity;
 use model::Position;
 use otel_tests::otel_test;
 use rust_decimal::Decimal;
@@ -106,6 +107,7 @@ async fn collaboratively_close_an_open_cfd(
             initial_price: initial_price_for(contract_symbol),
             quantity: Contracts::new(100),
             taker_leverage: Leverage::TWO,
+            granularity: PayoutGranularity::default(),
         },
     )
     .await;
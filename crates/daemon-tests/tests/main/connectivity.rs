@@ -1,5 +1,6 @@
 use daemon::online_status::ConnectionStatus;
 use daemon_tests::flow::next_with;
+use daemon_tests::simulation::Simulation;
 use daemon_tests::Maker;
 use daemon_tests::MakerConfig;
 use daemon_tests::Taker;
@@ -25,6 +26,20 @@ async fn taker_notices_lack_of_maker() {
     wait_next_connection_status_to_maker(&mut taker, ConnectionStatus::Online).await;
 }
 
+#[tokio::test(start_paused = true)]
+async fn taker_reconnects_once_network_partition_heals() {
+    let mut simulation = Simulation::new(0);
+    let (_maker, mut taker) = simulation.start_both().await;
+
+    wait_next_connection_status_to_maker(&mut taker, ConnectionStatus::Online).await;
+
+    simulation.network.partition("maker", "taker");
+    wait_next_connection_status_to_maker(&mut taker, ConnectionStatus::Offline).await;
+
+    simulation.network.heal("maker", "taker");
+    wait_next_connection_status_to_maker(&mut taker, ConnectionStatus::Online).await;
+}
+
 /// Wait indefinitely until the `taker`'s connection status to the maker is the one `expected` by
 /// the caller.
 async fn wait_next_connection_status_to_maker(taker: &mut Taker, expected: ConnectionStatus) {
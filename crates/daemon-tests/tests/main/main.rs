@@ -6,3 +6,4 @@ mod offer;
 mod order;
 mod refund;
 mod rollover;
+mod simulation;
@@ -12,6 +12,7 @@ use model::Position;
 use otel_tests::otel_test;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
+use time::Duration;
 
 #[otel_test]
 async fn taker_receives_btc_usd_offer_from_maker_on_publication() {
@@ -32,6 +33,34 @@ async fn taker_can_receive_offers_with_different_symbols_from_maker() {
     test_offer(&mut maker, &mut taker, ContractSymbol::EthUsd).await;
 }
 
+#[otel_test]
+async fn taker_receives_offer_with_custom_settlement_interval() {
+    let (mut maker, mut taker) = start_both().await;
+    ensure_null_next_offers(taker.offers_feed()).await.unwrap();
+
+    let symbol = ContractSymbol::BtcUsd;
+    maker
+        .set_offer_params(
+            OfferParamsBuilder::new(symbol)
+                .settlement_interval(Duration::hours(8))
+                .build(),
+        )
+        .await;
+
+    let (published, received) =
+        next_maker_offers(maker.offers_feed(), taker.offers_feed(), &symbol)
+            .await
+            .unwrap();
+    assert_eq_offers(published.clone(), received.clone());
+
+    let eight_hours_in_secs = Duration::hours(8).whole_seconds() as u64;
+    let received_long = received.btcusd_long.unwrap();
+    assert_eq!(
+        received_long.settlement_time_interval_in_secs,
+        eight_hours_in_secs
+    );
+}
+
 async fn publish_offer(maker: &mut Maker, contract_symbol: ContractSymbol) {
     let leverage = Leverage::TWO;
     maker
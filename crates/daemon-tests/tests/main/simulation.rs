@@ -0,0 +1,30 @@
+use daemon_tests::simulation::Simulation;
+
+/// Number of randomized scenarios to run if `SIMULATION_RUNS` is not set.
+const DEFAULT_RUNS: u64 = 10;
+
+/// Run randomized maker/taker scenarios, each seeded by its index.
+///
+/// Set `SIMULATION_RUNS` to run more scenarios, or `SIMULATION_SEED` to replay a single one.
+#[tokio::test(start_paused = true)]
+async fn randomized_scenarios() {
+    let seeds = match env_var("SIMULATION_SEED") {
+        Some(seed) => seed..seed + 1,
+        None => 0..env_var("SIMULATION_RUNS").unwrap_or(DEFAULT_RUNS),
+    };
+
+    for seed in seeds {
+        let mut simulation = Simulation::new(seed);
+        simulation.run_random_scenario().await;
+    }
+}
+
+fn env_var(name: &str) -> Option<u64> {
+    let value = std::env::var(name).ok()?;
+
+    Some(
+        value
+            .parse()
+            .unwrap_or_else(|_| panic!("{name} must be a number, got {value}")),
+    )
+}
@@ -0,0 +1,167 @@
+//! End-to-end tests against a local regtest network.
+//!
+//! These tests build, sign and broadcast real transactions and therefore need `bitcoind` and
+//! `electrs` to be installed. Point `BITCOIND_EXE` and `ELECTRS_EXE` at the binaries if they are
+//! not on the `PATH` and run the tests with `cargo test -p daemon-tests --test regtest --
+//! --ignored`.
+
+use daemon::projection::CfdState;
+use daemon_tests::flow::next_with;
+use daemon_tests::flow::one_cfd_with_state;
+use daemon_tests::regtest::initial_price;
+use daemon_tests::regtest::mock_quotes;
+use daemon_tests::regtest::open_cfd;
+use daemon_tests::regtest::Maker;
+use daemon_tests::regtest::Regtest;
+use daemon_tests::regtest::Taker;
+use daemon_tests::wait_next_state;
+use model::Contracts;
+use model::OrderId;
+use model::Position;
+use model::CET_TIMELOCK;
+use otel_tests::otel_test;
+
+/// Confirmations after which the monitor considers a closing transaction final.
+const CLOSE_FINALITY_CONFIRMATIONS: u32 = 3;
+
+#[otel_test]
+#[ignore = "requires bitcoind and electrs"]
+async fn rollover_an_open_cfd() {
+    let regtest = Regtest::start().await.unwrap();
+    let (mut maker, mut taker, order_id) = start_and_open_cfd(&regtest).await;
+    let commit_txid_before_rollover = taker.latest_dlc().commit.0.txid();
+
+    taker.trigger_rollover(order_id).await.unwrap();
+    wait_next_state!(order_id, maker, taker, CfdState::RolloverSetup);
+    wait_next_state!(order_id, maker, taker, CfdState::Open);
+
+    assert_ne!(
+        taker.latest_dlc().commit.0.txid(),
+        commit_txid_before_rollover
+    );
+    assert_eq!(
+        taker.latest_dlc().commit.0.txid(),
+        maker.latest_dlc().commit.0.txid()
+    );
+}
+
+#[otel_test]
+#[ignore = "requires bitcoind and electrs"]
+async fn collaboratively_settle_an_open_cfd() {
+    let regtest = Regtest::start().await.unwrap();
+    let (mut maker, mut taker, order_id) = start_and_open_cfd(&regtest).await;
+    mock_quotes(&mut maker, &mut taker).await.unwrap();
+
+    taker.system.propose_settlement(order_id).await.unwrap();
+    wait_next_state!(
+        order_id,
+        maker,
+        taker,
+        CfdState::IncomingSettlementProposal,
+        CfdState::OutgoingSettlementProposal
+    );
+
+    maker.system.accept_settlement(order_id).await.unwrap();
+    wait_next_state!(order_id, maker, taker, CfdState::PendingClose);
+
+    regtest.mine(CLOSE_FINALITY_CONFIRMATIONS).await.unwrap();
+    wait_next_state!(order_id, maker, taker, CfdState::Closed);
+}
+
+#[otel_test]
+#[ignore = "requires bitcoind and electrs"]
+async fn force_close_an_open_cfd_with_the_attested_cet() {
+    let regtest = Regtest::start().await.unwrap();
+    let (mut maker, mut taker, order_id) = start_and_open_cfd(&regtest).await;
+
+    commit(&regtest, &mut maker, &mut taker, order_id).await;
+
+    regtest.mine(CET_TIMELOCK).await.unwrap();
+
+    let event_id = taker.latest_dlc().settlement_event_id;
+    let price = initial_price().to_u64();
+    maker
+        .attestor
+        .attest(order_id, event_id, price)
+        .await
+        .unwrap();
+    taker
+        .attestor
+        .attest(order_id, event_id, price)
+        .await
+        .unwrap();
+    wait_next_state!(order_id, maker, taker, CfdState::PendingCet);
+
+    regtest.mine(CLOSE_FINALITY_CONFIRMATIONS).await.unwrap();
+    wait_next_state!(order_id, maker, taker, CfdState::Closed);
+}
+
+#[otel_test]
+#[ignore = "requires bitcoind and electrs"]
+async fn refund_an_open_cfd_after_the_refund_timelock() {
+    let regtest = Regtest::start().await.unwrap();
+    let (mut maker, mut taker, order_id) = start_and_open_cfd(&regtest).await;
+
+    commit(&regtest, &mut maker, &mut taker, order_id).await;
+
+    let refund_timelock = taker.latest_dlc().refund_timelock;
+    regtest.mine(refund_timelock).await.unwrap();
+    wait_next_state!(order_id, maker, taker, CfdState::PendingRefund);
+
+    regtest.mine(CLOSE_FINALITY_CONFIRMATIONS).await.unwrap();
+    wait_next_state!(order_id, maker, taker, CfdState::Refunded);
+}
+
+#[otel_test]
+#[ignore = "requires bitcoind and electrs"]
+async fn publishing_a_revoked_commit_transaction_is_detected() {
+    let regtest = Regtest::start().await.unwrap();
+    let (mut maker, mut taker, order_id) = start_and_open_cfd(&regtest).await;
+    let revoked_dlc = maker.latest_dlc();
+
+    taker.trigger_rollover(order_id).await.unwrap();
+    wait_next_state!(order_id, maker, taker, CfdState::RolloverSetup);
+    wait_next_state!(order_id, maker, taker, CfdState::Open);
+
+    // The maker cheats by publishing the commit transaction revoked during rollover
+    let revoked_commit_tx = revoked_dlc.signed_commit_tx().unwrap();
+    regtest
+        .bitcoind
+        .broadcast(&revoked_commit_tx)
+        .await
+        .unwrap();
+    regtest.mine(1).await.unwrap();
+
+    // Punishing is not implemented yet, detecting the revoked commit transaction moves the CFD
+    // into the committed state
+    wait_next_state!(order_id, maker, taker, CfdState::OpenCommitted);
+}
+
+async fn start_and_open_cfd(regtest: &Regtest) -> (Maker, Taker, OrderId) {
+    let (mut maker, mut taker) = regtest.start_both().await.unwrap();
+
+    let order_id = open_cfd(
+        regtest,
+        &mut maker,
+        &mut taker,
+        Position::Short,
+        Contracts::new(100),
+    )
+    .await
+    .unwrap();
+
+    (maker, taker, order_id)
+}
+
+/// Publish the commit transaction of the taker and wait for it to be confirmed.
+async fn commit(regtest: &Regtest, maker: &mut Maker, taker: &mut Taker, order_id: OrderId) {
+    taker.system.commit(order_id).await.unwrap();
+    next_with(taker.cfd_feed(), |maybe_cfds| {
+        maybe_cfds.and_then(one_cfd_with_state(CfdState::PendingCommit))
+    })
+    .await
+    .unwrap();
+
+    regtest.mine(1).await.unwrap();
+    wait_next_state!(order_id, maker, taker, CfdState::OpenCommitted);
+}
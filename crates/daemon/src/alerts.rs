@@ -0,0 +1,610 @@
+//! Warnings about open positions of the taker before they are closed without its involvement.
+//!
+//! A position is liquidated through the oracle once the price reaches its liquidation price, and a
+//! perpetual position that is not rolled over before its settlement event, e.g. because the maker
+//! is offline, is settled at the price of that event. Both are reported ahead of time in the feed
+//! and, optionally, pushed to notification sinks.
+//!
+//! Every alert is pushed only once per CFD and distance, respectively once per settlement event,
+//! such that a price oscillating around a threshold does not flood the sinks. Which alerts were
+//! pushed is only kept in memory: alerts that are still active when the taker is restarted are
+//! pushed again.
+
+use crate::notifications;
+use crate::online_status::ConnectionStatus;
+use crate::projection;
+use crate::projection::CfdState;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use model::ContractSymbol;
+use model::ContractType;
+use model::OrderId;
+use model::Position;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::watch;
+use xtra_productivity::xtra_productivity;
+use xtras::SendAsyncNext;
+use xtras::SendInterval;
+
+/// Interval at which the time to the settlement event of open CFDs is checked.
+const CHECK_EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Timeout for running the alert command.
+const NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Distance of the price to the liquidation price, relative to the price.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(transparent)]
+pub struct Distance(Decimal);
+
+impl Distance {
+    /// Construct a distance from a percentage, e.g. 10 for 10%.
+    pub fn from_percent(percent: Decimal) -> Result<Self> {
+        if percent <= Decimal::ZERO || percent >= Decimal::ONE_HUNDRED {
+            bail!("Distance must be between 0% and 100%, got {percent}%");
+        }
+
+        Ok(Self(percent))
+    }
+
+    pub fn percent(&self) -> Decimal {
+        self.0
+    }
+}
+
+impl FromStr for Distance {
+    type Err = anyhow::Error;
+
+    /// Parses a percentage with or without the percent sign, e.g. "10%" or "2.5"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let percent = s
+            .trim()
+            .trim_end_matches('%')
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid distance: {s}"))?;
+
+        Self::from_percent(percent)
+    }
+}
+
+impl fmt::Display for Distance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Warn once the price comes closer to the liquidation price than any of these distances
+    pub liquidation_distances: Vec<Distance>,
+    /// Warn if the maker is offline this long before the settlement event of a perpetual CFD
+    pub maker_offline_before_expiry: time::Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Alert {
+    /// The price is within `distance` of the liquidation price of the CFD
+    ///
+    /// `price` is the price at which the alert was raised, it is not updated while the alert is
+    /// active.
+    Liquidation {
+        order_id: OrderId,
+        contract_symbol: ContractSymbol,
+        position: Position,
+        price: Decimal,
+        liquidation_price: Decimal,
+        distance: Distance,
+    },
+    /// The maker is offline shortly before the CFD settles at its settlement event
+    MakerOffline {
+        order_id: OrderId,
+        contract_symbol: ContractSymbol,
+        #[serde(with = "time::serde::timestamp")]
+        expiry_timestamp: OffsetDateTime,
+    },
+}
+
+impl Alert {
+    fn order_id(&self) -> OrderId {
+        match self {
+            Alert::Liquidation { order_id, .. } | Alert::MakerOffline { order_id, .. } => *order_id,
+        }
+    }
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Alert::Liquidation {
+                order_id,
+                contract_symbol,
+                price,
+                liquidation_price,
+                distance,
+                ..
+            } => write!(
+                f,
+                "{contract_symbol} CFD {order_id} is within {distance} of its liquidation price {liquidation_price} at price {price}"
+            ),
+            Alert::MakerOffline {
+                order_id,
+                contract_symbol,
+                expiry_timestamp,
+            } => write!(
+                f,
+                "Maker is offline and {contract_symbol} CFD {order_id} settles at {expiry_timestamp} unless it is rolled over"
+            ),
+        }
+    }
+}
+
+/// Compute the alerts for the open CFDs.
+///
+/// Only the smallest distance the price is within is reported per CFD.
+pub fn alerts(
+    cfds: &[projection::Cfd],
+    quotes: &projection::LatestQuotes,
+    maker_status: ConnectionStatus,
+    config: &Config,
+    now: OffsetDateTime,
+) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+
+    for cfd in cfds.iter().filter(|cfd| is_open(cfd.state)) {
+        let liquidation_alert = quotes.get(&cfd.contract_symbol).and_then(|quote| {
+            let price = quote.mid();
+            let distance = liquidation_distance(cfd.position, price, cfd.liquidation_price);
+
+            let threshold = config
+                .liquidation_distances
+                .iter()
+                .filter(|threshold| distance <= threshold.0)
+                .min()?;
+
+            Some(Alert::Liquidation {
+                order_id: cfd.order_id,
+                contract_symbol: cfd.contract_symbol,
+                position: cfd.position,
+                price,
+                liquidation_price: cfd.liquidation_price,
+                distance: *threshold,
+            })
+        });
+        alerts.extend(liquidation_alert);
+
+        if let Some(expiry_timestamp) = cfd.expiry_timestamp {
+            let expires_soon = expiry_timestamp > now
+                && expiry_timestamp - now <= config.maker_offline_before_expiry;
+
+            if maker_status == ConnectionStatus::Offline
+                && cfd.contract_type == Some(ContractType::Perpetual)
+                && cfd.state == CfdState::Open
+                && expires_soon
+            {
+                alerts.push(Alert::MakerOffline {
+                    order_id: cfd.order_id,
+                    contract_symbol: cfd.contract_symbol,
+                    expiry_timestamp,
+                });
+            }
+        }
+    }
+
+    alerts
+}
+
+/// Whether the CFD can still be liquidated or settled at its settlement event
+fn is_open(state: CfdState) -> bool {
+    matches!(
+        state,
+        CfdState::PendingOpen
+            | CfdState::Open
+            | CfdState::IncomingSettlementProposal
+            | CfdState::OutgoingSettlementProposal
+            | CfdState::RolloverSetup
+            | CfdState::PendingCommit
+            | CfdState::OpenCommitted
+    )
+}
+
+/// Distance of `price` to `liquidation_price` in percent of `price`
+///
+/// Zero if the price already crossed the liquidation price.
+fn liquidation_distance(position: Position, price: Decimal, liquidation_price: Decimal) -> Decimal {
+    if price <= Decimal::ZERO {
+        return Decimal::ZERO;
+    }
+
+    let distance = match position {
+        Position::Long => price - liquidation_price,
+        Position::Short => liquidation_price - price,
+    };
+
+    (distance.max(Decimal::ZERO) / price) * Decimal::ONE_HUNDRED
+}
+
+/// A destination alerts are pushed to.
+#[async_trait]
+pub trait NotificationSink: Send + Sync + 'static {
+    async fn notify(&self, alert: &Alert) -> Result<()>;
+}
+
+/// Queues alerts as JSON in the notifications outbox, to be posted to the notification webhooks.
+pub struct Webhook {
+    outbox: notifications::Outbox,
+}
+
+impl Webhook {
+    pub fn new(outbox: notifications::Outbox) -> Self {
+        Self { outbox }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for Webhook {
+    async fn notify(&self, alert: &Alert) -> Result<()> {
+        let alert = serde_json::to_string(alert)?;
+
+        self.outbox
+            .queue(&alert)
+            .await
+            .context("Failed to queue alert")?;
+
+        Ok(())
+    }
+}
+
+/// Runs a local command with the alert as JSON in its only argument.
+pub struct Command {
+    program: PathBuf,
+}
+
+impl Command {
+    pub fn new(program: PathBuf) -> Self {
+        Self { program }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for Command {
+    async fn notify(&self, alert: &Alert) -> Result<()> {
+        let alert = serde_json::to_string(alert)?;
+
+        let output = tokio_extras::time::timeout(
+            NOTIFICATION_TIMEOUT,
+            tokio::process::Command::new(&self.program)
+                .arg(alert)
+                .kill_on_drop(true)
+                .output(),
+            || tracing::debug_span!("run alert command"),
+        )
+        .await
+        .context("Alert command timed out")?
+        .with_context(|| format!("Failed to run {}", self.program.display()))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!(
+                "{} exited with {}: {stderr}",
+                self.program.display(),
+                output.status
+            );
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Actor {
+    config: Config,
+    rx_cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+    rx_quote: watch::Receiver<projection::LatestQuotes>,
+    rx_maker_status: watch::Receiver<ConnectionStatus>,
+    sinks: Arc<Vec<Box<dyn NotificationSink>>>,
+    /// Alerts which were already pushed to the sinks since the actor was started
+    notified: HashSet<(OrderId, AlertKey)>,
+    feed: watch::Sender<Vec<Alert>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum AlertKey {
+    Liquidation(Distance),
+    MakerOffline(OffsetDateTime),
+}
+
+impl From<&Alert> for AlertKey {
+    fn from(alert: &Alert) -> Self {
+        match alert {
+            Alert::Liquidation { distance, .. } => AlertKey::Liquidation(*distance),
+            Alert::MakerOffline {
+                expiry_timestamp, ..
+            } => AlertKey::MakerOffline(*expiry_timestamp),
+        }
+    }
+}
+
+impl Actor {
+    pub fn new(
+        config: Config,
+        rx_cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+        rx_quote: watch::Receiver<projection::LatestQuotes>,
+        rx_maker_status: watch::Receiver<ConnectionStatus>,
+        sinks: Vec<Box<dyn NotificationSink>>,
+    ) -> (Self, watch::Receiver<Vec<Alert>>) {
+        let (feed, receiver) = watch::channel(Vec::new());
+
+        let actor = Self {
+            config,
+            rx_cfds,
+            rx_quote,
+            rx_maker_status,
+            sinks: Arc::new(sinks),
+            notified: HashSet::new(),
+            feed,
+        };
+
+        (actor, receiver)
+    }
+
+    /// Record the alerts and return those which were not pushed yet.
+    ///
+    /// Alerts of CFDs which are no longer open are forgotten.
+    fn new_alerts(&mut self, alerts: &[Alert], open: &HashSet<OrderId>) -> Vec<Alert> {
+        self.notified
+            .retain(|(order_id, _)| open.contains(order_id));
+
+        alerts
+            .iter()
+            .filter(|alert| {
+                self.notified
+                    .insert((alert.order_id(), AlertKey::from(*alert)))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Publish the alerts in the feed, returning whether they changed.
+    ///
+    /// Only a change of the alerts is published, not every change of the price.
+    fn publish(&self, alerts: Vec<Alert>) -> bool {
+        self.feed.send_if_modified(|current| {
+            if keys(current) == keys(&alerts) {
+                return false;
+            }
+
+            *current = alerts;
+            true
+        })
+    }
+}
+
+fn keys(alerts: &[Alert]) -> Vec<(OrderId, AlertKey)> {
+    alerts
+        .iter()
+        .map(|alert| (alert.order_id(), AlertKey::from(alert)))
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+struct Update;
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we are alive");
+
+        let mut rx_cfds = self.rx_cfds.clone();
+        let mut rx_quote = self.rx_quote.clone();
+        let mut rx_maker_status = self.rx_maker_status.clone();
+        let notify = {
+            let this = this.clone();
+            async move {
+                loop {
+                    tokio::select! {
+                        Ok(()) = rx_cfds.changed() => {},
+                        Ok(()) = rx_quote.changed() => {},
+                        Ok(()) = rx_maker_status.changed() => {},
+                        else => return,
+                    }
+
+                    if this.send(Update).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        };
+
+        tokio_extras::spawn(&this.clone(), notify);
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(CHECK_EXPIRY_INTERVAL, || Update, xtras::IncludeSpan::Never),
+        );
+        this.send_async_next(Update).await;
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: Update, ctx: &mut xtra::Context<Self>) {
+        let (alerts, open) = {
+            let cfds = self.rx_cfds.borrow();
+            let cfds = cfds.as_deref().unwrap_or_default();
+            let quotes = self.rx_quote.borrow();
+            let maker_status = *self.rx_maker_status.borrow();
+
+            let alerts = alerts(
+                cfds,
+                &quotes,
+                maker_status,
+                &self.config,
+                tokio_extras::time::now_utc(),
+            );
+            let open = cfds
+                .iter()
+                .filter(|cfd| is_open(cfd.state))
+                .map(|cfd| cfd.order_id)
+                .collect::<HashSet<_>>();
+
+            (alerts, open)
+        };
+
+        let new_alerts = self.new_alerts(&alerts, &open);
+        self.publish(alerts);
+
+        if new_alerts.is_empty() {
+            return;
+        }
+
+        for alert in new_alerts.iter() {
+            tracing::warn!(order_id = %alert.order_id(), "{alert}");
+        }
+
+        let this = ctx.address().expect("we are alive");
+        let sinks = self.sinks.clone();
+        tokio_extras::spawn(&this, async move {
+            for alert in new_alerts.iter() {
+                for sink in sinks.iter() {
+                    if let Err(e) = sink.notify(alert).await {
+                        tracing::warn!(order_id = %alert.order_id(), "Failed to push alert: {e:#}");
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn distance_parses_percentages() {
+        assert_eq!(
+            Distance::from_str("10%").unwrap(),
+            Distance::from_percent(dec!(10)).unwrap()
+        );
+        assert_eq!(
+            Distance::from_str("2.5").unwrap(),
+            Distance::from_percent(dec!(2.5)).unwrap()
+        );
+        assert!(Distance::from_str("0").is_err());
+        assert!(Distance::from_str("100%").is_err());
+        assert!(Distance::from_str("ten").is_err());
+    }
+
+    #[test]
+    fn liquidation_distance_is_relative_to_price() {
+        assert_eq!(
+            liquidation_distance(Position::Long, dec!(20_000), dec!(18_000)),
+            dec!(10)
+        );
+        assert_eq!(
+            liquidation_distance(Position::Short, dec!(20_000), dec!(21_000)),
+            dec!(5)
+        );
+    }
+
+    #[test]
+    fn liquidation_distance_is_zero_once_liquidation_price_is_crossed() {
+        assert_eq!(
+            liquidation_distance(Position::Long, dec!(17_000), dec!(18_000)),
+            Decimal::ZERO
+        );
+        assert_eq!(
+            liquidation_distance(Position::Short, dec!(22_000), dec!(21_000)),
+            Decimal::ZERO
+        );
+    }
+
+    #[test]
+    fn given_alert_was_pushed_when_computed_again_then_it_is_not_new() {
+        let (mut actor, _) = dummy_actor();
+        let alert = dummy_liquidation_alert(dec!(10));
+        let open = HashSet::from([alert.order_id()]);
+
+        assert_eq!(
+            actor.new_alerts(&[alert.clone()], &open),
+            vec![alert.clone()]
+        );
+        assert_eq!(actor.new_alerts(&[], &open), vec![]);
+        assert_eq!(actor.new_alerts(&[alert], &open), vec![]);
+    }
+
+    #[test]
+    fn given_alert_was_pushed_when_distance_decreases_then_alert_is_new() {
+        let (mut actor, _) = dummy_actor();
+        let alert_10 = dummy_liquidation_alert(dec!(10));
+        let alert_5 = dummy_liquidation_alert(dec!(5));
+
+        let open = HashSet::from([alert_10.order_id()]);
+
+        actor.new_alerts(&[alert_10.clone()], &open);
+        assert_eq!(actor.new_alerts(&[alert_5.clone()], &open), vec![alert_5]);
+        assert_eq!(actor.new_alerts(&[alert_10], &open), vec![]);
+    }
+
+    #[test]
+    fn given_cfd_was_closed_when_computing_alerts_then_its_alerts_are_forgotten() {
+        let (mut actor, _) = dummy_actor();
+        let alert = dummy_liquidation_alert(dec!(10));
+
+        actor.new_alerts(&[alert.clone()], &HashSet::from([alert.order_id()]));
+        actor.new_alerts(&[], &HashSet::new());
+
+        assert!(actor.notified.is_empty());
+    }
+
+    #[test]
+    fn given_alert_in_feed_when_only_price_changes_then_feed_is_not_updated() {
+        let (actor, _feed) = dummy_actor();
+        let alert = dummy_liquidation_alert(dec!(10));
+        let mut alert_at_other_price = alert.clone();
+        if let Alert::Liquidation { price, .. } = &mut alert_at_other_price {
+            *price = dec!(19_900);
+        }
+
+        assert!(actor.publish(vec![alert]));
+        assert!(!actor.publish(vec![alert_at_other_price]));
+        assert!(actor.publish(vec![]));
+    }
+
+    fn dummy_actor() -> (Actor, watch::Receiver<Vec<Alert>>) {
+        Actor::new(
+            Config {
+                liquidation_distances: vec![],
+                maker_offline_before_expiry: time::Duration::hours(1),
+            },
+            watch::channel(None).1,
+            watch::channel(projection::LatestQuotes::default()).1,
+            watch::channel(ConnectionStatus::Online).1,
+            vec![],
+        )
+    }
+
+    fn dummy_liquidation_alert(distance: Decimal) -> Alert {
+        Alert::Liquidation {
+            order_id: OrderId::from(uuid::Uuid::nil()),
+            contract_symbol: ContractSymbol::BtcUsd,
+            position: Position::Long,
+            price: dec!(20_000),
+            liquidation_price: dec!(18_000),
+            distance: Distance::from_percent(distance).unwrap(),
+        }
+    }
+}
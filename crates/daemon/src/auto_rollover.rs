@@ -11,7 +11,6 @@ use model::OrderId;
 use rollover::taker::ProposeRollover;
 use sqlite_db;
 use std::time::Duration;
-use time::OffsetDateTime;
 use xtra::Address;
 use xtra_productivity::xtra_productivity;
 use xtras::SendAsyncNext;
@@ -99,7 +98,7 @@ impl Actor {
             let order_id = cfd.id();
             let maker_peer_id = cfd.counterparty_peer_id();
 
-            match cfd.can_auto_rollover_taker(OffsetDateTime::now_utc()) {
+            match cfd.can_auto_rollover_taker(tokio_extras::time::now_utc()) {
                 Ok((from_commit_txid, from_settlement_event_id)) => {
                     this.send_async_next(Rollover {
                         order_id,
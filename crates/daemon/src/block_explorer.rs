@@ -0,0 +1,163 @@
+use anyhow::bail;
+use anyhow::Result;
+use bdk::bitcoin::Network;
+use bdk::bitcoin::Txid;
+
+const TXID: &str = "{txid}";
+const VOUT: &str = "{vout}";
+
+/// The block explorer transactions are linked to
+///
+/// Links are built from URL templates in which `{txid}` is replaced with the transaction ID. To
+/// highlight a particular output, a second template may additionally use `{vout}` for the output
+/// index. Without an output template, outputs are linked like their transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockExplorer {
+    /// Don't link transactions, e.g. for privacy reasons or on regtest
+    None,
+    Templates {
+        tx: String,
+        output: Option<String>,
+    },
+}
+
+impl BlockExplorer {
+    /// Configure the block explorer from the URL templates
+    ///
+    /// A transaction template of `none` disables links.
+    pub fn new(tx: &str, output: Option<&str>) -> Result<Self> {
+        if tx.eq_ignore_ascii_case("none") {
+            if output.is_some() {
+                bail!("Cannot configure an output URL template without block explorer");
+            }
+
+            return Ok(Self::None);
+        }
+
+        if !tx.contains(TXID) {
+            bail!("Transaction URL template {tx} does not contain {TXID}");
+        }
+
+        if let Some(output) = output {
+            if !output.contains(TXID) || !output.contains(VOUT) {
+                bail!("Output URL template {output} does not contain {TXID} and {VOUT}");
+            }
+        }
+
+        Ok(Self::Templates {
+            tx: tx.to_owned(),
+            output: output.map(str::to_owned),
+        })
+    }
+
+    /// The mempool.space explorer of the network, no links on regtest
+    pub fn default_for(network: Network) -> Self {
+        let base = match network {
+            Network::Bitcoin => "https://mempool.space",
+            Network::Testnet => "https://mempool.space/testnet",
+            Network::Signet => "https://mempool.space/signet",
+            Network::Regtest => return Self::None,
+        };
+
+        Self::Templates {
+            tx: format!("{base}/tx/{TXID}"),
+            output: Some(format!("{base}/tx/{TXID}:{VOUT}")),
+        }
+    }
+
+    /// Link to the transaction, if any
+    pub fn tx_url(&self, txid: Txid) -> Option<String> {
+        match self {
+            BlockExplorer::None => None,
+            BlockExplorer::Templates { tx, .. } => Some(tx.replace(TXID, &txid.to_string())),
+        }
+    }
+
+    /// Link to a particular output of the transaction, if any
+    pub fn output_url(&self, txid: Txid, vout: u32) -> Option<String> {
+        match self {
+            BlockExplorer::None => None,
+            BlockExplorer::Templates {
+                output: Some(output),
+                ..
+            } => Some(
+                output
+                    .replace(TXID, &txid.to_string())
+                    .replace(VOUT, &vout.to_string()),
+            ),
+            BlockExplorer::Templates { output: None, .. } => self.tx_url(txid),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn txid() -> Txid {
+        Txid::from_str("fdd8ac4f2c2e9c7ba0d4c9d3b4f6b3a8e1d6d2b1a1f6c6e0b2b3f3c4d5e6f7a8").unwrap()
+    }
+
+    #[test]
+    fn default_explorer_links_to_mempool_space_except_on_regtest() {
+        let txid = txid();
+
+        assert_eq!(
+            BlockExplorer::default_for(Network::Testnet).tx_url(txid),
+            Some(format!("https://mempool.space/testnet/tx/{txid}"))
+        );
+        assert_eq!(
+            BlockExplorer::default_for(Network::Bitcoin).output_url(txid, 1),
+            Some(format!("https://mempool.space/tx/{txid}:1"))
+        );
+        assert_eq!(
+            BlockExplorer::default_for(Network::Regtest).tx_url(txid),
+            None
+        );
+    }
+
+    #[test]
+    fn custom_templates_are_filled_in() {
+        let txid = txid();
+        let explorer = BlockExplorer::new(
+            "https://explorer.example/tx/{txid}",
+            Some("https://explorer.example/tx/{txid}?output={vout}"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            explorer.tx_url(txid),
+            Some(format!("https://explorer.example/tx/{txid}"))
+        );
+        assert_eq!(
+            explorer.output_url(txid, 2),
+            Some(format!("https://explorer.example/tx/{txid}?output=2"))
+        );
+    }
+
+    #[test]
+    fn outputs_are_linked_like_transactions_without_output_template() {
+        let txid = txid();
+        let explorer = BlockExplorer::new("https://explorer.example/tx/{txid}", None).unwrap();
+
+        assert_eq!(explorer.output_url(txid, 2), explorer.tx_url(txid));
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        assert!(BlockExplorer::new("https://explorer.example/tx", None).is_err());
+        assert!(BlockExplorer::new(
+            "https://explorer.example/tx/{txid}",
+            Some("https://explorer.example/tx/{txid}")
+        )
+        .is_err());
+        assert!(
+            BlockExplorer::new("none", Some("https://explorer.example/{txid}/{vout}")).is_err()
+        );
+        assert_eq!(
+            BlockExplorer::new("none", None).unwrap(),
+            BlockExplorer::None
+        );
+    }
+}
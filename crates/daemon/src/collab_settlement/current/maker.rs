@@ -35,15 +35,13 @@ type ListenerConnection = (
 pub struct Actor {
     pending_protocols: HashMap<OrderId, ListenerConnection>,
     executor: command::Executor,
-    n_payouts: usize,
 }
 
 impl Actor {
-    pub fn new(executor: command::Executor, n_payouts: usize) -> Self {
+    pub fn new(executor: command::Executor) -> Self {
         Self {
             pending_protocols: HashMap::default(),
             executor,
-            n_payouts,
         }
     }
 }
@@ -105,11 +103,7 @@ impl Actor {
             .executor
             .execute(order_id, |cfd| {
                 cfd.verify_counterparty_peer_id(&peer_id.into())?;
-                cfd.start_collab_settlement_maker_olivia_max(
-                    propose.price,
-                    self.n_payouts,
-                    &propose.unsigned_tx,
-                )
+                cfd.start_collab_settlement_maker_olivia_max(propose.price, &propose.unsigned_tx)
             })
             .await
             .context("Failed to start collab settlement protocol");
@@ -2,7 +2,7 @@ use std::time::Duration;
 
 use crate::bitcoin::secp256k1::ecdsa::Signature;
 use crate::bitcoin::Transaction;
-use crate::collab_settlement::PROTOCOL;
+use crate::collab_settlement::DIALER_PROTOCOLS;
 use crate::command;
 use anyhow::anyhow;
 use anyhow::Context;
@@ -37,8 +37,8 @@ pub async fn dialer(
     counterparty: PeerId,
     collab_settlement_tx: SettlementTransaction,
 ) -> Result<CollaborativeSettlement, DialerFailed> {
-    let substream = endpoint
-        .send(OpenSubstream::single_protocol(counterparty, PROTOCOL))
+    let (_, substream) = endpoint
+        .send(OpenSubstream::versioned(counterparty, DIALER_PROTOCOLS))
         .await
         .context("Endpoint is disconnected")?
         .context("No connection to peer")?
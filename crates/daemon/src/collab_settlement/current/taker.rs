@@ -14,16 +14,11 @@ use xtra_productivity::xtra_productivity;
 pub struct Actor {
     endpoint: Address<Endpoint>,
     executor: command::Executor,
-    n_payouts: usize,
 }
 
 impl Actor {
-    pub fn new(endpoint: Address<Endpoint>, executor: command::Executor, n_payouts: usize) -> Self {
-        Self {
-            endpoint,
-            executor,
-            n_payouts,
-        }
+    pub fn new(endpoint: Address<Endpoint>, executor: command::Executor) -> Self {
+        Self { endpoint, executor }
     }
 }
 
@@ -52,9 +47,7 @@ impl Actor {
 
         let (collab_settlement_tx, _) = self
             .executor
-            .execute(order_id, |cfd| {
-                cfd.start_collab_settlement_taker(price, self.n_payouts)
-            })
+            .execute(order_id, |cfd| cfd.start_collab_settlement_taker(price))
             .await
             .context("could not start closing position")?;
 
@@ -37,16 +37,14 @@ pub struct Actor {
     protocol_tasks: HashMap<OrderId, Tasks>,
     pending_protocols: HashMap<OrderId, ListenerConnection>,
     executor: command::Executor,
-    n_payouts: usize,
 }
 
 impl Actor {
-    pub fn new(executor: command::Executor, n_payouts: usize) -> Self {
+    pub fn new(executor: command::Executor) -> Self {
         Self {
             protocol_tasks: HashMap::default(),
             pending_protocols: HashMap::default(),
             executor,
-            n_payouts,
         }
     }
 }
@@ -110,7 +108,6 @@ impl Actor {
                 cfd.verify_counterparty_peer_id(&peer_id.into())?;
                 cfd.start_collab_settlement_maker_double_initial(
                     propose.price,
-                    self.n_payouts,
                     &propose.unsigned_tx,
                 )
             })
@@ -5,6 +5,7 @@ use async_trait::async_trait;
 use std::collections::HashMap;
 use tokio::sync::watch;
 use tokio_extras::spawn_fallible;
+use xtra::message_channel::MessageChannel;
 use xtra::Address;
 use xtra::Context;
 use xtra_libp2p::endpoint;
@@ -13,11 +14,20 @@ use xtra_libp2p::libp2p::PeerId;
 use xtra_libp2p::Endpoint;
 use xtra_libp2p::OpenSubstream;
 use xtra_productivity::xtra_productivity;
+use xtras::SendAsyncNext;
 
 pub struct Actor {
     endpoint: Address<Endpoint>,
     peer_infos: HashMap<PeerId, PeerInfo>,
     peer_info_channel: Option<watch::Sender<Option<PeerInfo>>>,
+    peer_identified_subscriber: Option<MessageChannel<PeerIdentified, ()>>,
+}
+
+/// Notification about the peer info received from a peer
+#[derive(Debug, Clone)]
+pub struct PeerIdentified {
+    pub peer_id: PeerId,
+    pub peer_info: PeerInfo,
 }
 
 impl Actor {
@@ -28,6 +38,18 @@ impl Actor {
             endpoint,
             peer_infos: HashMap::default(),
             peer_info_channel: None,
+            peer_identified_subscriber: None,
+        }
+    }
+
+    /// Notify the subscriber about the peer info of every peer that connects
+    pub fn new_with_peer_identified_subscriber(
+        endpoint: Address<Endpoint>,
+        subscriber: MessageChannel<PeerIdentified, ()>,
+    ) -> Self {
+        Self {
+            peer_identified_subscriber: Some(subscriber),
+            ..Self::new(endpoint)
         }
     }
 
@@ -43,6 +65,7 @@ impl Actor {
                 endpoint,
                 peer_infos: HashMap::default(),
                 peer_info_channel: Some(sender),
+                peer_identified_subscriber: None,
             },
             receiver,
         )
@@ -98,6 +121,15 @@ impl Actor {
                 .inc();
         }
 
+        if let Some(subscriber) = &self.peer_identified_subscriber {
+            subscriber
+                .send_async_next(PeerIdentified {
+                    peer_id,
+                    peer_info: peer_info.clone(),
+                })
+                .await;
+        }
+
         if let Some(peer_info_channel) = &self.peer_info_channel {
             if let Err(e) = peer_info_channel.send(Some(peer_info)) {
                 tracing::warn!("Failed to send identity info to notify channel: {e:#}");
@@ -1,6 +1,5 @@
 #![cfg_attr(not(test), warn(clippy::unwrap_used))]
 
-use crate::bitcoin::util::psbt::PartiallySignedTransaction;
 use crate::bitcoin::Txid;
 use crate::listen_protocols::TAKER_LISTEN_PROTOCOLS;
 use anyhow::bail;
@@ -9,15 +8,16 @@ use anyhow::Result;
 pub use bdk;
 use bdk::bitcoin;
 use bdk::bitcoin::Amount;
-use bdk::FeeRate;
+use bdk::bitcoin::OutPoint;
+use futures::AsyncRead;
+use futures::AsyncWrite;
 use identify::PeerInfo;
 use libp2p_core::Multiaddr;
-use libp2p_tcp::TokioTcpConfig;
+use libp2p_core::Transport;
 pub use maia;
 pub use maia_core;
 use maia_core::secp256k1_zkp::XOnlyPublicKey;
 use model::libp2p::PeerId;
-use model::olivia;
 use model::Contracts;
 use model::Identity;
 use model::Leverage;
@@ -46,15 +46,18 @@ use xtra_libp2p::Endpoint;
 use xtras::supervisor::always_restart_after;
 use xtras::supervisor::Supervisor;
 
+pub mod alerts;
 pub mod archive_closed_cfds;
 pub mod archive_failed_cfds;
 pub mod auto_rollover;
+pub mod block_explorer;
 pub mod collab_settlement;
 pub mod command;
 pub mod identify;
 pub mod libp2p_utils;
 pub mod listen_protocols;
 pub mod monitor;
+pub mod notifications;
 pub mod online_status;
 pub mod oracle;
 pub mod order;
@@ -62,6 +65,7 @@ pub mod position_metrics;
 pub mod process_manager;
 pub mod projection;
 pub mod seed;
+pub mod swap;
 pub mod taker_cfd;
 pub mod wallet;
 
@@ -74,8 +78,6 @@ pub const RESTART_INTERVAL: Duration = Duration::from_secs(5);
 pub const ENDPOINT_CONNECTION_TIMEOUT: Duration = Duration::from_secs(20);
 pub const PING_INTERVAL: Duration = Duration::from_secs(30);
 
-pub const N_PAYOUTS: usize = 200;
-
 pub struct TakerActorSystem<O, W, P> {
     pub cfd_actor: Address<taker_cfd::Actor>,
     wallet_actor: Address<W>,
@@ -98,13 +100,16 @@ pub struct TakerActorSystem<O, W, P> {
 impl<O, W, P> TakerActorSystem<O, W, P>
 where
     O: Handler<oracle::MonitorAttestations, Return = ()>
-        + Handler<
-            oracle::GetAnnouncements,
-            Return = Result<Vec<olivia::Announcement>, oracle::NoAnnouncement>,
-        > + Actor<Stop = ()>,
+        + Handler<oracle::GetAnnouncements, Return = oracle::PendingAnnouncements>
+        + Actor<Stop = ()>,
     W: Handler<wallet::BuildPartyParams, Return = Result<maia_core::PartyParams>>
-        + Handler<wallet::Sign, Return = Result<PartiallySignedTransaction>>
-        + Handler<wallet::Withdraw, Return = Result<Txid>>
+        + Handler<wallet::Sign, Return = wallet::PendingSignature>
+        + Handler<wallet::Withdraw, Return = wallet::PendingTxid>
+        + Handler<wallet::PreviewWithdraw, Return = Result<wallet::WithdrawPreview>>
+        + Handler<wallet::BumpFee, Return = wallet::PendingTxid>
+        + Handler<wallet::EstimateFeeRates, Return = Result<Vec<wallet::FeeEstimate>>>
+        + Handler<wallet::ListUtxos, Return = Result<Vec<wallet::Utxo>>>
+        + Handler<wallet::UpdateUtxo, Return = Result<()>>
         + Handler<wallet::Sync, Return = ()>
         + Actor<Stop = ()>,
     P: Handler<
@@ -116,14 +121,13 @@ where
         name = "Create TakerActorSystem",
         skip_all,
         fields(
-            %n_payouts,
             connect_timeout_secs = %connect_timeout.as_secs(),
             %environment,
         )
         err,
     )]
     #[allow(clippy::too_many_arguments)]
-    pub fn new<M>(
+    pub fn new<M, T>(
         db: sqlite_db::Connection,
         wallet_actor_addr: Address<W>,
         oracle_pk: XOnlyPublicKey,
@@ -131,12 +135,13 @@ where
         oracle_constructor: impl FnOnce(command::Executor) -> O,
         monitor_constructor: impl FnOnce(command::Executor) -> Result<M>,
         price_feed_actor: Address<P>,
-        n_payouts: usize,
         connect_timeout: Duration,
         projection_actor: Address<projection::Actor>,
         maker_identity: Identity,
         maker_multiaddr: Multiaddr,
+        transport: Box<dyn Fn() -> T + Send + 'static>,
         environment: Environment,
+        notifications: Option<notifications::Outbox>,
     ) -> Result<Self>
     where
         M: Handler<monitor::MonitorAfterContractSetup, Return = ()>
@@ -146,6 +151,12 @@ where
             + Handler<monitor::MonitorCetFinality, Return = Result<()>>
             + Handler<monitor::TryBroadcastTransaction, Return = Result<()>>
             + Actor<Stop = ()>,
+        T: Transport + Send + Sync + 'static,
+        T::Output: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        T::Error: Send + Sync,
+        T::Listener: Send + 'static,
+        T::Dial: Send + 'static,
+        T::ListenerUpgrade: Send + 'static,
     {
         let (maker_online_status_feed_sender, maker_online_status_feed_receiver) =
             watch::channel(ConnectionStatus::Offline);
@@ -173,6 +184,8 @@ where
             monitor_addr.clone().into(),
             monitor_addr.into(),
             oracle_addr.clone().into(),
+            Vec::new(),
+            notifications,
         )));
 
         let (endpoint_addr, endpoint_context) = Context::new(None);
@@ -186,7 +199,6 @@ where
             let endpoint = endpoint_addr.clone();
             move || {
                 order::taker::Actor::new(
-                    n_payouts,
                     oracle_pk,
                     oracle.clone().into(),
                     (db.clone(), process_manager.clone()),
@@ -200,13 +212,7 @@ where
         let (collab_settlement_supervisor, collab_settlement_addr) = Supervisor::new({
             let endpoint_addr = endpoint_addr.clone();
             let executor = executor.clone();
-            move || {
-                collab_settlement::taker::Actor::new(
-                    endpoint_addr.clone(),
-                    executor.clone(),
-                    n_payouts,
-                )
-            }
+            move || collab_settlement::taker::Actor::new(endpoint_addr.clone(), executor.clone())
         });
         tasks.add(collab_settlement_supervisor.run_log_summary());
 
@@ -236,7 +242,6 @@ where
                     executor.clone(),
                     oracle_pk,
                     oracle::AnnouncementsChannel::new(oracle_addr.clone().into()),
-                    n_payouts,
                 )
             }
         });
@@ -298,7 +303,7 @@ where
         tasks.add(supervisor.run_log_summary());
 
         let endpoint = Endpoint::new(
-            Box::new(TokioTcpConfig::new),
+            transport,
             identity.libp2p,
             ENDPOINT_CONNECTION_TIMEOUT,
             TAKER_LISTEN_PROTOCOLS.inbound_substream_handlers(
@@ -429,13 +434,68 @@ where
         &self,
         amount: Option<Amount>,
         address: bitcoin::Address,
-        fee_rate: FeeRate,
+        fee: wallet::WithdrawFee,
+        coins: Vec<OutPoint>,
     ) -> Result<Txid> {
         self.wallet_actor
             .send(wallet::Withdraw {
                 amount,
                 address,
-                fee: Some(fee_rate),
+                fee: Some(fee),
+                coins,
+            })
+            .await?
+            .await
+    }
+
+    #[instrument(skip(self), err)]
+    pub async fn preview_withdraw(
+        &self,
+        amount: Option<Amount>,
+        address: bitcoin::Address,
+        fee: wallet::WithdrawFee,
+        coins: Vec<OutPoint>,
+    ) -> Result<wallet::WithdrawPreview> {
+        self.wallet_actor
+            .send(wallet::PreviewWithdraw(wallet::Withdraw {
+                amount,
+                address,
+                fee: Some(fee),
+                coins,
+            }))
+            .await?
+    }
+
+    #[instrument(skip(self), err)]
+    pub async fn bump_fee(&self, txid: Txid, fee: wallet::WithdrawFee) -> Result<Txid> {
+        self.wallet_actor
+            .send(wallet::BumpFee { txid, fee })
+            .await?
+            .await
+    }
+
+    #[instrument(skip(self), err)]
+    pub async fn estimate_fee_rates(&self) -> Result<Vec<wallet::FeeEstimate>> {
+        self.wallet_actor.send(wallet::EstimateFeeRates).await?
+    }
+
+    #[instrument(skip(self), err)]
+    pub async fn list_utxos(&self) -> Result<Vec<wallet::Utxo>> {
+        self.wallet_actor.send(wallet::ListUtxos).await?
+    }
+
+    #[instrument(skip(self), err)]
+    pub async fn update_utxo(
+        &self,
+        outpoint: OutPoint,
+        label: Option<String>,
+        frozen: bool,
+    ) -> Result<()> {
+        self.wallet_actor
+            .send(wallet::UpdateUtxo {
+                outpoint,
+                label,
+                frozen,
             })
             .await?
     }
@@ -6,6 +6,7 @@ use std::net::SocketAddr;
 
 use libp2p_core::Multiaddr;
 use libp2p_core::PeerId;
+use libp2p_tcp::TokioTcpConfig;
 
 /// Creates MultiAddr from SocketAddr and PeerId
 pub fn create_connect_tcp_multiaddr(
@@ -41,6 +42,11 @@ pub fn create_listen_tcp_multiaddr(ip: &IpAddr, port: u16) -> Result<Multiaddr>
         .with_context(|| "failed to construct multiaddr")
 }
 
+/// Construct the TCP transport over which the daemons talk to each other.
+pub fn tcp_transport() -> TokioTcpConfig {
+    TokioTcpConfig::new()
+}
+
 /// Determine whether to use libp2p or fallback to a legacy protocol
 pub fn can_use_libp2p(cfd: &model::Cfd) -> bool {
     // Our abitily to kick-off a libp2p version of protocol is constrained by
@@ -1,51 +1,58 @@
 use crate::collab_settlement;
-use crate::command;
 use crate::identify;
-use crate::oracle;
 use crate::order;
 use ping_pong::pong;
 use std::collections::HashSet;
 use xtra::message_channel::MessageChannel;
 use xtra::Address;
 use xtra_libp2p::NewInboundSubstream;
+use xtra_libp2p::VersionedProtocol;
 
 pub const MAKER_LISTEN_PROTOCOLS: MakerListenProtocols = MakerListenProtocols::new(
-    ping_pong::PROTOCOL,
-    identify::PROTOCOL,
-    (order::PROTOCOL, order::deprecated::PROTOCOL),
-    (rollover::PROTOCOL, rollover::deprecated::PROTOCOL),
-    (
-        collab_settlement::PROTOCOL,
-        collab_settlement::deprecated::PROTOCOL,
-    ),
+    PING,
+    IDENTIFY,
+    order::PROTOCOLS,
+    rollover::PROTOCOLS,
+    collab_settlement::PROTOCOLS,
 );
 
 pub const TAKER_LISTEN_PROTOCOLS: TakerListenProtocols =
     TakerListenProtocols::new(ping_pong::PROTOCOL, identify::PROTOCOL, offer::PROTOCOL);
 
-pub const REQUIRED_MAKER_LISTEN_PROTOCOLS: RequiredMakerListenProtocols =
-    RequiredMakerListenProtocols::new(
-        ping_pong::PROTOCOL,
-        identify::PROTOCOL,
-        order::PROTOCOL,
-        rollover::PROTOCOL,
-        collab_settlement::PROTOCOL,
-    );
+/// The protocols that the taker dials on the maker.
+///
+/// The maker has to listen for at least one version of each of them.
+pub const TAKER_DIALED_PROTOCOLS: [VersionedProtocol; 5] = [
+    PING,
+    IDENTIFY,
+    order::DIALER_PROTOCOLS,
+    rollover::PROTOCOLS,
+    collab_settlement::DIALER_PROTOCOLS,
+];
+
+const PING: VersionedProtocol = VersionedProtocol::new("/ipfs/ping", &[ping_pong::PROTOCOL]);
+const IDENTIFY: VersionedProtocol = VersionedProtocol::new("/itchysats/id", &[identify::PROTOCOL]);
+
+/// The handlers for the versions of a protocol, keyed by the version they serve.
+pub type VersionHandlers = Vec<(&'static str, MessageChannel<NewInboundSubstream, ()>)>;
 
 /// Verify if the listen protocols that the `maker` supports are
-/// sufficient to fulfil the `requirements` of the taker.
+/// sufficient for the protocols that the taker `dials`.
+///
+/// Returns the latest version of every protocol for which the maker does not listen for any
+/// version we can dial.
 pub fn does_maker_satisfy_taker_needs(
     maker: &HashSet<String>,
-    requirements: RequiredMakerListenProtocols,
+    dials: &[VersionedProtocol],
 ) -> Result<(), HashSet<String>> {
-    // missing protocols are those that are in requirements but not in maker protocols
-    let missing_protocols = &HashSet::<String>::from(requirements)
-        .difference(maker)
-        .cloned()
+    let missing_protocols = dials
+        .iter()
+        .filter(|protocol| protocol.select(maker).is_none())
+        .map(|protocol| protocol.latest().to_string())
         .collect::<HashSet<_>>();
 
     if !missing_protocols.is_empty() {
-        return Err(missing_protocols.clone());
+        return Err(missing_protocols);
     }
 
     Ok(())
@@ -54,101 +61,101 @@ pub fn does_maker_satisfy_taker_needs(
 /// The set of protocols that the maker's `Endpoint` is listening for.
 #[derive(Clone, Copy)]
 pub struct MakerListenProtocols {
-    ping: &'static str,
-    identify: &'static str,
-    order: &'static str,
-    order_deprecated: &'static str,
-    rollover: &'static str,
-    rollover_deprecated: &'static str,
-    collaborative_settlement: &'static str,
-    collaborative_settlement_deprecated: &'static str,
+    ping: VersionedProtocol,
+    identify: VersionedProtocol,
+    order: VersionedProtocol,
+    rollover: VersionedProtocol,
+    collaborative_settlement: VersionedProtocol,
 }
 
-type RolloverAddress<R> =
-    Address<rollover::maker::Actor<command::Executor, oracle::AnnouncementsChannel, R>>;
-
-type RolloverDeprecatedAddress<RD> = Address<
-    rollover::deprecated::maker::Actor<command::Executor, oracle::AnnouncementsChannel, RD>,
->;
-
 impl MakerListenProtocols {
-    pub const NR_OF_SUPPORTED_PROTOCOLS: usize = 8;
+    pub const NR_OF_SUPPORTED_PROTOCOLS: usize = MAKER_LISTEN_PROTOCOLS.nr_of_versions();
 
     pub const fn new(
-        ping: &'static str,
-        identify: &'static str,
-        (order, order_deprecated): (&'static str, &'static str),
-        (rollover, rollover_deprecated): (&'static str, &'static str),
-        (collaborative_settlement, collaborative_settlement_deprecated): (
-            &'static str,
-            &'static str,
-        ),
+        ping: VersionedProtocol,
+        identify: VersionedProtocol,
+        order: VersionedProtocol,
+        rollover: VersionedProtocol,
+        collaborative_settlement: VersionedProtocol,
     ) -> Self {
         Self {
             ping,
             identify,
             order,
-            order_deprecated,
             rollover,
-            rollover_deprecated,
             collaborative_settlement,
-            collaborative_settlement_deprecated,
         }
     }
 
+    const fn nr_of_versions(&self) -> usize {
+        self.ping.versions().len()
+            + self.identify.versions().len()
+            + self.order.versions().len()
+            + self.rollover.versions().len()
+            + self.collaborative_settlement.versions().len()
+    }
+
     /// Construct a map of protocol identifiers to actor addresses.
     ///
     /// This is used so that the `Endpoint` knows who to delegate to
-    /// when receiving new inbound substreams.
-    pub fn inbound_substream_handlers<R, RD>(
+    /// when receiving new inbound substreams. Every version of a
+    /// versioned protocol needs exactly one handler.
+    pub fn inbound_substream_handlers(
         &self,
         ping_handler: Address<pong::Actor>,
         identify_handler: Address<identify::listener::Actor>,
-        (order_handler, order_deprecated_handler): (
-            Address<order::maker::Actor>,
-            Address<order::deprecated::maker::Actor>,
-        ),
-        (rollover_handler, rollover_deprecated_handler): (
-            RolloverAddress<R>,
-            RolloverDeprecatedAddress<RD>,
-        ),
-        (collaborative_settlement_handler, collaborative_settlement_deprecated_handler): (
-            Address<collab_settlement::maker::Actor>,
-            Address<collab_settlement::deprecated::maker::Actor>,
-        ),
+        order_handlers: VersionHandlers,
+        rollover_handlers: VersionHandlers,
+        collaborative_settlement_handlers: VersionHandlers,
     ) -> [(&'static str, MessageChannel<NewInboundSubstream, ()>); Self::NR_OF_SUPPORTED_PROTOCOLS]
-    where
-        R: rollover::protocol::GetRates + Send + Sync + Clone + 'static,
-        RD: rollover::deprecated::protocol::GetRates + Send + Sync + Clone + 'static,
     {
         // We deconstruct to ensure that all protocols are being used
         let MakerListenProtocols {
             ping,
             identify,
             order,
-            order_deprecated,
             rollover,
-            rollover_deprecated,
             collaborative_settlement,
-            collaborative_settlement_deprecated,
         } = self;
 
-        [
-            (ping, ping_handler.into()),
-            (identify, identify_handler.into()),
-            (order, order_handler.into()),
-            (order_deprecated, order_deprecated_handler.into()),
-            (rollover, rollover_handler.into()),
-            (rollover_deprecated, rollover_deprecated_handler.into()),
-            (
-                collaborative_settlement,
-                collaborative_settlement_handler.into(),
-            ),
+        let handlers = [
+            (*ping, vec![(ping.latest(), ping_handler.into())]),
             (
-                collaborative_settlement_deprecated,
-                collaborative_settlement_deprecated_handler.into(),
+                *identify,
+                vec![(identify.latest(), identify_handler.into())],
             ),
+            (*order, order_handlers),
+            (*rollover, rollover_handlers),
+            (*collaborative_settlement, collaborative_settlement_handlers),
         ]
+        .into_iter()
+        .flat_map(|(protocol, handlers)| {
+            let mut versions = handlers
+                .iter()
+                .map(|(version, _)| *version)
+                .collect::<Vec<_>>();
+            versions.sort_unstable();
+            let mut expected = protocol.versions().to_vec();
+            expected.sort_unstable();
+
+            assert_eq!(
+                versions,
+                expected,
+                "Every version of {} needs exactly one handler",
+                protocol.name()
+            );
+
+            handlers
+        })
+        .collect::<Vec<_>>();
+
+        handlers.try_into().unwrap_or_else(|handlers: Vec<_>| {
+            panic!(
+                "Expected {} handlers but got {}",
+                Self::NR_OF_SUPPORTED_PROTOCOLS,
+                handlers.len()
+            )
+        })
     }
 }
 
@@ -159,73 +166,15 @@ impl From<MakerListenProtocols> for HashSet<String> {
             ping,
             identify,
             order,
-            order_deprecated,
             rollover,
-            rollover_deprecated,
             collaborative_settlement,
-            collaborative_settlement_deprecated,
         } = maker;
 
-        HashSet::from([
-            ping.to_string(),
-            identify.to_string(),
-            order.to_string(),
-            order_deprecated.to_string(),
-            rollover.to_string(),
-            rollover_deprecated.to_string(),
-            collaborative_settlement.to_string(),
-            collaborative_settlement_deprecated.to_string(),
-        ])
-    }
-}
-
-/// The set of protocols that the maker's `Endpoint` is expected to
-/// be listening for.
-#[derive(Clone, Copy)]
-pub struct RequiredMakerListenProtocols {
-    ping: &'static str,
-    identify: &'static str,
-    order: &'static str,
-    rollover: &'static str,
-    collaborative_settlement: &'static str,
-}
-
-impl RequiredMakerListenProtocols {
-    pub const fn new(
-        ping: &'static str,
-        identify: &'static str,
-        order: &'static str,
-        rollover: &'static str,
-        collaborative_settlement: &'static str,
-    ) -> Self {
-        Self {
-            ping,
-            identify,
-            order,
-            rollover,
-            collaborative_settlement,
-        }
-    }
-}
-
-impl From<RequiredMakerListenProtocols> for HashSet<String> {
-    fn from(required: RequiredMakerListenProtocols) -> Self {
-        // We deconstruct to ensure that all protocols are being used
-        let RequiredMakerListenProtocols {
-            ping,
-            identify,
-            order,
-            rollover,
-            collaborative_settlement,
-        } = required;
-
-        HashSet::from([
-            ping.to_string(),
-            identify.to_string(),
-            order.to_string(),
-            rollover.to_string(),
-            collaborative_settlement.to_string(),
-        ])
+        [ping, identify, order, rollover, collaborative_settlement]
+            .iter()
+            .flat_map(|protocol| protocol.versions())
+            .map(|version| version.to_string())
+            .collect()
     }
 }
 
@@ -293,10 +242,8 @@ mod tests {
 
     #[test]
     fn ensure_required_taker_protocols_are_supported_by_maker() {
-        let result = does_maker_satisfy_taker_needs(
-            &MAKER_LISTEN_PROTOCOLS.into(),
-            REQUIRED_MAKER_LISTEN_PROTOCOLS,
-        );
+        let result =
+            does_maker_satisfy_taker_needs(&MAKER_LISTEN_PROTOCOLS.into(), &TAKER_DIALED_PROTOCOLS);
 
         assert!(result.is_ok(), "Missing protocols detected: {result:?}");
     }
@@ -307,11 +254,9 @@ mod tests {
         // remove the ping protocol, we assume that it always is in there
         maker_protocols_as_hashset.remove(ping_pong::PROTOCOL);
 
-        let err = does_maker_satisfy_taker_needs(
-            &maker_protocols_as_hashset,
-            REQUIRED_MAKER_LISTEN_PROTOCOLS,
-        )
-        .unwrap_err();
+        let err =
+            does_maker_satisfy_taker_needs(&maker_protocols_as_hashset, &TAKER_DIALED_PROTOCOLS)
+                .unwrap_err();
 
         assert_eq!(err, HashSet::from([ping_pong::PROTOCOL.to_string()]))
     }
@@ -322,14 +267,52 @@ mod tests {
         // remove the ping protocol, we assume that it always is in there
         maker_protocols_as_hashset.insert("blablubb".to_string());
 
-        let result = does_maker_satisfy_taker_needs(
-            &maker_protocols_as_hashset,
-            REQUIRED_MAKER_LISTEN_PROTOCOLS,
-        );
+        let result =
+            does_maker_satisfy_taker_needs(&maker_protocols_as_hashset, &TAKER_DIALED_PROTOCOLS);
 
         assert!(result.is_ok(), "Missing protocols detected: {result:?}");
     }
 
+    #[test]
+    fn ensure_all_versions_of_versioned_protocols_are_supported_by_maker() {
+        let maker_protocols_as_hashset: HashSet<String> = MAKER_LISTEN_PROTOCOLS.into();
+
+        for protocol in [
+            order::PROTOCOLS,
+            rollover::PROTOCOLS,
+            collab_settlement::PROTOCOLS,
+        ] {
+            for version in protocol.versions() {
+                assert!(
+                    maker_protocols_as_hashset.contains(*version),
+                    "Maker does not listen for {version}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn given_maker_does_not_support_any_dialed_version_then_error_with_latest_version() {
+        let mut maker_protocols_as_hashset: HashSet<String> = MAKER_LISTEN_PROTOCOLS.into();
+        maker_protocols_as_hashset.remove(rollover::PROTOCOLS.latest());
+
+        let result =
+            does_maker_satisfy_taker_needs(&maker_protocols_as_hashset, &TAKER_DIALED_PROTOCOLS);
+
+        assert!(
+            result.is_ok(),
+            "Taker negotiates the deprecated rollover version: {result:?}"
+        );
+
+        maker_protocols_as_hashset.remove(order::PROTOCOLS.latest());
+
+        let err =
+            does_maker_satisfy_taker_needs(&maker_protocols_as_hashset, &TAKER_DIALED_PROTOCOLS)
+                .unwrap_err();
+
+        assert_eq!(err, HashSet::from([order::PROTOCOLS.latest().to_string()]))
+    }
+
     #[test]
     fn ensure_nr_of_maker_protocols_matches_hashset_len() {
         let maker_protocols_as_hashset: HashSet<String> = MAKER_LISTEN_PROTOCOLS.into();
@@ -0,0 +1,411 @@
+//! Push notifications about CFD events to webhooks.
+//!
+//! Notifications are queued in an outbox in the database before they are delivered, such that
+//! they are not lost across restarts. Failed deliveries are retried with exponential backoff until
+//! [`MAX_ATTEMPTS`] is reached.
+//!
+//! Every notification is posted as JSON and signed with HMAC-SHA256 over the body, using the
+//! configured secret as the key. The hex-encoded signature is sent in the
+//! [`SIGNATURE_HEADER`] header, prefixed with `sha256=`.
+
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::stream;
+use futures::StreamExt;
+use hmac::Hmac;
+use hmac::Mac;
+use model::CfdEvent;
+use model::EventKind;
+use model::OrderId;
+use model::Role;
+use model::Timestamp;
+use serde::Serialize;
+use sha2::Sha256;
+use sqlite_db::notification_outbox::PendingNotification;
+use std::time::Duration;
+use tokio_extras::Tasks;
+use xtra::Actor as _;
+use xtra::Address;
+use xtra_productivity::xtra_productivity;
+use xtras::SendAsyncNext;
+use xtras::SendInterval;
+
+pub const SIGNATURE_HEADER: &str = "X-Itchysats-Signature";
+
+/// Number of failed deliveries after which a notification is dropped.
+pub const MAX_ATTEMPTS: u32 = 20;
+
+/// Interval at which the outbox is checked for notifications due for delivery.
+const DELIVER_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Timeout for posting a notification to a webhook.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum number of notifications loaded for delivery at once.
+const DELIVERY_BATCH_SIZE: u32 = 100;
+
+/// Maximum number of notifications posted concurrently.
+const DELIVERY_CONCURRENCY: usize = 10;
+
+const INITIAL_BACKOFF: time::Duration = time::Duration::seconds(10);
+const MAX_BACKOFF: time::Duration = time::Duration::hours(1);
+
+/// The body of a notification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Notification {
+    pub order_id: OrderId,
+    /// Our role in the CFD
+    pub role: Role,
+    /// The name of the event, e.g. `ContractSetupCompleted`
+    pub event: String,
+    pub timestamp: Timestamp,
+}
+
+impl Notification {
+    /// The notification for an event, if the event is worth notifying about.
+    pub fn new(event: &CfdEvent, role: Role) -> Option<Self> {
+        use EventKind::*;
+        match event.event {
+            ContractSetupCompleted { dlc: Some(_) }
+            | ContractSetupFailed
+            | RolloverFailed
+            | CollaborativeSettlementStarted { .. }
+            | CollaborativeSettlementRejected
+            | CollaborativeSettlementCompleted { .. }
+            | CollaborativeSettlementFailed
+            | ManualCommit { .. }
+            | OracleAttestedPriorCetTimelock {
+                commit_tx: Some(_), ..
+            }
+            | CetConfirmed
+            | RefundTimelockExpired { .. } => Some(Self {
+                order_id: event.id,
+                role,
+                event: event.event.to_string(),
+                timestamp: event.timestamp,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Sign `body` with HMAC-SHA256, returning the hex-encoded signature.
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC to accept keys of any size");
+    mac.update(body.as_bytes());
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Delay before the next delivery after `attempts` failed deliveries
+fn backoff(attempts: u32) -> time::Duration {
+    let exponent = attempts.saturating_sub(1).min(16);
+
+    (INITIAL_BACKOFF * 2_i32.pow(exponent)).min(MAX_BACKOFF)
+}
+
+/// Queues notifications in the outbox and wakes up the [`Actor`] delivering them.
+#[derive(Clone)]
+pub struct Outbox {
+    db: sqlite_db::Connection,
+    role: Role,
+    webhooks: Vec<String>,
+    actor: Address<Actor>,
+}
+
+impl Outbox {
+    /// Create the outbox and spawn the actor delivering its notifications onto `tasks`.
+    pub fn new(
+        db: sqlite_db::Connection,
+        role: Role,
+        webhooks: Vec<String>,
+        secret: String,
+        tasks: &mut Tasks,
+    ) -> Self {
+        let actor = Actor::new(db.clone(), secret).create(None).spawn(tasks);
+
+        Self {
+            db,
+            role,
+            webhooks,
+            actor,
+        }
+    }
+
+    /// Append an event, queueing the notification about it in the same transaction.
+    pub async fn append_event(&self, event: CfdEvent) -> Result<()> {
+        let notification = match Notification::new(&event, self.role) {
+            Some(notification) => notification,
+            None => return self.db.append_event(event).await,
+        };
+        let payload = serde_json::to_string(&notification)?;
+
+        self.db
+            .append_event_with_notification(
+                event,
+                &self.webhooks,
+                &payload,
+                tokio_extras::time::now_utc(),
+            )
+            .await?;
+
+        self.actor.send_async_next(Deliver).await;
+
+        Ok(())
+    }
+
+    /// Queue a notification with the given JSON body.
+    pub async fn queue(&self, payload: &str) -> Result<()> {
+        self.db
+            .insert_notifications(&self.webhooks, payload, tokio_extras::time::now_utc())
+            .await?;
+
+        self.actor.send_async_next(Deliver).await;
+
+        Ok(())
+    }
+}
+
+/// Delivers the notifications which are due, outside of the [`Actor`].
+#[derive(Clone)]
+struct Delivery {
+    db: sqlite_db::Connection,
+    secret: String,
+    client: reqwest::Client,
+}
+
+impl Delivery {
+    async fn post(&self, webhook: &str, payload: String) -> Result<()> {
+        let signature = sign(&self.secret, &payload);
+
+        self.client
+            .post(webhook)
+            .timeout(DELIVERY_TIMEOUT)
+            .header("content-type", "application/json")
+            .header(SIGNATURE_HEADER, format!("sha256={signature}"))
+            .body(payload)
+            .send()
+            .await
+            .context("Failed to post notification")?
+            .error_for_status()
+            .context("Webhook rejected notification")?;
+
+        Ok(())
+    }
+
+    async fn deliver_all(self) -> Result<()> {
+        let now = tokio_extras::time::now_utc();
+        let due = self
+            .db
+            .load_due_notifications(now, DELIVERY_BATCH_SIZE)
+            .await?;
+
+        let this = &self;
+        stream::iter(due)
+            .for_each_concurrent(DELIVERY_CONCURRENCY, |notification| async move {
+                let id = notification.id;
+                if let Err(e) = this.deliver(notification, now).await {
+                    tracing::error!(%id, "Failed to update notification: {e:#}");
+                }
+            })
+            .await;
+
+        Ok(())
+    }
+
+    async fn deliver(
+        &self,
+        notification: PendingNotification,
+        now: time::OffsetDateTime,
+    ) -> Result<()> {
+        let id = notification.id;
+
+        let e = match self
+            .post(&notification.webhook, notification.payload.clone())
+            .await
+        {
+            Ok(()) => return self.db.delete_notification(id).await,
+            Err(e) => e,
+        };
+
+        let attempts = notification.attempts + 1;
+
+        if attempts >= MAX_ATTEMPTS {
+            tracing::error!(
+                webhook = %notification.webhook,
+                payload = %notification.payload,
+                "Dropping notification after {attempts} failed attempts: {e:#}"
+            );
+            return self.db.delete_notification(id).await;
+        }
+
+        let next_attempt_at = now + backoff(attempts);
+        tracing::warn!(
+            webhook = %notification.webhook,
+            %next_attempt_at,
+            "Failed to deliver notification: {e:#}"
+        );
+        self.db
+            .reschedule_notification(id, attempts, next_attempt_at)
+            .await
+    }
+}
+
+pub struct Actor {
+    delivery: Delivery,
+    /// Whether a delivery is in progress
+    delivering: bool,
+    /// Whether notifications were queued while a delivery was in progress
+    deliver_again: bool,
+}
+
+impl Actor {
+    fn new(db: sqlite_db::Connection, secret: String) -> Self {
+        Self {
+            delivery: Delivery {
+                db,
+                secret,
+                client: reqwest::Client::new(),
+            },
+            delivering: false,
+            deliver_again: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Deliver;
+
+#[derive(Clone, Copy)]
+struct Delivered;
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we are alive");
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(DELIVER_INTERVAL, || Deliver, xtras::IncludeSpan::Never),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: Deliver, ctx: &mut xtra::Context<Self>) {
+        if self.delivering {
+            self.deliver_again = true;
+            return;
+        }
+        self.delivering = true;
+
+        let this = ctx.address().expect("we are alive");
+        let delivery = self.delivery.clone();
+        tokio_extras::spawn(&this.clone(), async move {
+            if let Err(e) = delivery.deliver_all().await {
+                tracing::error!("Failed to deliver notifications: {e:#}");
+            }
+
+            this.send_async_next(Delivered).await;
+        });
+    }
+
+    async fn handle(&mut self, _: Delivered, ctx: &mut xtra::Context<Self>) {
+        self.delivering = false;
+
+        if std::mem::take(&mut self.deliver_again) {
+            let this = ctx.address().expect("we are alive");
+            this.send_async_next(Deliver).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_is_hmac_sha256_of_body() {
+        // Test case 2 of RFC 4231
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_until_maximum() {
+        assert_eq!(backoff(1), time::Duration::seconds(10));
+        assert_eq!(backoff(2), time::Duration::seconds(20));
+        assert_eq!(backoff(3), time::Duration::seconds(40));
+        assert_eq!(backoff(10), MAX_BACKOFF);
+        assert_eq!(backoff(MAX_ATTEMPTS), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn only_key_events_are_notified() {
+        let id = OrderId::default();
+
+        let notification =
+            Notification::new(&CfdEvent::new(id, EventKind::RolloverFailed), Role::Taker).unwrap();
+        assert_eq!(notification.event, "RolloverFailed");
+        assert_eq!(notification.order_id, id);
+
+        assert!(
+            Notification::new(&CfdEvent::new(id, EventKind::CetConfirmed), Role::Maker).is_some()
+        );
+        assert!(
+            Notification::new(&CfdEvent::new(id, EventKind::RolloverStarted), Role::Maker)
+                .is_none()
+        );
+        assert!(Notification::new(
+            &CfdEvent::new(id, EventKind::ContractSetupCompleted { dlc: None }),
+            Role::Maker
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn attestation_prior_cet_timelock_is_only_notified_when_committing() {
+        let tx = || bdk::bitcoin::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![],
+        };
+        let attested = |commit_tx| {
+            CfdEvent::new(
+                OrderId::default(),
+                EventKind::OracleAttestedPriorCetTimelock {
+                    timelocked_cet: tx(),
+                    commit_tx,
+                    price: model::Price::new(rust_decimal_macros::dec!(20_000)).unwrap(),
+                },
+            )
+        };
+
+        assert!(Notification::new(&attested(Some(tx())), Role::Taker).is_some());
+        assert!(Notification::new(&attested(None), Role::Taker).is_none());
+    }
+
+    #[test]
+    fn notification_is_serialized_with_event_name() {
+        let notification = Notification {
+            order_id: OrderId::from(uuid::Uuid::nil()),
+            role: Role::Maker,
+            event: "CetConfirmed".to_owned(),
+            timestamp: Timestamp::new(1_600_000_000),
+        };
+
+        assert_eq!(
+            serde_json::to_string(&notification).unwrap(),
+            r#"{"order_id":"00000000-0000-0000-0000-000000000000","role":"Maker","event":"CetConfirmed","timestamp":1600000000}"#
+        );
+    }
+}
@@ -3,6 +3,8 @@ use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
+use futures::future::BoxFuture;
+use futures::FutureExt;
 use futures::StreamExt;
 use maia_core::secp256k1_zkp::XOnlyPublicKey;
 use model::olivia;
@@ -49,18 +51,20 @@ pub struct Actor {
 /// For a rollover to happen successfully we need to know the oracle announcement details.
 /// Our actor is checking if a new announcement can be fetched every SYNC_ANNOUNCEMENTS_INTERVAL and
 /// ANNOUNCEMENT_LOOKAHEAD hours into the future. Given we rollover every for
-/// hour model::SETTLEMENT_INTERVAL into the future, we want to have at least
-/// model::SETTLEMENT_INTERVAL announcements ready. Due to sync interval coincidence, it might
+/// hour model::DEFAULT_SETTLEMENT_INTERVAL into the future, we want to have at least
+/// model::DEFAULT_SETTLEMENT_INTERVAL announcements ready. Due to sync interval coincidence, it might
 /// happen that we do not have synced for a specific announcement yet. Hence, we need to fetch more
-/// announcements. We fetch model::SETTLEMENT_INTERVAL + 2 announcement into the future because of
+/// announcements. We fetch model::DEFAULT_SETTLEMENT_INTERVAL + 2 announcement into the future because of
 /// this example:
 ///
 /// Assume the last fetch was at 01.01.2022 00:59:55, i.e. 5 seconds before midnight and we would
-/// have synced for model::SETTLEMENT_INTERVAL+1 we would have synced announcements until 02.01.2022
+/// have synced for model::DEFAULT_SETTLEMENT_INTERVAL+1 we would have synced announcements until 02.01.2022
 /// 01:00:00. A rollover request happening exactly at 01.01.2022 01:00:00 would ask for the
 /// announcement at 02.01.2022 02:00:00 because of how olivia::next_announcement_after works. Note:
 /// even if the underlying logic of olivia::next_announcement_after changes, fetching model::
-/// SETTLEMENT_INTERVAL + 2 won't hurt.
+/// DEFAULT_SETTLEMENT_INTERVAL + 2 won't hurt.
+///
+/// Announcements for offers with a longer settlement interval are fetched on demand.
 const ANNOUNCEMENT_LOOKAHEAD: Duration = Duration::hours(26);
 
 #[derive(Clone, Copy)]
@@ -165,7 +169,7 @@ impl Actor {
     ) {
         for hour in 1..ANNOUNCEMENT_LOOKAHEAD.whole_hours() {
             let event_id = next_announcement_after(
-                OffsetDateTime::now_utc() + Duration::hours(hour),
+                tokio_extras::time::now_utc() + Duration::hours(hour),
                 contract_symbol,
             );
 
@@ -177,25 +181,7 @@ impl Actor {
 
             let this_clone = this.clone();
             let task = async move {
-                let url = event_id.to_olivia_url();
-
-                tracing::debug!(event_id = %event_id, "Fetching announcement");
-
-                let response = client
-                    .get(url.clone())
-                    .send()
-                    .await
-                    .with_context(|| format!("Failed to GET {url}"))?;
-
-                let code = response.status();
-                if !code.is_success() {
-                    bail!("GET {url} responded with {code}");
-                }
-
-                let announcement = response
-                    .json::<olivia::Announcement>()
-                    .await
-                    .context("Failed to deserialize as Announcement")?;
+                let announcement = fetch_announcement(&client, event_id).await?;
 
                 this.send(NewAnnouncementFetched {
                     id: event_id,
@@ -285,22 +271,58 @@ impl Actor {
     fn handle_get_announcements(
         &mut self,
         GetAnnouncements(ids): GetAnnouncements,
-    ) -> Result<Vec<olivia::Announcement>, NoAnnouncement> {
-        let announcements = ids
+        ctx: &mut xtra::Context<Self>,
+    ) -> PendingAnnouncements {
+        let synced = ids
             .iter()
             .map(|id| {
                 self.announcements
-                    .get_key_value(id)
-                    .map(|(id, (time, nonce_pks))| olivia::Announcement {
+                    .get(id)
+                    .map(|(time, nonce_pks)| olivia::Announcement {
                         id: *id,
                         expected_outcome_time: *time,
                         nonce_pks: nonce_pks.clone(),
                     })
-                    .ok_or(NoAnnouncement(*id))
             })
-            .collect::<Result<_, _>>()?;
+            .collect::<Vec<_>>();
+        let client = self.client.clone();
+        let this = ctx.address().expect("self to be alive");
+
+        async move {
+            let mut announcements = Vec::with_capacity(ids.len());
+
+            for (id, synced) in ids.into_iter().zip(synced) {
+                let announcement = match synced {
+                    Some(announcement) => announcement,
+                    // Announcements outside of `ANNOUNCEMENT_LOOKAHEAD`, e.g. the expiry of a dated
+                    // future, are not synced periodically and have to be fetched on demand
+                    None => {
+                        let announcement = fetch_announcement(&client, id).await.map_err(|e| {
+                            tracing::debug!("Failed to fetch announcement on demand: {e:#}");
+                            NoAnnouncement(id)
+                        })?;
+
+                        if let Err(e) = this
+                            .send(NewAnnouncementFetched {
+                                id,
+                                nonce_pks: announcement.nonce_pks.clone(),
+                                expected_outcome_time: announcement.expected_outcome_time,
+                            })
+                            .await
+                        {
+                            tracing::debug!("Failed to cache announcement: {e:#}");
+                        }
 
-        Ok(announcements)
+                        announcement
+                    }
+                };
+
+                announcements.push(announcement);
+            }
+
+            Ok(announcements)
+        }
+        .boxed()
     }
 
     fn handle_new_announcement_fetched(&mut self, msg: NewAnnouncementFetched) {
@@ -343,6 +365,41 @@ impl Actor {
 #[error("Announcement {0} not found")]
 pub struct NoAnnouncement(pub BitMexPriceEventId);
 
+/// The announcements requested via [`GetAnnouncements`].
+///
+/// Announcements that the oracle actor has not synced are fetched when the future is polled, i.e.
+/// without blocking the actor.
+pub type PendingAnnouncements =
+    BoxFuture<'static, Result<Vec<olivia::Announcement>, NoAnnouncement>>;
+
+async fn fetch_announcement(
+    client: &reqwest::Client,
+    event_id: BitMexPriceEventId,
+) -> Result<olivia::Announcement> {
+    let url = event_id.to_olivia_url();
+
+    tracing::debug!(event_id = %event_id, "Fetching announcement");
+
+    let response = client
+        .get(url.clone())
+        .timeout(REQWEST_TIMEOUT)
+        .send()
+        .await
+        .with_context(|| format!("Failed to GET {url}"))?;
+
+    let code = response.status();
+    if !code.is_success() {
+        bail!("GET {url} responded with {code}");
+    }
+
+    let announcement = response
+        .json::<olivia::Announcement>()
+        .await
+        .context("Failed to deserialize as Announcement")?;
+
+    Ok(announcement)
+}
+
 #[async_trait]
 impl xtra::Actor for Actor {
     type Stop = ();
@@ -420,17 +477,10 @@ impl Attestation {
 /// provide the same use. It is needed so that we can implement
 /// foreign traits on it to fulfil the requirements of external APIs.
 #[derive(Clone)]
-pub struct AnnouncementsChannel(
-    MessageChannel<GetAnnouncements, Result<Vec<olivia::Announcement>, NoAnnouncement>>,
-);
+pub struct AnnouncementsChannel(MessageChannel<GetAnnouncements, PendingAnnouncements>);
 
 impl AnnouncementsChannel {
-    pub fn new(
-        channel: MessageChannel<
-            GetAnnouncements,
-            Result<Vec<olivia::Announcement>, NoAnnouncement>,
-        >,
-    ) -> Self {
+    pub fn new(channel: MessageChannel<GetAnnouncements, PendingAnnouncements>) -> Self {
         Self(channel)
     }
 }
@@ -446,6 +496,7 @@ impl rollover::deprecated::protocol::GetAnnouncements for AnnouncementsChannel {
             .send(GetAnnouncements(events))
             .await
             .context("Oracle actor disconnected")?
+            .await
             .context("Failed to get announcements")?;
 
         Ok(announcements)
@@ -463,6 +514,7 @@ impl rollover::protocol::GetAnnouncements for AnnouncementsChannel {
             .send(GetAnnouncements(events))
             .await
             .context("Oracle actor disconnected")?
+            .await
             .context("Failed to get announcements")?;
 
         Ok(announcements)
@@ -476,7 +528,7 @@ pub mod tests {
         use time::Duration;
         assert_eq!(
             crate::oracle::ANNOUNCEMENT_LOOKAHEAD,
-            model::SETTLEMENT_INTERVAL + Duration::hours(2)
+            model::DEFAULT_SETTLEMENT_INTERVAL + Duration::hours(2)
         );
     }
 }
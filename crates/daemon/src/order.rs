@@ -1,4 +1,19 @@
+use xtra_libp2p::VersionedProtocol;
+
 mod current;
 pub mod deprecated;
 
 pub use current::*;
+
+/// All supported versions of the order protocol, ordered from latest to oldest.
+pub const PROTOCOLS: VersionedProtocol = VersionedProtocol::new(
+    "/itchysats/order",
+    &[current::PROTOCOL, deprecated::PROTOCOL],
+);
+
+/// The versions of the order protocol the taker dials, ordered from latest to oldest.
+///
+/// The taker only implements the latest version, the deprecated versions are only listened on by
+/// the maker.
+pub const DIALER_PROTOCOLS: VersionedProtocol =
+    VersionedProtocol::new("/itchysats/order", &[current::PROTOCOL]);
@@ -45,6 +45,7 @@ use model::CET_TIMELOCK;
 use std::collections::HashMap;
 use std::ops::RangeInclusive;
 use std::time::Duration;
+use tokio_extras::time::already_instrumented;
 use tokio_extras::FutureExt;
 use tracing::instrument;
 use tracing::Instrument;
@@ -59,6 +60,12 @@ use super::protocol::SetupMsg;
 /// more time to see them less often.
 const CONTRACT_SETUP_MSG_TIMEOUT: Duration = Duration::from_secs(120);
 
+/// How long contract setup waits for the wallet to sign the lock transaction
+///
+/// Signing takes a while if the wallet relies on an external signer. We have to send our signature
+/// before the counterparty stops waiting for it after [`CONTRACT_SETUP_MSG_TIMEOUT`].
+const LOCK_TX_SIGN_TIMEOUT: Duration = Duration::from_secs(100);
+
 /// Given an initial set of parameters, sets up the CFD contract with
 /// the counterparty.
 #[allow(clippy::too_many_arguments)]
@@ -74,7 +81,7 @@ pub async fn new(
     (oracle_pk, announcements): (XOnlyPublicKey, Vec<olivia::Announcement>),
     setup_params: SetupParams,
     build_party_params_channel: MessageChannel<wallet::BuildPartyParams, Result<PartyParams>>,
-    sign_channel: MessageChannel<wallet::Sign, Result<PartiallySignedTransaction>>,
+    sign_channel: MessageChannel<wallet::Sign, wallet::PendingSignature>,
     own_role: Role,
     position: Position,
     n_payouts: usize,
@@ -151,14 +158,25 @@ pub async fn new(
     )
     .await?;
 
-    let mut signed_lock_tx = sign_channel
-        .send(wallet::Sign {
-            psbt: verified.lock_tx,
-        })
-        .instrument(tracing::debug_span!("Send Sign to wallet actor"))
-        .await
-        .context("Failed to send message to wallet actor")?
-        .context("Failed to sign transaction")?;
+    let mut signed_lock_tx = async {
+        sign_channel
+            .send(wallet::Sign {
+                psbt: verified.lock_tx,
+            })
+            .await
+            .context("Failed to send message to wallet actor")?
+            .await
+            .context("Failed to sign transaction")
+    }
+    .instrument(tracing::debug_span!("Send Sign to wallet actor"))
+    .timeout(LOCK_TX_SIGN_TIMEOUT, already_instrumented)
+    .await
+    .with_context(|| {
+        format!(
+            "Lock transaction was not signed within {} seconds",
+            LOCK_TX_SIGN_TIMEOUT.as_secs()
+        )
+    })??;
 
     sink.send(SetupMsg::Msg2(Msg2 {
         signed_lock: signed_lock_tx.clone(),
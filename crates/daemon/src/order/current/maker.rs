@@ -1,6 +1,5 @@
 use crate::command;
 use crate::oracle;
-use crate::oracle::NoAnnouncement;
 use crate::order::current::contract_setup;
 use crate::order::current::protocol;
 use crate::order::current::protocol::MakerMessage;
@@ -15,14 +14,12 @@ use anyhow::Result;
 use async_trait::async_trait;
 use asynchronous_codec::Framed;
 use asynchronous_codec::JsonCodec;
-use bdk::bitcoin::psbt::PartiallySignedTransaction;
 use bdk::bitcoin::XOnlyPublicKey;
 use futures::channel::oneshot;
 use futures::future;
 use futures::SinkExt;
 use futures::StreamExt;
 use maia_core::PartyParams;
-use model::olivia;
 use model::Cfd;
 use model::Identity;
 use model::OfferId;
@@ -44,12 +41,10 @@ const ORDER_TIMEOUT: Duration = Duration::from_secs(5);
 pub struct Actor {
     executor: command::Executor,
     oracle_pk: XOnlyPublicKey,
-    get_announcement:
-        MessageChannel<oracle::GetAnnouncements, Result<Vec<olivia::Announcement>, NoAnnouncement>>,
+    get_announcement: MessageChannel<oracle::GetAnnouncements, oracle::PendingAnnouncements>,
     build_party_params: MessageChannel<wallet::BuildPartyParams, Result<PartyParams>>,
-    sign: MessageChannel<wallet::Sign, Result<PartiallySignedTransaction>>,
+    sign: MessageChannel<wallet::Sign, wallet::PendingSignature>,
     projection: xtra::Address<projection::Actor>,
-    n_payouts: usize,
     decision_senders: HashMap<OrderId, oneshot::Sender<protocol::Decision>>,
     db: sqlite_db::Connection,
     latest_offers: MessageChannel<offer::maker::GetLatestOffers, Vec<model::Offer>>,
@@ -57,16 +52,12 @@ pub struct Actor {
 
 impl Actor {
     pub fn new(
-        n_payouts: usize,
         oracle_pk: XOnlyPublicKey,
-        get_announcement: MessageChannel<
-            oracle::GetAnnouncements,
-            Result<Vec<olivia::Announcement>, NoAnnouncement>,
-        >,
+        get_announcement: MessageChannel<oracle::GetAnnouncements, oracle::PendingAnnouncements>,
         (db, process_manager): (sqlite_db::Connection, xtra::Address<process_manager::Actor>),
         (build_party_params, sign): (
             MessageChannel<wallet::BuildPartyParams, Result<PartyParams>>,
-            MessageChannel<wallet::Sign, Result<PartiallySignedTransaction>>,
+            MessageChannel<wallet::Sign, wallet::PendingSignature>,
         ),
         projection: xtra::Address<projection::Actor>,
         latest_offers: MessageChannel<offer::maker::GetLatestOffers, Vec<model::Offer>>,
@@ -78,7 +69,6 @@ impl Actor {
             build_party_params,
             sign,
             projection,
-            n_payouts,
             decision_senders: HashMap::default(),
             db,
             latest_offers,
@@ -179,6 +169,7 @@ impl Actor {
         };
 
         let oracle_event_id = offer.oracle_event_id;
+        let n_payouts = offer.granularity.n_payouts;
 
         let cfd = Cfd::from_order(
             order_id,
@@ -217,7 +208,6 @@ impl Actor {
             let get_announcement = self.get_announcement.clone();
             let executor = self.executor.clone();
             let oracle_pk = self.oracle_pk;
-            let n_payouts = self.n_payouts;
             async move {
                 match receiver.await? {
                     protocol::Decision::Accept => {
@@ -252,7 +242,8 @@ impl Actor {
 
                 let announcement = get_announcement
                     .send(oracle::GetAnnouncements(vec![oracle_event_id]))
-                    .await??;
+                    .await?
+                    .await?;
 
                 let dlc = contract_setup::new(
                     sink.with(|msg| future::ok(MakerMessage::ContractSetupMsg(Box::new(msg)))),
@@ -1,13 +1,12 @@
 use crate::command;
 use crate::oracle;
-use crate::oracle::NoAnnouncement;
 use crate::order::current::contract_setup;
 use crate::order::current::protocol;
 use crate::order::current::protocol::Decision;
 use crate::order::current::protocol::MakerMessage;
 use crate::order::current::protocol::SetupMsg;
 use crate::order::current::protocol::TakerMessage;
-use crate::order::current::PROTOCOL;
+use crate::order::DIALER_PROTOCOLS;
 use crate::process_manager;
 use crate::projection;
 use crate::wallet;
@@ -17,14 +16,12 @@ use anyhow::Result;
 use async_trait::async_trait;
 use asynchronous_codec::Framed;
 use asynchronous_codec::JsonCodec;
-use bdk::bitcoin::psbt::PartiallySignedTransaction;
 use bdk::bitcoin::XOnlyPublicKey;
 use futures::future;
 use futures::SinkExt;
 use futures::StreamExt;
 use libp2p_core::PeerId;
 use maia_core::PartyParams;
-use model::olivia;
 use model::Cfd;
 use model::Contracts;
 use model::Identity;
@@ -46,27 +43,21 @@ pub struct Actor {
     endpoint: xtra::Address<Endpoint>,
     executor: command::Executor,
     oracle_pk: XOnlyPublicKey,
-    get_announcement:
-        MessageChannel<oracle::GetAnnouncements, Result<Vec<olivia::Announcement>, NoAnnouncement>>,
+    get_announcement: MessageChannel<oracle::GetAnnouncements, oracle::PendingAnnouncements>,
     build_party_params: MessageChannel<wallet::BuildPartyParams, Result<PartyParams>>,
-    sign: MessageChannel<wallet::Sign, Result<PartiallySignedTransaction>>,
+    sign: MessageChannel<wallet::Sign, wallet::PendingSignature>,
     projection: xtra::Address<projection::Actor>,
-    n_payouts: usize,
     db: sqlite_db::Connection,
 }
 
 impl Actor {
     pub fn new(
-        n_payouts: usize,
         oracle_pk: XOnlyPublicKey,
-        get_announcement: MessageChannel<
-            oracle::GetAnnouncements,
-            Result<Vec<olivia::Announcement>, NoAnnouncement>,
-        >,
+        get_announcement: MessageChannel<oracle::GetAnnouncements, oracle::PendingAnnouncements>,
         (db, process_manager): (sqlite_db::Connection, xtra::Address<process_manager::Actor>),
         (build_party_params, sign): (
             MessageChannel<wallet::BuildPartyParams, Result<PartyParams>>,
-            MessageChannel<wallet::Sign, Result<PartiallySignedTransaction>>,
+            MessageChannel<wallet::Sign, wallet::PendingSignature>,
         ),
         projection: xtra::Address<projection::Actor>,
         endpoint: xtra::Address<Endpoint>,
@@ -79,7 +70,6 @@ impl Actor {
             build_party_params,
             sign,
             projection,
-            n_payouts,
             db,
         }
     }
@@ -98,7 +88,6 @@ impl Actor {
             let executor = self.executor.clone();
             let db = self.db.clone();
             let oracle_pk = self.oracle_pk;
-            let n_payouts = self.n_payouts;
             let projection = self.projection.clone();
             async move {
                 tracing::info!(order = ?msg, "Placing order");
@@ -113,6 +102,7 @@ impl Actor {
                 } = msg;
 
                 let oracle_event_id = offer.oracle_event_id;
+                let n_payouts = offer.granularity.n_payouts;
                 let cfd = Cfd::from_order(
                     order_id,
                     &offer,
@@ -132,8 +122,8 @@ impl Actor {
 
                 projection.send(projection::CfdChanged(cfd.id())).await?;
 
-                let stream = endpoint
-                    .send(OpenSubstream::single_protocol(maker_peer_id, PROTOCOL))
+                let (_, stream) = endpoint
+                    .send(OpenSubstream::versioned(maker_peer_id, DIALER_PROTOCOLS))
                     .await
                     .context("Endpoint is disconnected")?
                     .context("No connection to peer")?
@@ -191,7 +181,8 @@ impl Actor {
 
                 let announcement = get_announcement
                     .send(oracle::GetAnnouncements(vec![oracle_event_id]))
-                    .await??;
+                    .await?
+                    .await?;
 
                 let dlc = contract_setup::new(
                     sink.with(|msg| future::ok(TakerMessage::ContractSetupMsg(Box::new(msg)))),
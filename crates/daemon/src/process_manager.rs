@@ -4,6 +4,7 @@ use crate::monitor::MonitorCetFinality;
 use crate::monitor::MonitorCollaborativeSettlement;
 use crate::monitor::TransactionKind;
 use crate::monitor::TryBroadcastTransaction;
+use crate::notifications;
 use crate::oracle;
 use crate::position_metrics;
 use crate::projection;
@@ -29,6 +30,8 @@ pub struct Actor {
     monitor_cet_finality: MessageChannel<MonitorCetFinality, Result<()>>,
     monitor_collaborative_settlement: MessageChannel<MonitorCollaborativeSettlement, ()>,
     monitor_attestation: MessageChannel<oracle::MonitorAttestations, ()>,
+    event_subscribers: Vec<MessageChannel<EventAppended, ()>>,
+    notifications: Option<notifications::Outbox>,
 }
 
 pub struct Event(CfdEvent);
@@ -39,6 +42,10 @@ impl Event {
     }
 }
 
+/// Notification about an event that was appended to the event log of a CFD.
+#[derive(Debug, Clone)]
+pub struct EventAppended(pub CfdEvent);
+
 impl Actor {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
@@ -52,6 +59,8 @@ impl Actor {
         monitor_cet_finality: MessageChannel<MonitorCetFinality, Result<()>>,
         monitor_collaborative_settlement: MessageChannel<MonitorCollaborativeSettlement, ()>,
         monitor_attestation: MessageChannel<oracle::MonitorAttestations, ()>,
+        event_subscribers: Vec<MessageChannel<EventAppended, ()>>,
+        notifications: Option<notifications::Outbox>,
     ) -> Self {
         Self {
             db,
@@ -64,6 +73,8 @@ impl Actor {
             monitor_cet_finality,
             monitor_collaborative_settlement,
             monitor_attestation,
+            event_subscribers,
+            notifications,
         }
     }
 }
@@ -73,12 +84,15 @@ impl Actor {
     fn handle(&mut self, msg: Event) -> Result<()> {
         let event = msg.0;
 
-        // 1. Safe in DB
-        self.db.append_event(event.clone()).await?;
+        // 1. Safe in DB, together with the notification about the event
+        match &self.notifications {
+            Some(outbox) => outbox.append_event(event.clone()).await?,
+            None => self.db.append_event(event.clone()).await?,
+        }
 
         // 2. Post process event
         use EventKind::*;
-        match event.event {
+        match event.event.clone() {
             ContractSetupCompleted { dlc: Some(dlc), .. } => {
                 let lock_tx = dlc.lock.0.clone();
 
@@ -232,6 +246,16 @@ impl Actor {
             .send_async_safe(position_metrics::CfdChanged(event.id))
             .await?;
 
+        // 5. Notify subscribers, e.g. for hedging
+        for subscriber in &self.event_subscribers {
+            if let Err(e) = subscriber
+                .send_async_safe(EventAppended(event.clone()))
+                .await
+            {
+                tracing::warn!("Failed to notify about appended event: {e:#}");
+            }
+        }
+
         Ok(())
     }
 }
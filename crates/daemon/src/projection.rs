@@ -1,8 +1,8 @@
+use crate::block_explorer::BlockExplorer;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
 use bdk::bitcoin::Amount;
-use bdk::bitcoin::Network;
 use bdk::bitcoin::Script;
 use bdk::bitcoin::SignedAmount;
 use bdk::bitcoin::Transaction;
@@ -24,6 +24,7 @@ use model::market_closing_price;
 use model::CfdEvent;
 use model::ClosedCfd;
 use model::ContractSymbol;
+use model::ContractType;
 use model::Contracts;
 use model::Dlc;
 use model::EventKind;
@@ -32,6 +33,7 @@ use model::FailedKind;
 use model::FeeAccount;
 use model::FundingFee;
 use model::FundingRate;
+use model::Identity;
 use model::Leverage;
 use model::LotSize;
 use model::OfferId;
@@ -41,7 +43,7 @@ use model::Price;
 use model::Role;
 use model::Settlement;
 use model::Timestamp;
-use model::SETTLEMENT_INTERVAL;
+use model::FUNDING_INTERVAL;
 use parse_display::Display;
 use parse_display::FromStr;
 use rust_decimal::Decimal;
@@ -51,10 +53,12 @@ use serde::Serialize;
 use sqlite_db;
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::fmt::Write;
+use std::collections::VecDeque;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 use time::OffsetDateTime;
+use tokio::sync::broadcast;
 use tokio::sync::watch;
 use tracing::info_span;
 use tracing::Instrument;
@@ -87,37 +91,210 @@ pub struct FeedReceivers {
     pub quote: watch::Receiver<LatestQuotes>,
     pub offers: watch::Receiver<MakerOffers>,
     pub cfds: watch::Receiver<Option<Vec<Cfd>>>,
+    pub cfd_updates: CfdUpdates,
 }
 
 pub struct FeedSenders {
     pub quote: watch::Sender<LatestQuotes>,
     pub offers: watch::Sender<MakerOffers>,
     pub cfds: watch::Sender<Option<Vec<Cfd>>>,
+    pub cfd_updates: CfdUpdates,
 }
 
 pub fn feeds() -> (FeedSenders, FeedReceivers) {
     let (tx_quote, rx_quote) = watch::channel(LatestQuotes::default());
     let (tx_offers, rx_offers) = watch::channel(MakerOffers::default());
     let (tx_cfds, rx_cfds) = watch::channel(None);
+    let cfd_updates = CfdUpdates::new();
 
     (
         FeedSenders {
             quote: tx_quote,
             offers: tx_offers,
             cfds: tx_cfds,
+            cfd_updates: cfd_updates.clone(),
         },
         FeedReceivers {
             quote: rx_quote,
             offers: rx_offers,
             cfds: rx_cfds,
+            cfd_updates,
         },
     )
 }
 
+/// Number of CFD updates kept around for clients resuming the feed
+const CFD_UPDATES_RETAINED: usize = 10_000;
+
+/// Number of CFD updates buffered for each subscriber before it lags behind
+const CFD_UPDATES_BUFFERED: usize = 1_000;
+
+/// A change to a single CFD in the feed
+#[derive(Debug, Clone)]
+pub enum CfdChange {
+    /// The CFD was added or changed
+    Upsert(Box<Cfd>),
+    /// The CFD is no longer part of the feed
+    Remove(OrderId),
+}
+
+#[derive(Debug, Clone)]
+pub struct CfdUpdate {
+    pub sequence: u64,
+    pub change: CfdChange,
+}
+
+/// How a subscriber catches up with the CFD feed
+#[derive(Debug)]
+pub enum CfdSync {
+    /// All CFDs as of the given sequence number
+    Snapshot { sequence: u64, cfds: Vec<Cfd> },
+    /// The updates that happened after the sequence number the subscriber resumed from
+    Resume(Vec<CfdUpdate>),
+}
+
+pub struct CfdSubscription {
+    pub sync: CfdSync,
+    /// Updates after the ones covered by `sync`
+    ///
+    /// If the receiver lags behind the subscriber has to subscribe again, resuming from the last
+    /// sequence number it has seen.
+    pub updates: broadcast::Receiver<CfdUpdate>,
+}
+
+/// Sequenced per-CFD updates of the CFD feed
+///
+/// In contrast to the `cfds` watch channel, which always carries the full list, this only carries
+/// the CFDs that actually changed. The most recent updates are retained so that subscribers can
+/// resume from the last sequence number they have seen after reconnecting. Subscribers that are
+/// too far behind (or new ones) start from a snapshot instead.
+#[derive(Clone)]
+pub struct CfdUpdates {
+    log: Arc<Mutex<CfdLog>>,
+    tx: broadcast::Sender<CfdUpdate>,
+}
+
+struct CfdLog {
+    /// Sequence number of the latest update
+    sequence: u64,
+    /// The CFDs as of the latest update, in the order they are published
+    cfds: Vec<Cfd>,
+    updates: VecDeque<CfdUpdate>,
+}
+
+impl CfdUpdates {
+    fn new() -> Self {
+        let (tx, _) = broadcast::channel(CFD_UPDATES_BUFFERED);
+
+        // Sequence numbers of a previous run are unlikely to be valid in this run, starting at the
+        // current time (instead of zero) makes sure they are not mistaken for valid ones.
+        let sequence = (tokio_extras::time::now_utc().unix_timestamp_nanos() / 1_000) as u64;
+
+        Self {
+            log: Arc::new(Mutex::new(CfdLog {
+                sequence,
+                cfds: Vec::new(),
+                updates: VecDeque::new(),
+            })),
+            tx,
+        }
+    }
+
+    /// Subscribe to the updates of the CFD feed
+    ///
+    /// Resumes after the given sequence number if possible, otherwise starts with a snapshot.
+    pub fn subscribe(&self, resume_from: Option<u64>) -> CfdSubscription {
+        let log = self.log.lock().expect("lock not to be poisoned");
+
+        // Subscribing while holding the lock ensures that we don't miss any updates
+        let updates = self.tx.subscribe();
+
+        CfdSubscription {
+            sync: log.sync(resume_from),
+            updates,
+        }
+    }
+
+    /// Publish the changes between the previously published CFDs and `cfds`
+    fn publish(&self, cfds: &[Cfd]) {
+        let mut log = self.log.lock().expect("lock not to be poisoned");
+
+        for change in diff_cfds(&log.cfds, cfds) {
+            let update = log.append(change);
+
+            // Sending only fails if nobody is subscribed
+            let _ = self.tx.send(update);
+        }
+
+        log.cfds = cfds.to_vec();
+    }
+}
+
+impl CfdLog {
+    fn sync(&self, resume_from: Option<u64>) -> CfdSync {
+        if let Some(resume_from) = resume_from {
+            let oldest_retained = self
+                .updates
+                .front()
+                .map_or(self.sequence, |update| update.sequence - 1);
+
+            if (oldest_retained..=self.sequence).contains(&resume_from) {
+                return CfdSync::Resume(
+                    self.updates
+                        .iter()
+                        .filter(|update| update.sequence > resume_from)
+                        .cloned()
+                        .collect(),
+                );
+            }
+        }
+
+        CfdSync::Snapshot {
+            sequence: self.sequence,
+            cfds: self.cfds.clone(),
+        }
+    }
+
+    fn append(&mut self, change: CfdChange) -> CfdUpdate {
+        self.sequence += 1;
+
+        let update = CfdUpdate {
+            sequence: self.sequence,
+            change,
+        };
+
+        if self.updates.len() == CFD_UPDATES_RETAINED {
+            self.updates.pop_front();
+        }
+        self.updates.push_back(update.clone());
+
+        update
+    }
+}
+
+fn diff_cfds(old: &[Cfd], new: &[Cfd]) -> Vec<CfdChange> {
+    let old_cfds = old
+        .iter()
+        .map(|cfd| (cfd.order_id, cfd))
+        .collect::<HashMap<_, _>>();
+    let new_ids = new.iter().map(|cfd| cfd.order_id).collect::<HashSet<_>>();
+
+    let upserts = new
+        .iter()
+        .filter(|cfd| old_cfds.get(&cfd.order_id) != Some(cfd))
+        .map(|cfd| CfdChange::Upsert(Box::new(cfd.clone())));
+    let removals = old
+        .iter()
+        .filter(|cfd| !new_ids.contains(&cfd.order_id))
+        .map(|cfd| CfdChange::Remove(cfd.order_id));
+
+    upserts.chain(removals).collect()
+}
+
 impl Actor {
     pub fn new(
         db: sqlite_db::Connection,
-        network: Network,
+        block_explorer: BlockExplorer,
         price_feed: MessageChannel<GetLatestQuotes, xtra_bitmex_price_feed::LatestQuotes>,
         role: Role,
         feed_senders: Arc<FeedSenders>,
@@ -125,7 +302,7 @@ impl Actor {
         Self {
             db,
             tx: Tx(feed_senders),
-            state: State::new(network),
+            state: State::new(block_explorer),
             price_feed,
             role,
         }
@@ -189,17 +366,26 @@ pub struct Cfd {
     #[serde(with = "::time::serde::timestamp::option")]
     pub expiry_timestamp: Option<OffsetDateTime>,
 
+    /// Whether the CFD is rolled over or settles at a fixed expiry
+    ///
+    /// Only known for open CFDs, closed and failed CFDs are only kept as a summary.
+    pub contract_type: Option<ContractType>,
+
     pub counterparty: PeerId,
 
     #[serde(with = "round_to_two_dp::opt")]
     pub pending_settlement_proposal_price: Option<Price>,
 
+    /// Legacy network identity of the counterparty
+    #[serde(skip)]
+    counterparty_network_identity: Identity,
+
     #[serde(skip)]
     #[derivative(PartialEq = "ignore")]
     aggregated: Aggregated,
 
     #[serde(skip)]
-    network: Network,
+    block_explorer: BlockExplorer,
 }
 
 /// Bundle all state extracted from the events in one struct.
@@ -238,6 +424,8 @@ pub struct Aggregated {
 
     version: u32,
     creation_timestamp: Timestamp,
+    /// Time of the event that closed or failed the CFD
+    closing_timestamp: Option<Timestamp>,
 }
 
 impl Aggregated {
@@ -256,6 +444,7 @@ impl Aggregated {
             settlement_state: None,
             version: 0,
             creation_timestamp: Timestamp::now(),
+            closing_timestamp: None,
         }
     }
 
@@ -328,15 +517,18 @@ impl Cfd {
             position,
             initial_price,
             taker_leverage,
+            settlement_interval,
             quantity,
+            counterparty_network_identity,
             counterparty_peer_id,
             role,
             opening_fee,
             initial_funding_rate,
             contract_symbol,
+            contract_type,
             ..
         }: sqlite_db::Cfd,
-        network: Network,
+        block_explorer: BlockExplorer,
     ) -> Self {
         let (our_leverage, counterparty_leverage) = match role {
             Role::Maker => (Leverage::ONE, taker_leverage),
@@ -371,7 +563,7 @@ impl Cfd {
             long_leverage,
             short_leverage,
             initial_funding_rate,
-            SETTLEMENT_INTERVAL.whole_hours(),
+            contract_type.initial_funding_hours(settlement_interval),
             contract_symbol,
         )
         .expect("values from db to be sane");
@@ -410,11 +602,14 @@ impl Cfd {
             details: CfdDetails {
                 tx_url_list: HashSet::new(),
             },
-            expiry_timestamp: None,
+            // Dated futures expire at a fixed time, even before the DLC is set up
+            expiry_timestamp: contract_type.expiry(),
+            contract_type: Some(contract_type),
             counterparty: counterparty_peer_id.unwrap_or_else(PeerId::placeholder),
             pending_settlement_proposal_price: None,
+            counterparty_network_identity,
             aggregated: Aggregated::new(fee_account),
-            network,
+            block_explorer,
         }
     }
 
@@ -578,19 +773,28 @@ impl Cfd {
         self.state = self.aggregated.derive_cfd_state(self.role);
         self.actions = self.derive_actions();
 
-        if let Some(lock_tx_url) = self.lock_tx_url(self.network) {
+        if self.aggregated.closing_timestamp.is_none()
+            && matches!(
+                self.aggregated.state,
+                CfdState::Closed | CfdState::Refunded | CfdState::Rejected | CfdState::SetupFailed
+            )
+        {
+            self.aggregated.closing_timestamp = Some(event.timestamp);
+        }
+
+        if let Some(lock_tx_url) = self.lock_tx_url() {
             self.details.tx_url_list.insert(lock_tx_url);
         }
-        if let Some(commit_tx_url) = self.commit_tx_url(self.network) {
+        if let Some(commit_tx_url) = self.commit_tx_url() {
             self.details.tx_url_list.insert(commit_tx_url);
         }
-        if let Some(collab_settlement_tx_url) = self.collab_settlement_tx_url(self.network) {
+        if let Some(collab_settlement_tx_url) = self.collab_settlement_tx_url() {
             self.details.tx_url_list.insert(collab_settlement_tx_url);
         }
-        if let Some(refund_tx_url) = self.refund_tx_url(self.network) {
+        if let Some(refund_tx_url) = self.refund_tx_url() {
             self.details.tx_url_list.insert(refund_tx_url);
         }
-        if let Some(cet_url) = self.cet_url(self.network) {
+        if let Some(cet_url) = self.cet_url() {
             self.details.tx_url_list.insert(cet_url);
         }
 
@@ -599,6 +803,25 @@ impl Cfd {
         self
     }
 
+    /// Time of the first event of the CFD
+    pub fn creation_timestamp(&self) -> Timestamp {
+        self.aggregated.creation_timestamp
+    }
+
+    /// Time at which the CFD was closed or failed, `None` as long as it is open
+    pub fn closing_timestamp(&self) -> Option<Timestamp> {
+        self.aggregated.closing_timestamp
+    }
+
+    pub fn counterparty_network_identity(&self) -> Identity {
+        self.counterparty_network_identity
+    }
+
+    /// The opening and funding fees charged so far
+    pub fn fee_account(&self) -> FeeAccount {
+        self.aggregated.fee_account
+    }
+
     pub fn with_current_quote(self, latest_quotes: Option<&LatestQuotes>) -> Self {
         // If the payout was already set we don't care about the current quote, this applies to
         // closed CFDs
@@ -720,25 +943,25 @@ impl Cfd {
     /// Returns the URL to the lock transaction.
     ///
     /// If we have a DLC, we also have a lock transaction.
-    fn lock_tx_url(&self, network: Network) -> Option<TxUrl> {
+    fn lock_tx_url(&self) -> Option<TxUrl> {
         let dlc = self.aggregated.latest_dlc.as_ref()?;
         let url = TxUrl::from_transaction(
             &dlc.lock.0.clone(),
             &dlc.lock.1.script_pubkey(),
-            network,
+            &self.block_explorer,
             TxLabel::Lock,
         );
 
         Some(url)
     }
 
-    fn commit_tx_url(&self, network: Network) -> Option<TxUrl> {
+    fn commit_tx_url(&self) -> Option<TxUrl> {
         if !self.aggregated.commit_published {
             return None;
         }
 
         let dlc = self.aggregated.latest_dlc.as_ref()?;
-        let url = TxUrl::new(dlc.commit.0.txid(), network, TxLabel::Commit);
+        let url = TxUrl::new(dlc.commit.0.txid(), &self.block_explorer, TxLabel::Commit);
 
         Some(url)
     }
@@ -748,14 +971,14 @@ impl Cfd {
         &self.aggregated
     }
 
-    fn collab_settlement_tx_url(&self, network: Network) -> Option<TxUrl> {
+    fn collab_settlement_tx_url(&self) -> Option<TxUrl> {
         let (tx, script) = self.aggregated.collab_settlement_tx.as_ref()?;
-        let url = TxUrl::from_transaction(tx, script, network, TxLabel::Collaborative);
+        let url = TxUrl::from_transaction(tx, script, &self.block_explorer, TxLabel::Collaborative);
 
         Some(url)
     }
 
-    fn refund_tx_url(&self, network: Network) -> Option<TxUrl> {
+    fn refund_tx_url(&self) -> Option<TxUrl> {
         if !self.aggregated.refund_published {
             return None;
         }
@@ -765,19 +988,23 @@ impl Cfd {
         let url = TxUrl::from_transaction(
             &dlc.refund.0.clone(),
             &dlc.script_pubkey_for(self.role),
-            network,
+            &self.block_explorer,
             TxLabel::Refund,
         );
 
         Some(url)
     }
 
-    fn cet_url(&self, network: Network) -> Option<TxUrl> {
+    fn cet_url(&self) -> Option<TxUrl> {
         let tx = self.aggregated.cet.as_ref()?;
         let dlc = self.aggregated.latest_dlc.as_ref()?;
 
-        let url =
-            TxUrl::from_transaction(tx, &dlc.script_pubkey_for(self.role), network, TxLabel::Cet);
+        let url = TxUrl::from_transaction(
+            tx,
+            &dlc.script_pubkey_for(self.role),
+            &self.block_explorer,
+            TxLabel::Cet,
+        );
 
         Some(url)
     }
@@ -797,8 +1024,9 @@ impl Tx {
                     &a.aggregated.creation_timestamp,
                 )
             })
-            .collect();
+            .collect::<Vec<_>>();
 
+        self.0.cfd_updates.publish(&cfds_with_quote);
         let _ = self.0.cfds.send(Some(cfds_with_quote));
     }
 
@@ -815,7 +1043,7 @@ impl Tx {
 
 /// Internal struct to keep state in one place
 struct State {
-    network: Network,
+    block_explorer: BlockExplorer,
     latest_quotes: LatestQuotes,
     offers: MakerOffers,
     /// All hydrated CFDs.
@@ -823,7 +1051,7 @@ struct State {
 }
 
 impl sqlite_db::CfdAggregate for Cfd {
-    type CtorArgs = Network;
+    type CtorArgs = BlockExplorer;
 
     fn new(args: Self::CtorArgs, cfd: sqlite_db::Cfd) -> Self {
         Cfd::new(cfd, args)
@@ -839,7 +1067,7 @@ impl sqlite_db::CfdAggregate for Cfd {
 }
 
 impl sqlite_db::ClosedCfdAggregate for Cfd {
-    fn new_closed(network: Self::CtorArgs, closed_cfd: ClosedCfd) -> Self {
+    fn new_closed(block_explorer: Self::CtorArgs, closed_cfd: ClosedCfd) -> Self {
         let ClosedCfd {
             id,
             offer_id,
@@ -847,6 +1075,7 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
             initial_price,
             taker_leverage,
             n_contracts: quantity,
+            counterparty_network_identity,
             counterparty_peer_id,
             role,
             fees,
@@ -854,6 +1083,7 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
             lock,
             settlement,
             creation_timestamp,
+            closing_timestamp,
             contract_symbol,
             ..
         } = closed_cfd;
@@ -883,10 +1113,12 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
         let (details, closing_price, payout, state) = {
             let mut tx_url_list = HashSet::default();
 
-            tx_url_list.insert(
-                TxUrl::new(lock.txid, network, TxLabel::Lock)
-                    .with_output_index(lock.dlc_vout.into()),
-            );
+            tx_url_list.insert(TxUrl::output(
+                lock.txid,
+                lock.dlc_vout.into(),
+                &block_explorer,
+                TxLabel::Lock,
+            ));
 
             let (price, payout, state) = match settlement {
                 Settlement::Collaborative {
@@ -895,10 +1127,12 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
                     payout,
                     price,
                 } => {
-                    tx_url_list.insert(
-                        TxUrl::new(txid, network, TxLabel::Collaborative)
-                            .with_output_index(vout.into()),
-                    );
+                    tx_url_list.insert(TxUrl::output(
+                        txid,
+                        vout.into(),
+                        &block_explorer,
+                        TxLabel::Collaborative,
+                    ));
                     (Some(price), payout, CfdState::Closed)
                 }
                 Settlement::Cet {
@@ -908,13 +1142,19 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
                     payout,
                     price,
                 } => {
-                    tx_url_list.insert(
-                        TxUrl::new(commit_txid, network, TxLabel::Commit).with_output_index(0),
-                    );
-
-                    tx_url_list.insert(
-                        TxUrl::new(txid, network, TxLabel::Cet).with_output_index(vout.into()),
-                    );
+                    tx_url_list.insert(TxUrl::output(
+                        commit_txid,
+                        0,
+                        &block_explorer,
+                        TxLabel::Commit,
+                    ));
+
+                    tx_url_list.insert(TxUrl::output(
+                        txid,
+                        vout.into(),
+                        &block_explorer,
+                        TxLabel::Cet,
+                    ));
                     (Some(price), payout, CfdState::Closed)
                 }
                 Settlement::Refund {
@@ -923,13 +1163,19 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
                     vout,
                     payout,
                 } => {
-                    tx_url_list.insert(
-                        TxUrl::new(commit_txid, network, TxLabel::Commit).with_output_index(0),
-                    );
-
-                    tx_url_list.insert(
-                        TxUrl::new(txid, network, TxLabel::Refund).with_output_index(vout.into()),
-                    );
+                    tx_url_list.insert(TxUrl::output(
+                        commit_txid,
+                        0,
+                        &block_explorer,
+                        TxLabel::Commit,
+                    ));
+
+                    tx_url_list.insert(TxUrl::output(
+                        txid,
+                        vout.into(),
+                        &block_explorer,
+                        TxLabel::Refund,
+                    ));
                     (None, payout, CfdState::Refunded)
                 }
             };
@@ -945,6 +1191,7 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
 
         // set the creation_timestamp to be able to sort closed CFDs
         aggregated.creation_timestamp = creation_timestamp;
+        aggregated.closing_timestamp = Some(closing_timestamp);
 
         Self {
             order_id: id,
@@ -969,16 +1216,18 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
             actions: HashSet::default(),
             details,
             expiry_timestamp: Some(expiry_timestamp),
+            contract_type: None,
             counterparty: counterparty_peer_id,
             pending_settlement_proposal_price: None,
+            counterparty_network_identity,
             aggregated,
-            network,
+            block_explorer,
         }
     }
 }
 
 impl sqlite_db::FailedCfdAggregate for Cfd {
-    fn new_failed(network: Self::CtorArgs, failed_cfd: FailedCfd) -> Self {
+    fn new_failed(block_explorer: Self::CtorArgs, failed_cfd: FailedCfd) -> Self {
         let FailedCfd {
             id,
             offer_id,
@@ -986,11 +1235,13 @@ impl sqlite_db::FailedCfdAggregate for Cfd {
             initial_price,
             taker_leverage,
             n_contracts: quantity,
+            counterparty_network_identity,
             counterparty_peer_id,
             role,
             fees,
             kind,
             creation_timestamp,
+            closing_timestamp,
             contract_symbol,
             ..
         } = failed_cfd;
@@ -1028,6 +1279,7 @@ impl sqlite_db::FailedCfdAggregate for Cfd {
 
         // set the creation_timestamp to be able to sort failed CFDs
         aggregated.creation_timestamp = creation_timestamp;
+        aggregated.closing_timestamp = Some(closing_timestamp);
 
         Self {
             order_id: id,
@@ -1054,18 +1306,20 @@ impl sqlite_db::FailedCfdAggregate for Cfd {
                 tx_url_list: HashSet::default(),
             },
             expiry_timestamp: None,
+            contract_type: None,
             counterparty: counterparty_peer_id,
             pending_settlement_proposal_price: None,
+            counterparty_network_identity,
             aggregated,
-            network,
+            block_explorer,
         }
     }
 }
 
 impl State {
-    fn new(network: Network) -> Self {
+    fn new(block_explorer: BlockExplorer) -> Self {
         Self {
-            network,
+            block_explorer,
             latest_quotes: LatestQuotes::default(),
             cfds: None,
             offers: MakerOffers::default(),
@@ -1073,7 +1327,7 @@ impl State {
     }
 
     async fn update_cfd(&mut self, db: sqlite_db::Connection, id: OrderId) -> Result<()> {
-        let cfd = db.load_open_cfd(id, self.network).await?;
+        let cfd = db.load_open_cfd(id, self.block_explorer.clone()).await?;
 
         let cfds = self
             .cfds
@@ -1120,7 +1374,9 @@ impl State {
 #[xtra_productivity]
 impl Actor {
     async fn handle(&mut self, _: Initialize) {
-        let mut stream = self.db.load_all_cfds::<Cfd>(self.state.network);
+        let mut stream = self
+            .db
+            .load_all_cfds::<Cfd>(self.state.block_explorer.clone());
 
         let mut cfds = HashMap::new();
 
@@ -1250,6 +1506,21 @@ pub struct Quote {
     last_updated_at: Timestamp,
 }
 
+impl Quote {
+    pub fn bid(&self) -> Decimal {
+        self.bid
+    }
+
+    pub fn ask(&self) -> Decimal {
+        self.ask
+    }
+
+    /// The price in the middle between bid and ask
+    pub fn mid(&self) -> Decimal {
+        (self.bid + self.ask) / Decimal::TWO
+    }
+}
+
 impl From<xtra_bitmex_price_feed::Quote> for Quote {
     fn from(quote: xtra_bitmex_price_feed::Quote) -> Self {
         Quote {
@@ -1332,6 +1603,14 @@ pub struct CfdOffer {
 
     pub creation_timestamp: Timestamp,
     pub settlement_time_interval_in_secs: u64,
+
+    /// Whether the offer is for a perpetual CFD or a dated future, including its expiry
+    pub contract_type: ContractType,
+
+    /// Number of payouts per oracle event
+    pub n_payouts: usize,
+    /// Time between the oracle events at which the position can be liquidated
+    pub liquidation_interval_in_secs: u64,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
@@ -1397,7 +1676,9 @@ impl CfdOffer {
                     long_leverage,
                     short_leverage,
                     offer.funding_rate,
-                    SETTLEMENT_INTERVAL.whole_hours(),
+                    offer
+                        .contract_type
+                        .initial_funding_hours(offer.settlement_interval),
                     offer.contract_symbol,
                 )
                 .context("unable to calculate initial funding fee")?;
@@ -1432,6 +1713,14 @@ impl CfdOffer {
                 .whole_seconds()
                 .try_into()
                 .context("unable to convert settlement interval")?,
+            contract_type: offer.contract_type,
+            n_payouts: offer.granularity.n_payouts,
+            liquidation_interval_in_secs: offer
+                .granularity
+                .liquidation_interval
+                .whole_seconds()
+                .try_into()
+                .context("unable to convert liquidation interval")?,
             opening_fee: Some(offer.opening_fee.to_inner()),
             funding_rate_annualized_percent: AnnualisedFundingPercent::from(offer.funding_rate)
                 .to_string(),
@@ -1440,7 +1729,7 @@ impl CfdOffer {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CfdState {
     PendingSetup,
     ContractSetup,
@@ -1561,35 +1850,32 @@ mod round_to_two_dp {
     }
 }
 
-/// Construct a mempool.space URL for a given txid
-pub fn to_mempool_url(txid: Txid, network: Network) -> String {
-    match network {
-        Network::Bitcoin => format!("https://mempool.space/tx/{txid}"),
-        Network::Testnet => format!("https://mempool.space/testnet/tx/{txid}"),
-        Network::Signet => format!("https://mempool.space/signet/tx/{txid}"),
-        Network::Regtest => txid.to_string(),
-    }
-}
-
-/// Link to transaction on mempool.space for UI representation
+/// Link to a transaction on the configured block explorer for UI representation
+///
+/// The `url` is `null` if links to the block explorer are disabled.
 #[derive(Debug, Clone, Serialize, PartialEq, Eq, Hash)]
 struct TxUrl {
     pub label: TxLabel,
-    pub url: String,
+    pub txid: Txid,
+    pub url: Option<String>,
 }
 
 impl TxUrl {
-    fn new(txid: Txid, network: Network, label: TxLabel) -> Self {
+    fn new(txid: Txid, block_explorer: &BlockExplorer, label: TxLabel) -> Self {
         Self {
             label,
-            url: to_mempool_url(txid, network),
+            txid,
+            url: block_explorer.tx_url(txid),
         }
     }
 
     /// Highlight particular transaction output in the TxUrl
-    fn with_output_index(mut self, index: u32) -> Self {
-        let _ = write!(self.url, ":{index}");
-        self
+    fn output(txid: Txid, vout: u32, block_explorer: &BlockExplorer, label: TxLabel) -> Self {
+        Self {
+            label,
+            txid,
+            url: block_explorer.output_url(txid, vout),
+        }
     }
 
     /// If the Transaction contains the script_pubkey, output will be selected
@@ -1597,15 +1883,15 @@ impl TxUrl {
     fn from_transaction(
         transaction: &Transaction,
         script_pubkey: &Script,
-        network: Network,
+        block_explorer: &BlockExplorer,
         label: TxLabel,
     ) -> Self {
         debug_assert!(label != TxLabel::Commit, "commit transaction has a single output which does not belong to either party - this won't highlight anything");
-        let tx_url = Self::new(transaction.txid(), network, label);
+        let txid = transaction.txid();
         if let Ok(outpoint) = transaction.outpoint(script_pubkey) {
-            tx_url.with_output_index(outpoint.vout)
+            Self::output(txid, outpoint.vout, block_explorer, label)
         } else {
-            tx_url
+            Self::new(txid, block_explorer, label)
         }
     }
 }
@@ -1628,9 +1914,7 @@ impl From<FundingRate> for AnnualisedFundingPercent {
                 .to_decimal()
                 .checked_mul(dec!(100))
                 .expect("Not to overflow for funding rate")
-                .checked_mul(Decimal::from(
-                    (24 / SETTLEMENT_INTERVAL.whole_hours()) * 365,
-                ))
+                .checked_mul(Decimal::from((24 / FUNDING_INTERVAL.whole_hours()) * 365))
                 .expect("not to overflow"),
         )
     }
@@ -1651,7 +1935,7 @@ impl From<FundingRate> for HourlyFundingPercent {
                 .to_decimal()
                 .checked_mul(dec!(100))
                 .expect("Not to overflow for funding rate")
-                .checked_div(Decimal::from(SETTLEMENT_INTERVAL.whole_hours()))
+                .checked_div(Decimal::from(FUNDING_INTERVAL.whole_hours()))
                 .expect("Not to fail as funding rate is sanitised"),
         )
     }
@@ -1668,6 +1952,7 @@ mod tests {
     use super::*;
     use model::OfferId;
     use model::OpeningFee;
+    use model::PayoutGranularity;
     use model::TxFeeRate;
     use sqlite_db::memory;
 
@@ -1713,6 +1998,8 @@ mod tests {
             FundingRate::default(),
             TxFeeRate::default(),
             ContractSymbol::BtcUsd,
+            ContractType::Perpetual,
+            PayoutGranularity::default(),
         )
     }
 
@@ -1754,6 +2041,8 @@ mod tests {
             FundingRate::default(),
             TxFeeRate::default(),
             ContractSymbol::BtcUsd,
+            ContractType::Perpetual,
+            PayoutGranularity::default(),
         );
 
         let contract_setup_completed =
@@ -1808,7 +2097,7 @@ mod tests {
 
         let projection_open = {
             let projection_open = db
-                .load_open_cfd::<Cfd>(order_id, bdk::bitcoin::Network::Testnet)
+                .load_open_cfd::<Cfd>(order_id, BlockExplorer::None)
                 .await
                 .unwrap();
             projection_open.with_current_quote(None) // unconditional processing in `projection`
@@ -1818,7 +2107,7 @@ mod tests {
 
         let projection_failed = {
             let projection_failed = db
-                .load_failed_cfd::<Cfd>(order_id, bdk::bitcoin::Network::Testnet)
+                .load_failed_cfd::<Cfd>(order_id, BlockExplorer::None)
                 .await
                 .unwrap();
             projection_failed.with_current_quote(None) // unconditional processing in `projection`
@@ -1844,7 +2133,7 @@ mod tests {
 
         let projection_open = {
             let projection_open = db
-                .load_open_cfd::<Cfd>(order_id, bdk::bitcoin::Network::Testnet)
+                .load_open_cfd::<Cfd>(order_id, BlockExplorer::None)
                 .await
                 .unwrap();
             projection_open.with_current_quote(None) // unconditional processing in `projection`
@@ -1854,7 +2143,7 @@ mod tests {
 
         let projection_failed = {
             let projection_failed = db
-                .load_failed_cfd::<Cfd>(order_id, bdk::bitcoin::Network::Testnet)
+                .load_failed_cfd::<Cfd>(order_id, BlockExplorer::None)
                 .await
                 .unwrap();
             projection_failed.with_current_quote(None) // unconditional processing in `projection`
@@ -1888,7 +2177,7 @@ mod tests {
 
         let projection_open = {
             let projection_open = db
-                .load_open_cfd::<Cfd>(order_id, bdk::bitcoin::Network::Testnet)
+                .load_open_cfd::<Cfd>(order_id, BlockExplorer::None)
                 .await
                 .unwrap();
             projection_open.with_current_quote(None) // unconditional processing in `projection`
@@ -1898,7 +2187,7 @@ mod tests {
 
         let projection_closed = {
             let projection_closed = db
-                .load_closed_cfd::<Cfd>(order_id, bdk::bitcoin::Network::Testnet)
+                .load_closed_cfd::<Cfd>(order_id, BlockExplorer::None)
                 .await
                 .unwrap();
             let mut projection_closed = projection_closed.with_current_quote(None); // unconditional processing in `projection`
@@ -1923,4 +2212,69 @@ mod tests {
         // from a closed CFD
         assert_eq!(projection_open, projection_closed);
     }
+
+    #[tokio::test]
+    async fn cfd_updates_resume_after_retained_sequence_or_start_from_snapshot() {
+        let db = memory().await.unwrap();
+
+        let cfd = dummy_cfd();
+        db.insert_cfd(&cfd).await.unwrap();
+        let projection_cfd = db
+            .load_open_cfd::<Cfd>(cfd.id(), BlockExplorer::None)
+            .await
+            .unwrap()
+            .with_current_quote(None);
+
+        let cfd_updates = CfdUpdates::new();
+        let mut subscription = cfd_updates.subscribe(None);
+        let initial_sequence = match subscription.sync {
+            CfdSync::Snapshot { sequence, cfds } => {
+                assert!(cfds.is_empty());
+                sequence
+            }
+            CfdSync::Resume(_) => panic!("expected snapshot on first subscription"),
+        };
+
+        cfd_updates.publish(&[projection_cfd.clone()]);
+        cfd_updates.publish(&[projection_cfd]); // unchanged, hence no update
+        cfd_updates.publish(&[]);
+
+        let resumed = match cfd_updates.subscribe(Some(initial_sequence)).sync {
+            CfdSync::Resume(updates) => updates,
+            CfdSync::Snapshot { .. } => panic!("expected to resume"),
+        };
+        assert!(matches!(
+            resumed.as_slice(),
+            [
+                CfdUpdate { sequence: upserted, change: CfdChange::Upsert(upsert) },
+                CfdUpdate { sequence: removed, change: CfdChange::Remove(order_id) },
+            ] if *upserted == initial_sequence + 1
+                && upsert.order_id == cfd.id()
+                && *removed == initial_sequence + 2
+                && *order_id == cfd.id()
+        ));
+
+        let broadcast_sequences = [
+            subscription.updates.try_recv().unwrap().sequence,
+            subscription.updates.try_recv().unwrap().sequence,
+        ];
+        assert_eq!(
+            broadcast_sequences,
+            [initial_sequence + 1, initial_sequence + 2]
+        );
+        assert!(subscription.updates.try_recv().is_err());
+
+        assert!(matches!(
+            cfd_updates.subscribe(Some(initial_sequence + 2)).sync,
+            CfdSync::Resume(updates) if updates.is_empty()
+        ));
+        assert!(matches!(
+            cfd_updates.subscribe(Some(initial_sequence + 3)).sync,
+            CfdSync::Snapshot { cfds, .. } if cfds.is_empty()
+        ));
+        assert!(matches!(
+            cfd_updates.subscribe(Some(initial_sequence - 1)).sync,
+            CfdSync::Snapshot { .. }
+        ));
+    }
 }
@@ -133,6 +133,12 @@ impl RandomSeed {
     }
 }
 
+impl From<[u8; 256]> for RandomSeed {
+    fn from(bytes: [u8; 256]) -> Self {
+        Self(bytes)
+    }
+}
+
 impl Default for RandomSeed {
     fn default() -> Self {
         let mut seed = [0u8; 256];
@@ -0,0 +1,1137 @@
+//! Funding the wallet from Lightning through reverse submarine swaps.
+//!
+//! The taker generates a preimage and requests a swap locked to its hash. The swap server returns
+//! a hold invoice for that hash and, once the invoice is paid, locks the swapped amount in an
+//! on-chain HTLC which only the taker can claim with the preimage, see [`htlc`]. Claiming the HTLC
+//! reveals the preimage on chain, which in turn lets the swap server settle the invoice. The swap
+//! server is thus only paid once the taker claimed the on-chain funds.
+//!
+//! The HTLC is only claimed once it is confirmed, locks at least the swapped amount and does not
+//! time out within [`CLAIM_MARGIN_BLOCKS`]. Otherwise the preimage is never revealed: the swap
+//! server cannot settle the invoice, the Lightning payment is returned to the taker and the swap
+//! server refunds the HTLC to itself after its timeout.
+//!
+//! The claimed funds, minus the fee of the claim transaction, are sent to an address of the
+//! taker's wallet. From there they can be used to open positions like any other on-chain funds.
+//! Funding the inputs of a lock transaction directly is out of scope, swaps only fund the wallet.
+//!
+//! Swaps are stored in the database, including their preimage and claim key, such that HTLCs
+//! locked while the daemon was not running are claimed after a restart.
+
+use crate::swap::htlc::Htlc;
+use crate::swap::invoice::Invoice;
+use crate::wallet;
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use bdk::bitcoin::hashes::sha256;
+use bdk::bitcoin::hashes::Hash;
+use bdk::bitcoin::secp256k1;
+use bdk::bitcoin::Address;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::OutPoint;
+use bdk::bitcoin::PublicKey;
+use bdk::bitcoin::Script;
+use bdk::bitcoin::Transaction;
+use bdk::bitcoin::Txid;
+use bdk::electrum_client;
+use bdk::electrum_client::ElectrumApi;
+use bdk::FeeRate;
+use bdk_ext::SecretKeyExt;
+use model::Timestamp;
+use serde::Deserialize;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use xtra::prelude::MessageChannel;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+mod htlc;
+mod invoice;
+
+/// Interval at which the HTLCs of unfinished swaps are checked on chain.
+const UPDATE_SWAPS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Timeout of a single request to the swap server.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long after the invoice expired the HTLC of an unpaid swap is looked for.
+const LOCKUP_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+/// Minimum number of blocks until the HTLC times out when the swap is created.
+const MIN_TIMEOUT_BLOCKS: u32 = 36;
+
+/// The HTLC is not claimed if it times out within this many blocks, as the claim transaction
+/// might not confirm before the swap server can refund the HTLC.
+const CLAIM_MARGIN_BLOCKS: u32 = 12;
+
+/// Confirmation target of the claim transaction in blocks.
+const CLAIM_CONFIRMATION_TARGET: usize = 2;
+
+/// A service swapping Lightning payments for on-chain funds.
+#[async_trait]
+pub trait SwapService: Send + Sync + 'static {
+    /// Request a swap locking `amount` in an HTLC once the returned invoice is paid.
+    async fn create_swap(&self, request: SwapRequest) -> Result<SwapOffer>;
+}
+
+/// The blockchain on which the HTLCs of swaps are locked.
+#[async_trait]
+pub trait Blockchain: Send + Sync + 'static {
+    async fn block_height(&self) -> Result<u32>;
+
+    /// Transactions paying to or spending from `script_pubkey` with the height of the block they
+    /// were confirmed in, if any.
+    async fn script_history(
+        &self,
+        script_pubkey: &Script,
+    ) -> Result<Vec<(Transaction, Option<u32>)>>;
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<()>;
+
+    async fn fee_rate(&self) -> Result<FeeRate>;
+}
+
+/// Identifier of a swap, assigned by the swap server.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SwapId(String);
+
+impl SwapId {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+}
+
+impl std::fmt::Display for SwapId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwapRequest {
+    /// Amount to be locked in the HTLC
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub amount: Amount,
+    /// Hash of the preimage the invoice and the HTLC are locked to
+    pub payment_hash: sha256::Hash,
+    /// Key the HTLC can be claimed with
+    pub claim_public_key: PublicKey,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwapOffer {
+    pub id: SwapId,
+    /// BOLT11 hold invoice to be paid by the taker
+    pub invoice: String,
+    /// Fee charged by the swap server on top of the swapped amount
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub fee: Amount,
+    /// The swap expires if the invoice is not paid by then
+    pub expires_at: Timestamp,
+    /// Key the swap server can refund the HTLC with after the timeout
+    pub refund_public_key: PublicKey,
+    pub timeout_block_height: u32,
+    /// Address of the HTLC
+    pub lockup_address: Address,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SwapStatus {
+    /// Waiting for the invoice to be paid and the HTLC to be locked
+    InvoicePending,
+    /// The HTLC is locked but not confirmed yet
+    LockupBroadcast {
+        txid: Txid,
+    },
+    /// The transaction claiming the HTLC was broadcast, revealing the preimage
+    ClaimBroadcast {
+        txid: Txid,
+    },
+    ClaimConfirmed {
+        txid: Txid,
+    },
+    /// The HTLC was not locked in time
+    Expired,
+    /// The HTLC will not be claimed
+    Failed {
+        reason: String,
+    },
+}
+
+impl SwapStatus {
+    /// Whether the status of the swap can still change.
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            SwapStatus::ClaimConfirmed { .. } | SwapStatus::Expired | SwapStatus::Failed { .. }
+        )
+    }
+}
+
+/// A swap as reported in the feed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Swap {
+    pub id: SwapId,
+    pub invoice: String,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub amount: Amount,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub fee: Amount,
+    /// Address of the wallet the HTLC is claimed to
+    pub address: Address,
+    pub lockup_address: Address,
+    pub timeout_block_height: u32,
+    pub expires_at: Timestamp,
+    #[serde(flatten)]
+    pub status: SwapStatus,
+}
+
+/// A swap with everything needed to claim its HTLC, as stored in the database.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SwapState {
+    swap: Swap,
+    htlc: Htlc,
+    preimage: [u8; 32],
+    claim_key: [u8; 32],
+    /// The transaction claiming the HTLC, once built
+    claim_tx: Option<Transaction>,
+}
+
+impl SwapState {
+    fn claim_key(&self) -> Result<secp256k1::SecretKey> {
+        secp256k1::SecretKey::from_slice(&self.claim_key).context("Invalid claim key")
+    }
+}
+
+/// Client of the HTTP API of a swap server.
+///
+/// `POST <url>/swaps` with a [`SwapRequest`] returns a [`SwapOffer`].
+pub struct HttpSwapService {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpSwapService {
+    pub fn new(url: &str) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .context("Failed to build HTTP client")?;
+
+        Ok(Self {
+            client,
+            url: url.trim_end_matches('/').to_owned(),
+        })
+    }
+}
+
+#[async_trait]
+impl SwapService for HttpSwapService {
+    async fn create_swap(&self, request: SwapRequest) -> Result<SwapOffer> {
+        let offer = self
+            .client
+            .post(format!("{}/swaps", self.url))
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to request swap")?
+            .error_for_status()
+            .context("Swap server rejected swap")?
+            .json()
+            .await
+            .context("Invalid swap offer")?;
+
+        Ok(offer)
+    }
+}
+
+/// [`Blockchain`] backed by an Electrum server.
+pub struct Electrum {
+    client: electrum_client::Client,
+}
+
+impl Electrum {
+    pub fn new(electrum_rpc_url: &str) -> Result<Self> {
+        let client = electrum_client::Client::new(electrum_rpc_url)
+            .context("Failed to initialize Electrum RPC client")?;
+
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl Blockchain for Electrum {
+    async fn block_height(&self) -> Result<u32> {
+        let header = self
+            .client
+            .block_headers_subscribe()
+            .context("Failed to get latest block")?;
+
+        Ok(u32::try_from(header.height)?)
+    }
+
+    async fn script_history(
+        &self,
+        script_pubkey: &Script,
+    ) -> Result<Vec<(Transaction, Option<u32>)>> {
+        let history = self
+            .client
+            .script_get_history(script_pubkey)
+            .context("Failed to get script history")?;
+
+        history
+            .into_iter()
+            .map(|entry| {
+                let tx = self
+                    .client
+                    .transaction_get(&entry.tx_hash)
+                    .with_context(|| format!("Failed to get transaction {}", entry.tx_hash))?;
+                // Unconfirmed transactions have a height of 0 or -1
+                let height = u32::try_from(entry.height)
+                    .ok()
+                    .filter(|height| *height > 0);
+
+                Ok((tx, height))
+            })
+            .collect()
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<()> {
+        self.client
+            .transaction_broadcast(tx)
+            .with_context(|| format!("Failed to broadcast transaction {}", tx.txid()))?;
+
+        Ok(())
+    }
+
+    async fn fee_rate(&self) -> Result<FeeRate> {
+        let btc_per_kvb = self
+            .client
+            .estimate_fee(CLAIM_CONFIRMATION_TARGET)
+            .context("Failed to estimate fee rate")?;
+        let fee_rate = FeeRate::from_btc_per_kvb(btc_per_kvb as f32);
+
+        // Electrum servers return -1 if they cannot estimate the fee rate
+        if fee_rate < FeeRate::default_min_relay_fee() {
+            return Ok(FeeRate::default_min_relay_fee());
+        }
+
+        Ok(fee_rate)
+    }
+}
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    service: Arc<dyn SwapService>,
+    blockchain: Arc<dyn Blockchain>,
+    new_address: MessageChannel<wallet::NewAddress, Result<Address>>,
+    swaps: Vec<SwapState>,
+    feed: watch::Sender<Vec<Swap>>,
+}
+
+impl Actor {
+    pub fn new(
+        db: sqlite_db::Connection,
+        service: Arc<dyn SwapService>,
+        blockchain: Arc<dyn Blockchain>,
+        new_address: MessageChannel<wallet::NewAddress, Result<Address>>,
+    ) -> (Self, watch::Receiver<Vec<Swap>>) {
+        let (feed, receiver) = watch::channel(Vec::new());
+
+        let actor = Self {
+            db,
+            service,
+            blockchain,
+            new_address,
+            swaps: Vec::new(),
+            feed,
+        };
+
+        (actor, receiver)
+    }
+
+    fn update_feed(&self) {
+        let swaps = self.swaps.iter().map(|state| state.swap.clone()).collect();
+        let _ = self.feed.send(swaps);
+    }
+
+    async fn load_swaps(&mut self) -> Result<()> {
+        for data in self.db.load_swaps().await? {
+            let state = serde_json::from_str::<SwapState>(&data).context("Invalid swap")?;
+            self.swaps.push(state);
+        }
+
+        Ok(())
+    }
+
+    async fn update_swaps(&mut self) -> Result<()> {
+        let height = self.blockchain.block_height().await?;
+        let fee_rate = self.blockchain.fee_rate().await?;
+        let now = Timestamp::now();
+
+        let mut changed = false;
+
+        for state in self
+            .swaps
+            .iter_mut()
+            .filter(|state| !state.swap.status.is_final())
+        {
+            let before = state.clone();
+
+            match update_swap(
+                &self.db,
+                self.blockchain.as_ref(),
+                state,
+                height,
+                now,
+                fee_rate,
+            )
+            .await
+            {
+                Ok(()) if state.swap.status != before.swap.status => {
+                    tracing::info!(id = %state.swap.id, status = ?state.swap.status, "Swap status changed");
+                    changed = true;
+                }
+                Ok(()) => {}
+                Err(e) => {
+                    tracing::warn!(id = %state.swap.id, "Failed to update swap: {e:#}");
+                    *state = before;
+                }
+            }
+        }
+
+        if changed {
+            self.update_feed();
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        if let Err(e) = self.load_swaps().await {
+            tracing::error!("Failed to load swaps: {e:#}");
+        }
+        self.update_feed();
+
+        let this = ctx.address().expect("we are alive");
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                UPDATE_SWAPS_INTERVAL,
+                || UpdateSwaps,
+                xtras::IncludeSpan::Always,
+            ),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: CreateSwap) -> Result<Swap> {
+        if msg.amount == Amount::ZERO {
+            bail!("Cannot swap zero amount");
+        }
+
+        let address = self
+            .new_address
+            .send(wallet::NewAddress)
+            .await
+            .context("Wallet actor disconnected")??;
+
+        let preimage = rand::random::<[u8; 32]>();
+        let claim_key = rand::random::<[u8; 32]>();
+        let claim_public_key = PublicKey::new(
+            secp256k1::SecretKey::from_slice(&claim_key)
+                .context("Invalid claim key")?
+                .to_public_key(),
+        );
+        let payment_hash = sha256::Hash::hash(&preimage);
+
+        let offer = self
+            .service
+            .create_swap(SwapRequest {
+                amount: msg.amount,
+                payment_hash,
+                claim_public_key,
+            })
+            .await?;
+        let htlc = Htlc {
+            payment_hash,
+            claim_public_key,
+            refund_public_key: offer.refund_public_key,
+            timeout_block_height: offer.timeout_block_height,
+        };
+        let height = self.blockchain.block_height().await?;
+        let invoice = verify_offer(&offer, &htlc, msg.amount, Timestamp::now(), height)
+            .with_context(|| format!("Swap server returned an invalid offer for {}", offer.id))?;
+
+        let state = SwapState {
+            swap: Swap {
+                id: offer.id,
+                invoice: offer.invoice,
+                amount: msg.amount,
+                fee: offer.fee,
+                address,
+                lockup_address: offer.lockup_address,
+                timeout_block_height: offer.timeout_block_height,
+                expires_at: invoice.expires_at,
+                status: SwapStatus::InvoicePending,
+            },
+            htlc,
+            preimage,
+            claim_key,
+            claim_tx: None,
+        };
+        self.db
+            .insert_swap(&state.swap.id.to_string(), &serde_json::to_string(&state)?)
+            .await
+            .context("Failed to store swap")?;
+
+        let swap = state.swap.clone();
+        tracing::info!(id = %swap.id, amount = %swap.amount, address = %swap.address, "Created swap");
+
+        self.swaps.push(state);
+        self.update_feed();
+
+        Ok(swap)
+    }
+
+    async fn handle(&mut self, _: UpdateSwaps) {
+        if self.swaps.iter().all(|state| state.swap.status.is_final()) {
+            return;
+        }
+
+        if let Err(e) = self.update_swaps().await {
+            tracing::warn!("Failed to update swaps: {e:#}");
+        }
+    }
+}
+
+/// Check that the invoice of `offer` pays for `amount` plus the fee, is locked to the payment hash
+/// of the `htlc` and expires with the swap, and that the offered HTLC is the one we can claim.
+fn verify_offer(
+    offer: &SwapOffer,
+    htlc: &Htlc,
+    amount: Amount,
+    now: Timestamp,
+    height: u32,
+) -> Result<Invoice> {
+    let invoice = Invoice::parse(&offer.invoice)?;
+
+    let expected_msat = (amount + offer.fee).as_sat() * 1_000;
+    ensure!(
+        invoice.amount_msat == Some(expected_msat),
+        "Invoice amount {:?} msat does not match the swapped amount plus fee of {expected_msat} msat",
+        invoice.amount_msat
+    );
+    ensure!(
+        invoice.payment_hash == htlc.payment_hash,
+        "Invoice is not locked to our payment hash"
+    );
+    ensure!(
+        invoice.expires_at <= offer.expires_at,
+        "Invoice expires after the swap"
+    );
+    ensure!(invoice.expires_at > now, "Invoice already expired");
+    ensure!(
+        offer.lockup_address.script_pubkey() == htlc.script_pubkey(),
+        "Lockup address {} is not the HTLC claimable by us",
+        offer.lockup_address
+    );
+    ensure!(
+        htlc.timeout_block_height >= height + MIN_TIMEOUT_BLOCKS,
+        "HTLC times out at block {}, less than {MIN_TIMEOUT_BLOCKS} blocks after {height}",
+        htlc.timeout_block_height
+    );
+
+    Ok(invoice)
+}
+
+/// Advance a swap according to its HTLC on chain, storing its new state.
+async fn update_swap(
+    db: &sqlite_db::Connection,
+    blockchain: &dyn Blockchain,
+    state: &mut SwapState,
+    height: u32,
+    now: Timestamp,
+    fee_rate: FeeRate,
+) -> Result<()> {
+    let id = state.swap.id.to_string();
+    let status = state.swap.status.clone();
+
+    let history = blockchain
+        .script_history(&state.htlc.script_pubkey())
+        .await?;
+    let claim_tx = progress(state, &history, height, now, fee_rate)?;
+
+    // The claim transaction is stored before it is broadcast, such that the same transaction is
+    // rebroadcast after a restart
+    if state.swap.status != status {
+        db.update_swap(&id, &serde_json::to_string(state)?).await?;
+    }
+
+    if let Some(claim_tx) = claim_tx {
+        blockchain.broadcast(&claim_tx).await?;
+        tracing::info!(%id, txid = %claim_tx.txid(), "Broadcast claim transaction");
+    }
+
+    Ok(())
+}
+
+/// Advance a swap according to the `history` of its HTLC on chain.
+///
+/// Returns the claim transaction if it needs to be broadcast.
+fn progress(
+    state: &mut SwapState,
+    history: &[(Transaction, Option<u32>)],
+    height: u32,
+    now: Timestamp,
+    fee_rate: FeeRate,
+) -> Result<Option<Transaction>> {
+    let script_pubkey = state.htlc.script_pubkey();
+    let lockup = history.iter().find_map(|(tx, confirmation_height)| {
+        let vout = tx
+            .output
+            .iter()
+            .position(|output| output.script_pubkey == script_pubkey)?;
+
+        Some((tx, vout, *confirmation_height))
+    });
+
+    match &state.swap.status {
+        SwapStatus::InvoicePending | SwapStatus::LockupBroadcast { .. } => {
+            let (lockup_tx, vout, confirmation_height) = match lockup {
+                Some(lockup) => lockup,
+                None => {
+                    let deadline =
+                        state.swap.expires_at.seconds() + LOCKUP_GRACE_PERIOD.as_secs() as i64;
+                    if now.seconds() > deadline {
+                        state.swap.status = SwapStatus::Expired;
+                    }
+
+                    return Ok(None);
+                }
+            };
+
+            let txid = lockup_tx.txid();
+            let value = Amount::from_sat(lockup_tx.output[vout].value);
+            if value < state.swap.amount {
+                state.swap.status = SwapStatus::Failed {
+                    reason: format!("HTLC of {value} is less than the swapped amount"),
+                };
+                return Ok(None);
+            }
+
+            if confirmation_height.is_none() {
+                state.swap.status = SwapStatus::LockupBroadcast { txid };
+                return Ok(None);
+            }
+
+            if height + CLAIM_MARGIN_BLOCKS >= state.htlc.timeout_block_height {
+                state.swap.status = SwapStatus::Failed {
+                    reason: "HTLC confirmed too close to its timeout to be claimed safely"
+                        .to_owned(),
+                };
+                return Ok(None);
+            }
+
+            let claim_tx = state.htlc.claim_transaction(
+                OutPoint::new(txid, vout as u32),
+                value,
+                &state.swap.address,
+                &state.preimage,
+                &state.claim_key()?,
+                fee_rate,
+            )?;
+
+            state.swap.status = SwapStatus::ClaimBroadcast {
+                txid: claim_tx.txid(),
+            };
+            state.claim_tx = Some(claim_tx.clone());
+
+            Ok(Some(claim_tx))
+        }
+        SwapStatus::ClaimBroadcast { txid } => {
+            let txid = *txid;
+            let claim_tx = state
+                .claim_tx
+                .clone()
+                .context("Claim transaction of broadcast claim is missing")?;
+
+            match history.iter().find(|(tx, _)| tx.txid() == txid) {
+                Some((_, Some(_))) => {
+                    state.swap.status = SwapStatus::ClaimConfirmed { txid };
+                    Ok(None)
+                }
+                // Still in the mempool
+                Some((_, None)) => Ok(None),
+                None => {
+                    let htlc_outpoint = claim_tx.input[0].previous_output;
+                    let refunded = history.iter().any(|(tx, confirmation_height)| {
+                        confirmation_height.is_some()
+                            && tx
+                                .input
+                                .iter()
+                                .any(|input| input.previous_output == htlc_outpoint)
+                    });
+
+                    if refunded {
+                        state.swap.status = SwapStatus::Failed {
+                            reason: "HTLC was refunded before the claim transaction confirmed"
+                                .to_owned(),
+                        };
+                        return Ok(None);
+                    }
+
+                    Ok(Some(claim_tx))
+                }
+            }
+        }
+        SwapStatus::ClaimConfirmed { .. } | SwapStatus::Expired | SwapStatus::Failed { .. } => {
+            Ok(None)
+        }
+    }
+}
+
+/// Swap a Lightning payment for `amount` paid to the wallet.
+pub struct CreateSwap {
+    pub amount: Amount,
+}
+
+struct UpdateSwaps;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk::bitcoin::Network;
+    use bdk::bitcoin::TxIn;
+    use bdk::bitcoin::TxOut;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+    use std::sync::Mutex;
+    use xtra::spawn::TokioGlobalSpawnExt;
+    use xtra::Actor as _;
+
+    #[tokio::test]
+    async fn confirmed_htlc_is_claimed_revealing_the_preimage() {
+        let chain = Arc::new(LocalChain::default());
+        let service = Arc::new(LocalSwapService::new(chain.clone()));
+        let (actor, mut feed) = spawn_actor(sqlite_db::memory().await.unwrap(), &service, &chain);
+
+        let swap = create_swap(&actor).await.unwrap();
+        assert_eq!(swap.status, SwapStatus::InvoicePending);
+        assert_eq!(swap.address, dummy_address());
+        assert_eq!(feed.borrow_and_update().clone(), vec![swap.clone()]);
+
+        let lockup_txid = service.pay_invoice(&swap.id, swap.amount);
+        actor.send(UpdateSwaps).await.unwrap();
+        assert_eq!(
+            status(&feed),
+            SwapStatus::LockupBroadcast { txid: lockup_txid }
+        );
+        assert_eq!(service.settle(&swap.id), None);
+
+        chain.mine();
+        actor.send(UpdateSwaps).await.unwrap();
+        let claim_txid = match status(&feed) {
+            SwapStatus::ClaimBroadcast { txid } => txid,
+            status => panic!("Expected claim to be broadcast, got {status:?}"),
+        };
+        let claim_tx = chain.transaction(&claim_txid).unwrap();
+        assert_eq!(
+            claim_tx.output[0].script_pubkey,
+            dummy_address().script_pubkey()
+        );
+        assert!(claim_tx.output[0].value < swap.amount.as_sat());
+        assert!(service.settle(&swap.id).is_some());
+
+        chain.mine();
+        actor.send(UpdateSwaps).await.unwrap();
+        assert_eq!(
+            status(&feed),
+            SwapStatus::ClaimConfirmed { txid: claim_txid }
+        );
+    }
+
+    #[tokio::test]
+    async fn htlc_below_swapped_amount_is_not_claimed() {
+        let chain = Arc::new(LocalChain::default());
+        let service = Arc::new(LocalSwapService::new(chain.clone()));
+        let (actor, feed) = spawn_actor(sqlite_db::memory().await.unwrap(), &service, &chain);
+        let swap = create_swap(&actor).await.unwrap();
+
+        service.pay_invoice(&swap.id, swap.amount - Amount::from_sat(1));
+        chain.mine();
+        actor.send(UpdateSwaps).await.unwrap();
+
+        assert!(matches!(status(&feed), SwapStatus::Failed { .. }));
+        assert_eq!(service.settle(&swap.id), None);
+    }
+
+    #[tokio::test]
+    async fn htlc_close_to_timeout_is_not_claimed() {
+        let chain = Arc::new(LocalChain::default());
+        let service = Arc::new(LocalSwapService::new(chain.clone()));
+        let (actor, feed) = spawn_actor(sqlite_db::memory().await.unwrap(), &service, &chain);
+        let swap = create_swap(&actor).await.unwrap();
+
+        service.pay_invoice(&swap.id, swap.amount);
+        for _ in 0..(MIN_TIMEOUT_BLOCKS - CLAIM_MARGIN_BLOCKS) {
+            chain.mine();
+        }
+        actor.send(UpdateSwaps).await.unwrap();
+
+        assert!(matches!(status(&feed), SwapStatus::Failed { .. }));
+        assert_eq!(service.settle(&swap.id), None);
+    }
+
+    #[tokio::test]
+    async fn htlc_is_claimed_after_restart() {
+        let db = sqlite_db::memory().await.unwrap();
+        let chain = Arc::new(LocalChain::default());
+        let service = Arc::new(LocalSwapService::new(chain.clone()));
+        let (actor, _) = spawn_actor(db.clone(), &service, &chain);
+        let swap = create_swap(&actor).await.unwrap();
+        drop(actor);
+
+        service.pay_invoice(&swap.id, swap.amount);
+        chain.mine();
+        let (actor, feed) = spawn_actor(db, &service, &chain);
+        actor.send(UpdateSwaps).await.unwrap();
+
+        assert!(matches!(status(&feed), SwapStatus::ClaimBroadcast { .. }));
+        assert!(service.settle(&swap.id).is_some());
+    }
+
+    #[tokio::test]
+    async fn swap_with_invoice_for_wrong_amount_is_rejected() {
+        let chain = Arc::new(LocalChain::default());
+        let service = Arc::new(LocalSwapService {
+            invoice_amount_offset: Amount::from_sat(1),
+            ..LocalSwapService::new(chain.clone())
+        });
+        let (actor, feed) = spawn_actor(sqlite_db::memory().await.unwrap(), &service, &chain);
+
+        assert!(create_swap(&actor).await.is_err());
+        assert!(feed.borrow().is_empty());
+    }
+
+    #[tokio::test]
+    async fn swap_with_invoice_for_other_payment_hash_is_rejected() {
+        let chain = Arc::new(LocalChain::default());
+        let service = Arc::new(LocalSwapService {
+            other_payment_hash: true,
+            ..LocalSwapService::new(chain.clone())
+        });
+        let (actor, feed) = spawn_actor(sqlite_db::memory().await.unwrap(), &service, &chain);
+
+        assert!(create_swap(&actor).await.is_err());
+        assert!(feed.borrow().is_empty());
+    }
+
+    #[tokio::test]
+    async fn swap_with_early_timeout_is_rejected() {
+        let chain = Arc::new(LocalChain::default());
+        let service = Arc::new(LocalSwapService {
+            timeout_blocks: MIN_TIMEOUT_BLOCKS - 1,
+            ..LocalSwapService::new(chain.clone())
+        });
+        let (actor, feed) = spawn_actor(sqlite_db::memory().await.unwrap(), &service, &chain);
+
+        assert!(create_swap(&actor).await.is_err());
+        assert!(feed.borrow().is_empty());
+    }
+
+    #[tokio::test]
+    async fn unpaid_swap_expires_after_grace_period() {
+        let chain = Arc::new(LocalChain::default());
+        let service = Arc::new(LocalSwapService::new(chain.clone()));
+        let (actor, _) = spawn_actor(sqlite_db::memory().await.unwrap(), &service, &chain);
+        create_swap(&actor).await.unwrap();
+        let mut state = actor.send(GetSwapState).await.unwrap();
+
+        let deadline = state.swap.expires_at.seconds() + LOCKUP_GRACE_PERIOD.as_secs() as i64;
+        let fee_rate = FeeRate::from_sat_per_vb(1.0);
+
+        progress(&mut state, &[], 0, Timestamp::new(deadline), fee_rate).unwrap();
+        assert_eq!(state.swap.status, SwapStatus::InvoicePending);
+
+        progress(&mut state, &[], 0, Timestamp::new(deadline + 1), fee_rate).unwrap();
+        assert_eq!(state.swap.status, SwapStatus::Expired);
+    }
+
+    #[test]
+    fn invoice_must_expire_with_swap() {
+        let now = Timestamp::new(1_000);
+        let (_, public_key) = bdk_ext::keypair::new(&mut rand::thread_rng());
+        let htlc = Htlc {
+            payment_hash: sha256::Hash::hash(b"preimage"),
+            claim_public_key: public_key,
+            refund_public_key: public_key,
+            timeout_block_height: 100 + MIN_TIMEOUT_BLOCKS,
+        };
+        let offer = |expires_at| SwapOffer {
+            id: SwapId::new("swap"),
+            invoice: invoice::dummy_invoice(100_500_000, htlc.payment_hash, now, 600),
+            fee: Amount::from_sat(500),
+            expires_at,
+            refund_public_key: public_key,
+            timeout_block_height: htlc.timeout_block_height,
+            lockup_address: Address::p2wsh(&htlc.witness_script(), Network::Regtest),
+        };
+        let amount = Amount::from_sat(100_000);
+
+        assert!(verify_offer(&offer(Timestamp::new(1_600)), &htlc, amount, now, 100).is_ok());
+        assert!(verify_offer(&offer(Timestamp::new(1_599)), &htlc, amount, now, 100).is_err());
+        assert!(verify_offer(
+            &offer(Timestamp::new(1_600)),
+            &htlc,
+            amount,
+            Timestamp::new(1_600),
+            100
+        )
+        .is_err());
+    }
+
+    fn spawn_actor(
+        db: sqlite_db::Connection,
+        service: &Arc<LocalSwapService>,
+        chain: &Arc<LocalChain>,
+    ) -> (xtra::Address<Actor>, watch::Receiver<Vec<Swap>>) {
+        let wallet = DummyWallet.create(None).spawn_global();
+        let (actor, feed) = Actor::new(db, service.clone(), chain.clone(), wallet.into());
+
+        (actor.create(None).spawn_global(), feed)
+    }
+
+    async fn create_swap(actor: &xtra::Address<Actor>) -> Result<Swap> {
+        actor
+            .send(CreateSwap {
+                amount: Amount::from_sat(100_000),
+            })
+            .await
+            .unwrap()
+    }
+
+    fn status(feed: &watch::Receiver<Vec<Swap>>) -> SwapStatus {
+        feed.borrow().first().unwrap().status.clone()
+    }
+
+    /// Returns the state of the first swap.
+    struct GetSwapState;
+
+    #[xtra_productivity]
+    impl Actor {
+        async fn handle(&mut self, _: GetSwapState) -> SwapState {
+            self.swaps[0].clone()
+        }
+    }
+
+    /// Stand-in for a blockchain on which transactions are confirmed on demand.
+    #[derive(Default)]
+    struct LocalChain {
+        height: Mutex<u32>,
+        transactions: Mutex<Vec<(Transaction, Option<u32>)>>,
+    }
+
+    impl LocalChain {
+        /// Confirm all unconfirmed transactions in a new block.
+        fn mine(&self) {
+            let mut height = self.height.lock().unwrap();
+            *height += 1;
+
+            for (_, confirmation_height) in self.transactions.lock().unwrap().iter_mut() {
+                confirmation_height.get_or_insert(*height);
+            }
+        }
+
+        fn transaction(&self, txid: &Txid) -> Option<Transaction> {
+            self.transactions
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(tx, _)| tx)
+                .find(|tx| tx.txid() == *txid)
+                .cloned()
+        }
+    }
+
+    #[async_trait]
+    impl Blockchain for LocalChain {
+        async fn block_height(&self) -> Result<u32> {
+            Ok(*self.height.lock().unwrap())
+        }
+
+        async fn script_history(
+            &self,
+            script_pubkey: &Script,
+        ) -> Result<Vec<(Transaction, Option<u32>)>> {
+            let transactions = self.transactions.lock().unwrap();
+            let pays_to_script = |tx: &Transaction| {
+                tx.output
+                    .iter()
+                    .any(|output| output.script_pubkey == *script_pubkey)
+            };
+            let spends_from_script = |tx: &Transaction| {
+                tx.input.iter().any(|input| {
+                    transactions.iter().any(|(prev_tx, _)| {
+                        prev_tx.txid() == input.previous_output.txid && pays_to_script(prev_tx)
+                    })
+                })
+            };
+
+            Ok(transactions
+                .iter()
+                .filter(|(tx, _)| pays_to_script(tx) || spends_from_script(tx))
+                .cloned()
+                .collect())
+        }
+
+        async fn broadcast(&self, tx: &Transaction) -> Result<()> {
+            let mut transactions = self.transactions.lock().unwrap();
+            if transactions
+                .iter()
+                .all(|(known, _)| known.txid() != tx.txid())
+            {
+                transactions.push((tx.clone(), None));
+            }
+
+            Ok(())
+        }
+
+        async fn fee_rate(&self) -> Result<FeeRate> {
+            Ok(FeeRate::from_sat_per_vb(1.0))
+        }
+    }
+
+    /// Stand-in for a swap server which locks the HTLC of a swap once its invoice is paid.
+    struct LocalSwapService {
+        chain: Arc<LocalChain>,
+        htlcs: Mutex<HashMap<SwapId, Htlc>>,
+        timeout_blocks: u32,
+        /// Added to the amount of the invoices to simulate a misbehaving server
+        invoice_amount_offset: Amount,
+        /// Lock invoices to a different payment hash to simulate a misbehaving server
+        other_payment_hash: bool,
+    }
+
+    impl LocalSwapService {
+        fn new(chain: Arc<LocalChain>) -> Self {
+            Self {
+                chain,
+                htlcs: Mutex::default(),
+                timeout_blocks: MIN_TIMEOUT_BLOCKS,
+                invoice_amount_offset: Amount::ZERO,
+                other_payment_hash: false,
+            }
+        }
+
+        /// Lock `amount` in the HTLC of the swap as if its invoice was paid.
+        fn pay_invoice(&self, id: &SwapId, amount: Amount) -> Txid {
+            let htlc = self.htlcs.lock().unwrap()[id];
+            let lockup_tx = Transaction {
+                version: 2,
+                lock_time: 0,
+                input: vec![TxIn {
+                    previous_output: OutPoint::new(Txid::from_inner([2; 32]), 0),
+                    ..TxIn::default()
+                }],
+                output: vec![TxOut {
+                    value: amount.as_sat(),
+                    script_pubkey: htlc.script_pubkey(),
+                }],
+            };
+            self.chain
+                .transactions
+                .lock()
+                .unwrap()
+                .push((lockup_tx.clone(), None));
+
+            lockup_tx.txid()
+        }
+
+        /// The preimage to settle the invoice of the swap with, once revealed on chain.
+        fn settle(&self, id: &SwapId) -> Option<Vec<u8>> {
+            let htlc = self.htlcs.lock().unwrap()[id];
+
+            self.chain
+                .transactions
+                .lock()
+                .unwrap()
+                .iter()
+                .flat_map(|(tx, _)| tx.input.iter())
+                .filter_map(|input| input.witness.to_vec().get(1).cloned())
+                .find(|preimage| sha256::Hash::hash(preimage) == htlc.payment_hash)
+        }
+    }
+
+    #[async_trait]
+    impl SwapService for LocalSwapService {
+        async fn create_swap(&self, request: SwapRequest) -> Result<SwapOffer> {
+            let mut htlcs = self.htlcs.lock().unwrap();
+            let id = SwapId::new(format!("swap-{}", htlcs.len()));
+
+            let (_, refund_public_key) = bdk_ext::keypair::new(&mut rand::thread_rng());
+            let htlc = Htlc {
+                payment_hash: request.payment_hash,
+                claim_public_key: request.claim_public_key,
+                refund_public_key,
+                timeout_block_height: *self.chain.height.lock().unwrap() + self.timeout_blocks,
+            };
+            htlcs.insert(id.clone(), htlc);
+
+            let fee = Amount::from_sat(500);
+            let now = Timestamp::now();
+            let invoice_msat = (request.amount + fee + self.invoice_amount_offset).as_sat() * 1_000;
+            let payment_hash = if self.other_payment_hash {
+                sha256::Hash::hash(&request.payment_hash[..])
+            } else {
+                request.payment_hash
+            };
+
+            Ok(SwapOffer {
+                id,
+                invoice: invoice::dummy_invoice(invoice_msat, payment_hash, now, 600),
+                fee,
+                expires_at: Timestamp::new(now.seconds() + 600),
+                refund_public_key,
+                timeout_block_height: htlc.timeout_block_height,
+                lockup_address: Address::p2wsh(&htlc.witness_script(), Network::Regtest),
+            })
+        }
+    }
+
+    struct DummyWallet;
+
+    #[async_trait]
+    impl xtra::Actor for DummyWallet {
+        type Stop = ();
+
+        async fn stopped(self) -> Self::Stop {}
+    }
+
+    #[xtra_productivity]
+    impl DummyWallet {
+        async fn handle(&mut self, _: wallet::NewAddress) -> Result<Address> {
+            Ok(dummy_address())
+        }
+    }
+
+    fn dummy_address() -> Address {
+        Address::from_str("tb1q7ysq6mc5qavt5ppps0mkcqwf6fm4zamc7zhpqn").unwrap()
+    }
+}
@@ -0,0 +1,264 @@
+//! The on-chain HTLC of a reverse submarine swap and the transaction claiming it.
+//!
+//! The swap server locks the swapped amount in a P2WSH output with the witness script
+//!
+//! ```text
+//! OP_SIZE 32 OP_EQUAL
+//! OP_IF
+//!     OP_HASH160 <RIPEMD160(payment hash)> OP_EQUALVERIFY <claim public key>
+//! OP_ELSE
+//!     OP_DROP <timeout block height> OP_CHECKLOCKTIMEVERIFY OP_DROP <refund public key>
+//! OP_ENDIF
+//! OP_CHECKSIG
+//! ```
+//!
+//! The taker claims the output with the preimage of the payment hash and a signature of its claim
+//! key. Once the timeout block height is reached, the swap server can refund the output to itself.
+
+use anyhow::ensure;
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::blockdata::opcodes::all::OP_CHECKSIG;
+use bdk::bitcoin::blockdata::opcodes::all::OP_CLTV;
+use bdk::bitcoin::blockdata::opcodes::all::OP_DROP;
+use bdk::bitcoin::blockdata::opcodes::all::OP_ELSE;
+use bdk::bitcoin::blockdata::opcodes::all::OP_ENDIF;
+use bdk::bitcoin::blockdata::opcodes::all::OP_EQUAL;
+use bdk::bitcoin::blockdata::opcodes::all::OP_EQUALVERIFY;
+use bdk::bitcoin::blockdata::opcodes::all::OP_HASH160;
+use bdk::bitcoin::blockdata::opcodes::all::OP_IF;
+use bdk::bitcoin::blockdata::opcodes::all::OP_SIZE;
+use bdk::bitcoin::blockdata::script::Builder;
+use bdk::bitcoin::hashes::ripemd160;
+use bdk::bitcoin::hashes::sha256;
+use bdk::bitcoin::hashes::Hash;
+use bdk::bitcoin::secp256k1;
+use bdk::bitcoin::secp256k1::Secp256k1;
+use bdk::bitcoin::util::sighash::SighashCache;
+use bdk::bitcoin::Address;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::EcdsaSighashType;
+use bdk::bitcoin::OutPoint;
+use bdk::bitcoin::PublicKey;
+use bdk::bitcoin::Script;
+use bdk::bitcoin::Transaction;
+use bdk::bitcoin::TxIn;
+use bdk::bitcoin::TxOut;
+use bdk::bitcoin::Witness;
+use bdk::FeeRate;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Outputs below this value are not relayed.
+const DUST_LIMIT_SAT: u64 = 546;
+
+/// Maximum size of a DER-encoded signature plus the sighash type.
+const MAX_SIGNATURE_LEN: usize = 73;
+
+/// Signals replaceability of the claim transaction.
+const RBF_SEQUENCE: u32 = 0xFFFF_FFFD;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Htlc {
+    pub payment_hash: sha256::Hash,
+    pub claim_public_key: PublicKey,
+    pub refund_public_key: PublicKey,
+    /// Block height from which the swap server can refund the HTLC
+    pub timeout_block_height: u32,
+}
+
+impl Htlc {
+    pub fn witness_script(&self) -> Script {
+        let payment_hash160 = ripemd160::Hash::hash(&self.payment_hash[..]);
+
+        Builder::new()
+            .push_opcode(OP_SIZE)
+            .push_int(32)
+            .push_opcode(OP_EQUAL)
+            .push_opcode(OP_IF)
+            .push_opcode(OP_HASH160)
+            .push_slice(&payment_hash160[..])
+            .push_opcode(OP_EQUALVERIFY)
+            .push_key(&self.claim_public_key)
+            .push_opcode(OP_ELSE)
+            .push_opcode(OP_DROP)
+            .push_int(i64::from(self.timeout_block_height))
+            .push_opcode(OP_CLTV)
+            .push_opcode(OP_DROP)
+            .push_key(&self.refund_public_key)
+            .push_opcode(OP_ENDIF)
+            .push_opcode(OP_CHECKSIG)
+            .into_script()
+    }
+
+    pub fn script_pubkey(&self) -> Script {
+        Script::new_v0_p2wsh(&self.witness_script().wscript_hash())
+    }
+
+    /// The transaction spending the HTLC output at `outpoint` to `address`, paying the fee out of
+    /// the output.
+    pub fn claim_transaction(
+        &self,
+        outpoint: OutPoint,
+        value: Amount,
+        address: &Address,
+        preimage: &[u8; 32],
+        claim_key: &secp256k1::SecretKey,
+        fee_rate: FeeRate,
+    ) -> Result<Transaction> {
+        ensure!(
+            sha256::Hash::hash(preimage) == self.payment_hash,
+            "Preimage does not match payment hash"
+        );
+
+        let witness_script = self.witness_script();
+        let witness = |signature: Vec<u8>| {
+            Witness::from_vec(vec![
+                signature,
+                preimage.to_vec(),
+                witness_script.to_bytes(),
+            ])
+        };
+
+        let mut claim_tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: outpoint,
+                script_sig: Script::new(),
+                sequence: RBF_SEQUENCE,
+                witness: witness(vec![0; MAX_SIGNATURE_LEN]),
+            }],
+            output: vec![TxOut {
+                value: value.as_sat(),
+                script_pubkey: address.script_pubkey(),
+            }],
+        };
+
+        let vbytes = (claim_tx.weight() + 3) / 4;
+        let fee = (fee_rate.as_sat_vb() * vbytes as f32).ceil() as u64;
+        let claimed = value
+            .as_sat()
+            .checked_sub(fee)
+            .filter(|claimed| *claimed >= DUST_LIMIT_SAT)
+            .with_context(|| format!("HTLC of {value} does not cover fee of {fee} sat"))?;
+        claim_tx.output[0].value = claimed;
+
+        let sighash = SighashCache::new(&claim_tx)
+            .segwit_signature_hash(0, &witness_script, value.as_sat(), EcdsaSighashType::All)
+            .context("Failed to compute sighash of claim transaction")?;
+        let message = secp256k1::Message::from_slice(&sighash[..])?;
+        let signature = Secp256k1::signing_only().sign_ecdsa(&message, claim_key);
+
+        let mut signature = signature.serialize_der().to_vec();
+        signature.push(EcdsaSighashType::All as u8);
+        claim_tx.input[0].witness = witness(signature);
+
+        Ok(claim_tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk::bitcoin::Network;
+    use bdk::bitcoin::Txid;
+
+    #[test]
+    fn claim_transaction_reveals_preimage_and_signs_with_claim_key() {
+        let preimage = [7; 32];
+        let (claim_key, claim_public_key) = bdk_ext::keypair::new(&mut rand::thread_rng());
+        let (_, refund_public_key) = bdk_ext::keypair::new(&mut rand::thread_rng());
+        let htlc = Htlc {
+            payment_hash: sha256::Hash::hash(&preimage),
+            claim_public_key,
+            refund_public_key,
+            timeout_block_height: 800_000,
+        };
+        let outpoint = OutPoint::new(Txid::from_inner([1; 32]), 0);
+        let value = Amount::from_sat(100_000);
+        let address = Address::p2wsh(&htlc.witness_script(), Network::Regtest);
+
+        let claim_tx = htlc
+            .claim_transaction(
+                outpoint,
+                value,
+                &address,
+                &preimage,
+                &claim_key,
+                FeeRate::from_sat_per_vb(2.0),
+            )
+            .unwrap();
+
+        let witness = claim_tx.input[0].witness.to_vec();
+        assert_eq!(witness[1], preimage.to_vec());
+        assert_eq!(witness[2], htlc.witness_script().to_bytes());
+
+        let fee = value.as_sat() - claim_tx.output[0].value;
+        let vbytes = (claim_tx.weight() as u64 + 3) / 4;
+        assert!(fee >= 2 * vbytes, "fee {fee} too low for {vbytes} vbytes");
+
+        let sighash = SighashCache::new(&claim_tx)
+            .segwit_signature_hash(
+                0,
+                &htlc.witness_script(),
+                value.as_sat(),
+                EcdsaSighashType::All,
+            )
+            .unwrap();
+        let (sighash_type, signature) = witness[0].split_last().unwrap();
+        assert_eq!(*sighash_type, EcdsaSighashType::All as u8);
+        Secp256k1::verification_only()
+            .verify_ecdsa(
+                &secp256k1::Message::from_slice(&sighash[..]).unwrap(),
+                &secp256k1::ecdsa::Signature::from_der(signature).unwrap(),
+                &claim_public_key.inner,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn claim_transaction_requires_matching_preimage() {
+        let (claim_key, claim_public_key) = bdk_ext::keypair::new(&mut rand::thread_rng());
+        let htlc = Htlc {
+            payment_hash: sha256::Hash::hash(&[7; 32]),
+            claim_public_key,
+            refund_public_key: claim_public_key,
+            timeout_block_height: 800_000,
+        };
+
+        let result = htlc.claim_transaction(
+            OutPoint::default(),
+            Amount::from_sat(100_000),
+            &Address::p2wsh(&htlc.witness_script(), Network::Regtest),
+            &[8; 32],
+            &claim_key,
+            FeeRate::from_sat_per_vb(1.0),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn claim_transaction_must_not_create_dust() {
+        let preimage = [7; 32];
+        let (claim_key, claim_public_key) = bdk_ext::keypair::new(&mut rand::thread_rng());
+        let htlc = Htlc {
+            payment_hash: sha256::Hash::hash(&preimage),
+            claim_public_key,
+            refund_public_key: claim_public_key,
+            timeout_block_height: 800_000,
+        };
+
+        let result = htlc.claim_transaction(
+            OutPoint::default(),
+            Amount::from_sat(1_000),
+            &Address::p2wsh(&htlc.witness_script(), Network::Regtest),
+            &preimage,
+            &claim_key,
+            FeeRate::from_sat_per_vb(10.0),
+        );
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! Just enough of [BOLT11](https://github.com/lightning/bolts/blob/master/11-payment-encoding.md)
+//! to check the invoices of a swap server before the taker pays them.
+//!
+//! The signature of the invoice is not verified, the taker's Lightning wallet does that when
+//! paying it.
+
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::bech32;
+use bdk::bitcoin::bech32::u5;
+use bdk::bitcoin::bech32::FromBase32;
+use bdk::bitcoin::hashes::sha256;
+use bdk::bitcoin::hashes::Hash;
+use model::Timestamp;
+
+/// Number of 5-bit words of the signature at the end of the data part.
+const SIGNATURE_WORDS: usize = 104;
+
+/// Number of 5-bit words of the timestamp at the start of the data part.
+const TIMESTAMP_WORDS: usize = 7;
+
+/// Tag of the payment hash field, i.e. `p`.
+const PAYMENT_HASH_TAG: u8 = 1;
+
+/// Number of 5-bit words of the payment hash field.
+const PAYMENT_HASH_WORDS: usize = 52;
+
+/// Tag of the expiry field, i.e. `x`.
+const EXPIRY_TAG: u8 = 6;
+
+/// Expiry of an invoice without an expiry field in seconds.
+const DEFAULT_EXPIRY: i64 = 3600;
+
+const MSAT_PER_BTC: u64 = 100_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Invoice {
+    /// Amount to be paid in millisatoshi, any amount can be paid if `None`
+    pub amount_msat: Option<u64>,
+    /// The hash of the preimage which is revealed once the invoice is paid
+    pub payment_hash: sha256::Hash,
+    /// The invoice cannot be paid after this time
+    pub expires_at: Timestamp,
+}
+
+impl Invoice {
+    pub fn parse(invoice: &str) -> Result<Self> {
+        let (hrp, data, _) = bech32::decode(invoice).context("Invoice is not bech32 encoded")?;
+
+        let hrp = hrp
+            .strip_prefix("ln")
+            .context("Invoice does not start with `ln`")?;
+        let amount_msat = match hrp.find(|c: char| c.is_ascii_digit()) {
+            Some(start) => Some(parse_amount(&hrp[start..])?),
+            None => None,
+        };
+
+        ensure!(
+            data.len() >= TIMESTAMP_WORDS + SIGNATURE_WORDS,
+            "Invoice is too short"
+        );
+        let timestamp = to_int(&data[..TIMESTAMP_WORDS]) as i64;
+
+        let mut fields = &data[TIMESTAMP_WORDS..data.len() - SIGNATURE_WORDS];
+        let mut payment_hash = None;
+        let mut expiry = DEFAULT_EXPIRY;
+        while !fields.is_empty() {
+            ensure!(fields.len() >= 3, "Truncated invoice field");
+            let tag = fields[0].to_u8();
+            let len = to_int(&fields[1..3]) as usize;
+            let value = fields.get(3..3 + len).context("Truncated invoice field")?;
+
+            match tag {
+                // Fields of unexpected length must be skipped
+                PAYMENT_HASH_TAG if len == PAYMENT_HASH_WORDS => {
+                    let bytes =
+                        Vec::<u8>::from_base32(value).context("Invalid payment hash in invoice")?;
+                    payment_hash = Some(
+                        sha256::Hash::from_slice(&bytes)
+                            .context("Invalid payment hash in invoice")?,
+                    );
+                }
+                EXPIRY_TAG => {
+                    ensure!(len <= 7, "Invoice expiry out of range");
+                    expiry = to_int(value) as i64;
+                }
+                _ => {}
+            }
+
+            fields = &fields[3 + len..];
+        }
+
+        Ok(Self {
+            amount_msat,
+            payment_hash: payment_hash.context("Invoice has no payment hash")?,
+            expires_at: Timestamp::new(timestamp + expiry),
+        })
+    }
+}
+
+/// Parse the amount of the human-readable part, e.g. `2500u`, into millisatoshi.
+fn parse_amount(amount: &str) -> Result<u64> {
+    let (digits, msat_per_unit) = match amount.chars().last() {
+        Some('m') => (&amount[..amount.len() - 1], MSAT_PER_BTC / 1_000),
+        Some('u') => (&amount[..amount.len() - 1], MSAT_PER_BTC / 1_000_000),
+        Some('n') => (&amount[..amount.len() - 1], MSAT_PER_BTC / 1_000_000_000),
+        Some('p') => {
+            let pico_btc = amount[..amount.len() - 1]
+                .parse::<u64>()
+                .context("Invalid invoice amount")?;
+            ensure!(
+                pico_btc % 10 == 0,
+                "Invoice amount is not a whole millisatoshi"
+            );
+
+            return Ok(pico_btc / 10);
+        }
+        Some(c) if c.is_ascii_digit() => (amount, MSAT_PER_BTC),
+        _ => bail!("Invalid invoice amount {amount}"),
+    };
+
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|digits| digits.checked_mul(msat_per_unit))
+        .with_context(|| format!("Invalid invoice amount {amount}"))
+}
+
+/// Interpret 5-bit words as a big-endian integer.
+fn to_int(words: &[u5]) -> u64 {
+    words
+        .iter()
+        .fold(0, |int, word| (int << 5) | u64::from(word.to_u8()))
+}
+
+/// Encode an unsigned invoice for a local stand-in of a swap server.
+#[cfg(test)]
+pub fn dummy_invoice(
+    amount_msat: u64,
+    payment_hash: sha256::Hash,
+    timestamp: Timestamp,
+    expiry: i64,
+) -> String {
+    use bdk::bitcoin::bech32::ToBase32;
+
+    let to_words = |int: u64, len: usize| {
+        (0..len)
+            .rev()
+            .map(|i| u5::try_from_u8(((int >> (5 * i)) & 31) as u8).unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    let mut data = to_words(timestamp.seconds() as u64, TIMESTAMP_WORDS);
+    data.push(u5::try_from_u8(PAYMENT_HASH_TAG).unwrap());
+    data.extend(to_words(PAYMENT_HASH_WORDS as u64, 2));
+    data.extend(payment_hash.to_base32());
+    data.push(u5::try_from_u8(EXPIRY_TAG).unwrap());
+    data.extend(to_words(4, 2));
+    data.extend(to_words(expiry as u64, 4));
+    data.extend(to_words(0, SIGNATURE_WORDS));
+
+    bech32::encode(
+        &format!("lnbcrt{}p", amount_msat * 10),
+        data,
+        bech32::Variant::Bech32,
+    )
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_amount_and_expiry_of_bolt11_example() {
+        // "Please send $3 for a cup of coffee to the same peer, within one minute"
+        let invoice = Invoice::parse("lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp").unwrap();
+
+        assert_eq!(invoice.amount_msat, Some(250_000_000));
+        assert_eq!(
+            invoice.payment_hash.to_string(),
+            "0001020304050607080900010203040506070809000102030405060708090102"
+        );
+        assert_eq!(invoice.expires_at, Timestamp::new(1496314658 + 60));
+    }
+
+    #[test]
+    fn parses_dummy_invoice() {
+        let payment_hash = sha256::Hash::hash(b"preimage");
+
+        let invoice = Invoice::parse(&dummy_invoice(
+            100_500_000,
+            payment_hash,
+            Timestamp::new(1_000),
+            600,
+        ))
+        .unwrap();
+
+        assert_eq!(invoice.amount_msat, Some(100_500_000));
+        assert_eq!(invoice.payment_hash, payment_hash);
+        assert_eq!(invoice.expires_at, Timestamp::new(1_600));
+    }
+
+    #[test]
+    fn amount_multipliers() {
+        assert_eq!(parse_amount("1").unwrap(), 100_000_000_000);
+        assert_eq!(parse_amount("20m").unwrap(), 2_000_000_000);
+        assert_eq!(parse_amount("2500u").unwrap(), 250_000_000);
+        assert_eq!(parse_amount("10n").unwrap(), 1_000);
+        assert_eq!(parse_amount("10p").unwrap(), 1);
+        assert!(parse_amount("1p").is_err());
+    }
+}
@@ -18,7 +18,6 @@ use model::Price;
 use model::Role;
 use sqlite_db;
 use std::collections::HashMap;
-use time::OffsetDateTime;
 use xtra_productivity::xtra_productivity;
 use xtras::SendAsyncSafe;
 
@@ -118,7 +117,7 @@ impl Actor {
             .get(&offer_id)
             .context("Offer to take could not be found in current maker offers, you might have an outdated offer")?;
 
-        if !offer.is_safe_to_take(OffsetDateTime::now_utc()) {
+        if !offer.is_safe_to_take(tokio_extras::time::now_utc()) {
             bail!("The maker's offer appears to be outdated, refusing to place order");
         }
 
@@ -158,7 +157,7 @@ impl Offers {
 
     fn remove_old_offers(&mut self) {
         self.0
-            .retain(|_, offer| offer.is_safe_to_take(OffsetDateTime::now_utc()));
+            .retain(|_, offer| offer.is_safe_to_take(tokio_extras::time::now_utc()));
     }
 }
 
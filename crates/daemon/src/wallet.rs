@@ -1,4 +1,6 @@
 use crate::bitcoin::secp256k1::Secp256k1;
+use crate::wallet::coin_control::CoinControl;
+use crate::wallet::withdrawals::Withdrawals;
 use anyhow::bail;
 use anyhow::ensure;
 use anyhow::Context;
@@ -7,6 +9,8 @@ use async_trait::async_trait;
 use bdk::bitcoin::blockdata::constants;
 use bdk::bitcoin::hashes::Hash;
 use bdk::bitcoin::util::bip32::ExtendedPrivKey;
+use bdk::bitcoin::util::bip32::ExtendedPubKey;
+use bdk::bitcoin::util::bip32::Fingerprint;
 use bdk::bitcoin::util::psbt::PartiallySignedTransaction;
 use bdk::bitcoin::Address;
 use bdk::bitcoin::Amount;
@@ -14,13 +18,16 @@ use bdk::bitcoin::BlockHash;
 use bdk::bitcoin::Network;
 use bdk::bitcoin::OutPoint;
 use bdk::bitcoin::PublicKey;
+use bdk::bitcoin::Script;
 use bdk::bitcoin::Txid;
 use bdk::blockchain::Blockchain;
 use bdk::blockchain::ElectrumBlockchain;
 use bdk::database::BatchDatabase;
+use bdk::descriptor::IntoWalletDescriptor;
 use bdk::electrum_client;
 use bdk::electrum_client::ElectrumApi;
 use bdk::sled;
+use bdk::template::DescriptorTemplate;
 use bdk::wallet::tx_builder::TxOrdering;
 use bdk::wallet::wallet_name_from_descriptor;
 use bdk::wallet::AddressIndex;
@@ -28,22 +35,51 @@ use bdk::FeeRate;
 use bdk::KeychainKind;
 use bdk::SignOptions;
 use bdk::SyncOptions;
+use bdk::TransactionDetails;
+use futures::future;
+use futures::future::BoxFuture;
+use futures::FutureExt as _;
 use maia_core::PartyParams;
 use maia_core::TxBuilderExt;
 use model::Timestamp;
 use model::TxFeeRate;
 use model::WalletInfo;
+use model::Withdrawal;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_with::serde_as;
+use serde_with::DisplayFromStr;
 use statrs::statistics::*;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
 use tokio::runtime::Handle;
 use tokio::sync::watch;
+use tokio_extras::FutureExt;
 use xtra::Actor as _;
 use xtra_productivity::xtra_productivity;
 use xtras::SendInterval;
 
+mod coin_control;
+mod descriptors;
+mod signer;
+mod utxo_management;
+mod withdrawals;
+
+pub use descriptors::Descriptors;
+pub use descriptors::ScriptType;
+pub use signer::AirGap;
+pub use signer::ExternalSigner;
+pub use signer::Hwi;
+pub use signer::SIGN_TIMEOUT;
+pub use utxo_management::UtxoDistribution;
+pub use utxo_management::UtxoManagementKind;
+pub use utxo_management::UtxoManagementReport;
+pub use utxo_management::UtxoManagementTx;
+
 const SYNC_INTERVAL: Duration = Duration::from_secs(3 * 60);
 pub const MAKER_WALLET_ID: &str = "maker-wallet";
 pub const TAKER_WALLET_ID: &str = "taker-wallet";
@@ -101,40 +137,125 @@ pub struct Actor<B, DB> {
     wallet: bdk::Wallet<DB>,
     blockchain_client: B,
     used_utxos: LockedUtxos,
+    coin_control: CoinControl,
+    withdrawals: Withdrawals,
+    /// Signs on behalf of the wallet if it only holds public keys
+    external_signer: Option<Arc<dyn ExternalSigner>>,
     sender: watch::Sender<Option<WalletInfo>>,
 }
 
+/// The keys of the wallet.
+pub enum WalletKeys {
+    /// BIP84 account of which the daemon holds the private keys.
+    Hot(ExtendedPrivKey),
+    /// Watch-only BIP84 account, transactions are signed by an external signer.
+    External {
+        /// Extended public key of the account, i.e. at `m/84'/<coin type>'/0'`
+        xpub: ExtendedPubKey,
+        /// Fingerprint of the signer's master key
+        fingerprint: Fingerprint,
+        signer: Arc<dyn ExternalSigner>,
+    },
+    /// Arbitrary output descriptors.
+    ///
+    /// Descriptors without private keys require an external signer.
+    Descriptors {
+        descriptors: Descriptors,
+        signer: Option<Arc<dyn ExternalSigner>>,
+    },
+}
+
 impl Actor<ElectrumBlockchain, sled::Tree> {
     pub fn spawn(
         electrum_rpc_url: &str,
-        ext_priv_key: ExtendedPrivKey,
+        network: Network,
+        keys: WalletKeys,
         db_path: PathBuf,
     ) -> Result<(xtra::Address<Self>, watch::Receiver<Option<WalletInfo>>)> {
         let client = electrum_client::Client::new(electrum_rpc_url)
             .context("Failed to initialize Electrum RPC client")?;
 
         ensure!(
-            seed_and_rpc_on_same_network(&client, ext_priv_key.network)?,
+            seed_and_rpc_on_same_network(&client, network)?,
             "Wallet seed and Electrum RPC client on different networks."
         );
 
+        let secp = Secp256k1::new();
+        let (descriptor, change_descriptor, external_signer) = match keys {
+            WalletKeys::Hot(xprv) => (
+                bdk::template::Bip84(xprv, KeychainKind::External)
+                    .build(network)?
+                    .into_wallet_descriptor(&secp, network)?,
+                Some(
+                    bdk::template::Bip84(xprv, KeychainKind::Internal)
+                        .build(network)?
+                        .into_wallet_descriptor(&secp, network)?,
+                ),
+                None,
+            ),
+            WalletKeys::External {
+                xpub,
+                fingerprint,
+                signer,
+            } => (
+                bdk::template::Bip84Public(xpub, fingerprint, KeychainKind::External)
+                    .build(network)?
+                    .into_wallet_descriptor(&secp, network)?,
+                Some(
+                    bdk::template::Bip84Public(xpub, fingerprint, KeychainKind::Internal)
+                        .build(network)?
+                        .into_wallet_descriptor(&secp, network)?,
+                ),
+                Some(signer),
+            ),
+            WalletKeys::Descriptors {
+                descriptors,
+                signer,
+            } => (
+                descriptors
+                    .receive
+                    .as_str()
+                    .into_wallet_descriptor(&secp, network)
+                    .with_context(|| format!("Invalid receive descriptor for {network}"))?,
+                descriptors
+                    .change
+                    .as_deref()
+                    .map(|change| change.into_wallet_descriptor(&secp, network))
+                    .transpose()
+                    .with_context(|| format!("Invalid change descriptor for {network}"))?,
+                signer,
+            ),
+        };
+
+        descriptors::ensure_segwit(&descriptor.0)?;
+        if let Some((change_descriptor, _)) = &change_descriptor {
+            descriptors::ensure_segwit(change_descriptor)?;
+        }
+
+        let (_, key_map) = &descriptor;
+        ensure!(
+            !key_map.is_empty() || external_signer.is_some(),
+            "Wallet descriptor without private keys requires an external signer"
+        );
+
         let wallet_name = wallet_name_from_descriptor(
-            bdk::template::Bip84(ext_priv_key, KeychainKind::External),
-            Some(bdk::template::Bip84(ext_priv_key, KeychainKind::Internal)),
-            ext_priv_key.network,
-            &Secp256k1::new(),
+            descriptor.clone(),
+            change_descriptor.clone(),
+            network,
+            &secp,
         )?;
 
         // Create a database (using default sled type) to store wallet data
         let db = sled::open(db_path)?;
+        let coin_control = CoinControl::new(db.open_tree(format!("{wallet_name}-coin-control"))?);
+        let withdrawals = Withdrawals::new(db.open_tree(format!("{wallet_name}-withdrawals"))?);
         let db = db.open_tree(wallet_name)?;
 
-        let wallet = bdk::Wallet::new(
-            bdk::template::Bip84(ext_priv_key, KeychainKind::External),
-            Some(bdk::template::Bip84(ext_priv_key, KeychainKind::Internal)),
-            ext_priv_key.network,
-            db,
-        )?;
+        let wallet = bdk::Wallet::new(descriptor, change_descriptor, network, db)?;
+
+        if external_signer.is_some() {
+            tracing::info!("Wallet is watch-only, transactions are signed by an external signer");
+        }
 
         // UTXOs chosen after coin selection will only be locked for a
         // few wallet sync intervals. UTXOs which were actually
@@ -149,6 +270,9 @@ impl Actor<ElectrumBlockchain, sled::Tree> {
             wallet,
             sender,
             used_utxos: LockedUtxos::new(time_to_lock),
+            coin_control,
+            withdrawals,
+            external_signer,
             blockchain_client: ElectrumBlockchain::from(client),
         };
 
@@ -204,22 +328,70 @@ where
         let address = self.wallet.get_address(AddressIndex::LastUnused)?.address;
         let transactions = self.wallet.list_transactions(false)?;
 
+        let confirmation_times = transactions
+            .iter()
+            .map(|tx| (tx.txid, tx.confirmation_time.clone()))
+            .collect::<HashMap<_, _>>();
+        let withdrawals = self
+            .withdrawals
+            .all()?
+            .into_iter()
+            .map(|record| Withdrawal {
+                txid: record.txid,
+                address: record.address,
+                amount: record.amount,
+                fee: record.fee,
+                created_at: record.created_at,
+                confirmation_time: confirmation_times.get(&record.txid).cloned().flatten(),
+                replaced_by: record.replaced_by,
+            })
+            .collect();
+
         let wallet_info = WalletInfo {
             network: self.wallet.network(),
             balance: Amount::from_sat(balance),
             address,
             last_updated_at: Timestamp::now(),
             transactions,
+            withdrawals,
         };
 
         tracing::trace!(target : "wallet", sync_time_sec = %now.elapsed().as_secs(), "Wallet sync done");
         Ok(wallet_info)
     }
+
+    fn estimate_fee_rate(&self, preset: FeePreset) -> Result<FeeRate> {
+        let fee_rate = self
+            .blockchain_client
+            .estimate_fee(preset.target_blocks())
+            .with_context(|| format!("Failed to estimate fee rate for {preset:?}"))?;
+
+        // Electrum servers may return estimates below the minimum relay fee if the mempool is
+        // empty
+        let fee_rate = if fee_rate < FeeRate::default_min_relay_fee() {
+            FeeRate::default_min_relay_fee()
+        } else {
+            fee_rate
+        };
+
+        Ok(fee_rate)
+    }
+
+    fn withdraw_fee_rate(&self, fee: Option<WithdrawFee>) -> Result<FeeRate> {
+        let fee_rate = match fee {
+            None => FeeRate::default_min_relay_fee(),
+            Some(WithdrawFee::Rate(fee_rate)) => fee_rate,
+            Some(WithdrawFee::Preset(preset)) => self.estimate_fee_rate(preset)?,
+        };
+
+        Ok(fee_rate)
+    }
 }
 
 #[xtra_productivity]
 impl<DB> Actor<ElectrumBlockchain, DB>
 where
+    Self: xtra::Actor,
     DB: BatchDatabase,
 {
     pub fn handle_sync(&mut self, _msg: Sync) {
@@ -233,55 +405,264 @@ where
         let _ = self.sender.send(wallet_info_update);
     }
 
-    pub fn handle_withdraw(&mut self, msg: Withdraw) -> Result<Txid> {
-        self.sync_internal()?;
+    pub fn handle_withdraw(&mut self, msg: Withdraw, ctx: &mut xtra::Context<Self>) -> PendingTxid {
+        let (psbt, details) = match self.sync_internal().and_then(|_| {
+            let fee_rate = self.withdraw_fee_rate(msg.fee)?;
+            self.build_withdraw_tx(&msg, fee_rate)
+        }) {
+            Ok(tx) => tx,
+            Err(e) => return future::ready(Err(e)).boxed(),
+        };
 
-        if msg.address.network != self.wallet.network() {
-            bail!(
-                "Address has invalid network. It was {} but the wallet is connected to {}",
-                msg.address.network,
-                self.wallet.network()
-            )
+        match msg.amount {
+            Some(amount) => {
+                tracing::info!(%amount, address = %msg.address, "Withdrawing from wallet")
+            }
+            None => tracing::info!(address = %msg.address, "Draining wallet"),
         }
 
-        let fee_rate = msg.fee.unwrap_or_else(FeeRate::default_min_relay_fee);
-        let address = msg.address;
+        self.publish_withdrawal(
+            PublishWithdrawal {
+                psbt,
+                fee: Amount::from_sat(details.fee.unwrap_or_default()),
+                drain: msg.amount.is_none(),
+                address: msg.address,
+                replaces: None,
+            },
+            ctx,
+        )
+    }
 
-        let mut psbt = {
-            let mut tx_builder = self.wallet.build_tx();
+    pub fn handle_bump_fee(&mut self, msg: BumpFee, ctx: &mut xtra::Context<Self>) -> PendingTxid {
+        let (psbt, details, record) = match self.sync_internal().and_then(|_| {
+            let fee_rate = self.withdraw_fee_rate(Some(msg.fee))?;
+            self.build_fee_bump_tx(msg.txid, fee_rate)
+        }) {
+            Ok(tx) => tx,
+            Err(e) => return future::ready(Err(e)).boxed(),
+        };
 
-            tx_builder
-                .fee_rate(fee_rate)
-                // Turn on RBF signaling
-                .enable_rbf();
+        self.publish_withdrawal(
+            PublishWithdrawal {
+                psbt,
+                fee: Amount::from_sat(details.fee.unwrap_or_default()),
+                drain: record.drain,
+                address: record.address,
+                replaces: Some(msg.txid),
+            },
+            ctx,
+        )
+    }
 
-            match msg.amount {
-                Some(amount) => {
-                    tracing::info!(%amount, %address, "Withdrawing from wallet");
+    fn handle_publish_withdrawal(&mut self, msg: PublishWithdrawal) -> Result<Txid> {
+        let PublishWithdrawal {
+            psbt,
+            fee,
+            drain,
+            address,
+            replaces,
+        } = msg;
 
-                    tx_builder.add_recipient(address.script_pubkey(), amount.as_sat());
-                }
-                None => {
-                    tracing::info!(%address, "Draining wallet");
+        let tx = psbt.extract_tx();
+        let txid = tx.txid();
+        self.blockchain_client.broadcast(&tx)?;
+
+        let recipient = address.script_pubkey();
+        let record = withdrawals::Record {
+            txid,
+            amount: sum_outputs(&tx.output, |script| script == &recipient),
+            address,
+            fee,
+            created_at: Timestamp::now(),
+            drain,
+            replaced_by: None,
+        };
+
+        match replaces {
+            None => {
+                tracing::info!(%txid, "Withdraw successful");
 
-                    tx_builder.drain_wallet().drain_to(address.script_pubkey());
+                if let Err(e) = self.withdrawals.insert(&record) {
+                    // The withdrawal was already broadcast, failing here would suggest otherwise
+                    tracing::error!(%txid, "Failed to record withdrawal: {e:#}");
                 }
             }
+            Some(replaced) => {
+                tracing::info!(%replaced, %txid, %fee, "Bumped fee of withdrawal");
+
+                if let Err(e) = self.withdrawals.mark_replaced(replaced, &record) {
+                    // The replacement was already broadcast, failing here would suggest otherwise
+                    tracing::error!(
+                        %replaced,
+                        %txid,
+                        "Failed to record replacement of withdrawal: {e:#}"
+                    );
+                }
+            }
+        }
+
+        Ok(txid)
+    }
+
+    pub fn handle_preview_withdraw(&mut self, msg: PreviewWithdraw) -> Result<WithdrawPreview> {
+        let PreviewWithdraw(withdraw) = msg;
+
+        self.sync_internal()?;
+
+        let fee_rate = self.withdraw_fee_rate(withdraw.fee)?;
+        let (psbt, details) = self.build_withdraw_tx(&withdraw, fee_rate)?;
+
+        let recipient = withdraw.address.script_pubkey();
+        let tx = psbt.unsigned_tx;
+
+        Ok(WithdrawPreview {
+            txid: tx.txid(),
+            amount: sum_outputs(&tx.output, |script| script == &recipient),
+            fee: Amount::from_sat(details.fee.unwrap_or_default()),
+            change: sum_outputs(&tx.output, |script| {
+                script != &recipient && self.wallet.is_mine(script).unwrap_or(false)
+            }),
+            sat_per_vb: fee_rate.as_sat_vb(),
+            inputs: tx.input.iter().map(|input| input.previous_output).collect(),
+        })
+    }
+
+    pub fn handle_manage_utxos(&mut self, msg: ManageUtxos) -> Result<UtxoManagementReport> {
+        let ManageUtxos {
+            distribution,
+            max_fee_rate,
+        } = msg;
+
+        ensure!(
+            self.external_signer.is_none(),
+            "UTXO management is not supported for wallets with an external signer"
+        );
+
+        self.sync_internal()?;
+
+        let locked_utxos = self.used_utxos.list();
+        let frozen_utxos = self.coin_control.frozen()?;
+
+        let mut utxos = Vec::new();
+        for utxo in self.wallet.list_unspent()? {
+            if utxo.is_spent || frozen_utxos.contains(&utxo.outpoint) {
+                continue;
+            }
 
-            let (psbt, _) = tx_builder.finish()?;
+            // Unconfirmed UTXOs are left alone to avoid long chains of unconfirmed transactions
+            let is_confirmed = self
+                .wallet
+                .get_tx(&utxo.outpoint.txid, false)?
+                .map_or(false, |tx| tx.confirmation_time.is_some());
+            if is_confirmed {
+                utxos.push((utxo.outpoint, Amount::from_sat(utxo.txout.value)));
+            }
+        }
 
-            psbt
+        let usable_utxos = utxos
+            .iter()
+            .filter(|(_, amount)| *amount >= distribution.target_size)
+            .count();
+        let mut report = UtxoManagementReport {
+            usable_utxos,
+            transaction: None,
         };
 
-        self.wallet.sign(&mut psbt, SignOptions::default())?;
+        if !locked_utxos.is_empty() {
+            tracing::debug!("Not managing UTXOs while contract setups are in progress");
+            return Ok(report);
+        }
+
+        let plan = match utxo_management::plan(&utxos, distribution, max_fee_rate) {
+            Some(plan) => plan,
+            None => return Ok(report),
+        };
+
+        let fee_rate = self.estimate_fee_rate(FeePreset::Economy)?;
+        if fee_rate > max_fee_rate {
+            tracing::debug!(
+                sat_per_vb = %fee_rate.as_sat_vb(),
+                "Not managing UTXOs while fee rates are high"
+            );
+            return Ok(report);
+        }
 
+        let (kind, mut psbt, details) = {
+            let mut tx_builder = self.wallet.build_tx();
+            tx_builder.fee_rate(fee_rate).enable_rbf();
+
+            let kind = match &plan {
+                utxo_management::Plan::Split { utxo, outputs } => {
+                    tx_builder.add_utxo(*utxo)?.manually_selected_only();
+                    for _ in 0..*outputs {
+                        let address = self.wallet.get_address(AddressIndex::New)?.address;
+                        tx_builder.add_recipient(
+                            address.script_pubkey(),
+                            distribution.target_size.as_sat(),
+                        );
+                    }
+
+                    UtxoManagementKind::Split
+                }
+                utxo_management::Plan::Consolidate { utxos } => {
+                    let address = self.wallet.get_address(AddressIndex::New)?.address;
+                    tx_builder
+                        .add_utxos(utxos)?
+                        .manually_selected_only()
+                        .drain_to(address.script_pubkey());
+
+                    UtxoManagementKind::Consolidate
+                }
+            };
+
+            let (psbt, details) = tx_builder.finish()?;
+
+            (kind, psbt, details)
+        };
+
+        self.wallet.sign(&mut psbt, SignOptions::default())?;
         let tx = psbt.extract_tx();
         let txid = tx.txid();
         self.blockchain_client.broadcast(&tx)?;
 
-        tracing::info!(%txid, "Withdraw successful");
+        let transaction = UtxoManagementTx {
+            kind,
+            txid,
+            inputs: tx.input.len(),
+            outputs: tx.output.len(),
+            fee: Amount::from_sat(details.fee.unwrap_or_default()),
+            created_at: Timestamp::now(),
+        };
 
-        Ok(txid)
+        tracing::info!(
+            %txid,
+            ?kind,
+            inputs = %transaction.inputs,
+            outputs = %transaction.outputs,
+            "Published UTXO management transaction"
+        );
+
+        report.transaction = Some(transaction);
+
+        Ok(report)
+    }
+
+    pub fn handle_estimate_fee_rates(
+        &mut self,
+        _msg: EstimateFeeRates,
+    ) -> Result<Vec<FeeEstimate>> {
+        FeePreset::ALL
+            .into_iter()
+            .map(|preset| {
+                let fee_rate = self.estimate_fee_rate(preset)?;
+
+                Ok(FeeEstimate {
+                    preset,
+                    target_blocks: preset.target_blocks(),
+                    sat_per_vb: fee_rate.as_sat_vb(),
+                })
+            })
+            .collect()
     }
 }
 
@@ -291,22 +672,37 @@ where
     Self: xtra::Actor,
     DB: BatchDatabase,
 {
-    pub fn handle_sign(&mut self, msg: Sign) -> Result<PartiallySignedTransaction> {
-        let mut psbt = msg.psbt;
+    pub fn handle_sign(&mut self, msg: Sign, ctx: &mut xtra::Context<Self>) -> PendingSignature {
+        self.sign_psbt(
+            msg.psbt,
+            SignOptions {
+                trust_witness_utxo: true,
+                ..Default::default()
+            },
+            ctx,
+        )
+        .map(|signed| signed.context("could not sign transaction"))
+        .boxed()
+    }
 
-        self.wallet
-            .sign(
-                &mut psbt,
-                SignOptions {
-                    trust_witness_utxo: true,
-                    ..Default::default()
-                },
-            )
-            .context("could not sign transaction")?;
+    fn handle_finalize(&mut self, msg: Finalize) -> Result<PartiallySignedTransaction> {
+        let Finalize {
+            mut psbt,
+            signed,
+            sign_options,
+        } = msg;
+
+        psbt.combine(signed)
+            .context("External signer returned a different transaction")?;
+        self.wallet.finalize_psbt(&mut psbt, sign_options)?;
 
         Ok(psbt)
     }
 
+    fn handle_unlock_utxos(&mut self, UnlockUtxos(utxos): UnlockUtxos) {
+        self.used_utxos.remove(&utxos);
+    }
+
     pub fn build_party_params(
         &mut self,
         BuildPartyParams {
@@ -315,9 +711,13 @@ where
             fee_rate,
         }: BuildPartyParams,
     ) -> Result<PartyParams> {
-        let psbt = self
-            .wallet
-            .build_lock_tx(amount, &mut self.used_utxos, fee_rate.into())?;
+        let frozen_utxos = self.coin_control.frozen()?;
+        let psbt = self.wallet.build_lock_tx(
+            amount,
+            &mut self.used_utxos,
+            frozen_utxos,
+            fee_rate.into(),
+        )?;
 
         Ok(PartyParams {
             lock_psbt: psbt,
@@ -326,6 +726,259 @@ where
             address: self.wallet.get_address(AddressIndex::New)?.address,
         })
     }
+
+    pub fn handle_new_address(&mut self, _msg: NewAddress) -> Result<Address> {
+        let address = self.wallet.get_address(AddressIndex::New)?.address;
+
+        Ok(address)
+    }
+
+    pub fn handle_list_utxos(&mut self, _msg: ListUtxos) -> Result<Vec<Utxo>> {
+        let locked_utxos = self.used_utxos.list();
+        let network = self.wallet.network();
+
+        self.wallet
+            .list_unspent()?
+            .into_iter()
+            .filter(|utxo| !utxo.is_spent)
+            .map(|utxo| {
+                let entry = self.coin_control.get(utxo.outpoint)?;
+
+                Ok(Utxo {
+                    outpoint: utxo.outpoint,
+                    amount: Amount::from_sat(utxo.txout.value),
+                    address: Address::from_script(&utxo.txout.script_pubkey, network),
+                    is_change: utxo.keychain == KeychainKind::Internal,
+                    label: entry.label,
+                    frozen: entry.frozen,
+                    locked: locked_utxos.contains(&utxo.outpoint),
+                })
+            })
+            .collect()
+    }
+
+    pub fn handle_update_utxo(&mut self, msg: UpdateUtxo) -> Result<()> {
+        let UpdateUtxo {
+            outpoint,
+            label,
+            frozen,
+        } = msg;
+
+        let is_ours = self
+            .wallet
+            .list_unspent()?
+            .iter()
+            .any(|utxo| utxo.outpoint == outpoint && !utxo.is_spent);
+        ensure!(is_ours, "UTXO {outpoint} is not owned by the wallet");
+
+        self.coin_control
+            .set(outpoint, coin_control::Entry { label, frozen })?;
+
+        tracing::info!(%outpoint, %frozen, "Updated UTXO");
+
+        Ok(())
+    }
+}
+
+impl<B, DB> Actor<B, DB>
+where
+    DB: BatchDatabase,
+{
+    /// Sign the wallet's inputs of `psbt`.
+    ///
+    /// A watch-only wallet passes the PSBT to the external signer. The returned future waits for
+    /// the signature outside of the actor, so the wallet keeps processing messages in the
+    /// meantime. It fails if the external signer does not respond within [`SIGN_TIMEOUT`].
+    fn sign_psbt(
+        &mut self,
+        mut psbt: PartiallySignedTransaction,
+        sign_options: SignOptions,
+        ctx: &mut xtra::Context<Self>,
+    ) -> PendingSignature
+    where
+        Self: xtra::Handler<Finalize, Return = Result<PartiallySignedTransaction>>,
+    {
+        let signer = match &self.external_signer {
+            Some(signer) => signer.clone(),
+            None => {
+                let signed = self.wallet.sign(&mut psbt, sign_options).map(|_| psbt);
+                return future::ready(signed.map_err(anyhow::Error::from)).boxed();
+            }
+        };
+        let this = ctx.address().expect("self to be alive");
+
+        async move {
+            let txid = psbt.unsigned_tx.txid();
+            tracing::info!(%txid, "Requesting signature from external signer");
+
+            let signed = signer
+                .sign(psbt.clone())
+                .timeout(SIGN_TIMEOUT, || {
+                    tracing::debug_span!("Sign with external signer")
+                })
+                .await
+                .with_context(|| {
+                    format!(
+                        "External signer did not sign {txid} within {} seconds",
+                        SIGN_TIMEOUT.as_secs()
+                    )
+                })?
+                .context("External signer failed")?;
+
+            this.send(Finalize {
+                psbt,
+                signed,
+                sign_options,
+            })
+            .await
+            .context("Wallet actor disconnected")?
+        }
+        .boxed()
+    }
+
+    /// Sign `psbt` and broadcast it as a withdrawal.
+    ///
+    /// The inputs stay locked while the transaction is being signed, so that a contract setup
+    /// does not pick them in the meantime.
+    fn publish_withdrawal(
+        &mut self,
+        msg: PublishWithdrawal,
+        ctx: &mut xtra::Context<Self>,
+    ) -> PendingTxid
+    where
+        Self: xtra::Handler<Finalize, Return = Result<PartiallySignedTransaction>>
+            + xtra::Handler<PublishWithdrawal, Return = Result<Txid>>
+            + xtra::Handler<UnlockUtxos, Return = ()>,
+    {
+        let inputs = msg
+            .psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|input| input.previous_output)
+            .collect::<Vec<_>>();
+        self.used_utxos.extend(inputs.clone());
+
+        let signature = self.sign_psbt(msg.psbt.clone(), SignOptions::default(), ctx);
+        let this = ctx.address().expect("self to be alive");
+
+        async move {
+            let published = async {
+                let psbt = signature.await?;
+
+                this.send(PublishWithdrawal { psbt, ..msg })
+                    .await
+                    .context("Wallet actor disconnected")?
+            }
+            .await;
+
+            if published.is_err() {
+                let _ = this.send(UnlockUtxos(inputs)).await;
+            }
+
+            published
+        }
+        .boxed()
+    }
+
+    fn build_withdraw_tx(
+        &mut self,
+        msg: &Withdraw,
+        fee_rate: FeeRate,
+    ) -> Result<(PartiallySignedTransaction, TransactionDetails)> {
+        if msg.address.network != self.wallet.network() {
+            bail!(
+                "Address has invalid network. It was {} but the wallet is connected to {}",
+                msg.address.network,
+                self.wallet.network()
+            )
+        }
+
+        let frozen_utxos = self.coin_control.frozen()?;
+        if let Some(frozen) = msg.coins.iter().find(|coin| frozen_utxos.contains(coin)) {
+            bail!("UTXO {frozen} is frozen and cannot be spent");
+        }
+
+        // UTXOs locked by in-flight contract setups would otherwise be double-spent
+        let locked_utxos = self.used_utxos.list();
+        if let Some(locked) = msg.coins.iter().find(|coin| locked_utxos.contains(coin)) {
+            bail!("UTXO {locked} is locked by a contract setup and cannot be spent");
+        }
+
+        let mut tx_builder = self.wallet.build_tx();
+
+        tx_builder
+            .fee_rate(fee_rate)
+            // Turn on RBF signaling
+            .enable_rbf();
+
+        if msg.coins.is_empty() {
+            tx_builder.unspendable(frozen_utxos.into_iter().chain(locked_utxos).collect());
+        } else {
+            tx_builder.add_utxos(&msg.coins)?.manually_selected_only();
+        }
+
+        let script_pubkey = msg.address.script_pubkey();
+        match msg.amount {
+            Some(amount) => {
+                tx_builder.add_recipient(script_pubkey, amount.as_sat());
+            }
+            // Without manually selected coins this spends all coins that are not frozen
+            None if msg.coins.is_empty() => {
+                tx_builder.drain_wallet().drain_to(script_pubkey);
+            }
+            None => {
+                tx_builder.drain_to(script_pubkey);
+            }
+        }
+
+        let (psbt, details) = tx_builder.finish()?;
+
+        Ok((psbt, details))
+    }
+
+    /// Build a transaction replacing the withdrawal `txid` at a higher fee rate.
+    ///
+    /// Only withdrawals that are neither confirmed nor already replaced can be bumped.
+    fn build_fee_bump_tx(
+        &mut self,
+        txid: Txid,
+        fee_rate: FeeRate,
+    ) -> Result<(
+        PartiallySignedTransaction,
+        TransactionDetails,
+        withdrawals::Record,
+    )> {
+        let record = self
+            .withdrawals
+            .get(txid)?
+            .with_context(|| format!("Transaction {txid} is not a withdrawal of this wallet"))?;
+
+        if let Some(replacement) = record.replaced_by {
+            bail!("Withdrawal {txid} was already replaced by {replacement}");
+        }
+
+        let tx = self
+            .wallet
+            .get_tx(&txid, false)?
+            .with_context(|| format!("Withdrawal {txid} is not known to the wallet"))?;
+        if tx.confirmation_time.is_some() {
+            bail!("Withdrawal {txid} is already confirmed");
+        }
+
+        let mut tx_builder = self.wallet.build_fee_bump(txid)?;
+
+        tx_builder.fee_rate(fee_rate).enable_rbf();
+
+        // Without change output the recipient pays for the fee increase
+        if record.drain {
+            tx_builder.allow_shrinking(record.address.script_pubkey())?;
+        }
+
+        let (psbt, details) = tx_builder.finish()?;
+
+        Ok((psbt, details, record))
+    }
 }
 
 #[async_trait]
@@ -361,10 +1014,153 @@ pub struct Sign {
     pub psbt: PartiallySignedTransaction,
 }
 
+#[derive(Clone)]
 pub struct Withdraw {
+    /// Amount to send, all selected coins are spent if `None`
     pub amount: Option<Amount>,
-    pub fee: Option<FeeRate>,
+    /// Defaults to the minimum relay fee if `None`
+    pub fee: Option<WithdrawFee>,
     pub address: Address,
+    /// Coins to spend, coin selection picks from all coins that are not frozen if empty
+    pub coins: Vec<OutPoint>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum WithdrawFee {
+    Rate(FeeRate),
+    Preset(FeePreset),
+}
+
+/// Replace an unconfirmed withdrawal with a transaction paying a higher fee rate.
+pub struct BumpFee {
+    pub txid: Txid,
+    pub fee: WithdrawFee,
+}
+
+/// Build the transaction of a withdrawal without signing or broadcasting it.
+pub struct PreviewWithdraw(pub Withdraw);
+
+/// A transaction that is being signed, possibly by an external signer.
+pub type PendingSignature = BoxFuture<'static, Result<PartiallySignedTransaction>>;
+
+/// A withdrawal that is being signed and broadcast.
+pub type PendingTxid = BoxFuture<'static, Result<Txid>>;
+
+/// Add the signatures returned by the external signer to `psbt` and finalize it.
+struct Finalize {
+    psbt: PartiallySignedTransaction,
+    signed: PartiallySignedTransaction,
+    sign_options: SignOptions,
+}
+
+/// Broadcast a signed withdrawal and record it.
+struct PublishWithdrawal {
+    psbt: PartiallySignedTransaction,
+    address: Address,
+    fee: Amount,
+    drain: bool,
+    /// The withdrawal that is replaced if this one bumps its fee
+    replaces: Option<Txid>,
+}
+
+/// Release the inputs of a withdrawal that could not be published.
+struct UnlockUtxos(Vec<OutPoint>);
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize)]
+pub struct WithdrawPreview {
+    pub txid: Txid,
+    /// Amount sent to the withdrawal address
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub amount: Amount,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub fee: Amount,
+    /// Amount paid back to the wallet
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub change: Amount,
+    pub sat_per_vb: f32,
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub inputs: Vec<OutPoint>,
+}
+
+/// Fee rate presets based on the number of blocks until confirmation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeePreset {
+    Fastest,
+    Fast,
+    Normal,
+    Economy,
+}
+
+impl FeePreset {
+    pub const ALL: [FeePreset; 4] = [
+        FeePreset::Fastest,
+        FeePreset::Fast,
+        FeePreset::Normal,
+        FeePreset::Economy,
+    ];
+
+    pub fn target_blocks(&self) -> usize {
+        match self {
+            FeePreset::Fastest => 1,
+            FeePreset::Fast => 3,
+            FeePreset::Normal => 6,
+            FeePreset::Economy => 144,
+        }
+    }
+}
+
+/// Message to estimate the fee rate of every [`FeePreset`].
+#[derive(Clone, Copy)]
+pub struct EstimateFeeRates;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct FeeEstimate {
+    pub preset: FeePreset,
+    pub target_blocks: usize,
+    pub sat_per_vb: f32,
+}
+
+/// Publish a transaction splitting or consolidating UTXOs towards `distribution`.
+///
+/// Nothing is published while contract setups are in progress or if the economy fee rate exceeds
+/// `max_fee_rate`.
+#[derive(Clone, Copy)]
+pub struct ManageUtxos {
+    pub distribution: UtxoDistribution,
+    pub max_fee_rate: FeeRate,
+}
+
+/// Message to derive a new receive address of the wallet.
+#[derive(Clone, Copy)]
+pub struct NewAddress;
+
+/// Message to list the unspent outputs of the wallet.
+#[derive(Clone, Copy)]
+pub struct ListUtxos;
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize)]
+pub struct Utxo {
+    #[serde_as(as = "DisplayFromStr")]
+    pub outpoint: OutPoint,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub amount: Amount,
+    pub address: Option<Address>,
+    pub is_change: bool,
+    pub label: Option<String>,
+    /// Frozen UTXOs are excluded from coin selection until they are unfrozen.
+    pub frozen: bool,
+    /// Locked UTXOs were recently selected for a lock transaction.
+    pub locked: bool,
+}
+
+/// Replace the label and freeze state of a UTXO.
+pub struct UpdateUtxo {
+    pub outpoint: OutPoint,
+    pub label: Option<String>,
+    pub frozen: bool,
 }
 
 /// Bitcoin error codes: <https://github.com/bitcoin/bitcoin/blob/97d3500601c1d28642347d014a6de1e38f53ae4e/src/rpc/protocol.h#L23>
@@ -394,6 +1190,7 @@ trait BuildLockTx {
         &mut self,
         amount: Amount,
         used_utxos: &mut LockedUtxos,
+        frozen_utxos: Vec<OutPoint>,
         fee_rate: FeeRate,
     ) -> Result<PartiallySignedTransaction>;
 }
@@ -406,14 +1203,18 @@ where
         &mut self,
         amount: Amount,
         used_utxos: &mut LockedUtxos,
+        frozen_utxos: Vec<OutPoint>,
         fee_rate: FeeRate,
     ) -> Result<PartiallySignedTransaction> {
+        let mut unspendable = used_utxos.list();
+        unspendable.extend(frozen_utxos);
+
         let mut builder = self.build_tx();
 
         builder
             .ordering(TxOrdering::Bip69Lexicographic) // TODO: I think this is pointless but we did this in maia.
             .fee_rate(fee_rate)
-            .unspendable(used_utxos.list())
+            .unspendable(unspendable)
             .add_2of2_multisig_recipient(amount);
 
         let (psbt, _) = builder.finish()?;
@@ -459,6 +1260,11 @@ impl LockedUtxos {
         self.inner.iter().map(|(_, utxo)| utxo).copied().collect()
     }
 
+    /// Remove `utxos` from the set of locked UTXOs.
+    fn remove(&mut self, utxos: &[OutPoint]) {
+        self.inner.retain(|(_, utxo)| !utxos.contains(utxo));
+    }
+
     /// Remove all elements in the set of locked UTXOs which have been
     /// stored for longer than `time_to_lock`.
     fn remove_expired(&mut self) {
@@ -472,6 +1278,16 @@ impl LockedUtxos {
     }
 }
 
+fn sum_outputs(outputs: &[bdk::bitcoin::TxOut], predicate: impl Fn(&Script) -> bool) -> Amount {
+    let sats = outputs
+        .iter()
+        .filter(|output| predicate(&output.script_pubkey))
+        .map(|output| output.value)
+        .sum();
+
+    Amount::from_sat(sats)
+}
+
 /// Compare the hash of the genesis block of the electrum RPC endpoint to the expected network's
 /// genesis block hash. If they differ, the electrum RPC is not for the network that we expect.
 fn seed_and_rpc_on_same_network(rpc: &electrum_client::Client, network: Network) -> Result<bool> {
@@ -504,6 +1320,8 @@ mod tests {
 
             let (sender, _receiver) = watch::channel(None);
 
+            let db = sled::Config::new().temporary(true).open()?;
+
             Ok(Self {
                 wallet,
                 sender,
@@ -511,6 +1329,9 @@ mod tests {
                     inner: HashSet::default(),
                     time_to_lock,
                 },
+                coin_control: CoinControl::new(db.open_tree("coin-control")?),
+                withdrawals: Withdrawals::new(db.open_tree("withdrawals")?),
+                external_signer: None,
                 blockchain_client: (),
             })
         }
@@ -538,6 +1359,7 @@ mod tests {
             .build_lock_tx(
                 Amount::from_sat(2500),
                 &mut used_utxos,
+                vec![],
                 FeeRate::default_min_relay_fee(),
             )
             .unwrap();
@@ -545,6 +1367,7 @@ mod tests {
             .build_lock_tx(
                 Amount::from_sat(2500),
                 &mut used_utxos,
+                vec![],
                 FeeRate::default_min_relay_fee(),
             )
             .unwrap();
@@ -666,4 +1489,169 @@ mod tests {
             .unwrap()
             .expect("single UTXO to be available after unlocking it");
     }
+
+    #[tokio::test]
+    async fn frozen_utxo_is_not_used_for_party_params() {
+        let mut tasks = Tasks::default();
+
+        let actor = Actor::new_offline(Amount::ONE_BTC, 2, Duration::from_secs(120))
+            .unwrap()
+            .create(None)
+            .spawn(&mut tasks);
+
+        let utxos = actor.send(ListUtxos).await.unwrap().unwrap();
+        let frozen = utxos[0].outpoint;
+        actor
+            .send(UpdateUtxo {
+                outpoint: frozen,
+                label: Some("cold storage".to_owned()),
+                frozen: true,
+            })
+            .await
+            .unwrap()
+            .unwrap();
+
+        let (_, identity_pk) = keypair::new(&mut thread_rng());
+        let party_params = actor
+            .send(BuildPartyParams {
+                amount: Amount::from_btc(0.2).unwrap(),
+                identity_pk,
+                fee_rate: TxFeeRate::default(),
+            })
+            .await
+            .unwrap()
+            .expect("unfrozen UTXO to be available");
+
+        assert!(party_params
+            .lock_psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .all(|input| input.previous_output != frozen));
+
+        // the only UTXO which is not frozen is now locked
+        actor
+            .send(BuildPartyParams {
+                amount: Amount::from_btc(0.2).unwrap(),
+                identity_pk,
+                fee_rate: TxFeeRate::default(),
+            })
+            .await
+            .unwrap()
+            .expect_err("frozen UTXO not to be used");
+
+        let utxos = actor.send(ListUtxos).await.unwrap().unwrap();
+        let frozen_utxo = utxos.iter().find(|utxo| utxo.outpoint == frozen).unwrap();
+        assert!(frozen_utxo.frozen);
+        assert!(!frozen_utxo.locked);
+        assert_eq!(frozen_utxo.label.as_deref(), Some("cold storage"));
+    }
+
+    #[test]
+    fn withdrawal_only_spends_selected_coins() {
+        let mut actor = Actor::new_offline(Amount::ONE_BTC, 3, Duration::from_secs(120)).unwrap();
+        let address = actor.wallet.get_address(AddressIndex::New).unwrap().address;
+        let selected = actor.wallet.list_unspent().unwrap()[0].outpoint;
+
+        let (psbt, _) = actor
+            .build_withdraw_tx(
+                &Withdraw {
+                    amount: None,
+                    fee: None,
+                    address,
+                    coins: vec![selected],
+                },
+                FeeRate::default_min_relay_fee(),
+            )
+            .unwrap();
+
+        let inputs = psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|input| input.previous_output)
+            .collect::<Vec<_>>();
+        assert_eq!(inputs, vec![selected]);
+    }
+
+    #[test]
+    fn withdrawal_of_frozen_coin_fails() {
+        let mut actor = Actor::new_offline(Amount::ONE_BTC, 1, Duration::from_secs(120)).unwrap();
+        let address = actor.wallet.get_address(AddressIndex::New).unwrap().address;
+        let frozen = actor.wallet.list_unspent().unwrap()[0].outpoint;
+        actor
+            .coin_control
+            .set(
+                frozen,
+                coin_control::Entry {
+                    label: None,
+                    frozen: true,
+                },
+            )
+            .unwrap();
+
+        let result = actor.build_withdraw_tx(
+            &Withdraw {
+                amount: None,
+                fee: None,
+                address,
+                coins: vec![frozen],
+            },
+            FeeRate::default_min_relay_fee(),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn withdrawal_of_coin_locked_by_contract_setup_fails() {
+        let mut actor = Actor::new_offline(Amount::ONE_BTC, 1, Duration::from_secs(120)).unwrap();
+        let address = actor.wallet.get_address(AddressIndex::New).unwrap().address;
+        let locked = actor.wallet.list_unspent().unwrap()[0].outpoint;
+        actor.used_utxos.extend(vec![locked]);
+
+        let result = actor.build_withdraw_tx(
+            &Withdraw {
+                amount: None,
+                fee: None,
+                address,
+                coins: vec![locked],
+            },
+            FeeRate::default_min_relay_fee(),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn fee_of_replaced_withdrawal_cannot_be_bumped() {
+        let mut actor = Actor::new_offline(Amount::ONE_BTC, 1, Duration::from_secs(120)).unwrap();
+        let address = actor.wallet.get_address(AddressIndex::New).unwrap().address;
+        let txid = actor.wallet.list_unspent().unwrap()[0].outpoint.txid;
+
+        let record = withdrawals::Record {
+            txid,
+            address,
+            amount: Amount::from_sat(10_000),
+            fee: Amount::from_sat(200),
+            created_at: Timestamp::now(),
+            drain: false,
+            replaced_by: Some(txid),
+        };
+        actor.withdrawals.insert(&record).unwrap();
+
+        let result = actor.build_fee_bump_tx(txid, FeeRate::from_sat_per_vb(10.0));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn fee_of_unknown_transaction_cannot_be_bumped() {
+        let mut actor = Actor::new_offline(Amount::ONE_BTC, 1, Duration::from_secs(120)).unwrap();
+        let txid = actor.wallet.list_unspent().unwrap()[0].outpoint.txid;
+
+        let result = actor.build_fee_bump_tx(txid, FeeRate::from_sat_per_vb(10.0));
+
+        assert!(result.is_err());
+    }
 }
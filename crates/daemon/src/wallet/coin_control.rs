@@ -0,0 +1,143 @@
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::OutPoint;
+use bdk::sled;
+use serde::Deserialize;
+use serde::Serialize;
+use std::str::FromStr;
+
+/// User-defined metadata of the UTXOs in the wallet.
+///
+/// Entries are persisted in a dedicated tree of the wallet database, keyed by outpoint.
+pub struct CoinControl {
+    tree: sled::Tree,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub label: Option<String>,
+    /// Frozen UTXOs are never picked by coin selection.
+    pub frozen: bool,
+}
+
+impl CoinControl {
+    pub fn new(tree: sled::Tree) -> Self {
+        Self { tree }
+    }
+
+    pub fn get(&self, outpoint: OutPoint) -> Result<Entry> {
+        let entry = match self.tree.get(outpoint.to_string())? {
+            Some(bytes) => serde_json::from_slice(&bytes).context("Invalid coin control entry")?,
+            None => Entry::default(),
+        };
+
+        Ok(entry)
+    }
+
+    /// Replace the entry of a UTXO.
+    ///
+    /// Entries without label that are not frozen are removed.
+    pub fn set(&self, outpoint: OutPoint, entry: Entry) -> Result<()> {
+        let key = outpoint.to_string();
+
+        if entry == Entry::default() {
+            self.tree.remove(key)?;
+        } else {
+            self.tree.insert(key, serde_json::to_vec(&entry)?)?;
+        }
+
+        self.tree.flush()?;
+
+        Ok(())
+    }
+
+    pub fn frozen(&self) -> Result<Vec<OutPoint>> {
+        let mut frozen = Vec::new();
+
+        for item in self.tree.iter() {
+            let (key, value) = item?;
+            let entry =
+                serde_json::from_slice::<Entry>(&value).context("Invalid coin control entry")?;
+
+            if entry.frozen {
+                let outpoint = std::str::from_utf8(&key).context("Invalid outpoint key")?;
+                frozen.push(OutPoint::from_str(outpoint)?);
+            }
+        }
+
+        Ok(frozen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk::bitcoin::Txid;
+
+    #[test]
+    fn given_frozen_entry_when_unfrozen_without_label_then_entry_is_removed() {
+        let coin_control = temporary_coin_control();
+        let outpoint = dummy_outpoint(0);
+
+        coin_control
+            .set(
+                outpoint,
+                Entry {
+                    label: None,
+                    frozen: true,
+                },
+            )
+            .unwrap();
+        assert_eq!(coin_control.frozen().unwrap(), vec![outpoint]);
+
+        coin_control.set(outpoint, Entry::default()).unwrap();
+
+        assert!(coin_control.frozen().unwrap().is_empty());
+        assert!(coin_control.tree.is_empty());
+    }
+
+    #[test]
+    fn only_frozen_entries_are_listed_as_frozen() {
+        let coin_control = temporary_coin_control();
+
+        let labelled = Entry {
+            label: Some("cold storage".to_owned()),
+            frozen: false,
+        };
+        coin_control
+            .set(dummy_outpoint(0), labelled.clone())
+            .unwrap();
+        coin_control
+            .set(
+                dummy_outpoint(1),
+                Entry {
+                    label: None,
+                    frozen: true,
+                },
+            )
+            .unwrap();
+
+        assert_eq!(coin_control.frozen().unwrap(), vec![dummy_outpoint(1)]);
+        assert_eq!(coin_control.get(dummy_outpoint(0)).unwrap(), labelled);
+        assert_eq!(
+            coin_control.get(dummy_outpoint(2)).unwrap(),
+            Entry::default()
+        );
+    }
+
+    fn temporary_coin_control() -> CoinControl {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+
+        CoinControl::new(db.open_tree("coin-control").unwrap())
+    }
+
+    fn dummy_outpoint(vout: u32) -> OutPoint {
+        OutPoint {
+            txid: Txid::from_str(
+                "0000000000000000000000000000000000000000000000000000000000000001",
+            )
+            .unwrap(),
+            vout,
+        }
+    }
+}
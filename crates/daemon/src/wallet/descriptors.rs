@@ -0,0 +1,189 @@
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use bdk::descriptor::ExtendedDescriptor;
+use bdk::miniscript::descriptor::DescriptorType;
+use parse_display::Display;
+use parse_display::FromStr;
+use serde::Deserialize;
+
+/// Output descriptors of the receive and change addresses of the wallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Descriptors {
+    pub receive: String,
+    /// Change is sent to receive addresses if `None`
+    pub change: Option<String>,
+}
+
+/// Script types of the descriptors in a descriptor-wallet backup.
+#[derive(Debug, Clone, Copy, Display, FromStr, PartialEq, Eq)]
+#[display(style = "kebab-case")]
+pub enum ScriptType {
+    Wpkh,
+    ShWpkh,
+    Tr,
+}
+
+impl ScriptType {
+    fn matches(&self, descriptor: &str) -> bool {
+        let prefix = match self {
+            ScriptType::Wpkh => "wpkh(",
+            ScriptType::ShWpkh => "sh(wpkh(",
+            ScriptType::Tr => "tr(",
+        };
+
+        descriptor.starts_with(prefix)
+    }
+}
+
+/// Fail if `descriptor` does not describe segwit outputs.
+///
+/// The lock transaction of a contract is only signed after the transactions spending from it were
+/// built, so its inputs must not allow malleating its txid.
+pub fn ensure_segwit(descriptor: &ExtendedDescriptor) -> Result<()> {
+    match descriptor.desc_type() {
+        DescriptorType::Bare
+        | DescriptorType::Sh
+        | DescriptorType::Pkh
+        | DescriptorType::ShSortedMulti => {
+            bail!("Descriptor {descriptor} is not segwit, only segwit descriptors are supported")
+        }
+        DescriptorType::Wpkh
+        | DescriptorType::ShWpkh
+        | DescriptorType::Wsh
+        | DescriptorType::ShWsh
+        | DescriptorType::ShWshSortedMulti
+        | DescriptorType::WshSortedMulti
+        | DescriptorType::Tr => Ok(()),
+    }
+}
+
+#[derive(Deserialize)]
+struct Backup {
+    descriptors: Vec<BackupDescriptor>,
+}
+
+#[derive(Deserialize)]
+struct BackupDescriptor {
+    desc: String,
+    #[serde(default)]
+    active: bool,
+    #[serde(default)]
+    internal: bool,
+}
+
+impl Descriptors {
+    /// Import the active descriptors of the given script type from a descriptor-wallet backup.
+    ///
+    /// The backup is the JSON output of Bitcoin Core's `listdescriptors` RPC. Only backups listed
+    /// with private keys (`listdescriptors true`) can sign without an external signer.
+    pub fn from_backup(backup: &str, script_type: ScriptType) -> Result<Self> {
+        let backup =
+            serde_json::from_str::<Backup>(backup).context("Invalid descriptor-wallet backup")?;
+
+        let active = |internal: bool| {
+            backup
+                .descriptors
+                .iter()
+                .find(|descriptor| {
+                    descriptor.active
+                        && descriptor.internal == internal
+                        && script_type.matches(&descriptor.desc)
+                })
+                .map(|descriptor| descriptor.desc.clone())
+        };
+
+        let receive = active(false).with_context(|| {
+            format!("Backup does not contain an active {script_type} receive descriptor")
+        })?;
+
+        Ok(Self {
+            receive,
+            change: active(true),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk::bitcoin::secp256k1::Secp256k1;
+    use bdk::bitcoin::util::bip32::ExtendedPrivKey;
+    use bdk::bitcoin::Network;
+    use bdk::descriptor::IntoWalletDescriptor;
+
+    const BACKUP: &str = r#"{
+        "wallet_name": "maker",
+        "descriptors": [
+            {
+                "desc": "sh(wpkh([d34db33f/49'/1'/0']tpubDCRb2TSHi9ktwo3N9x3VqaBsLCt7QvQCFbwGsJVqXyopxTJKaiDRdM7TfojS4JtKUcxcvEMGkv4Mq5QD2hSAucGWwXRaHmhkxJHCZBfeV7A/0/*))#dqlpzahz",
+                "timestamp": 1664960000,
+                "active": true,
+                "internal": false
+            },
+            {
+                "desc": "wpkh([d34db33f/84'/1'/0']tpubDC8msFGeGuwnKG9Upg7DM2b4DaRqg3CUZa5g8v2SRQ6K4NSkxUgd7HsL2XVWbVm39yBA4LAxysQAm397zwQSQoQgewGiYZqrA9DsP4zbQ1M/1/*)#vpskx9jr",
+                "timestamp": 1664960000,
+                "active": true,
+                "internal": true
+            },
+            {
+                "desc": "wpkh([d34db33f/84'/1'/0']tpubDC8msFGeGuwnKG9Upg7DM2b4DaRqg3CUZa5g8v2SRQ6K4NSkxUgd7HsL2XVWbVm39yBA4LAxysQAm397zwQSQoQgewGiYZqrA9DsP4zbQ1M/0/*)#8kcdd9qn",
+                "timestamp": 1664960000,
+                "active": true,
+                "internal": false
+            },
+            {
+                "desc": "wpkh([d34db33f/84'/1'/1']tpubDC8msFGeGuwnKG9Upg7DM2b4DaRqg3CUZa5g8v2SRQ6K4NSkxUgd7HsL2XVWbVm39yBA4LAxysQAm397zwQSQoQgewGiYZqrA9DsP4zbQ1M/0/*)#ln8lvx5q",
+                "timestamp": 1664960000,
+                "active": false,
+                "internal": false
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn imports_active_descriptors_of_script_type() {
+        let descriptors = Descriptors::from_backup(BACKUP, ScriptType::Wpkh).unwrap();
+
+        assert!(descriptors.receive.starts_with("wpkh([d34db33f/84'/1'/0']"));
+        assert!(descriptors.receive.contains("/0/*)"));
+        assert!(descriptors.change.unwrap().contains("/1/*)"));
+    }
+
+    #[test]
+    fn change_descriptor_is_optional() {
+        let descriptors = Descriptors::from_backup(BACKUP, ScriptType::ShWpkh).unwrap();
+
+        assert!(descriptors.receive.starts_with("sh(wpkh("));
+        assert_eq!(descriptors.change, None);
+    }
+
+    #[test]
+    fn fails_without_active_receive_descriptor_of_script_type() {
+        assert!(Descriptors::from_backup(BACKUP, ScriptType::Tr).is_err());
+    }
+
+    #[test]
+    fn only_segwit_descriptors_are_supported() {
+        let secp = Secp256k1::new();
+        let xprv = ExtendedPrivKey::new_master(Network::Testnet, &[0; 32]).unwrap();
+        let descriptor = |descriptor: String| {
+            descriptor
+                .as_str()
+                .into_wallet_descriptor(&secp, Network::Testnet)
+                .unwrap()
+                .0
+        };
+
+        assert!(ensure_segwit(&descriptor(format!("wpkh({xprv}/0/*)"))).is_ok());
+        assert!(ensure_segwit(&descriptor(format!("sh(wpkh({xprv}/0/*))"))).is_ok());
+        assert!(ensure_segwit(&descriptor(format!("pkh({xprv}/0/*)"))).is_err());
+    }
+
+    #[test]
+    fn script_type_is_parsed_in_kebab_case() {
+        assert_eq!("sh-wpkh".parse::<ScriptType>().unwrap(), ScriptType::ShWpkh);
+        assert_eq!(ScriptType::Tr.to_string(), "tr");
+    }
+}
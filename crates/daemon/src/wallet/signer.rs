@@ -0,0 +1,228 @@
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use bdk::bitcoin::util::bip32::Fingerprint;
+use bdk::bitcoin::util::psbt::PartiallySignedTransaction;
+use bdk::bitcoin::Network;
+use serde::Deserialize;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// How long the wallet waits for an external signer to return a signed PSBT.
+///
+/// This has to be shorter than the time the counterparty waits for our signed lock transaction
+/// during contract setup.
+pub const SIGN_TIMEOUT: Duration = Duration::from_secs(90);
+
+const AIR_GAP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Signs PSBTs on behalf of a watch-only wallet.
+///
+/// The wallet waits for signatures outside of its actor, so it keeps processing other messages in
+/// the meantime.
+#[async_trait]
+pub trait ExternalSigner: Send + Sync + 'static {
+    /// Return `psbt` with signatures for the inputs owned by the signer.
+    async fn sign(&self, psbt: PartiallySignedTransaction) -> Result<PartiallySignedTransaction>;
+}
+
+/// Signs with a hardware wallet through the [HWI](https://github.com/bitcoin-core/HWI) command
+/// line tool.
+pub struct Hwi {
+    command: PathBuf,
+    fingerprint: Fingerprint,
+    network: Network,
+}
+
+impl Hwi {
+    pub fn new(command: PathBuf, fingerprint: Fingerprint, network: Network) -> Self {
+        Self {
+            command,
+            fingerprint,
+            network,
+        }
+    }
+
+    fn chain(&self) -> &'static str {
+        match self.network {
+            Network::Bitcoin => "main",
+            Network::Testnet => "test",
+            Network::Signet => "signet",
+            Network::Regtest => "regtest",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HwiResponse {
+    Signed { psbt: String },
+    Error { error: String, code: i64 },
+}
+
+#[async_trait]
+impl ExternalSigner for Hwi {
+    async fn sign(&self, psbt: PartiallySignedTransaction) -> Result<PartiallySignedTransaction> {
+        let output = Command::new(&self.command)
+            .arg("--fingerprint")
+            .arg(self.fingerprint.to_string())
+            .arg("--chain")
+            .arg(self.chain())
+            .arg("signtx")
+            .arg(psbt.to_string())
+            .kill_on_drop(true)
+            .output()
+            .await
+            .with_context(|| format!("Failed to run {}", self.command.display()))?;
+
+        if !output.status.success() {
+            bail!(
+                "{} exited with {}: {}",
+                self.command.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+
+        match serde_json::from_slice(&output.stdout).context("Invalid response from HWI")? {
+            HwiResponse::Signed { psbt } => {
+                PartiallySignedTransaction::from_str(&psbt).context("HWI returned an invalid PSBT")
+            }
+            HwiResponse::Error { error, code } => bail!("HWI failed to sign ({code}): {error}"),
+        }
+    }
+}
+
+/// Exchanges PSBTs with an air-gapped signer through a directory.
+///
+/// The base64 encoded PSBT of a transaction is written to `<txid>.psbt`. The signer is expected to
+/// put the signed PSBT into `<txid>.signed.psbt`, ideally by moving it there once it is written
+/// completely.
+pub struct AirGap {
+    dir: PathBuf,
+}
+
+impl AirGap {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+}
+
+#[async_trait]
+impl ExternalSigner for AirGap {
+    async fn sign(&self, psbt: PartiallySignedTransaction) -> Result<PartiallySignedTransaction> {
+        let txid = psbt.unsigned_tx.txid();
+        let unsigned_path = self.dir.join(format!("{txid}.psbt"));
+        let signed_path = self.dir.join(format!("{txid}.signed.psbt"));
+
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        tokio::fs::write(&unsigned_path, psbt.to_string())
+            .await
+            .with_context(|| format!("Failed to write {}", unsigned_path.display()))?;
+        // Remove the unsigned PSBT once we stop waiting for it, also if the wallet gives up after
+        // `SIGN_TIMEOUT`
+        let _unsigned = RemoveOnDrop(unsigned_path.clone());
+
+        tracing::info!(%txid, path = %unsigned_path.display(), "Waiting for PSBT to be signed");
+
+        let signed = loop {
+            match tokio::fs::read_to_string(&signed_path).await {
+                Ok(signed) => break signed,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    tokio_extras::time::sleep_silent(AIR_GAP_POLL_INTERVAL).await
+                }
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to read {}", signed_path.display()))
+                }
+            }
+        };
+
+        if let Err(e) = tokio::fs::remove_file(&signed_path).await {
+            tracing::warn!(path = %signed_path.display(), "Failed to clean up PSBT: {e:#}");
+        }
+
+        PartiallySignedTransaction::from_str(signed.trim())
+            .with_context(|| format!("Invalid PSBT in {}", signed_path.display()))
+    }
+}
+
+/// Removes the file at the path when dropped, also if the future owning it is cancelled.
+struct RemoveOnDrop(PathBuf);
+
+impl Drop for RemoveOnDrop {
+    fn drop(&mut self) {
+        match std::fs::remove_file(&self.0) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!(path = %self.0.display(), "Failed to clean up PSBT: {e:#}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk::bitcoin::Transaction;
+    use bdk::bitcoin::TxIn;
+    use bdk::bitcoin::TxOut;
+    use tokio_extras::FutureExt;
+
+    #[tokio::test]
+    async fn air_gap_signer_returns_psbt_from_signed_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = AirGap::new(dir.path().to_path_buf());
+
+        let psbt = PartiallySignedTransaction::from_unsigned_tx(Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn::default()],
+            output: vec![TxOut::default()],
+        })
+        .unwrap();
+        let txid = psbt.unsigned_tx.txid();
+
+        let sign_air_gapped = async {
+            let unsigned_path = dir.path().join(format!("{txid}.psbt"));
+            while !unsigned_path.exists() {
+                tokio_extras::time::sleep_silent(Duration::from_millis(10)).await;
+            }
+            let unsigned = std::fs::read_to_string(&unsigned_path).unwrap();
+            std::fs::write(dir.path().join(format!("{txid}.signed.psbt")), unsigned).unwrap();
+        };
+
+        let (signed, ()) = tokio::join!(signer.sign(psbt.clone()), sign_air_gapped);
+        let signed = signed.unwrap();
+
+        assert_eq!(signed, psbt);
+        assert!(std::fs::read_dir(dir.path()).unwrap().next().is_none());
+    }
+
+    #[tokio::test]
+    async fn air_gap_signer_removes_unsigned_psbt_when_cancelled() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = AirGap::new(dir.path().to_path_buf());
+
+        let psbt = PartiallySignedTransaction::from_unsigned_tx(Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn::default()],
+            output: vec![TxOut::default()],
+        })
+        .unwrap();
+        let unsigned_path = dir.path().join(format!("{}.psbt", psbt.unsigned_tx.txid()));
+
+        let sign = signer.sign(psbt).timeout(Duration::from_millis(100), || {
+            tracing::debug_span!("Sign with air-gapped signer")
+        });
+
+        assert!(sign.await.is_err());
+        assert!(!unsigned_path.exists());
+    }
+}
@@ -0,0 +1,235 @@
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::OutPoint;
+use bdk::bitcoin::Txid;
+use bdk::FeeRate;
+use model::Timestamp;
+use serde::Serialize;
+
+/// Minimum number of small UTXOs worth consolidating into one.
+const MIN_CONSOLIDATION_INPUTS: usize = 5;
+
+/// Upper bounds of the virtual size of a split transaction for the script types of the wallet.
+///
+/// The input is sized for `sh(wpkh())` and the outputs for `tr()`, the largest of the supported
+/// script types.
+const SPLIT_TX_OVERHEAD_VBYTES: u64 = 11;
+const SPLIT_TX_INPUT_VBYTES: u64 = 91;
+const SPLIT_TX_OUTPUT_VBYTES: u64 = 43;
+
+/// Distribution of UTXO sizes the wallet aims for.
+///
+/// Every contract setup locks at least one UTXO until its lock transaction is published. Having
+/// `target_count` UTXOs of at least `target_size` allows as many contract setups to run
+/// concurrently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct UtxoDistribution {
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub target_size: Amount,
+    pub target_count: usize,
+}
+
+impl UtxoDistribution {
+    /// UTXOs of at least the target size can fund a contract setup on their own.
+    fn is_usable(&self, amount: Amount) -> bool {
+        amount >= self.target_size
+    }
+
+    /// UTXOs below half the target size are consolidated.
+    fn is_small(&self, amount: Amount) -> bool {
+        amount < self.target_size / 2
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UtxoManagementKind {
+    Split,
+    Consolidate,
+}
+
+/// A transaction published to approach the target UTXO distribution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UtxoManagementTx {
+    pub kind: UtxoManagementKind,
+    pub txid: Txid,
+    pub inputs: usize,
+    pub outputs: usize,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub fee: Amount,
+    pub created_at: Timestamp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UtxoManagementReport {
+    /// Number of confirmed, spendable UTXOs of at least the target size before this run
+    pub usable_utxos: usize,
+    /// The transaction published in this run, if any
+    pub transaction: Option<UtxoManagementTx>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Plan {
+    /// Split `utxo` into `outputs` UTXOs of the target size plus change.
+    Split { utxo: OutPoint, outputs: usize },
+    /// Merge small UTXOs into one.
+    Consolidate { utxos: Vec<OutPoint> },
+}
+
+/// Decide on the next transaction towards the target `distribution` of the spendable `utxos`.
+///
+/// Splitting takes precedence because missing UTXOs block concurrent contract setups, whereas
+/// small UTXOs only make lock transactions more expensive. Transactions are never published above
+/// `max_fee_rate`, so the fee of a split is reserved at that rate.
+pub fn plan(
+    utxos: &[(OutPoint, Amount)],
+    distribution: UtxoDistribution,
+    max_fee_rate: FeeRate,
+) -> Option<Plan> {
+    if distribution.target_size == Amount::ZERO {
+        return None;
+    }
+
+    let usable = utxos
+        .iter()
+        .filter(|(_, amount)| distribution.is_usable(*amount))
+        .count();
+    let missing = distribution.target_count.saturating_sub(usable);
+
+    if missing > 0 {
+        let largest = utxos.iter().max_by_key(|(_, amount)| *amount);
+
+        if let Some((utxo, amount)) = largest {
+            // Leave at least the target size as change after paying the fee, so the split UTXO
+            // stays usable
+            let fee = |vbytes: u64| (max_fee_rate.as_sat_vb() * vbytes as f32).ceil() as u64;
+            let target_size = distribution.target_size.as_sat();
+            let splittable = amount
+                .as_sat()
+                .saturating_sub(target_size)
+                .saturating_sub(fee(SPLIT_TX_OVERHEAD_VBYTES
+                    + SPLIT_TX_INPUT_VBYTES
+                    + SPLIT_TX_OUTPUT_VBYTES))
+                / (target_size + fee(SPLIT_TX_OUTPUT_VBYTES));
+            let outputs = missing.min(splittable as usize);
+
+            if outputs > 0 {
+                return Some(Plan::Split {
+                    utxo: *utxo,
+                    outputs,
+                });
+            }
+        }
+    }
+
+    let small = utxos
+        .iter()
+        .filter(|(_, amount)| distribution.is_small(*amount))
+        .map(|(utxo, _)| *utxo)
+        .collect::<Vec<_>>();
+
+    if small.len() >= MIN_CONSOLIDATION_INPUTS {
+        return Some(Plan::Consolidate { utxos: small });
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn largest_utxo_is_split_into_missing_utxos() {
+        let utxos = [utxo(0, 1_500_000), utxo(1, 10_000_000)];
+
+        assert_eq!(
+            plan(&utxos, distribution(), max_fee_rate()),
+            Some(Plan::Split {
+                utxo: outpoint(1),
+                outputs: 2
+            })
+        );
+    }
+
+    #[test]
+    fn split_leaves_target_size_as_change() {
+        let utxos = [utxo(0, 2_500_000)];
+
+        assert_eq!(
+            plan(&utxos, distribution(), max_fee_rate()),
+            Some(Plan::Split {
+                utxo: outpoint(0),
+                outputs: 1
+            })
+        );
+    }
+
+    #[test]
+    fn utxo_of_exact_multiple_of_target_size_is_not_split_below_target_size() {
+        let utxos = [utxo(0, 2_000_000)];
+
+        assert_eq!(plan(&utxos, distribution(), max_fee_rate()), None);
+    }
+
+    #[test]
+    fn split_reserves_fee_for_every_output() {
+        // 3 outputs of the target size plus change of the target size leave 2_000 sat for fees
+        let utxos = [utxo(0, 4_002_000)];
+
+        assert_eq!(
+            plan(&utxos, distribution(), FeeRate::from_sat_per_vb(1.0)),
+            Some(Plan::Split {
+                utxo: outpoint(0),
+                outputs: 3
+            })
+        );
+        assert_eq!(
+            plan(&utxos, distribution(), FeeRate::from_sat_per_vb(10.0)),
+            Some(Plan::Split {
+                utxo: outpoint(0),
+                outputs: 2
+            })
+        );
+    }
+
+    #[test]
+    fn small_utxos_are_consolidated_once_distribution_is_reached() {
+        let usable = (0..4).map(|vout| utxo(vout, 1_000_000));
+        let small = (4..9).map(|vout| utxo(vout, 100_000));
+        let utxos = usable.chain(small).collect::<Vec<_>>();
+
+        assert_eq!(
+            plan(&utxos, distribution(), max_fee_rate()),
+            Some(Plan::Consolidate {
+                utxos: (4..9).map(outpoint).collect()
+            })
+        );
+    }
+
+    #[test]
+    fn nothing_to_do_without_splittable_or_enough_small_utxos() {
+        let utxos = [utxo(0, 1_200_000), utxo(1, 100_000)];
+
+        assert_eq!(plan(&utxos, distribution(), max_fee_rate()), None);
+    }
+
+    fn max_fee_rate() -> FeeRate {
+        FeeRate::from_sat_per_vb(2.0)
+    }
+
+    fn distribution() -> UtxoDistribution {
+        UtxoDistribution {
+            target_size: Amount::from_sat(1_000_000),
+            target_count: 4,
+        }
+    }
+
+    fn utxo(vout: u32, sats: u64) -> (OutPoint, Amount) {
+        (outpoint(vout), Amount::from_sat(sats))
+    }
+
+    fn outpoint(vout: u32) -> OutPoint {
+        OutPoint::from_str(&format!("{}:{vout}", "1".repeat(64))).unwrap()
+    }
+}
@@ -0,0 +1,158 @@
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::Address;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::Txid;
+use bdk::sled;
+use model::Timestamp;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// History of the withdrawals broadcast by the wallet.
+///
+/// Records are persisted in a dedicated tree of the wallet database, keyed by txid.
+pub struct Withdrawals {
+    tree: sled::Tree,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    pub txid: Txid,
+    pub address: Address,
+    /// Amount received by `address`
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub amount: Amount,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_sat")]
+    pub fee: Amount,
+    pub created_at: Timestamp,
+    /// Whether the output to `address` absorbs the change of the transaction.
+    ///
+    /// The fee of such a withdrawal is bumped by reducing the amount sent to `address`.
+    pub drain: bool,
+    pub replaced_by: Option<Txid>,
+}
+
+impl Withdrawals {
+    pub fn new(tree: sled::Tree) -> Self {
+        Self { tree }
+    }
+
+    pub fn insert(&self, record: &Record) -> Result<()> {
+        self.tree
+            .insert(record.txid.to_string(), serde_json::to_vec(record)?)?;
+        self.tree.flush()?;
+
+        Ok(())
+    }
+
+    pub fn get(&self, txid: Txid) -> Result<Option<Record>> {
+        let record = match self.tree.get(txid.to_string())? {
+            Some(bytes) => {
+                Some(serde_json::from_slice(&bytes).context("Invalid withdrawal record")?)
+            }
+            None => None,
+        };
+
+        Ok(record)
+    }
+
+    /// Record that the withdrawal `txid` was replaced by `replacement`.
+    pub fn mark_replaced(&self, txid: Txid, replacement: &Record) -> Result<()> {
+        let mut record = self
+            .get(txid)?
+            .with_context(|| format!("Unknown withdrawal {txid}"))?;
+        record.replaced_by = Some(replacement.txid);
+
+        self.insert(&record)?;
+        self.insert(replacement)?;
+
+        Ok(())
+    }
+
+    /// All withdrawals, most recent first.
+    pub fn all(&self) -> Result<Vec<Record>> {
+        let mut records = self
+            .tree
+            .iter()
+            .values()
+            .map(|value| {
+                serde_json::from_slice::<Record>(&value?).context("Invalid withdrawal record")
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        records.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn replaced_withdrawal_points_to_replacement() {
+        let withdrawals = temporary_withdrawals();
+
+        let original = dummy_record(1, Timestamp::new(1_000));
+        let replacement = Record {
+            txid: dummy_txid(2),
+            fee: Amount::from_sat(500),
+            created_at: Timestamp::new(2_000),
+            ..original.clone()
+        };
+
+        withdrawals.insert(&original).unwrap();
+        withdrawals
+            .mark_replaced(original.txid, &replacement)
+            .unwrap();
+
+        assert_eq!(
+            withdrawals.get(original.txid).unwrap().unwrap().replaced_by,
+            Some(replacement.txid)
+        );
+        assert_eq!(
+            withdrawals
+                .all()
+                .unwrap()
+                .into_iter()
+                .map(|record| record.txid)
+                .collect::<Vec<_>>(),
+            vec![replacement.txid, original.txid]
+        );
+    }
+
+    #[test]
+    fn replacing_unknown_withdrawal_fails() {
+        let withdrawals = temporary_withdrawals();
+        let replacement = dummy_record(2, Timestamp::new(2_000));
+
+        assert!(withdrawals
+            .mark_replaced(dummy_txid(1), &replacement)
+            .is_err());
+        assert!(withdrawals.all().unwrap().is_empty());
+    }
+
+    fn temporary_withdrawals() -> Withdrawals {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+
+        Withdrawals::new(db.open_tree("withdrawals").unwrap())
+    }
+
+    fn dummy_record(txid: u8, created_at: Timestamp) -> Record {
+        Record {
+            txid: dummy_txid(txid),
+            address: Address::from_str("tb1q7ysq6mc5qavt5ppps0mkcqwf6fm4zamc7zhpqn").unwrap(),
+            amount: Amount::from_sat(10_000),
+            fee: Amount::from_sat(200),
+            created_at,
+            drain: false,
+            replaced_by: None,
+        }
+    }
+
+    fn dummy_txid(n: u8) -> Txid {
+        Txid::from_str(&format!("{n:064x}")).unwrap()
+    }
+}
@@ -1,9 +1,14 @@
+use crate::blocked_peers;
+use crate::blocked_peers::BlocklistEntry;
 use crate::cfd;
+use crate::hedging;
+use crate::metrics::taker_analytics;
+use crate::metrics::taker_analytics::TakerAnalytics;
 use crate::metrics::time_to_first_position;
 use anyhow::Result;
 use bdk::bitcoin;
-use bdk::bitcoin::util::psbt::PartiallySignedTransaction;
 use bdk::bitcoin::Amount;
+use bdk::bitcoin::OutPoint;
 use bdk::bitcoin::Txid;
 use daemon::archive_closed_cfds;
 use daemon::archive_failed_cfds;
@@ -12,8 +17,8 @@ use daemon::command;
 use daemon::identify;
 use daemon::listen_protocols::MAKER_LISTEN_PROTOCOLS;
 use daemon::monitor;
+use daemon::notifications;
 use daemon::oracle;
-use daemon::oracle::NoAnnouncement;
 use daemon::order;
 use daemon::position_metrics;
 use daemon::process_manager;
@@ -21,17 +26,19 @@ use daemon::projection;
 use daemon::seed::Identities;
 use daemon::wallet;
 use daemon::Environment;
-use libp2p_tcp::TokioTcpConfig;
+use futures::AsyncRead;
+use futures::AsyncWrite;
 use maia_core::secp256k1_zkp::XOnlyPublicKey;
 use maia_core::PartyParams;
-use model::olivia::Announcement;
 use model::ContractSymbol;
+use model::ContractType;
 use model::Contracts;
 use model::FundingRate;
 use model::Leverage;
 use model::LotSize;
 use model::OpeningFee;
 use model::OrderId;
+use model::PayoutGranularity;
 use model::Price;
 use model::Role;
 use model::TxFeeRate;
@@ -40,6 +47,7 @@ use ping_pong::pong;
 use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
+use time::OffsetDateTime;
 use tokio_extras::Tasks;
 use xtra::Actor;
 use xtra::Address;
@@ -48,6 +56,7 @@ use xtra::Handler;
 use xtra_libp2p::endpoint;
 use xtra_libp2p::libp2p::Multiaddr;
 use xtra_libp2p::libp2p::PeerId;
+use xtra_libp2p::libp2p::Transport;
 use xtra_libp2p::listener;
 use xtra_libp2p::Endpoint;
 use xtras::supervisor::always_restart_after;
@@ -74,6 +83,8 @@ pub struct ActorSystem<O: 'static, W: 'static> {
             cfd::RatesChannel,
         >,
     >,
+    blocked_peers_actor: Address<blocked_peers::Actor>,
+    taker_analytics_actor: Address<taker_analytics::Actor>,
     _oracle_actor: Address<O>,
     _archive_closed_cfds_actor: Address<archive_closed_cfds::Actor>,
     _archive_failed_cfds_actor: Address<archive_failed_cfds::Actor>,
@@ -85,27 +96,34 @@ pub struct ActorSystem<O: 'static, W: 'static> {
 impl<O, W> ActorSystem<O, W>
 where
     O: Handler<oracle::MonitorAttestations, Return = ()>
-        + Handler<oracle::GetAnnouncements, Return = Result<Vec<Announcement>, NoAnnouncement>>
+        + Handler<oracle::GetAnnouncements, Return = oracle::PendingAnnouncements>
         + Actor<Stop = ()>,
     W: Handler<wallet::BuildPartyParams, Return = Result<PartyParams>>
-        + Handler<wallet::Sign, Return = Result<PartiallySignedTransaction>>
-        + Handler<wallet::Withdraw, Return = Result<Txid>>
+        + Handler<wallet::Sign, Return = wallet::PendingSignature>
+        + Handler<wallet::Withdraw, Return = wallet::PendingTxid>
+        + Handler<wallet::PreviewWithdraw, Return = Result<wallet::WithdrawPreview>>
+        + Handler<wallet::BumpFee, Return = wallet::PendingTxid>
+        + Handler<wallet::EstimateFeeRates, Return = Result<Vec<wallet::FeeEstimate>>>
+        + Handler<wallet::ListUtxos, Return = Result<Vec<wallet::Utxo>>>
+        + Handler<wallet::UpdateUtxo, Return = Result<()>>
         + Handler<wallet::Sync, Return = ()>
         + Actor<Stop = ()>,
 {
     #[allow(clippy::too_many_arguments)]
-    pub fn new<M>(
+    pub fn new<M, T>(
         db: sqlite_db::Connection,
         wallet_addr: Address<W>,
         oracle_pk: XOnlyPublicKey,
         oracle_constructor: impl FnOnce(command::Executor) -> O,
         monitor_constructor: impl FnOnce(command::Executor) -> Result<M>,
         settlement_interval: time::Duration,
-        n_payouts: usize,
         projection_actor: Address<projection::Actor>,
         identity: Identities,
         listen_multiaddr: Multiaddr,
+        transport: Box<dyn Fn() -> T + Send + 'static>,
         blocked_peers: HashSet<PeerId>,
+        hedging_actor: Option<Address<hedging::Actor>>,
+        notifications: Option<notifications::Outbox>,
     ) -> Result<Self>
     where
         M: Handler<monitor::MonitorAfterContractSetup, Return = ()>
@@ -115,11 +133,18 @@ where
             + Handler<monitor::TryBroadcastTransaction, Return = Result<()>>
             + Handler<monitor::MonitorCetFinality, Return = Result<()>>
             + Actor<Stop = ()>,
+        T: Transport + Send + Sync + 'static,
+        T::Output: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        T::Error: Send + Sync,
+        T::Listener: Send + 'static,
+        T::Dial: Send + 'static,
+        T::ListenerUpgrade: Send + 'static,
     {
         let (monitor_addr, monitor_ctx) = Context::new(None);
         let (oracle_addr, oracle_ctx) = Context::new(None);
         let (process_manager_addr, process_manager_ctx) = Context::new(None);
         let (time_to_first_position_addr, time_to_first_position_ctx) = Context::new(None);
+        let (taker_analytics_addr, taker_analytics_ctx) = Context::new(None);
 
         let executor = command::Executor::new(db.clone(), process_manager_addr.clone());
 
@@ -129,6 +154,11 @@ where
             .create(None)
             .spawn(&mut tasks);
 
+        let mut event_subscribers = Vec::new();
+        if let Some(hedging_actor) = hedging_actor {
+            event_subscribers.push(hedging_actor.into());
+        }
+
         tasks.add(process_manager_ctx.run(process_manager::Actor::new(
             db.clone(),
             Role::Maker,
@@ -140,16 +170,12 @@ where
             monitor_addr.clone().into(),
             monitor_addr.into(),
             oracle_addr.clone().into(),
+            event_subscribers,
+            notifications,
         )));
 
         let (endpoint_addr, endpoint_context) = Context::new(None);
 
-        let (supervisor, maker_offer_address_deprecated) = Supervisor::new({
-            let endpoint_addr = endpoint_addr.clone();
-            move || offer::deprecated::maker::Actor::new(endpoint_addr.clone())
-        });
-        tasks.add(supervisor.run_log_summary());
-
         let (supervisor, maker_offer_address) = Supervisor::new({
             let endpoint_addr = endpoint_addr.clone();
             move || offer::maker::Actor::new(endpoint_addr.clone())
@@ -165,7 +191,6 @@ where
             let maker_offer_address = maker_offer_address.clone();
             move || {
                 order::maker::Actor::new(
-                    n_payouts,
                     oracle_pk,
                     oracle.clone().into(),
                     (db.clone(), process_manager.clone()),
@@ -186,7 +211,6 @@ where
             let maker_offer_address = maker_offer_address.clone();
             move || {
                 order::deprecated::maker::Actor::new(
-                    n_payouts,
                     oracle_pk,
                     oracle.clone().into(),
                     (db.clone(), process_manager.clone()),
@@ -200,16 +224,14 @@ where
 
         let (collab_settlement_supervisor, collab_settlement_addr) = Supervisor::new({
             let executor = executor.clone();
-            move || collab_settlement::maker::Actor::new(executor.clone(), n_payouts)
+            move || collab_settlement::maker::Actor::new(executor.clone())
         });
         tasks.add(collab_settlement_supervisor.run_log_summary());
 
         let (collab_settlement_deprecated_supervisor, collab_settlement_deprecated_addr) =
             Supervisor::new({
                 let executor = executor.clone();
-                move || {
-                    collab_settlement::deprecated::maker::Actor::new(executor.clone(), n_payouts)
-                }
+                move || collab_settlement::deprecated::maker::Actor::new(executor.clone())
             });
         tasks.add(collab_settlement_deprecated_supervisor.run_log_summary());
 
@@ -221,10 +243,7 @@ where
                 collab_settlement_addr.clone(),
                 collab_settlement_deprecated_addr.clone(),
             ),
-            (
-                maker_offer_address.clone(),
-                maker_offer_address_deprecated.clone(),
-            ),
+            maker_offer_address.clone(),
             (order.clone(), order_deprecated.clone()),
         )
         .create(None)
@@ -240,7 +259,6 @@ where
                     oracle_pk,
                     oracle::AnnouncementsChannel::new(oracle_addr.clone().into()),
                     cfd::RatesChannel::new(cfd_actor_addr.clone().into()),
-                    n_payouts,
                 )
             }
         });
@@ -256,7 +274,6 @@ where
                     oracle_pk,
                     oracle::AnnouncementsChannel::new(oracle_addr.clone().into()),
                     cfd::RatesChannel::new(cfd_actor_addr.clone().into()),
-                    n_payouts,
                 )
             }
         });
@@ -292,41 +309,69 @@ where
             }
         });
 
-        let (identify_dialer_supervisor, identify_dialer_actor) =
-            Supervisor::new(move || identify::dialer::Actor::new(endpoint_addr.clone()));
+        let (identify_dialer_supervisor, identify_dialer_actor) = Supervisor::new({
+            let endpoint_addr = endpoint_addr.clone();
+            let taker_analytics_addr = taker_analytics_addr.clone();
+            move || {
+                identify::dialer::Actor::new_with_peer_identified_subscriber(
+                    endpoint_addr.clone(),
+                    taker_analytics_addr.clone().into(),
+                )
+            }
+        });
 
         let endpoint = Endpoint::new(
-            Box::new(TokioTcpConfig::new),
+            transport,
             identity.libp2p,
             ENDPOINT_CONNECTION_TIMEOUT,
             MAKER_LISTEN_PROTOCOLS.inbound_substream_handlers(
                 pong_address.clone(),
                 identify_listener_actor,
-                (order, order_deprecated),
-                (rollover_addr.clone(), rollover_deprecated_addr.clone()),
-                (collab_settlement_addr, collab_settlement_deprecated_addr),
+                vec![
+                    (order::PROTOCOL, order.into()),
+                    (order::deprecated::PROTOCOL, order_deprecated.into()),
+                ],
+                vec![
+                    (rollover::PROTOCOL, rollover_addr.clone().into()),
+                    (
+                        rollover::deprecated::PROTOCOL,
+                        rollover_deprecated_addr.clone().into(),
+                    ),
+                ],
+                vec![
+                    (collab_settlement::PROTOCOL, collab_settlement_addr.into()),
+                    (
+                        collab_settlement::deprecated::PROTOCOL,
+                        collab_settlement_deprecated_addr.into(),
+                    ),
+                ],
             ),
             endpoint::Subscribers::new(
                 vec![
                     ping_address.clone().into(),
                     maker_offer_address.clone().into(),
-                    maker_offer_address_deprecated.clone().into(),
                     identify_dialer_actor.clone().into(),
+                    taker_analytics_addr.clone().into(),
                 ],
                 vec![
                     ping_address.into(),
                     maker_offer_address.into(),
-                    maker_offer_address_deprecated.into(),
                     identify_dialer_actor.into(),
+                    taker_analytics_addr.clone().into(),
                 ],
                 vec![],
                 vec![listener_actor.into()],
             ),
-            Arc::new(blocked_peers),
+            Arc::new(blocked_peers.clone()),
         );
 
         tasks.add(endpoint_context.run(endpoint));
 
+        let blocked_peers_actor =
+            blocked_peers::Actor::new(db.clone(), endpoint_addr, blocked_peers)
+                .create(None)
+                .spawn(&mut tasks);
+
         tasks.add(listener_supervisor.run_log_summary());
         tasks.add(ping_supervisor.run_log_summary());
         tasks.add(identify_listener_supervisor.run_log_summary());
@@ -344,6 +389,8 @@ where
             .create(None)
             .spawn(&mut tasks);
 
+        tasks.add(taker_analytics_ctx.run(taker_analytics::Actor::new(db.clone())));
+
         tasks.add(time_to_first_position_ctx.run(time_to_first_position::Actor::new(db)));
 
         tracing::debug!("Maker actor system ready");
@@ -353,6 +400,8 @@ where
             wallet_actor: wallet_addr,
             rollover_actor: rollover_addr,
             rollover_actor_deprecated: rollover_deprecated_addr,
+            blocked_peers_actor,
+            taker_analytics_actor: taker_analytics_addr,
             _archive_closed_cfds_actor: archive_closed_cfds_actor,
             _archive_failed_cfds_actor: archive_failed_cfds_actor,
             executor,
@@ -379,6 +428,9 @@ where
         leverage_choices: Vec<Leverage>,
         contract_symbol: ContractSymbol,
         lot_size: LotSize,
+        settlement_interval: Option<time::Duration>,
+        contract_type: ContractType,
+        granularity: PayoutGranularity,
     ) -> Result<()> {
         self.cfd_actor
             .send(cfd::OfferParams {
@@ -393,6 +445,9 @@ where
                 leverage_choices,
                 contract_symbol,
                 lot_size,
+                settlement_interval,
+                contract_type,
+                granularity,
             })
             .await??;
 
@@ -435,13 +490,63 @@ where
         &self,
         amount: Option<Amount>,
         address: bitcoin::Address,
-        fee: f32,
+        fee: wallet::WithdrawFee,
+        coins: Vec<OutPoint>,
     ) -> Result<Txid> {
         self.wallet_actor
             .send(wallet::Withdraw {
                 amount,
                 address,
-                fee: Some(bdk::FeeRate::from_sat_per_vb(fee)),
+                fee: Some(fee),
+                coins,
+            })
+            .await?
+            .await
+    }
+
+    pub async fn preview_withdraw(
+        &self,
+        amount: Option<Amount>,
+        address: bitcoin::Address,
+        fee: wallet::WithdrawFee,
+        coins: Vec<OutPoint>,
+    ) -> Result<wallet::WithdrawPreview> {
+        self.wallet_actor
+            .send(wallet::PreviewWithdraw(wallet::Withdraw {
+                amount,
+                address,
+                fee: Some(fee),
+                coins,
+            }))
+            .await?
+    }
+
+    pub async fn bump_fee(&self, txid: Txid, fee: wallet::WithdrawFee) -> Result<Txid> {
+        self.wallet_actor
+            .send(wallet::BumpFee { txid, fee })
+            .await?
+            .await
+    }
+
+    pub async fn estimate_fee_rates(&self) -> Result<Vec<wallet::FeeEstimate>> {
+        self.wallet_actor.send(wallet::EstimateFeeRates).await?
+    }
+
+    pub async fn list_utxos(&self) -> Result<Vec<wallet::Utxo>> {
+        self.wallet_actor.send(wallet::ListUtxos).await?
+    }
+
+    pub async fn update_utxo(
+        &self,
+        outpoint: OutPoint,
+        label: Option<String>,
+        frozen: bool,
+    ) -> Result<()> {
+        self.wallet_actor
+            .send(wallet::UpdateUtxo {
+                outpoint,
+                label,
+                frozen,
             })
             .await?
     }
@@ -464,4 +569,47 @@ where
             .await?;
         Ok(())
     }
+
+    pub async fn block_peer(
+        &self,
+        peer_id: PeerId,
+        reason: String,
+        expires_at: Option<OffsetDateTime>,
+    ) -> Result<()> {
+        self.blocked_peers_actor
+            .send(blocked_peers::Block {
+                peer_id,
+                reason,
+                expires_at,
+            })
+            .await??;
+        Ok(())
+    }
+
+    /// Remove a peer from the blocklist.
+    ///
+    /// Returns `false` if the peer was not on the blocklist.
+    pub async fn unblock_peer(&self, peer_id: PeerId) -> Result<bool> {
+        let was_blocked = self
+            .blocked_peers_actor
+            .send(blocked_peers::Unblock { peer_id })
+            .await??;
+        Ok(was_blocked)
+    }
+
+    pub async fn blocked_peers(&self) -> Result<Vec<BlocklistEntry>> {
+        let blocked_peers = self
+            .blocked_peers_actor
+            .send(blocked_peers::GetBlockedPeers)
+            .await??;
+        Ok(blocked_peers)
+    }
+
+    pub async fn taker_analytics(&self) -> Result<Vec<TakerAnalytics>> {
+        let analytics = self
+            .taker_analytics_actor
+            .send(taker_analytics::GetTakerAnalytics)
+            .await??;
+        Ok(analytics)
+    }
 }
@@ -1,17 +1,35 @@
 use anyhow::Result;
+use async_trait::async_trait;
 use serde::Deserialize;
+use sqlite_db::blocked_peers::BlockedPeer;
 use std::collections::HashSet;
 use std::path::Path;
+use std::time::Duration;
+use time::OffsetDateTime;
+use xtra::Address;
 use xtra_libp2p::libp2p::PeerId;
+use xtra_libp2p::BlockPeer;
+use xtra_libp2p::Endpoint;
+use xtra_libp2p::UnblockPeer;
+use xtra_productivity::xtra_productivity;
+use xtras::SendAsyncSafe;
+use xtras::SendInterval;
 
 const FILENAME: &str = "blocked_peers.toml";
 
+/// Interval at which we check for blocklist entries that have expired.
+const REMOVE_EXPIRED_INTERVAL: Duration = Duration::from_secs(60);
+
 /// Convenience type to load the blocked peer list from toml
 #[derive(Deserialize)]
 struct BlockedPeers {
     blocked: HashSet<PeerId>,
 }
 
+/// Load the static blocklist from the config file in the data directory.
+///
+/// Peers in this file are always blocked on startup. Use the [`Actor`] to manage the blocklist at
+/// runtime.
 pub async fn load_blocked_peers(directory: &Path) -> Result<HashSet<PeerId>> {
     let path = directory.join(FILENAME);
 
@@ -24,3 +42,225 @@ pub async fn load_blocked_peers(directory: &Path) -> Result<HashSet<PeerId>> {
     let raw = tokio::fs::read_to_string(path).await?;
     Ok(toml::from_str::<BlockedPeers>(&raw)?.blocked)
 }
+
+/// Block a peer, disconnecting it if it is currently connected.
+pub struct Block {
+    pub peer_id: PeerId,
+    pub reason: String,
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+/// Unblock a peer.
+///
+/// Peers listed in the config file are only unblocked until the next restart, unless they are also
+/// removed from the file.
+///
+/// Returns `false` if the peer was not on the blocklist.
+#[derive(Clone, Copy)]
+pub struct Unblock {
+    pub peer_id: PeerId,
+}
+
+/// Return all active entries of the blocklist, from the database and the config file.
+#[derive(Clone, Copy)]
+pub struct GetBlockedPeers;
+
+/// Where a blocklist entry comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlocklistSource {
+    /// Listed in the static config file
+    Config,
+    /// Added at runtime and persisted in the database
+    Api,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlocklistEntry {
+    pub blocked_peer: BlockedPeer,
+    pub source: BlocklistSource,
+}
+
+/// Actor managing the blocklist and enforcing it on the [`Endpoint`].
+///
+/// The blocklist is made up of the peers in the static config file and the peers blocked at
+/// runtime, which are persisted in the database. A peer stays blocked as long as it is listed in
+/// either of them.
+pub struct Actor {
+    db: sqlite_db::Connection,
+    endpoint: Address<Endpoint>,
+    static_blocked_peers: HashSet<PeerId>,
+    started_at: OffsetDateTime,
+}
+
+impl Actor {
+    /// The `static_blocked_peers` are the peers loaded from the config file, which the
+    /// [`Endpoint`] is expected to block already.
+    pub fn new(
+        db: sqlite_db::Connection,
+        endpoint: Address<Endpoint>,
+        static_blocked_peers: HashSet<PeerId>,
+    ) -> Self {
+        Self {
+            db,
+            endpoint,
+            static_blocked_peers,
+            started_at: tokio_extras::time::now_utc(),
+        }
+    }
+
+    async fn block_persisted_peers(&mut self) -> Result<()> {
+        let now = tokio_extras::time::now_utc();
+
+        for blocked_peer in self.db.load_blocked_peers().await? {
+            if blocked_peer.is_expired(now) {
+                self.db.delete_blocked_peer(blocked_peer.peer_id).await?;
+                continue;
+            }
+
+            self.endpoint
+                .send_async_safe(BlockPeer(blocked_peer.peer_id.inner()))
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: Block) -> Result<()> {
+        let Block {
+            peer_id,
+            reason,
+            expires_at,
+        } = msg;
+
+        tracing::info!(%peer_id, %reason, ?expires_at, "Blocking peer");
+
+        self.db
+            .insert_blocked_peer(BlockedPeer {
+                peer_id: peer_id.into(),
+                reason,
+                blocked_at: tokio_extras::time::now_utc(),
+                expires_at,
+            })
+            .await?;
+
+        self.endpoint.send(BlockPeer(peer_id)).await?;
+
+        Ok(())
+    }
+
+    async fn handle(&mut self, msg: Unblock) -> Result<bool> {
+        let peer_id = msg.peer_id;
+
+        let was_persisted = self.db.delete_blocked_peer(peer_id.into()).await?;
+        let was_static = self.static_blocked_peers.remove(&peer_id);
+
+        self.endpoint.send(UnblockPeer(peer_id)).await?;
+
+        Ok(was_persisted || was_static)
+    }
+
+    async fn handle(&mut self, _: GetBlockedPeers) -> Result<Vec<BlocklistEntry>> {
+        let now = tokio_extras::time::now_utc();
+
+        let mut entries = self
+            .db
+            .load_blocked_peers()
+            .await?
+            .into_iter()
+            .filter(|blocked_peer| !blocked_peer.is_expired(now))
+            .map(|blocked_peer| BlocklistEntry {
+                blocked_peer,
+                source: BlocklistSource::Api,
+            })
+            .collect::<Vec<_>>();
+
+        let persisted = entries
+            .iter()
+            .map(|entry| entry.blocked_peer.peer_id.inner())
+            .collect::<HashSet<_>>();
+
+        entries.extend(
+            self.static_blocked_peers
+                .iter()
+                .filter(|peer_id| !persisted.contains(peer_id))
+                .map(|peer_id| BlocklistEntry {
+                    blocked_peer: BlockedPeer {
+                        peer_id: (*peer_id).into(),
+                        reason: format!("Listed in {FILENAME}"),
+                        blocked_at: self.started_at,
+                        expires_at: None,
+                    },
+                    source: BlocklistSource::Config,
+                }),
+        );
+
+        Ok(entries)
+    }
+
+    async fn handle(&mut self, _: RemoveExpired) {
+        let now = tokio_extras::time::now_utc();
+
+        let blocked_peers = match self.db.load_blocked_peers().await {
+            Ok(blocked_peers) => blocked_peers,
+            Err(e) => {
+                tracing::warn!("Failed to load blocked peers: {e:#}");
+                return;
+            }
+        };
+
+        for blocked_peer in blocked_peers
+            .into_iter()
+            .filter(|blocked_peer| blocked_peer.is_expired(now))
+        {
+            let peer_id = blocked_peer.peer_id;
+
+            if let Err(e) = self.db.delete_blocked_peer(peer_id).await {
+                tracing::warn!(%peer_id, "Failed to remove expired blocklist entry: {e:#}");
+                continue;
+            }
+
+            tracing::info!(%peer_id, "Blocklist entry expired");
+
+            if self.static_blocked_peers.contains(&peer_id.inner()) {
+                tracing::info!(%peer_id, "Peer remains blocked through {FILENAME}");
+                continue;
+            }
+
+            if let Err(e) = self
+                .endpoint
+                .send_async_safe(UnblockPeer(peer_id.inner()))
+                .await
+            {
+                tracing::warn!(%peer_id, "Failed to unblock peer: {e:#}");
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        if let Err(e) = self.block_persisted_peers().await {
+            tracing::error!("Failed to block persisted peers: {e:#}");
+        }
+
+        let this = ctx.address().expect("we are alive");
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                REMOVE_EXPIRED_INTERVAL,
+                || RemoveExpired,
+                xtras::IncludeSpan::Always,
+            ),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+struct RemoveExpired;
@@ -1,11 +1,14 @@
+use crate::liquidity;
 use crate::metrics::time_to_first_position;
 use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
+use bdk::bitcoin::Amount;
 use daemon::order;
 use daemon::projection;
 use model::ContractSymbol;
+use model::ContractType;
 use model::Contracts;
 use model::FundingRate;
 use model::Identity;
@@ -13,11 +16,11 @@ use model::Leverage;
 use model::LotSize;
 use model::OpeningFee;
 use model::OrderId;
+use model::PayoutGranularity;
 use model::Position;
 use model::Price;
 use model::Timestamp;
 use model::TxFeeRate;
-use nonempty::NonEmpty;
 use std::collections::HashMap;
 use time::Duration;
 use time::OffsetDateTime;
@@ -65,6 +68,10 @@ pub struct TakerDisconnected {
 #[derive(Clone, Copy)]
 pub struct GetRolloverParams(ContractSymbol);
 
+/// The balance available to fund new positions changed.
+#[derive(Clone, Copy)]
+pub struct FreeBalanceChanged(pub Amount);
+
 #[derive(Clone, Debug)]
 pub struct OfferParams {
     pub price_long: Option<Price>,
@@ -78,10 +85,14 @@ pub struct OfferParams {
     pub leverage_choices: Vec<Leverage>,
     pub contract_symbol: ContractSymbol,
     pub lot_size: LotSize,
+    /// Falls back to the maker's default settlement interval if not specified
+    pub settlement_interval: Option<Duration>,
+    pub contract_type: ContractType,
+    pub granularity: PayoutGranularity,
 }
 
 impl OfferParams {
-    fn into_offers(self, settlement_interval: Duration) -> Vec<model::Offer> {
+    fn into_offers(self, default_settlement_interval: Duration) -> Vec<model::Offer> {
         let Self {
             price_long,
             price_short,
@@ -94,8 +105,13 @@ impl OfferParams {
             leverage_choices,
             contract_symbol,
             lot_size,
+            settlement_interval,
+            contract_type,
+            granularity,
         } = self;
 
+        let settlement_interval = settlement_interval.unwrap_or(default_settlement_interval);
+
         let mut offers = Vec::new();
 
         if let Some(price_long) = price_long {
@@ -105,6 +121,8 @@ impl OfferParams {
                 min_quantity,
                 max_quantity,
                 settlement_interval,
+                contract_type,
+                granularity,
                 tx_fee_rate,
                 funding_rate_long,
                 opening_fee,
@@ -123,6 +141,8 @@ impl OfferParams {
                 min_quantity,
                 max_quantity,
                 settlement_interval,
+                contract_type,
+                granularity,
                 tx_fee_rate,
                 funding_rate_short,
                 opening_fee,
@@ -161,12 +181,14 @@ pub struct Actor {
     settlement_interval: Duration,
     projection: xtra::Address<projection::Actor>,
     rollover_params: RolloverParams,
+    offer_params: HashMap<ContractSymbol, OfferParams>,
+    /// Offers are capped to what this balance can fund, if known
+    free_balance: Option<Amount>,
     time_to_first_position: xtra::Address<time_to_first_position::Actor>,
     collab_settlement: xtra::Address<daemon::collab_settlement::maker::Actor>,
     collab_settlement_deprecated:
         xtra::Address<daemon::collab_settlement::deprecated::maker::Actor>,
     offer: xtra::Address<offer::maker::Actor>,
-    offer_deprecated: xtra::Address<offer::deprecated::maker::Actor>,
     order: xtra::Address<order::maker::Actor>,
     order_deprecated: xtra::Address<order::deprecated::maker::Actor>,
 }
@@ -180,10 +202,7 @@ impl Actor {
             xtra::Address<daemon::collab_settlement::maker::Actor>,
             xtra::Address<daemon::collab_settlement::deprecated::maker::Actor>,
         ),
-        (offer, offer_deprecated): (
-            xtra::Address<offer::maker::Actor>,
-            xtra::Address<offer::deprecated::maker::Actor>,
-        ),
+        offer: xtra::Address<offer::maker::Actor>,
         (order, order_deprecated): (
             xtra::Address<order::maker::Actor>,
             xtra::Address<order::deprecated::maker::Actor>,
@@ -193,11 +212,12 @@ impl Actor {
             settlement_interval,
             projection,
             rollover_params: RolloverParams::default(),
+            offer_params: HashMap::new(),
+            free_balance: None,
             time_to_first_position,
             collab_settlement,
             collab_settlement_deprecated,
             offer,
-            offer_deprecated,
             order,
             order_deprecated,
         }
@@ -211,7 +231,7 @@ impl Actor {
         tx_fee_rate: TxFeeRate,
     ) {
         let funding_rates = FundingRates { long, short };
-        let expiry = OffsetDateTime::now_utc() + ROLLOVER_PARAMS_TTL;
+        let expiry = tokio_extras::time::now_utc() + ROLLOVER_PARAMS_TTL;
 
         self.rollover_params
             .funding_rates
@@ -357,7 +377,7 @@ impl Actor {
             .get(&contract_symbol)
             .with_context(|| format!("Missing {contract_symbol} funding rates"))?;
 
-        if expiry < OffsetDateTime::now_utc() {
+        if expiry < tokio_extras::time::now_utc() {
             bail!("Outdated funding rates");
         }
 
@@ -370,59 +390,66 @@ impl Actor {
 #[xtra_productivity]
 impl Actor {
     async fn handle_offer_params(&mut self, offer_params: OfferParams) -> Result<()> {
-        // 1. Update internal state for rollovers
+        // Update internal state for rollovers
         self.udpate_rollover_params(
             offer_params.contract_symbol,
             offer_params.funding_rate_long,
             offer_params.funding_rate_short,
             offer_params.tx_fee_rate,
         );
+        self.offer_params
+            .insert(offer_params.contract_symbol, offer_params.clone());
+
+        self.publish_offers(offer_params).await
+    }
+
+    async fn handle(&mut self, msg: FreeBalanceChanged) {
+        let FreeBalanceChanged(free_balance) = msg;
+        self.free_balance = Some(free_balance);
+
+        for offer_params in self.offer_params.values().cloned().collect::<Vec<_>>() {
+            if let Err(e) = self.publish_offers(offer_params).await {
+                tracing::warn!("Failed to republish offers for free balance: {e:#}");
+            }
+        }
+    }
 
+    async fn handle(&mut self, msg: TakerConnected) -> Result<()> {
+        self.handle_taker_connected(msg.id).await
+    }
+
+    async fn handle(&mut self, msg: TakerDisconnected) -> Result<()> {
+        self.handle_taker_disconnected(msg.id).await
+    }
+}
+
+impl Actor {
+    async fn publish_offers(&self, offer_params: OfferParams) -> Result<()> {
         let offers = offer_params.into_offers(self.settlement_interval);
+        let offers = match self.free_balance {
+            Some(free_balance) => offers
+                .into_iter()
+                .filter_map(|offer| liquidity::cap_max_quantity(offer, free_balance))
+                .collect(),
+            None => offers,
+        };
 
-        // 2. Notify UI via feed
+        // 1. Notify UI via feed
         self.projection
             .send(projection::Update(offers.clone()))
             .await?;
 
-        // 3. Broadcast to all peers via offer actor
+        // 2. Broadcast to all peers via offer actor
         if let Err(e) = self
             .offer
-            .send_async_safe(offer::maker::NewOffers::new(offers.clone()))
+            .send_async_safe(offer::maker::NewOffers::new(offers))
             .await
         {
             tracing::warn!("{e:#}");
         }
 
-        // 4. Broadcast to all peers via deprecated offer actor
-        {
-            // Takers on the deprecated version only care (and know how to handle) BTCUSD offers
-            let btcusd_offers = offers
-                .into_iter()
-                .filter(|offer| offer.contract_symbol == ContractSymbol::BtcUsd)
-                .collect::<Vec<_>>();
-
-            if let Some(btcusd_offers) = NonEmpty::from_vec(btcusd_offers) {
-                if let Err(e) = self
-                    .offer_deprecated
-                    .send_async_safe(offer::deprecated::maker::NewOffers::new(btcusd_offers))
-                    .await
-                {
-                    tracing::warn!("{e:#}");
-                }
-            };
-        }
-
         Ok(())
     }
-
-    async fn handle(&mut self, msg: TakerConnected) -> Result<()> {
-        self.handle_taker_connected(msg.id).await
-    }
-
-    async fn handle(&mut self, msg: TakerDisconnected) -> Result<()> {
-        self.handle_taker_disconnected(msg.id).await
-    }
 }
 
 /// Source of offer rates used for rolling over CFDs.
@@ -0,0 +1,682 @@
+//! Profit and loss and exposure of the maker across all positions.
+//!
+//! The maker is the counterparty of every taker, so it is exposed to the price of each contract
+//! symbol with the net quantity of its positions. The report tells the operator how much to hedge
+//! elsewhere and how the book performs at the current quote.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::SignedAmount;
+use daemon::projection;
+use daemon::projection::CfdState;
+use daemon::projection::LatestQuotes;
+use model::calculate_long_liquidation_price;
+use model::calculate_margin;
+use model::calculate_payout_at_price;
+use model::calculate_profit;
+use model::calculate_short_liquidation_price;
+use model::long_and_short_leverage;
+use model::market_closing_price;
+use model::ContractSymbol;
+use model::Contracts;
+use model::FeeAccount;
+use model::Leverage;
+use model::Position;
+use model::Price;
+use model::Role;
+use serde::Serialize;
+use sqlite_db::exposure::ExposureSnapshot;
+use sqlite_db::exposure::SymbolExposureSnapshot;
+use std::time::Duration;
+use strum::IntoEnumIterator;
+use time::OffsetDateTime;
+use tokio::sync::watch;
+use xtra_productivity::xtra_productivity;
+use xtras::SendAsyncNext;
+
+/// Interval at which the report is stored in the database.
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Relative distance of the price to a liquidation price below which a CFD counts as near
+/// liquidation.
+const LIQUIDATION_PROXIMITY: f64 = 0.1;
+
+static NET_EXPOSURE_GAUGE: conquer_once::Lazy<prometheus::GaugeVec> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_gauge_vec!(
+            "maker_net_exposure_satoshis",
+            "The value of the maker's net position at the current price in satoshis.",
+            &["symbol"]
+        )
+        .unwrap()
+    });
+static UNREALISED_PNL_GAUGE: conquer_once::Lazy<prometheus::Gauge> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_gauge!(
+            "maker_unrealised_pnl_satoshis",
+            "The maker's profit and loss of all open positions at the current price in satoshis."
+        )
+        .unwrap()
+    });
+
+/// The maker's profit and loss and exposure as reported in the feed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExposureReport {
+    /// Profit and loss of settled CFDs
+    ///
+    /// Includes CFDs whose settlement transaction is not confirmed yet.
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub realised_pnl: SignedAmount,
+    /// Profit and loss of open CFDs if they were closed at the current quote
+    ///
+    /// `None` if the quote of a symbol with open positions is not known.
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc::opt")]
+    pub unrealised_pnl: Option<SignedAmount>,
+    /// Opening and funding fees of open CFDs, positive if the takers owe them to us
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub accrued_fees: SignedAmount,
+    /// Our margin in open CFDs whose price is close to our liquidation price
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub margin_at_risk: Amount,
+    pub symbols: Vec<SymbolExposure>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SymbolExposure {
+    pub contract_symbol: ContractSymbol,
+    /// Middle between bid and ask of the current quote
+    pub price: Option<Price>,
+    pub open_positions: u64,
+    /// Quantity of our long positions
+    pub long_contracts: Contracts,
+    /// Quantity of our short positions
+    pub short_contracts: Contracts,
+    /// Value of our net position at `price`, positive if we are net long
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc::opt")]
+    pub net_exposure_btc: Option<SignedAmount>,
+    /// Value of our net position at `price` and the current BTCUSD price
+    pub net_exposure_usd: Option<f64>,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc::opt")]
+    pub unrealised_pnl: Option<SignedAmount>,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub accrued_fees: SignedAmount,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub margin_at_risk: Amount,
+    /// Open CFDs whose price is close to our or the taker's liquidation price
+    pub near_liquidation: u64,
+}
+
+impl From<&ExposureReport> for ExposureSnapshot {
+    fn from(report: &ExposureReport) -> Self {
+        Self {
+            timestamp: tokio_extras::time::now_utc(),
+            realised_pnl: report.realised_pnl,
+            unrealised_pnl: report.unrealised_pnl,
+            accrued_fees: report.accrued_fees,
+            margin_at_risk: report.margin_at_risk,
+            symbols: report
+                .symbols
+                .iter()
+                .map(|symbol| SymbolExposureSnapshot {
+                    contract_symbol: symbol.contract_symbol,
+                    price: symbol.price,
+                    long_contracts: symbol.long_contracts,
+                    short_contracts: symbol.short_contracts,
+                    net_exposure: symbol.net_exposure_btc,
+                    unrealised_pnl: symbol.unrealised_pnl,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A report as stored in the database.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExposureHistoryEntry {
+    #[serde(with = "time::serde::timestamp")]
+    pub timestamp: OffsetDateTime,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub realised_pnl: SignedAmount,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc::opt")]
+    pub unrealised_pnl: Option<SignedAmount>,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub accrued_fees: SignedAmount,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub margin_at_risk: Amount,
+    pub symbols: Vec<SymbolExposureHistoryEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SymbolExposureHistoryEntry {
+    pub contract_symbol: ContractSymbol,
+    pub price: Option<Price>,
+    pub long_contracts: Contracts,
+    pub short_contracts: Contracts,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc::opt")]
+    pub net_exposure_btc: Option<SignedAmount>,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc::opt")]
+    pub unrealised_pnl: Option<SignedAmount>,
+}
+
+impl From<ExposureSnapshot> for ExposureHistoryEntry {
+    fn from(snapshot: ExposureSnapshot) -> Self {
+        Self {
+            timestamp: snapshot.timestamp,
+            realised_pnl: snapshot.realised_pnl,
+            unrealised_pnl: snapshot.unrealised_pnl,
+            accrued_fees: snapshot.accrued_fees,
+            margin_at_risk: snapshot.margin_at_risk,
+            symbols: snapshot
+                .symbols
+                .into_iter()
+                .map(|symbol| SymbolExposureHistoryEntry {
+                    contract_symbol: symbol.contract_symbol,
+                    price: symbol.price,
+                    long_contracts: symbol.long_contracts,
+                    short_contracts: symbol.short_contracts,
+                    net_exposure_btc: symbol.net_exposure,
+                    unrealised_pnl: symbol.unrealised_pnl,
+                })
+                .collect(),
+        }
+    }
+}
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    rx_cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+    rx_quote: watch::Receiver<LatestQuotes>,
+    feed: watch::Sender<Option<ExposureReport>>,
+}
+
+impl Actor {
+    pub fn new(
+        db: sqlite_db::Connection,
+        rx_cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+        rx_quote: watch::Receiver<LatestQuotes>,
+    ) -> (Self, watch::Receiver<Option<ExposureReport>>) {
+        let (feed, receiver) = watch::channel(None);
+
+        let actor = Self {
+            db,
+            rx_cfds,
+            rx_quote,
+            feed,
+        };
+
+        (actor, receiver)
+    }
+
+    fn report(&self) -> Option<ExposureReport> {
+        let cfds = self.rx_cfds.borrow();
+        let cfds = cfds.as_ref()?;
+
+        let prices = self
+            .rx_quote
+            .borrow()
+            .iter()
+            .filter_map(|(symbol, quote)| {
+                let bid = Price::new(quote.bid()).ok()?;
+                let ask = Price::new(quote.ask()).ok()?;
+                let mid = Price::new(quote.mid()).ok()?;
+
+                Some((*symbol, Quote { bid, ask, mid }))
+            })
+            .collect::<Vec<_>>();
+
+        let realised = cfds
+            .iter()
+            .filter(|cfd| is_settled(cfd.state))
+            .filter_map(|cfd| cfd.profit_btc);
+
+        let open = cfds
+            .iter()
+            .filter(|cfd| is_exposed(cfd.state))
+            .map(OpenCfd::from)
+            .collect::<Vec<_>>();
+
+        Some(report(realised, &open, &prices))
+    }
+
+    fn update(&mut self) {
+        let report = match self.report() {
+            Some(report) => report,
+            None => return,
+        };
+
+        if self.feed.borrow().as_ref() == Some(&report) {
+            return;
+        }
+
+        for symbol in report.symbols.iter() {
+            let exposure = symbol
+                .net_exposure_btc
+                .map(|exposure| exposure.as_sat() as f64)
+                .unwrap_or(f64::NAN);
+
+            NET_EXPOSURE_GAUGE
+                .with_label_values(&[&symbol.contract_symbol.to_string()])
+                .set(exposure);
+        }
+        UNREALISED_PNL_GAUGE.set(
+            report
+                .unrealised_pnl
+                .map(|pnl| pnl.as_sat() as f64)
+                .unwrap_or(f64::NAN),
+        );
+
+        let _ = self.feed.send(Some(report));
+    }
+
+    async fn store_snapshot(&self) -> Result<()> {
+        let snapshot = match self.feed.borrow().as_ref() {
+            Some(report) => ExposureSnapshot::from(report),
+            None => return Ok(()),
+        };
+
+        self.db.insert_exposure_snapshot(snapshot).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we are alive");
+
+        let mut rx_cfds = self.rx_cfds.clone();
+        let mut rx_quote = self.rx_quote.clone();
+        let notify = {
+            let this = this.clone();
+            async move {
+                loop {
+                    tokio::select! {
+                        Ok(()) = rx_cfds.changed() => {},
+                        Ok(()) = rx_quote.changed() => {},
+                        else => return,
+                    }
+
+                    if this.send(Update).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        };
+
+        tokio_extras::spawn(&this.clone(), notify);
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                SNAPSHOT_INTERVAL,
+                || StoreSnapshot,
+                xtras::IncludeSpan::Always,
+            ),
+        );
+        this.send_async_next(Update).await;
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: Update) {
+        self.update();
+    }
+
+    async fn handle(&mut self, _: StoreSnapshot) {
+        if let Err(e) = self.store_snapshot().await {
+            tracing::warn!("Failed to store exposure snapshot: {e:#}");
+        }
+    }
+}
+
+struct Update;
+
+#[derive(Clone, Copy)]
+struct StoreSnapshot;
+
+/// Whether the payout of the CFD depends on the price
+fn is_exposed(state: CfdState) -> bool {
+    matches!(
+        state,
+        CfdState::PendingOpen
+            | CfdState::Open
+            | CfdState::PendingCommit
+            | CfdState::OpenCommitted
+            | CfdState::IncomingSettlementProposal
+            | CfdState::OutgoingSettlementProposal
+            | CfdState::RolloverSetup
+    )
+}
+
+/// Whether the payout of the CFD is final
+fn is_settled(state: CfdState) -> bool {
+    matches!(
+        state,
+        CfdState::PendingClose
+            | CfdState::PendingCet
+            | CfdState::Closed
+            | CfdState::PendingRefund
+            | CfdState::Refunded
+    )
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Quote {
+    bid: Price,
+    ask: Price,
+    mid: Price,
+}
+
+/// The parts of an open CFD the report is built from.
+#[derive(Debug, Clone, Copy)]
+struct OpenCfd {
+    contract_symbol: ContractSymbol,
+    /// Our position
+    position: Position,
+    initial_price: Price,
+    quantity: Contracts,
+    taker_leverage: Leverage,
+    margin: Amount,
+    fee_account: FeeAccount,
+}
+
+impl From<&projection::Cfd> for OpenCfd {
+    fn from(cfd: &projection::Cfd) -> Self {
+        Self {
+            contract_symbol: cfd.contract_symbol,
+            position: cfd.position,
+            initial_price: cfd.initial_price,
+            quantity: cfd.quantity,
+            taker_leverage: cfd.leverage_taker,
+            margin: cfd.margin,
+            fee_account: cfd.fee_account(),
+        }
+    }
+}
+
+impl OpenCfd {
+    fn unrealised_pnl(&self, quote: Quote) -> Result<SignedAmount> {
+        let closing_price = market_closing_price(quote.bid, quote.ask, Role::Maker, self.position);
+        let (long_leverage, short_leverage) =
+            long_and_short_leverage(self.taker_leverage, Role::Maker, self.position);
+
+        let payout = calculate_payout_at_price(
+            self.contract_symbol,
+            self.initial_price,
+            closing_price,
+            self.quantity,
+            long_leverage,
+            short_leverage,
+            self.fee_account,
+        )?;
+        let (pnl, _) = calculate_profit(payout, self.margin);
+
+        Ok(pnl)
+    }
+
+    /// Value of the position at `price`, negative if we are short
+    fn exposure(&self, price: Price) -> SignedAmount {
+        let value = calculate_margin(self.contract_symbol, price, self.quantity, Leverage::ONE)
+            .to_signed()
+            .expect("amount to fit into signed amount");
+
+        match self.position {
+            Position::Long => value,
+            Position::Short => -value,
+        }
+    }
+
+    /// Whether `price` is close to the liquidation price of us and of the taker respectively
+    fn near_liquidation(&self, price: Price) -> (bool, bool) {
+        let (long_leverage, short_leverage) =
+            long_and_short_leverage(self.taker_leverage, Role::Maker, self.position);
+
+        let long_liquidation = Price::new(calculate_long_liquidation_price(
+            self.initial_price,
+            long_leverage,
+            self.contract_symbol,
+        ))
+        .ok();
+        let short_liquidation = Price::new(calculate_short_liquidation_price(
+            self.initial_price,
+            short_leverage,
+            self.contract_symbol,
+        ))
+        .ok();
+
+        // The party going long is liquidated as the price falls, the party going short as it rises
+        let price = price.to_f64();
+        let long_near = long_liquidation.map_or(false, |liquidation| {
+            price <= liquidation.to_f64() * (1.0 + LIQUIDATION_PROXIMITY)
+        });
+        let short_near = short_liquidation.map_or(false, |liquidation| {
+            price >= liquidation.to_f64() * (1.0 - LIQUIDATION_PROXIMITY)
+        });
+
+        match self.position {
+            Position::Long => (long_near, short_near),
+            Position::Short => (short_near, long_near),
+        }
+    }
+}
+
+fn report(
+    realised: impl Iterator<Item = SignedAmount>,
+    open: &[OpenCfd],
+    quotes: &[(ContractSymbol, Quote)],
+) -> ExposureReport {
+    let quote = |symbol| {
+        quotes
+            .iter()
+            .find(|(quote_symbol, _)| *quote_symbol == symbol)
+            .map(|(_, quote)| *quote)
+    };
+    let btc_usd = quote(ContractSymbol::BtcUsd).map(|quote| quote.mid.to_f64());
+
+    let symbols = ContractSymbol::iter()
+        .map(|contract_symbol| {
+            let quote = quote(contract_symbol);
+            let cfds = open
+                .iter()
+                .filter(|cfd| cfd.contract_symbol == contract_symbol)
+                .collect::<Vec<_>>();
+
+            let quantity = |position| {
+                cfds.iter()
+                    .filter(|cfd| cfd.position == position)
+                    .fold(Contracts::ZERO, |sum, cfd| sum + cfd.quantity)
+            };
+
+            let net_exposure_btc = quote.map(|quote| {
+                cfds.iter()
+                    .map(|cfd| cfd.exposure(quote.mid))
+                    .fold(SignedAmount::ZERO, |sum, exposure| sum + exposure)
+            });
+
+            let unrealised_pnl = match quote {
+                Some(quote) => cfds
+                    .iter()
+                    .map(|cfd| cfd.unrealised_pnl(quote))
+                    .try_fold(SignedAmount::ZERO, |sum, pnl| Ok(sum + pnl?))
+                    .map_err(|e: anyhow::Error| {
+                        tracing::warn!(%contract_symbol, "Failed to calculate profit/loss: {e:#}")
+                    })
+                    .ok(),
+                None if cfds.is_empty() => Some(SignedAmount::ZERO),
+                None => None,
+            };
+
+            let (margin_at_risk, near_liquidation) = match quote {
+                Some(quote) => cfds.iter().fold(
+                    (Amount::ZERO, 0),
+                    |(margin_at_risk, near_liquidation), cfd| match cfd.near_liquidation(quote.mid)
+                    {
+                        (true, _) => (margin_at_risk + cfd.margin, near_liquidation + 1),
+                        (false, true) => (margin_at_risk, near_liquidation + 1),
+                        (false, false) => (margin_at_risk, near_liquidation),
+                    },
+                ),
+                None => (Amount::ZERO, 0),
+            };
+
+            SymbolExposure {
+                contract_symbol,
+                price: quote.map(|quote| quote.mid),
+                open_positions: cfds.len() as u64,
+                long_contracts: quantity(Position::Long),
+                short_contracts: quantity(Position::Short),
+                net_exposure_btc,
+                net_exposure_usd: net_exposure_btc
+                    .zip(btc_usd)
+                    .map(|(exposure, btc_usd)| exposure.as_btc() * btc_usd),
+                unrealised_pnl,
+                accrued_fees: cfds.iter().fold(SignedAmount::ZERO, |sum, cfd| {
+                    sum - cfd.fee_account.balance()
+                }),
+                margin_at_risk,
+                near_liquidation,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    ExposureReport {
+        realised_pnl: realised.fold(SignedAmount::ZERO, |sum, pnl| sum + pnl),
+        unrealised_pnl: symbols
+            .iter()
+            .map(|symbol| symbol.unrealised_pnl)
+            .try_fold(SignedAmount::ZERO, |sum, pnl| Some(sum + pnl?)),
+        accrued_fees: symbols
+            .iter()
+            .fold(SignedAmount::ZERO, |sum, symbol| sum + symbol.accrued_fees),
+        margin_at_risk: symbols
+            .iter()
+            .fold(Amount::ZERO, |sum, symbol| sum + symbol.margin_at_risk),
+        symbols,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn net_exposure_is_long_minus_short_quantity() {
+        let open = [
+            open_cfd(Position::Long, 30_000, Leverage::TWO),
+            open_cfd(Position::Short, 10_000, Leverage::TWO),
+        ];
+
+        let report = report(std::iter::empty(), &open, &[btc_usd_quote(20_000)]);
+
+        let btc_usd = &report.symbols[0];
+        assert_eq!(btc_usd.contract_symbol, ContractSymbol::BtcUsd);
+        assert_eq!(btc_usd.open_positions, 2);
+        assert_eq!(btc_usd.long_contracts, Contracts::new(30_000));
+        assert_eq!(btc_usd.short_contracts, Contracts::new(10_000));
+        // 20_000 contracts of 1 USD each at 20_000 USD per BTC
+        assert_eq!(btc_usd.net_exposure_btc, Some(SignedAmount::ONE_BTC));
+        assert_eq!(btc_usd.net_exposure_usd, Some(20_000.0));
+    }
+
+    #[test]
+    fn unrealised_pnl_is_zero_sum_of_long_and_short_at_unchanged_price() {
+        let open = [
+            open_cfd(Position::Long, 10_000, Leverage::TWO),
+            open_cfd(Position::Short, 10_000, Leverage::TWO),
+        ];
+
+        let report = report(std::iter::empty(), &open, &[btc_usd_quote(20_000)]);
+
+        assert_eq!(report.unrealised_pnl, Some(SignedAmount::ZERO));
+        assert_eq!(report.symbols[0].net_exposure_btc, Some(SignedAmount::ZERO));
+    }
+
+    #[test]
+    fn unrealised_pnl_of_long_position_follows_price() {
+        let open = [open_cfd(Position::Long, 10_000, Leverage::TWO)];
+
+        let report = report(std::iter::empty(), &open, &[btc_usd_quote(25_000)]);
+
+        assert!(report.unrealised_pnl.unwrap() > SignedAmount::ZERO);
+    }
+
+    #[test]
+    fn unrealised_pnl_is_unknown_without_quote_of_open_positions() {
+        let open = [open_cfd(Position::Long, 10_000, Leverage::TWO)];
+
+        let report = report(
+            [SignedAmount::from_sat(1_000), SignedAmount::from_sat(-300)].into_iter(),
+            &open,
+            &[],
+        );
+
+        assert_eq!(report.realised_pnl, SignedAmount::from_sat(700));
+        assert_eq!(report.unrealised_pnl, None);
+        assert_eq!(report.symbols[0].net_exposure_btc, None);
+    }
+
+    #[test]
+    fn margin_is_at_risk_close_to_our_liquidation_price() {
+        // Going long with leverage one we are liquidated at half the initial price
+        let open = [
+            open_cfd(Position::Long, 10_000, Leverage::TWO),
+            open_cfd(Position::Short, 10_000, Leverage::TWO),
+        ];
+
+        let report = report(std::iter::empty(), &open, &[btc_usd_quote(10_500)]);
+
+        assert_eq!(report.margin_at_risk, open[0].margin);
+        assert_eq!(report.symbols[0].near_liquidation, 1);
+    }
+
+    #[test]
+    fn taker_close_to_liquidation_does_not_put_our_margin_at_risk() {
+        // The taker going long with leverage two is liquidated at two thirds of the initial price
+        let open = [open_cfd(Position::Short, 10_000, Leverage::TWO)];
+
+        let report = report(std::iter::empty(), &open, &[btc_usd_quote(14_000)]);
+
+        assert_eq!(report.margin_at_risk, Amount::ZERO);
+        assert_eq!(report.symbols[0].near_liquidation, 1);
+    }
+
+    fn open_cfd(position: Position, quantity: u64, taker_leverage: Leverage) -> OpenCfd {
+        let initial_price = Price::from_str("20000").unwrap();
+        let quantity = Contracts::new(quantity);
+
+        OpenCfd {
+            contract_symbol: ContractSymbol::BtcUsd,
+            position,
+            initial_price,
+            quantity,
+            taker_leverage,
+            margin: calculate_margin(
+                ContractSymbol::BtcUsd,
+                initial_price,
+                quantity,
+                Leverage::ONE,
+            ),
+            fee_account: FeeAccount::new(position, Role::Maker),
+        }
+    }
+
+    fn btc_usd_quote(price: u64) -> (ContractSymbol, Quote) {
+        let price = Price::from_str(&price.to_string()).unwrap();
+
+        (
+            ContractSymbol::BtcUsd,
+            Quote {
+                bid: price,
+                ask: price,
+                mid: price,
+            },
+        )
+    }
+}
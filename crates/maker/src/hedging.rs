@@ -0,0 +1,596 @@
+//! Hedging of the maker's positions on an exchange.
+//!
+//! Every CFD the maker opens changes its exposure to the price of the contract symbol, and so
+//! does closing it again. Whenever that happens, an order of the same quantity in the opposite
+//! direction is placed on the exchange, such that the maker's net exposure stays unchanged.
+//!
+//! Hedge orders are stored in the database before they are placed, such that every CFD is hedged
+//! and unwound at most once across restarts. Orders which could not be placed are retried with
+//! exponential backoff. Every order is placed with a client order ID derived from its hedge, such
+//! that retrying an order which reached the exchange despite failing does not place it twice. CFDs which were opened while hedging was disabled are neither hedged
+//! retroactively nor unwound when they are closed.
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use daemon::process_manager::EventAppended;
+use model::CfdEvent;
+use model::ContractSymbol;
+use model::Contracts;
+use model::EventKind;
+use model::OrderId;
+use model::Position;
+use sqlite_db::hedges::HedgeKind;
+use sqlite_db::hedges::PendingHedge;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use time::OffsetDateTime;
+use xtra_productivity::xtra_productivity;
+use xtras::SendAsyncNext;
+use xtras::SendInterval;
+
+pub mod bitmex;
+
+/// Interval at which hedges which are due for placement are placed.
+const PLACE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Maximum number of hedges placed at once.
+const PLACE_BATCH_SIZE: u32 = 100;
+
+const INITIAL_BACKOFF: time::Duration = time::Duration::seconds(10);
+const MAX_BACKOFF: time::Duration = time::Duration::minutes(10);
+
+static HEDGE_ORDERS_COUNTER: conquer_once::Lazy<prometheus::IntCounterVec> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_counter_vec!(
+            "maker_hedge_orders_total",
+            "The number of hedge orders by symbol and outcome.",
+            &["symbol", "outcome"]
+        )
+        .unwrap()
+    });
+
+/// How position changes are hedged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Off,
+    /// Log the orders which would be placed
+    DryRun,
+    BitMex,
+}
+
+impl FromStr for Mode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mode = match s {
+            "off" => Mode::Off,
+            "dry-run" => Mode::DryRun,
+            "bitmex" => Mode::BitMex,
+            other => bail!("Unknown hedging mode {other}, expected one of off, dry-run, bitmex"),
+        };
+
+        Ok(mode)
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Mode::Off => "off",
+            Mode::DryRun => "dry-run",
+            Mode::BitMex => "bitmex",
+        };
+
+        s.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+/// A market order on the exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Order {
+    pub contract_symbol: ContractSymbol,
+    pub side: Side,
+    pub quantity: Contracts,
+}
+
+/// An exchange on which hedge orders are placed.
+#[async_trait]
+pub trait Exchange: Send + Sync + 'static {
+    /// Place a market order, returning the ID the exchange assigned to it.
+    ///
+    /// If an order with `client_order_id` was placed before, no new order is placed and the ID of
+    /// the existing order is returned.
+    async fn place_order(&self, client_order_id: &str, order: Order) -> Result<String>;
+}
+
+/// Exchange which only logs orders instead of placing them.
+pub struct DryRun;
+
+#[async_trait]
+impl Exchange for DryRun {
+    async fn place_order(&self, client_order_id: &str, order: Order) -> Result<String> {
+        tracing::info!(
+            %client_order_id,
+            symbol = %order.contract_symbol,
+            side = ?order.side,
+            quantity = %order.quantity,
+            "Dry run: Not placing hedge order"
+        );
+
+        Ok("dry-run".to_owned())
+    }
+}
+
+/// A change of the maker's position caused by a CFD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PositionChange {
+    Opened,
+    Closed,
+}
+
+impl PositionChange {
+    /// The change of position of the CFD the event belongs to, if any
+    ///
+    /// A CFD is closed once its payout is fixed, even if the settlement transaction is not
+    /// confirmed yet.
+    fn from_event(event: &EventKind) -> Option<Self> {
+        use EventKind::*;
+        match event {
+            ContractSetupCompleted { dlc: Some(_), .. } => Some(PositionChange::Opened),
+            CollaborativeSettlementCompleted { .. }
+            | OracleAttestedPriorCetTimelock { .. }
+            | OracleAttestedPostCetTimelock { .. }
+            | RefundTimelockExpired { .. } => Some(PositionChange::Closed),
+            _ => None,
+        }
+    }
+}
+
+/// The order which offsets the change of our `position` in a CFD
+fn hedge_order(
+    kind: HedgeKind,
+    contract_symbol: ContractSymbol,
+    position: Position,
+    quantity: Contracts,
+) -> Order {
+    let position = match kind {
+        HedgeKind::Open => position,
+        HedgeKind::Close => position.counter_position(),
+    };
+
+    let side = match position {
+        Position::Long => Side::Sell,
+        Position::Short => Side::Buy,
+    };
+
+    Order {
+        contract_symbol,
+        side,
+        quantity,
+    }
+}
+
+/// The client order ID of the order placed for the hedge with the given database `id`
+fn client_order_id(id: i64) -> String {
+    format!("itchysats-hedge-{id}")
+}
+
+/// Delay before the next attempt after `attempts` failed attempts to place a hedge
+fn backoff(attempts: u32) -> time::Duration {
+    let exponent = attempts.saturating_sub(1).min(16);
+
+    (INITIAL_BACKOFF * 2_i32.pow(exponent)).min(MAX_BACKOFF)
+}
+
+/// Read-model of the CFD for hedging.
+#[derive(Debug, Clone, Copy)]
+struct Cfd {
+    contract_symbol: ContractSymbol,
+    /// Our position
+    position: Position,
+    quantity: Contracts,
+    version: u32,
+}
+
+impl sqlite_db::CfdAggregate for Cfd {
+    type CtorArgs = ();
+
+    fn new(_: Self::CtorArgs, cfd: sqlite_db::Cfd) -> Self {
+        Self {
+            contract_symbol: cfd.contract_symbol,
+            position: cfd.position,
+            quantity: cfd.quantity,
+            version: 0,
+        }
+    }
+
+    fn apply(mut self, _: CfdEvent) -> Self {
+        self.version += 1;
+
+        self
+    }
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    exchange: Box<dyn Exchange>,
+}
+
+impl Actor {
+    pub fn new(db: sqlite_db::Connection, exchange: Box<dyn Exchange>) -> Self {
+        Self { db, exchange }
+    }
+
+    /// Queue the hedge for the change of position of a CFD.
+    async fn queue(&self, id: OrderId, change: PositionChange) -> Result<()> {
+        let now = tokio_extras::time::now_utc();
+
+        let queued = match change {
+            PositionChange::Opened => {
+                let cfd = self.db.load_open_cfd::<Cfd>(id, ()).await?;
+
+                self.db
+                    .insert_opening_hedge(id, cfd.contract_symbol, cfd.position, cfd.quantity, now)
+                    .await?
+            }
+            PositionChange::Closed => self.db.insert_closing_hedge(id, now).await?,
+        };
+
+        if !queued {
+            tracing::debug!(order_id = %id, ?change, "CFD not hedged or hedge already queued");
+        }
+
+        Ok(())
+    }
+
+    /// Place all hedges which are due at `now`, rescheduling the ones which fail.
+    async fn place(&self, now: OffsetDateTime) -> Result<()> {
+        let due = self.db.load_due_hedges(now, PLACE_BATCH_SIZE).await?;
+
+        for hedge in due {
+            let PendingHedge {
+                id,
+                order_id,
+                kind,
+                contract_symbol,
+                position,
+                quantity,
+                attempts,
+            } = hedge;
+
+            let order = hedge_order(kind, contract_symbol, position, quantity);
+            let client_order_id = client_order_id(id);
+            let symbol = contract_symbol.to_string();
+
+            match self.exchange.place_order(&client_order_id, order).await {
+                Ok(exchange_order_id) => {
+                    tracing::info!(
+                        %order_id,
+                        %exchange_order_id,
+                        %client_order_id,
+                        %symbol,
+                        side = ?order.side,
+                        %quantity,
+                        "Placed hedge order"
+                    );
+                    HEDGE_ORDERS_COUNTER
+                        .with_label_values(&[&symbol, "placed"])
+                        .inc();
+
+                    self.db.mark_hedge_placed(id, &exchange_order_id).await?;
+                }
+                Err(e) => {
+                    HEDGE_ORDERS_COUNTER
+                        .with_label_values(&[&symbol, "failed"])
+                        .inc();
+
+                    let attempts = attempts + 1;
+                    let next_attempt_at = now + backoff(attempts);
+                    tracing::error!(
+                        %order_id,
+                        ?kind,
+                        %next_attempt_at,
+                        "Failed to place hedge order: {e:#}"
+                    );
+                    self.db
+                        .reschedule_hedge(id, attempts, next_attempt_at)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+struct PlaceHedges;
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we are alive");
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(PLACE_INTERVAL, || PlaceHedges, xtras::IncludeSpan::Never),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: EventAppended, ctx: &mut xtra::Context<Self>) {
+        let CfdEvent { id, event, .. } = msg.0;
+
+        let change = match PositionChange::from_event(&event) {
+            Some(change) => change,
+            None => return,
+        };
+
+        if let Err(e) = self.queue(id, change).await {
+            tracing::error!(order_id = %id, ?change, "Failed to queue hedge order: {e:#}");
+            return;
+        }
+
+        let this = ctx.address().expect("we are alive");
+        this.send_async_next(PlaceHedges).await;
+    }
+
+    async fn handle(&mut self, _: PlaceHedges) {
+        if let Err(e) = self.place(tokio_extras::time::now_utc()).await {
+            tracing::error!("Failed to place hedge orders: {e:#}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    /// Exchange which records the orders placed by client order ID.
+    ///
+    /// The first `failures` orders are rejected. The responses to the first `lost_responses`
+    /// orders which are placed are lost, i.e. an error is returned.
+    #[derive(Clone, Default)]
+    struct MockExchange {
+        orders: Arc<Mutex<Vec<(String, Order)>>>,
+        failures: Arc<Mutex<u32>>,
+        lost_responses: Arc<Mutex<u32>>,
+    }
+
+    impl MockExchange {
+        fn orders(&self) -> Vec<Order> {
+            let orders = self.orders.lock().unwrap();
+
+            orders.iter().map(|(_, order)| *order).collect()
+        }
+    }
+
+    #[async_trait]
+    impl Exchange for MockExchange {
+        async fn place_order(&self, client_order_id: &str, order: Order) -> Result<String> {
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                bail!("Exchange unavailable");
+            }
+
+            let mut orders = self.orders.lock().unwrap();
+            let exchange_order_id = match orders.iter().position(|(id, _)| id == client_order_id) {
+                Some(index) => return Ok(index.to_string()),
+                None => {
+                    orders.push((client_order_id.to_owned(), order));
+                    (orders.len() - 1).to_string()
+                }
+            };
+
+            let mut lost_responses = self.lost_responses.lock().unwrap();
+            if *lost_responses > 0 {
+                *lost_responses -= 1;
+                bail!("Connection reset");
+            }
+
+            Ok(exchange_order_id)
+        }
+    }
+
+    #[test]
+    fn hedge_of_long_position_sells_and_is_unwound_by_buying() {
+        assert_eq!(
+            dummy_order(HedgeKind::Open, Position::Long).side,
+            Side::Sell
+        );
+        assert_eq!(
+            dummy_order(HedgeKind::Close, Position::Long).side,
+            Side::Buy
+        );
+    }
+
+    #[test]
+    fn hedge_of_short_position_buys_and_is_unwound_by_selling() {
+        assert_eq!(
+            dummy_order(HedgeKind::Open, Position::Short).side,
+            Side::Buy
+        );
+        assert_eq!(
+            dummy_order(HedgeKind::Close, Position::Short).side,
+            Side::Sell
+        );
+    }
+
+    #[test]
+    fn only_events_fixing_the_payout_close_the_position() {
+        assert_eq!(
+            PositionChange::from_event(&EventKind::RefundTimelockExpired {
+                refund_tx: dummy_tx()
+            }),
+            Some(PositionChange::Closed)
+        );
+        assert_eq!(
+            PositionChange::from_event(&EventKind::CetTimelockExpiredPostOracleAttestation {
+                cet: dummy_tx()
+            }),
+            None
+        );
+        assert_eq!(
+            PositionChange::from_event(&EventKind::CollaborativeSettlementConfirmed),
+            None
+        );
+        assert_eq!(PositionChange::from_event(&EventKind::LockConfirmed), None);
+    }
+
+    #[test]
+    fn backoff_doubles_until_maximum() {
+        assert_eq!(backoff(1), time::Duration::seconds(10));
+        assert_eq!(backoff(2), time::Duration::seconds(20));
+        assert_eq!(backoff(7), MAX_BACKOFF);
+        assert_eq!(backoff(100), MAX_BACKOFF);
+    }
+
+    #[tokio::test]
+    async fn hedge_is_placed_on_open_and_unwound_once_on_close() {
+        let exchange = MockExchange::default();
+        let actor = Actor::new(
+            sqlite_db::memory().await.unwrap(),
+            Box::new(exchange.clone()),
+        );
+        let id = OrderId::default();
+        insert_opening_hedge(&actor.db, id, Position::Long).await;
+        let now = tokio_extras::time::now_utc();
+
+        actor.place(now).await.unwrap();
+        actor.queue(id, PositionChange::Closed).await.unwrap();
+        actor.queue(id, PositionChange::Closed).await.unwrap();
+        actor.place(now).await.unwrap();
+        actor.place(now).await.unwrap();
+
+        assert_eq!(
+            exchange.orders(),
+            vec![
+                dummy_order(HedgeKind::Open, Position::Long),
+                dummy_order(HedgeKind::Close, Position::Long),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn closing_cfd_without_hedge_places_no_order() {
+        let exchange = MockExchange::default();
+        let actor = Actor::new(
+            sqlite_db::memory().await.unwrap(),
+            Box::new(exchange.clone()),
+        );
+
+        actor
+            .queue(OrderId::default(), PositionChange::Closed)
+            .await
+            .unwrap();
+        actor.place(tokio_extras::time::now_utc()).await.unwrap();
+
+        assert!(exchange.orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_hedge_is_retried_after_backoff() {
+        let exchange = MockExchange {
+            failures: Arc::new(Mutex::new(1)),
+            ..MockExchange::default()
+        };
+        let actor = Actor::new(
+            sqlite_db::memory().await.unwrap(),
+            Box::new(exchange.clone()),
+        );
+        insert_opening_hedge(&actor.db, OrderId::default(), Position::Short).await;
+        let now = tokio_extras::time::now_utc();
+
+        actor.place(now).await.unwrap();
+        actor.place(now).await.unwrap();
+        assert!(exchange.orders().is_empty());
+
+        actor.place(now + backoff(1)).await.unwrap();
+        assert_eq!(
+            exchange.orders(),
+            vec![dummy_order(HedgeKind::Open, Position::Short)]
+        );
+    }
+
+    #[tokio::test]
+    async fn retried_hedge_whose_order_reached_exchange_is_not_placed_again() {
+        let exchange = MockExchange {
+            lost_responses: Arc::new(Mutex::new(1)),
+            ..MockExchange::default()
+        };
+        let actor = Actor::new(
+            sqlite_db::memory().await.unwrap(),
+            Box::new(exchange.clone()),
+        );
+        insert_opening_hedge(&actor.db, OrderId::default(), Position::Long).await;
+        let now = tokio_extras::time::now_utc();
+
+        actor.place(now).await.unwrap();
+        actor.place(now + backoff(1)).await.unwrap();
+        actor.place(now + backoff(2)).await.unwrap();
+
+        assert_eq!(
+            exchange.orders(),
+            vec![dummy_order(HedgeKind::Open, Position::Long)]
+        );
+        assert!(actor
+            .db
+            .load_due_hedges(now + MAX_BACKOFF, PLACE_BATCH_SIZE)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn mode_roundtrips_through_string() {
+        for mode in [Mode::Off, Mode::DryRun, Mode::BitMex] {
+            assert_eq!(Mode::from_str(&mode.to_string()).unwrap(), mode);
+        }
+        assert!(Mode::from_str("binance").is_err());
+    }
+
+    async fn insert_opening_hedge(db: &sqlite_db::Connection, id: OrderId, position: Position) {
+        db.insert_opening_hedge(
+            id,
+            ContractSymbol::BtcUsd,
+            position,
+            Contracts::new(100),
+            tokio_extras::time::now_utc(),
+        )
+        .await
+        .unwrap();
+    }
+
+    fn dummy_order(kind: HedgeKind, position: Position) -> Order {
+        hedge_order(kind, ContractSymbol::BtcUsd, position, Contracts::new(100))
+    }
+
+    fn dummy_tx() -> bdk::bitcoin::Transaction {
+        bdk::bitcoin::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![],
+        }
+    }
+}
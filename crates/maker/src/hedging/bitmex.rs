@@ -0,0 +1,285 @@
+use crate::hedging::Exchange;
+use crate::hedging::Order;
+use crate::hedging::Side;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use bitmex_stream::Credentials;
+use bitmex_stream::Method;
+use bitmex_stream::Network;
+use bitmex_stream::Url;
+use model::ContractSymbol;
+use serde::Deserialize;
+use serde::Serialize;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+/// How long a signed request stays valid.
+const REQUEST_EXPIRY: Duration = Duration::from_secs(10);
+
+/// The message with which BitMex rejects an order whose client order ID was used before.
+const DUPLICATE_CLIENT_ORDER_ID: &str = "Duplicate clOrdID";
+
+/// Places hedge orders through the BitMex REST API.
+///
+/// Before an order is placed, the order with the same client order ID is looked up, such that a
+/// retried order which reached BitMex before is not placed twice.
+pub struct BitMex {
+    client: reqwest::Client,
+    url: Url,
+    credentials: Credentials,
+}
+
+impl BitMex {
+    pub fn new(network: Network, credentials: Credentials) -> Result<Self> {
+        let url = Url::parse(&format!("https://{}/api/v1/order", network.to_url()))?;
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url,
+            credentials,
+        })
+    }
+
+    fn signed_request(
+        &self,
+        client_order_id: &str,
+        order: Order,
+        expires: u64,
+    ) -> Result<reqwest::RequestBuilder> {
+        let body = serde_json::to_string(&OrderRequest::new(client_order_id, order))?;
+        let signature = self
+            .credentials
+            .sign(Method::POST, expires, &self.url, &body);
+
+        let request = self
+            .client
+            .post(self.url.clone())
+            .header("content-type", "application/json")
+            .header("api-expires", signature.expires())
+            .header("api-key", signature.api_key())
+            .header("api-signature", signature.signature())
+            .body(body);
+
+        Ok(request)
+    }
+
+    fn signed_lookup_request(
+        &self,
+        client_order_id: &str,
+        expires: u64,
+    ) -> Result<reqwest::RequestBuilder> {
+        let filter = serde_json::to_string(&OrderFilter { client_order_id })?;
+        let url = Url::parse_with_params(self.url.as_str(), &[("filter", filter.as_str())])?;
+        let signature = self.credentials.sign(Method::GET, expires, &url, "");
+
+        let request = self
+            .client
+            .get(url)
+            .header("api-expires", signature.expires())
+            .header("api-key", signature.api_key())
+            .header("api-signature", signature.signature());
+
+        Ok(request)
+    }
+
+    /// Look up the ID of the order placed with `client_order_id`, if any.
+    async fn lookup_order(&self, client_order_id: &str) -> Result<Option<String>> {
+        let response = self
+            .signed_lookup_request(client_order_id, expires()?)?
+            .send()
+            .await
+            .context("Failed to look up order on BitMex")?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("BitMex rejected order lookup with status {status}: {body}");
+        }
+
+        let orders = response
+            .json::<Vec<OrderResponse>>()
+            .await
+            .context("Failed to deserialize BitMex orders")?;
+
+        Ok(orders.into_iter().next().map(|order| order.order_id))
+    }
+}
+
+#[async_trait]
+impl Exchange for BitMex {
+    async fn place_order(&self, client_order_id: &str, order: Order) -> Result<String> {
+        if let Some(order_id) = self.lookup_order(client_order_id).await? {
+            tracing::debug!(%client_order_id, "Order was already placed");
+            return Ok(order_id);
+        }
+
+        let response = self
+            .signed_request(client_order_id, order, expires()?)?
+            .send()
+            .await
+            .context("Failed to send order to BitMex")?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+
+            // The order was placed concurrently or the lookup did not see it yet
+            if body.contains(DUPLICATE_CLIENT_ORDER_ID) {
+                return self.lookup_order(client_order_id).await?.with_context(|| {
+                    format!("Order {client_order_id} rejected as duplicate but not found")
+                });
+            }
+
+            anyhow::bail!("BitMex rejected order with status {status}: {body}");
+        }
+
+        let response = response
+            .json::<OrderResponse>()
+            .await
+            .context("Failed to deserialize BitMex order response")?;
+
+        Ok(response.order_id)
+    }
+}
+
+/// The expiry of a request signed now, in seconds since the UNIX epoch.
+fn expires() -> Result<u64> {
+    let expires = SystemTime::now()
+        .duration_since(UNIX_EPOCH)?
+        .checked_add(REQUEST_EXPIRY)
+        .context("Overflow when computing request expiry")?
+        .as_secs();
+
+    Ok(expires)
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct OrderRequest<'a> {
+    symbol: &'static str,
+    side: &'static str,
+    order_qty: u64,
+    ord_type: &'static str,
+    #[serde(rename = "clOrdID")]
+    client_order_id: &'a str,
+}
+
+impl<'a> OrderRequest<'a> {
+    fn new(client_order_id: &'a str, order: Order) -> Self {
+        let symbol = match order.contract_symbol {
+            ContractSymbol::BtcUsd => "XBTUSD",
+            ContractSymbol::EthUsd => "ETHUSD",
+        };
+
+        let side = match order.side {
+            Side::Buy => "Buy",
+            Side::Sell => "Sell",
+        };
+
+        Self {
+            symbol,
+            side,
+            order_qty: order.quantity.to_u64(),
+            ord_type: "Market",
+            client_order_id,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct OrderFilter<'a> {
+    #[serde(rename = "clOrdID")]
+    client_order_id: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrderResponse {
+    #[serde(rename = "orderID")]
+    order_id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::Contracts;
+
+    #[test]
+    fn order_is_serialized_as_bitmex_market_order() {
+        let order = Order {
+            contract_symbol: ContractSymbol::BtcUsd,
+            side: Side::Sell,
+            quantity: Contracts::new(100),
+        };
+
+        let body = serde_json::to_string(&OrderRequest::new("itchysats-hedge-1", order)).unwrap();
+
+        assert_eq!(
+            body,
+            r#"{"symbol":"XBTUSD","side":"Sell","orderQty":100,"ordType":"Market","clOrdID":"itchysats-hedge-1"}"#
+        );
+    }
+
+    #[test]
+    fn request_is_signed_with_credentials() {
+        let bitmex = BitMex::new(Network::Testnet, Credentials::new("api_key", "secret")).unwrap();
+        let order = Order {
+            contract_symbol: ContractSymbol::EthUsd,
+            side: Side::Buy,
+            quantity: Contracts::new(10),
+        };
+
+        let request = bitmex
+            .signed_request("itchysats-hedge-2", order, 1_600_000_000)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let expected = bitmex.credentials.sign(
+            Method::POST,
+            1_600_000_000,
+            &bitmex.url,
+            r#"{"symbol":"ETHUSD","side":"Buy","orderQty":10,"ordType":"Market","clOrdID":"itchysats-hedge-2"}"#,
+        );
+        let headers = request.headers();
+        assert_eq!(
+            request.url().as_str(),
+            "https://testnet.bitmex.com/api/v1/order"
+        );
+        assert_eq!(headers["api-expires"], "1600000000");
+        assert_eq!(headers["api-key"], "api_key");
+        assert_eq!(headers["api-signature"], expected.signature());
+    }
+
+    #[test]
+    fn order_is_looked_up_by_client_order_id() {
+        let bitmex = BitMex::new(Network::Testnet, Credentials::new("api_key", "secret")).unwrap();
+
+        let request = bitmex
+            .signed_lookup_request("itchysats-hedge-3", 1_600_000_000)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let expected = bitmex
+            .credentials
+            .sign(Method::GET, 1_600_000_000, request.url(), "");
+        assert_eq!(request.method(), reqwest::Method::GET);
+        assert_eq!(
+            request.url().as_str(),
+            "https://testnet.bitmex.com/api/v1/order?filter=%7B%22clOrdID%22%3A%22itchysats-hedge-3%22%7D"
+        );
+        assert_eq!(request.headers()["api-signature"], expected.signature());
+    }
+
+    #[test]
+    fn order_id_is_deserialized_from_response() {
+        let response = serde_json::from_str::<OrderResponse>(
+            r#"{"orderID":"ab7ae2nf-c828-76fc-3190-a35883804599","symbol":"XBTUSD"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(response.order_id, "ab7ae2nf-c828-76fc-3190-a35883804599");
+    }
+}
@@ -1,7 +1,14 @@
 use bdk::bitcoin::util::bip32::ExtendedPrivKey;
+use bdk::bitcoin::util::bip32::ExtendedPubKey;
+use bdk::bitcoin::util::bip32::Fingerprint;
 use clap::Parser;
 use daemon::bdk;
+use daemon::bdk::bitcoin::Amount;
+use shared_bin::cli::BlockExplorerOpts;
+use shared_bin::cli::MetricsExportOpts;
 use shared_bin::cli::Network;
+use shared_bin::cli::NotificationOpts;
+use shared_bin::cli::WalletDescriptors;
 use shared_bin::logger::LevelFilter;
 use shared_bin::logger::LOCAL_COLLECTOR_ENDPOINT;
 use std::convert::Infallible;
@@ -14,8 +21,13 @@ pub use blocked_peers::load_blocked_peers;
 mod actor_system;
 mod blocked_peers;
 pub mod cfd;
+pub mod exposure;
+pub mod hedging;
+pub mod liquidity;
 mod metrics;
 pub mod routes;
+pub mod utxo_management;
+pub mod websocket;
 
 #[derive(Debug)]
 pub struct Password(String);
@@ -50,6 +62,12 @@ pub struct Opts {
     #[clap(long, default_value = "127.0.0.1:8001")]
     pub http_address: SocketAddr,
 
+    /// The address to serve the WebSocket API on, e.g. "127.0.0.1:8002".
+    ///
+    /// The WebSocket API is disabled if not specified.
+    #[clap(long)]
+    pub ws_address: Option<SocketAddr>,
+
     /// Where to permanently store data, defaults to the current working directory.
     #[clap(long)]
     pub data_dir: Option<PathBuf>,
@@ -101,6 +119,80 @@ pub struct Opts {
     #[clap(short, long)]
     pub wallet_xprv: Option<ExtendedPrivKey>,
 
+    /// Extended public key of the BIP84 account of an external signer, e.g. a hardware wallet.
+    ///
+    /// If provided the internal wallet is watch-only. Lock and withdrawal transactions are signed
+    /// through `hwi` unless `--psbt-dir` is given.
+    #[clap(long, conflicts_with = "wallet_xprv", requires = "wallet_fingerprint")]
+    pub wallet_xpub: Option<ExtendedPubKey>,
+
+    #[clap(flatten)]
+    pub wallet_descriptors: WalletDescriptors,
+
+    #[clap(flatten)]
+    pub block_explorer: BlockExplorerOpts,
+
+    #[clap(flatten)]
+    pub notifications: NotificationOpts,
+
+    #[clap(flatten)]
+    pub metrics_export: MetricsExportOpts,
+
+    /// Fingerprint of the master key of the external signer.
+    ///
+    /// Required to sign through `hwi`, also if the wallet descriptors do not have private keys.
+    #[clap(long)]
+    pub wallet_fingerprint: Option<Fingerprint>,
+
+    /// Path to the `hwi` executable used to sign with the external signer.
+    #[clap(long, default_value = "hwi")]
+    pub hwi: PathBuf,
+
+    /// Exchange PSBTs with an air-gapped external signer through this directory.
+    ///
+    /// Unsigned PSBTs are written to `<txid>.psbt`, the signed PSBT is expected in
+    /// `<txid>.signed.psbt`.
+    #[clap(long)]
+    pub psbt_dir: Option<PathBuf>,
+
+    /// Alert once the balance available for new positions drops below this amount, e.g. "0.1
+    /// BTC".
+    ///
+    /// The available balance is the wallet balance minus the margin of CFDs in contract setup.
+    #[clap(long, default_value = "0.01 BTC")]
+    pub low_balance_threshold: Amount,
+
+    /// Keep UTXOs of at least this size in the wallet, e.g. "0.05 BTC".
+    ///
+    /// If set, large UTXOs are split and small ones consolidated at low fee rates, such that
+    /// `--utxo-target-count` contract setups can run concurrently.
+    #[clap(long)]
+    pub utxo_target_size: Option<Amount>,
+
+    /// The number of UTXOs of at least `--utxo-target-size` to keep in the wallet.
+    #[clap(long, default_value = "10")]
+    pub utxo_target_count: usize,
+
+    /// UTXOs are only split or consolidated while the economy fee rate estimate is at most this
+    /// fee rate in sat/vB.
+    #[clap(long, default_value = "2.0")]
+    pub utxo_max_fee_rate: f32,
+
+    /// Hedge every opened and closed position on an exchange: one of off, dry-run, bitmex
+    ///
+    /// With dry-run the hedge orders are only logged. Hedging on BitMex requires
+    /// `--bitmex-api-key` and `--bitmex-api-secret`.
+    #[clap(long, default_value = "off")]
+    pub hedging: hedging::Mode,
+
+    /// Key of the BitMex API used for hedging.
+    #[clap(long, requires = "bitmex_api_secret")]
+    pub bitmex_api_key: Option<String>,
+
+    /// Secret of the BitMex API used for hedging.
+    #[clap(long, requires = "bitmex_api_key")]
+    pub bitmex_api_secret: Option<String>,
+
     /// Configure the log level, e.g.: one of Error, Warn, Info, Debug, Trace
     #[clap(short, long, default_value = "Debug")]
     pub log_level: LevelFilter,
@@ -0,0 +1,359 @@
+//! Forecast of the balance available to the maker for new positions.
+//!
+//! The wallet balance only drops once the lock transaction of a CFD is published. Until then, the
+//! margin of CFDs in contract setup is reserved, such that the maker does not accept or offer more
+//! than it can fund.
+
+use crate::cfd;
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use bdk::bitcoin::Amount;
+use daemon::projection;
+use daemon::projection::CfdState;
+use model::calculate_margin;
+use model::Contracts;
+use model::Leverage;
+use model::OrderId;
+use model::WalletInfo;
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio::sync::watch;
+use xtra::prelude::MessageChannel;
+use xtra_productivity::xtra_productivity;
+use xtras::SendAsyncNext;
+use xtras::SendAsyncSafe;
+
+static RESERVED_MARGIN_GAUGE: conquer_once::Lazy<prometheus::Gauge> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_gauge!(
+            "maker_reserved_margin_satoshis",
+            "The margin reserved for CFDs in contract setup in satoshis."
+        )
+        .unwrap()
+    });
+static FREE_BALANCE_GAUGE: conquer_once::Lazy<prometheus::Gauge> = conquer_once::Lazy::new(|| {
+    prometheus::register_gauge!(
+        "maker_free_balance_satoshis",
+        "The wallet balance minus the reserved margin in satoshis."
+    )
+    .unwrap()
+});
+static LOW_BALANCE_ALERTS_COUNTER: conquer_once::Lazy<prometheus::IntCounter> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_counter!(
+            "maker_low_balance_alerts_total",
+            "The number of times the free balance dropped below the low-balance threshold."
+        )
+        .unwrap()
+    });
+
+/// Snapshot of the maker's liquidity as reported in the feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Liquidity {
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub balance: Amount,
+    /// Margin of CFDs in contract setup
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub reserved: Amount,
+    /// Balance available for new positions
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub free: Amount,
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub low_balance_threshold: Amount,
+    /// Whether the free balance is below `low_balance_threshold`
+    pub low_balance: bool,
+}
+
+pub struct Actor {
+    low_balance_threshold: Amount,
+    rx_wallet: watch::Receiver<Option<WalletInfo>>,
+    rx_cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+    free_balance_changed: MessageChannel<cfd::FreeBalanceChanged, ()>,
+    /// Margin of orders accepted before the projection reports their contract setup
+    accepted: HashMap<OrderId, Amount>,
+    feed: watch::Sender<Option<Liquidity>>,
+}
+
+impl Actor {
+    pub fn new(
+        low_balance_threshold: Amount,
+        rx_wallet: watch::Receiver<Option<WalletInfo>>,
+        rx_cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+        free_balance_changed: MessageChannel<cfd::FreeBalanceChanged, ()>,
+    ) -> (Self, watch::Receiver<Option<Liquidity>>) {
+        let (feed, receiver) = watch::channel(None);
+
+        let actor = Self {
+            low_balance_threshold,
+            rx_wallet,
+            rx_cfds,
+            free_balance_changed,
+            accepted: HashMap::new(),
+            feed,
+        };
+
+        (actor, receiver)
+    }
+
+    fn reserved_margin(&mut self) -> Amount {
+        let cfds = self.rx_cfds.borrow().clone().unwrap_or_default();
+
+        // Accepted orders are covered by the projection once their contract setup started
+        self.accepted.retain(|order_id, _| {
+            cfds.iter()
+                .any(|cfd| cfd.order_id == *order_id && cfd.state == CfdState::PendingSetup)
+        });
+
+        let in_contract_setup = cfds
+            .iter()
+            .filter(|cfd| cfd.state == CfdState::ContractSetup)
+            .map(|cfd| cfd.margin);
+
+        in_contract_setup
+            .chain(self.accepted.values().copied())
+            .fold(Amount::ZERO, |reserved, margin| reserved + margin)
+    }
+
+    fn liquidity(&mut self) -> Option<Liquidity> {
+        let balance = self.rx_wallet.borrow().as_ref()?.balance;
+        let reserved = self.reserved_margin();
+        let free = balance.checked_sub(reserved).unwrap_or(Amount::ZERO);
+
+        Some(Liquidity {
+            balance,
+            reserved,
+            free,
+            low_balance_threshold: self.low_balance_threshold,
+            low_balance: free < self.low_balance_threshold,
+        })
+    }
+
+    async fn update(&mut self) {
+        let liquidity = match self.liquidity() {
+            Some(liquidity) => liquidity,
+            None => return,
+        };
+
+        let previous = *self.feed.borrow();
+        if previous == Some(liquidity) {
+            return;
+        }
+
+        RESERVED_MARGIN_GAUGE.set(liquidity.reserved.as_sat() as f64);
+        FREE_BALANCE_GAUGE.set(liquidity.free.as_sat() as f64);
+
+        let was_low_balance = previous.map_or(false, |previous| previous.low_balance);
+        if liquidity.low_balance && !was_low_balance {
+            LOW_BALANCE_ALERTS_COUNTER.inc();
+            tracing::warn!(
+                free = %liquidity.free,
+                reserved = %liquidity.reserved,
+                threshold = %liquidity.low_balance_threshold,
+                "Free balance is running low"
+            );
+        }
+
+        if previous.map(|previous| previous.free) != Some(liquidity.free) {
+            if let Err(e) = self
+                .free_balance_changed
+                .send_async_safe(cfd::FreeBalanceChanged(liquidity.free))
+                .await
+            {
+                tracing::warn!("Failed to update offers with free balance: {e:#}");
+            }
+        }
+
+        let _ = self.feed.send(Some(liquidity));
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we are alive");
+
+        let mut rx_wallet = self.rx_wallet.clone();
+        let mut rx_cfds = self.rx_cfds.clone();
+        let notify = {
+            let this = this.clone();
+            async move {
+                loop {
+                    tokio::select! {
+                        Ok(()) = rx_wallet.changed() => {},
+                        Ok(()) = rx_cfds.changed() => {},
+                        else => return,
+                    }
+
+                    if this.send(Update).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        };
+
+        tokio_extras::spawn(&this.clone(), notify);
+        this.send_async_next(Update).await;
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: Update) {
+        self.update().await;
+    }
+
+    async fn handle(&mut self, msg: ReserveOrder) -> Result<()> {
+        let ReserveOrder { order_id } = msg;
+
+        let margin = self
+            .rx_cfds
+            .borrow()
+            .iter()
+            .flatten()
+            .find(|cfd| cfd.order_id == order_id)
+            .with_context(|| format!("Unknown order {order_id}"))?
+            .margin;
+
+        let free = match self.liquidity() {
+            Some(liquidity) => liquidity.free,
+            None => bail!("Wallet balance not known yet"),
+        };
+
+        ensure!(
+            margin <= free,
+            "Insufficient free balance: order requires a margin of {margin} but only {free} are available"
+        );
+
+        self.accepted.insert(order_id, margin);
+        self.update().await;
+
+        Ok(())
+    }
+}
+
+struct Update;
+
+/// Reserve the margin of an order before accepting it.
+///
+/// Fails if the free balance does not cover the margin of the order.
+pub struct ReserveOrder {
+    pub order_id: OrderId,
+}
+
+/// Reduce the `max_quantity` of `offer` to the quantity `free_balance` can fund.
+///
+/// Returns `None` if not even the `min_quantity` can be funded, such an offer is not to be
+/// published.
+pub fn cap_max_quantity(mut offer: model::Offer, free_balance: Amount) -> Option<model::Offer> {
+    let lot_size = Contracts::from(offer.lot_size).to_u64();
+    let margin_per_lot = calculate_margin(
+        offer.contract_symbol,
+        offer.price,
+        offer.lot_size.into(),
+        Leverage::ONE,
+    );
+
+    if lot_size == 0 || margin_per_lot == Amount::ZERO {
+        return Some(offer);
+    }
+
+    let lots = free_balance.as_sat() / margin_per_lot.as_sat();
+    let affordable = Contracts::new(lots * lot_size);
+
+    if affordable < offer.min_quantity {
+        tracing::debug!(
+            offer_id = %offer.id,
+            min_quantity = %offer.min_quantity,
+            %affordable,
+            "Not publishing offer, free balance does not cover its min quantity"
+        );
+
+        return None;
+    }
+
+    if affordable < offer.max_quantity {
+        tracing::debug!(
+            offer_id = %offer.id,
+            max_quantity = %offer.max_quantity,
+            %affordable,
+            "Reducing max quantity of offer to free balance"
+        );
+
+        offer.max_quantity = affordable;
+    }
+
+    Some(offer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::ContractSymbol;
+    use model::ContractType;
+    use model::FundingRate;
+    use model::LotSize;
+    use model::OpeningFee;
+    use model::PayoutGranularity;
+    use model::Position;
+    use model::Price;
+    use model::TxFeeRate;
+    use std::str::FromStr;
+
+    #[test]
+    fn max_quantity_is_capped_to_whole_lots_of_free_balance() {
+        // At a price of 20_000 one lot of 100 contracts requires a margin of 0.005 BTC
+        let offer = dummy_offer(Contracts::new(10_000));
+
+        let offer = cap_max_quantity(offer, Amount::from_sat(1_250_000)).unwrap();
+
+        assert_eq!(offer.max_quantity, Contracts::new(200));
+    }
+
+    #[test]
+    fn max_quantity_is_kept_if_free_balance_suffices() {
+        let offer = dummy_offer(Contracts::new(1_000));
+
+        let offer = cap_max_quantity(offer, Amount::ONE_BTC).unwrap();
+
+        assert_eq!(offer.max_quantity, Contracts::new(1_000));
+    }
+
+    #[test]
+    fn offer_is_dropped_if_free_balance_does_not_cover_min_quantity() {
+        // The min quantity of 100 contracts requires a margin of 0.005 BTC
+        let offer = dummy_offer(Contracts::new(1_000));
+
+        assert!(cap_max_quantity(offer.clone(), Amount::from_sat(499_999)).is_none());
+        assert_eq!(
+            cap_max_quantity(offer, Amount::from_sat(500_000))
+                .unwrap()
+                .max_quantity,
+            Contracts::new(100)
+        );
+    }
+
+    fn dummy_offer(max_quantity: Contracts) -> model::Offer {
+        model::Offer::new(
+            Position::Short,
+            Price::from_str("20000").unwrap(),
+            Contracts::new(100),
+            max_quantity,
+            model::DEFAULT_SETTLEMENT_INTERVAL,
+            ContractType::Perpetual,
+            PayoutGranularity::default(),
+            TxFeeRate::default(),
+            FundingRate::default(),
+            OpeningFee::default(),
+            vec![Leverage::TWO],
+            ContractSymbol::BtcUsd,
+            LotSize::new(100),
+        )
+    }
+}
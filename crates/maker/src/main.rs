@@ -10,21 +10,29 @@ use daemon::seed::RandomSeed;
 use daemon::seed::Seed;
 use daemon::wallet;
 use daemon::wallet::MAKER_WALLET_ID;
-use daemon::N_PAYOUTS;
+use maker::exposure;
+use maker::hedging;
+use maker::liquidity;
 use maker::load_blocked_peers;
 use maker::routes;
+use maker::utxo_management;
 use maker::ActorSystem;
 use maker::Opts;
 use model::olivia;
 use model::Role;
-use model::SETTLEMENT_INTERVAL;
+use model::DEFAULT_SETTLEMENT_INTERVAL;
 use rocket_cookie_auth::users::Users;
 use shared_bin::catchers::default_catchers;
 use shared_bin::cli::Withdraw;
 use shared_bin::fairings;
 use shared_bin::logger;
+use shared_bin::metrics_export;
+use shared_bin::websocket;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::watch;
 use tokio_extras::Tasks;
+use xtra::Actor as _;
 use xtras::supervisor::always_restart;
 use xtras::supervisor::Supervisor;
 
@@ -57,34 +65,85 @@ async fn main() -> Result<()> {
     )
     .context("initialize logger")?;
     tracing::info!("Running version: {}", daemon::version());
-    let settlement_interval_hours = SETTLEMENT_INTERVAL.whole_hours();
+    let settlement_interval_hours = DEFAULT_SETTLEMENT_INTERVAL.whole_hours();
 
     tracing::info!(
-        "CFDs created with this release will settle after {settlement_interval_hours} hours"
+        "Offers settle after {settlement_interval_hours} hours unless specified otherwise"
     );
 
     let seed = RandomSeed::initialize(&data_dir.join("maker_seed")).await?;
 
     let bitcoin_network = opts.network.bitcoin_network();
+    let block_explorer = opts.block_explorer.block_explorer(bitcoin_network)?;
+
+    let external_signer: Option<Arc<dyn wallet::ExternalSigner>> =
+        match (opts.psbt_dir.clone(), opts.wallet_fingerprint) {
+            (Some(psbt_dir), _) => Some(Arc::new(wallet::AirGap::new(psbt_dir))),
+            (None, Some(fingerprint)) => Some(Arc::new(wallet::Hwi::new(
+                opts.hwi.clone(),
+                fingerprint,
+                bitcoin_network,
+            ))),
+            (None, None) => None,
+        };
+
+    let wallet_keys = match (
+        opts.wallet_descriptors.load()?,
+        opts.wallet_xpub,
+        opts.wallet_xprv,
+    ) {
+        (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+            bail!("Wallet descriptors cannot be combined with an extended key")
+        }
+        (Some(descriptors), None, None) => wallet::WalletKeys::Descriptors {
+            descriptors,
+            signer: external_signer,
+        },
+        (None, Some(wallet_xpub), _) => {
+            if wallet_xpub.network != bitcoin_network {
+                let network = wallet_xpub.network;
+                bail!("Invalid public key provided. Was '{network}' but should have been '{bitcoin_network}'");
+            }
 
-    let ext_priv_key = match opts.wallet_xprv {
-        Some(wallet_xprv) => {
+            wallet::WalletKeys::External {
+                xpub: wallet_xpub,
+                fingerprint: opts
+                    .wallet_fingerprint
+                    .context("Fingerprint of the external signer is required")?,
+                signer: external_signer.context("External signer is required")?,
+            }
+        }
+        (None, None, Some(wallet_xprv)) => {
             if wallet_xprv.network != bitcoin_network {
                 let network = wallet_xprv.network;
                 bail!("Invalid private key provided. Was '{network}' but should have been '{bitcoin_network}'");
             }
-            wallet_xprv
+            wallet::WalletKeys::Hot(wallet_xprv)
+        }
+        (None, None, None) => {
+            wallet::WalletKeys::Hot(seed.derive_extended_priv_key(bitcoin_network)?)
         }
-        None => seed.derive_extended_priv_key(bitcoin_network)?,
     };
 
     let mut tasks = Tasks::default();
 
+    if let Some(endpoint) = opts.metrics_export.endpoint(&opts.collector_endpoint) {
+        metrics_export::export(
+            endpoint,
+            opts.metrics_export.interval(),
+            metrics_export::resource(&opts.service_name, Role::Maker, bitcoin_network),
+        )?;
+    }
+
     let mut wallet_dir = data_dir.clone();
 
     wallet_dir.push(MAKER_WALLET_ID);
-    let (wallet, wallet_feed_receiver) =
-        wallet::Actor::spawn(opts.network.electrum(), ext_priv_key, wallet_dir)?;
+    let (wallet, wallet_feed_receiver) = wallet::Actor::spawn(
+        opts.network.electrum(),
+        bitcoin_network,
+        wallet_keys,
+        wallet_dir,
+    )?;
 
     if let Some(Withdraw::Withdraw {
         amount,
@@ -96,7 +155,10 @@ async fn main() -> Result<()> {
             .send(wallet::Withdraw {
                 amount: *amount,
                 address: address.clone(),
-                fee: fee.map(FeeRate::from_sat_per_vb),
+                fee: fee
+                    .map(FeeRate::from_sat_per_vb)
+                    .map(wallet::WithdrawFee::Rate),
+                coins: Vec::new(),
             })
             .await??;
 
@@ -144,10 +206,11 @@ async fn main() -> Result<()> {
 
     let (supervisor, projection_actor) = Supervisor::new({
         let db = db.clone();
+        let block_explorer = block_explorer.clone();
         move || {
             projection::Actor::new(
                 db.clone(),
-                bitcoin_network,
+                block_explorer.clone(),
                 price_feed.clone().into(),
                 Role::Maker,
                 feed_senders.clone(),
@@ -156,6 +219,34 @@ async fn main() -> Result<()> {
     });
     tasks.add(supervisor.run_log_summary());
 
+    let hedging_actor = match opts.hedging {
+        hedging::Mode::Off => None,
+        hedging::Mode::DryRun => Some(Box::new(hedging::DryRun) as Box<dyn hedging::Exchange>),
+        hedging::Mode::BitMex => {
+            let (api_key, secret) = opts
+                .bitmex_api_key
+                .clone()
+                .zip(opts.bitmex_api_secret.clone())
+                .context("Hedging on BitMex requires --bitmex-api-key and --bitmex-api-secret")?;
+            let exchange = hedging::bitmex::BitMex::new(
+                opts.network.bitmex_network(),
+                bitmex_stream::Credentials::new(api_key, secret),
+            )?;
+
+            Some(Box::new(exchange) as Box<dyn hedging::Exchange>)
+        }
+    }
+    .map(|exchange| {
+        tracing::info!(mode = %opts.hedging, "Hedging position changes");
+        hedging::Actor::new(db.clone(), exchange)
+            .create(None)
+            .spawn(&mut tasks)
+    });
+
+    let notifications = opts
+        .notifications
+        .outbox(db.clone(), Role::Maker, &mut tasks)?;
+
     let maker = ActorSystem::new(
         db.clone(),
         wallet.clone(),
@@ -165,13 +256,49 @@ async fn main() -> Result<()> {
             let electrum = opts.network.electrum().to_string();
             monitor::Actor::new(db.clone(), electrum, executor)
         },
-        SETTLEMENT_INTERVAL,
-        N_PAYOUTS,
+        DEFAULT_SETTLEMENT_INTERVAL,
         projection_actor.clone(),
         identities,
         endpoint_listen,
+        Box::new(daemon::libp2p_utils::tcp_transport),
         blocked_peers,
+        hedging_actor,
+        notifications,
     )?;
+    let maker = Arc::new(maker);
+
+    let (liquidity_actor, liquidity_feed_receiver) = liquidity::Actor::new(
+        opts.low_balance_threshold,
+        wallet_feed_receiver.clone(),
+        feed_receivers.cfds.clone(),
+        maker.cfd_actor.clone().into(),
+    );
+    let liquidity_actor = liquidity_actor.create(None).spawn(&mut tasks);
+
+    let (exposure_actor, exposure_feed_receiver) = exposure::Actor::new(
+        db.clone(),
+        feed_receivers.cfds.clone(),
+        feed_receivers.quote.clone(),
+    );
+    let _exposure_actor = exposure_actor.create(None).spawn(&mut tasks);
+
+    let (_utxo_management_actor, utxo_management_feed_receiver) = match opts.utxo_target_size {
+        Some(target_size) => {
+            let (utxo_management_actor, utxo_management_feed_receiver) =
+                utxo_management::Actor::new(
+                    wallet.clone().into(),
+                    wallet::UtxoDistribution {
+                        target_size,
+                        target_count: opts.utxo_target_count,
+                    },
+                    FeeRate::from_sat_per_vb(opts.utxo_max_fee_rate),
+                );
+            let utxo_management_actor = utxo_management_actor.create(None).spawn(&mut tasks);
+
+            (Some(utxo_management_actor), utxo_management_feed_receiver)
+        }
+        None => (None, watch::channel(None).1),
+    };
 
     if let Some(password) = opts.password {
         db.clone()
@@ -184,12 +311,36 @@ async fn main() -> Result<()> {
     let rocket_auth_db_connection = RocketAuthDbConnection::new(db.clone());
     let users = Users::new(Box::new(rocket_auth_db_connection));
 
+    if let Some(ws_address) = opts.ws_address {
+        let feeds = websocket::Feeds::new(
+            &feed_receivers,
+            wallet_feed_receiver.clone(),
+            block_explorer.clone(),
+        );
+        let commands = maker::websocket::Commands::new(maker.clone(), liquidity_actor.clone());
+
+        tasks.add_fallible(
+            websocket::serve(
+                ws_address,
+                Box::new(RocketAuthDbConnection::new(db.clone())),
+                feeds,
+                commands,
+            ),
+            |e| async move { tracing::error!("WebSocket API stopped: {e:#}") },
+        );
+    }
+
     let mission_success = rocket::custom(figment)
         .manage(feed_receivers)
         .manage(wallet_feed_receiver)
+        .manage(liquidity_actor)
+        .manage(liquidity_feed_receiver)
+        .manage(exposure_feed_receiver)
+        .manage(utxo_management_feed_receiver)
         .manage(maker)
         .manage(users)
-        .manage(bitcoin_network)
+        .manage(block_explorer)
+        .manage(db.clone())
         .mount(
             "/api",
             rocket::routes![
@@ -199,13 +350,26 @@ async fn main() -> Result<()> {
                 routes::post_cfd_action,
                 routes::get_health_check,
                 routes::get_cfds,
+                routes::get_cfd,
                 routes::get_metrics,
                 routes::put_sync_wallet,
+                routes::post_withdraw_request,
+                routes::post_withdraw_preview_request,
+                routes::post_bump_fee_request,
+                routes::get_fee_estimates,
+                routes::get_utxos,
+                routes::put_utxo,
                 routes::get_version,
                 routes::change_password,
                 routes::post_login,
                 routes::logout,
                 routes::is_authenticated,
+                routes::get_blocked_peers,
+                routes::put_blocked_peer,
+                routes::delete_blocked_peer,
+                routes::get_takers,
+                routes::get_exposure,
+                routes::get_exposure_history,
             ],
         )
         .register("/api", default_catchers())
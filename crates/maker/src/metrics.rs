@@ -1 +1,2 @@
+pub mod taker_analytics;
 pub mod time_to_first_position;
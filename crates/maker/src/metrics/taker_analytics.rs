@@ -0,0 +1,729 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use bdk::bitcoin::SignedAmount;
+use daemon::identify::dialer::PeerIdentified;
+use futures::StreamExt;
+use model::calculate_margin;
+use model::calculate_profit;
+use model::libp2p::PeerId;
+use model::CfdEvent;
+use model::ClosedCfd;
+use model::ContractSymbol;
+use model::Contracts;
+use model::EventKind;
+use model::FailedCfd;
+use model::Leverage;
+use model::Role;
+use model::Settlement;
+use serde::Serialize;
+use sqlite_db::event_log::CounterpartyEventCount;
+use std::collections::HashMap;
+use std::time::Duration;
+use time::OffsetDateTime;
+use xtra_libp2p::endpoint;
+use xtra_productivity::xtra_productivity;
+
+/// Interval at which the Prometheus metrics are updated.
+const UPDATE_METRICS_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Number of takers exported with their own label, ordered by volume.
+///
+/// The metrics of all other takers are summed up under a common label to keep the number
+/// of time series bounded.
+const MAX_LABELLED_TAKERS: usize = 10;
+
+/// Analytics of a taker we had CFDs with or that connected since the maker was started.
+///
+/// Connection uptime and daemon version are only known for takers that connected since the maker
+/// was started. CFDs from before the peer ID of the counterparty was recorded are reported under
+/// the placeholder peer ID.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TakerAnalytics {
+    pub peer_id: PeerId,
+    /// Quantity of all CFDs that were set up successfully, per contract symbol
+    pub volume: HashMap<ContractSymbol, Contracts>,
+    /// Number of CFDs that were set up successfully
+    pub positions: u64,
+    /// Sum of the maker's profit and loss of all closed CFDs
+    ///
+    /// This is net of fees because the payout of a CFD already settles its opening and funding
+    /// fees.
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub realised_pnl_btc: SignedAmount,
+    /// Opening and funding fees the maker earned from all closed CFDs, included in
+    /// `realised_pnl_btc`
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub realised_fees_btc: SignedAmount,
+    pub rollovers_completed: u64,
+    /// Rollovers that were rejected or failed
+    pub rollovers_failed: u64,
+    pub collab_settlements_accepted: u64,
+    pub collab_settlements_rejected: u64,
+    /// Share of collaborative settlement proposals accepted by the maker
+    pub collab_settlement_acceptance_rate: Option<f64>,
+    pub online: bool,
+    /// Share of the time the taker was connected since it first connected
+    pub uptime: Option<f64>,
+    pub daemon_version: Option<String>,
+}
+
+impl TakerAnalytics {
+    fn new(peer_id: PeerId) -> Self {
+        Self {
+            peer_id,
+            volume: HashMap::new(),
+            positions: 0,
+            realised_pnl_btc: SignedAmount::ZERO,
+            realised_fees_btc: SignedAmount::ZERO,
+            rollovers_completed: 0,
+            rollovers_failed: 0,
+            collab_settlements_accepted: 0,
+            collab_settlements_rejected: 0,
+            collab_settlement_acceptance_rate: None,
+            online: false,
+            uptime: None,
+            daemon_version: None,
+        }
+    }
+
+    fn total_volume(&self) -> u64 {
+        self.volume.values().map(Contracts::to_u64).sum()
+    }
+}
+
+pub struct Actor {
+    db: sqlite_db::Connection,
+    connections: HashMap<PeerId, Connection>,
+}
+
+impl Actor {
+    pub fn new(db: sqlite_db::Connection) -> Self {
+        Self {
+            db,
+            connections: HashMap::new(),
+        }
+    }
+
+    async fn analytics(&self) -> Result<Vec<TakerAnalytics>> {
+        let mut stream = self.db.load_all_cfds::<Cfd>(());
+
+        let mut cfds = Vec::new();
+        while let Some(cfd) = stream.next().await {
+            match cfd {
+                Ok(cfd) => cfds.push(cfd),
+                Err(e) => tracing::error!("Failed to rehydrate CFD: {e:#}"),
+            }
+        }
+
+        let event_counts = self.db.load_event_counts_by_counterparty().await?;
+
+        Ok(analytics(
+            &cfds,
+            &event_counts,
+            &self.connections,
+            tokio_extras::time::now_utc(),
+        ))
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we are alive");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                UPDATE_METRICS_INTERVAL,
+                || UpdateMetrics,
+                xtras::IncludeSpan::Always,
+            ),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+/// Load the analytics of all takers, ordered by volume.
+#[derive(Clone, Copy)]
+pub struct GetTakerAnalytics;
+
+#[derive(Clone, Copy)]
+struct UpdateMetrics;
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: GetTakerAnalytics) -> Result<Vec<TakerAnalytics>> {
+        let analytics = self.analytics().await?;
+        metrics::update(&analytics);
+
+        Ok(analytics)
+    }
+
+    async fn handle(&mut self, _: UpdateMetrics) {
+        match self.analytics().await {
+            Ok(analytics) => metrics::update(&analytics),
+            Err(e) => tracing::warn!("Failed to update taker analytics: {e:#}"),
+        }
+    }
+
+    async fn handle_connection_established(&mut self, msg: endpoint::ConnectionEstablished) {
+        let now = tokio_extras::time::now_utc();
+
+        self.connections
+            .entry(msg.peer_id.into())
+            .or_insert_with(|| Connection::new(now))
+            .connected(now);
+    }
+
+    async fn handle_connection_dropped(&mut self, msg: endpoint::ConnectionDropped) {
+        if let Some(connection) = self.connections.get_mut(&PeerId::from(msg.peer_id)) {
+            connection.dropped(tokio_extras::time::now_utc());
+        }
+    }
+
+    async fn handle_peer_identified(&mut self, msg: PeerIdentified) {
+        let now = tokio_extras::time::now_utc();
+
+        self.connections
+            .entry(msg.peer_id.into())
+            .or_insert_with(|| Connection::new(now))
+            .daemon_version = Some(msg.peer_info.daemon_version);
+    }
+}
+
+/// Connection history of a taker since the maker was started.
+#[derive(Debug, Clone)]
+struct Connection {
+    first_connected_at: OffsetDateTime,
+    connected_since: Option<OffsetDateTime>,
+    /// Total time of all connections that were dropped already
+    connected_for: time::Duration,
+    daemon_version: Option<String>,
+}
+
+impl Connection {
+    fn new(now: OffsetDateTime) -> Self {
+        Self {
+            first_connected_at: now,
+            connected_since: None,
+            connected_for: time::Duration::ZERO,
+            daemon_version: None,
+        }
+    }
+
+    fn connected(&mut self, now: OffsetDateTime) {
+        self.connected_since.get_or_insert(now);
+    }
+
+    fn dropped(&mut self, now: OffsetDateTime) {
+        if let Some(connected_since) = self.connected_since.take() {
+            self.connected_for += now - connected_since;
+        }
+    }
+
+    fn uptime(&self, now: OffsetDateTime) -> Option<f64> {
+        let total = now - self.first_connected_at;
+        if total <= time::Duration::ZERO {
+            return None;
+        }
+
+        let connected = self.connected_for
+            + self
+                .connected_since
+                .map(|connected_since| now - connected_since)
+                .unwrap_or_default();
+
+        Some((connected.as_seconds_f64() / total.as_seconds_f64()).min(1.0))
+    }
+}
+
+fn analytics(
+    cfds: &[Cfd],
+    event_counts: &[CounterpartyEventCount],
+    connections: &HashMap<PeerId, Connection>,
+    now: OffsetDateTime,
+) -> Vec<TakerAnalytics> {
+    let mut takers = HashMap::<PeerId, TakerAnalytics>::new();
+
+    for cfd in cfds.iter().filter(|cfd| cfd.opened) {
+        let taker = takers
+            .entry(cfd.counterparty)
+            .or_insert_with(|| TakerAnalytics::new(cfd.counterparty));
+
+        let volume = taker
+            .volume
+            .entry(cfd.contract_symbol)
+            .or_insert(Contracts::ZERO);
+        *volume = *volume + cfd.quantity;
+        taker.positions += 1;
+
+        if let Some((pnl, fees)) = cfd.realised {
+            taker.realised_pnl_btc += pnl;
+            taker.realised_fees_btc += fees;
+        }
+    }
+
+    for CounterpartyEventCount {
+        counterparty,
+        name,
+        count,
+    } in event_counts
+    {
+        let taker = takers
+            .entry(*counterparty)
+            .or_insert_with(|| TakerAnalytics::new(*counterparty));
+
+        match name.as_str() {
+            EventKind::ROLLOVER_COMPLETED_EVENT => taker.rollovers_completed += count,
+            EventKind::ROLLOVER_REJECTED | EventKind::ROLLOVER_FAILED => {
+                taker.rollovers_failed += count
+            }
+            EventKind::COLLABORATIVE_SETTLEMENT_PROPOSAL_ACCEPTED => {
+                taker.collab_settlements_accepted += count
+            }
+            EventKind::COLLABORATIVE_SETTLEMENT_REJECTED => {
+                taker.collab_settlements_rejected += count
+            }
+            _ => {}
+        }
+    }
+
+    for (peer_id, connection) in connections {
+        let taker = takers
+            .entry(*peer_id)
+            .or_insert_with(|| TakerAnalytics::new(*peer_id));
+
+        taker.online = connection.connected_since.is_some();
+        taker.uptime = connection.uptime(now);
+        taker.daemon_version = connection.daemon_version.clone();
+    }
+
+    let mut takers = takers
+        .into_values()
+        .map(|mut taker| {
+            let proposals = taker.collab_settlements_accepted + taker.collab_settlements_rejected;
+            taker.collab_settlement_acceptance_rate = (proposals > 0)
+                .then(|| taker.collab_settlements_accepted as f64 / proposals as f64);
+
+            taker
+        })
+        .collect::<Vec<_>>();
+
+    takers.sort_by(|a, b| {
+        b.total_volume()
+            .cmp(&a.total_volume())
+            .then_with(|| a.peer_id.to_string().cmp(&b.peer_id.to_string()))
+    });
+
+    takers
+}
+
+/// Read-model of the CFD for the taker analytics.
+#[derive(Debug, Clone, Copy)]
+struct Cfd {
+    counterparty: PeerId,
+    contract_symbol: ContractSymbol,
+    quantity: Contracts,
+    /// Whether the contract setup completed
+    opened: bool,
+    /// Our profit and loss and the fees we earned, only known once the CFD is closed
+    realised: Option<(SignedAmount, SignedAmount)>,
+    version: u32,
+}
+
+impl sqlite_db::CfdAggregate for Cfd {
+    type CtorArgs = ();
+
+    fn new(_: Self::CtorArgs, cfd: sqlite_db::Cfd) -> Self {
+        Self {
+            counterparty: cfd.counterparty_peer_id.unwrap_or_else(PeerId::placeholder),
+            contract_symbol: cfd.contract_symbol,
+            quantity: cfd.quantity,
+            opened: false,
+            realised: None,
+            version: 0,
+        }
+    }
+
+    fn apply(mut self, event: CfdEvent) -> Self {
+        self.version += 1;
+
+        if let EventKind::ContractSetupCompleted { .. } = event.event {
+            self.opened = true;
+        }
+
+        self
+    }
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+impl sqlite_db::ClosedCfdAggregate for Cfd {
+    fn new_closed(_: Self::CtorArgs, closed_cfd: ClosedCfd) -> Self {
+        let ClosedCfd {
+            counterparty_peer_id,
+            contract_symbol,
+            n_contracts: quantity,
+            initial_price,
+            taker_leverage,
+            role,
+            fees,
+            settlement,
+            ..
+        } = closed_cfd;
+
+        let our_leverage = match role {
+            Role::Maker => Leverage::ONE,
+            Role::Taker => taker_leverage,
+        };
+        let margin = calculate_margin(contract_symbol, initial_price, quantity, our_leverage);
+
+        let payout = match settlement {
+            Settlement::Collaborative { payout, .. }
+            | Settlement::Cet { payout, .. }
+            | Settlement::Refund { payout, .. } => payout,
+        };
+        let (pnl, _) = calculate_profit(payout.inner(), margin);
+
+        // A negative fee balance is owed to us by the taker
+        let fees = -fees.inner();
+
+        Self {
+            counterparty: counterparty_peer_id,
+            contract_symbol,
+            quantity,
+            opened: true,
+            realised: Some((pnl, fees)),
+            version: 0,
+        }
+    }
+}
+
+impl sqlite_db::FailedCfdAggregate for Cfd {
+    fn new_failed(_: Self::CtorArgs, failed_cfd: FailedCfd) -> Self {
+        Self {
+            counterparty: failed_cfd.counterparty_peer_id,
+            contract_symbol: failed_cfd.contract_symbol,
+            quantity: failed_cfd.n_contracts,
+            opened: false,
+            realised: None,
+            version: 0,
+        }
+    }
+}
+
+mod metrics {
+    use super::TakerAnalytics;
+    use super::MAX_LABELLED_TAKERS;
+    use model::ContractSymbol;
+    use model::Contracts;
+    use std::collections::HashMap;
+
+    const TAKER_LABEL: &str = "taker";
+    const OTHER_TAKERS_LABEL: &str = "other";
+
+    const SYMBOL_LABEL: &str = "symbol";
+
+    const OUTCOME_LABEL: &str = "outcome";
+    const OUTCOME_COMPLETED_LABEL: &str = "completed";
+    const OUTCOME_FAILED_LABEL: &str = "failed";
+    const OUTCOME_ACCEPTED_LABEL: &str = "accepted";
+    const OUTCOME_REJECTED_LABEL: &str = "rejected";
+
+    static TAKER_VOLUME_GAUGE: conquer_once::Lazy<prometheus::IntGaugeVec> =
+        conquer_once::Lazy::new(|| {
+            prometheus::register_int_gauge_vec!(
+                "maker_taker_volume_contracts",
+                "Quantity of all positions opened with a taker.",
+                &[TAKER_LABEL, SYMBOL_LABEL]
+            )
+            .unwrap()
+        });
+
+    static TAKER_POSITIONS_GAUGE: conquer_once::Lazy<prometheus::IntGaugeVec> =
+        conquer_once::Lazy::new(|| {
+            prometheus::register_int_gauge_vec!(
+                "maker_taker_positions",
+                "Number of positions opened with a taker.",
+                &[TAKER_LABEL]
+            )
+            .unwrap()
+        });
+
+    static TAKER_REALISED_PNL_GAUGE: conquer_once::Lazy<prometheus::IntGaugeVec> =
+        conquer_once::Lazy::new(|| {
+            prometheus::register_int_gauge_vec!(
+                "maker_taker_realised_pnl_satoshis",
+                "Net realised profit and loss of the maker from closed positions with a taker.",
+                &[TAKER_LABEL]
+            )
+            .unwrap()
+        });
+
+    static TAKER_REALISED_FEES_GAUGE: conquer_once::Lazy<prometheus::IntGaugeVec> =
+        conquer_once::Lazy::new(|| {
+            prometheus::register_int_gauge_vec!(
+                "maker_taker_realised_fees_satoshis",
+                "Opening and funding fees the maker earned from closed positions with a taker.",
+                &[TAKER_LABEL]
+            )
+            .unwrap()
+        });
+
+    static TAKER_ROLLOVERS_GAUGE: conquer_once::Lazy<prometheus::IntGaugeVec> =
+        conquer_once::Lazy::new(|| {
+            prometheus::register_int_gauge_vec!(
+                "maker_taker_rollovers",
+                "Number of rollovers with a taker by outcome.",
+                &[TAKER_LABEL, OUTCOME_LABEL]
+            )
+            .unwrap()
+        });
+
+    static TAKER_COLLAB_SETTLEMENTS_GAUGE: conquer_once::Lazy<prometheus::IntGaugeVec> =
+        conquer_once::Lazy::new(|| {
+            prometheus::register_int_gauge_vec!(
+                "maker_taker_collab_settlement_proposals",
+                "Number of collaborative settlement proposals of a taker by outcome.",
+                &[TAKER_LABEL, OUTCOME_LABEL]
+            )
+            .unwrap()
+        });
+
+    static TAKER_UPTIME_GAUGE: conquer_once::Lazy<prometheus::GaugeVec> =
+        conquer_once::Lazy::new(|| {
+            prometheus::register_gauge_vec!(
+                "maker_taker_uptime_ratio",
+                "Share of the time a taker was connected since it first connected.",
+                &[TAKER_LABEL]
+            )
+            .unwrap()
+        });
+
+    /// Replace the taker metrics with the given analytics which are expected to be ordered by
+    /// volume.
+    pub fn update(analytics: &[TakerAnalytics]) {
+        TAKER_VOLUME_GAUGE.reset();
+        TAKER_POSITIONS_GAUGE.reset();
+        TAKER_REALISED_PNL_GAUGE.reset();
+        TAKER_REALISED_FEES_GAUGE.reset();
+        TAKER_ROLLOVERS_GAUGE.reset();
+        TAKER_COLLAB_SETTLEMENTS_GAUGE.reset();
+        TAKER_UPTIME_GAUGE.reset();
+
+        let mut labelled = analytics
+            .iter()
+            .take(MAX_LABELLED_TAKERS)
+            .map(|taker| (taker.peer_id.to_string(), taker.clone()))
+            .collect::<Vec<_>>();
+
+        for taker in analytics.iter().take(MAX_LABELLED_TAKERS) {
+            if let Some(uptime) = taker.uptime {
+                TAKER_UPTIME_GAUGE
+                    .with(&HashMap::from([(
+                        TAKER_LABEL,
+                        taker.peer_id.to_string().as_str(),
+                    )]))
+                    .set(uptime);
+            }
+        }
+
+        if let Some(first) = analytics.get(MAX_LABELLED_TAKERS) {
+            let others = analytics[MAX_LABELLED_TAKERS + 1..].iter().fold(
+                first.clone(),
+                |mut sum, taker| {
+                    for (symbol, quantity) in &taker.volume {
+                        let volume = sum.volume.entry(*symbol).or_insert(Contracts::ZERO);
+                        *volume = *volume + *quantity;
+                    }
+                    sum.positions += taker.positions;
+                    sum.realised_pnl_btc += taker.realised_pnl_btc;
+                    sum.realised_fees_btc += taker.realised_fees_btc;
+                    sum.rollovers_completed += taker.rollovers_completed;
+                    sum.rollovers_failed += taker.rollovers_failed;
+                    sum.collab_settlements_accepted += taker.collab_settlements_accepted;
+                    sum.collab_settlements_rejected += taker.collab_settlements_rejected;
+
+                    sum
+                },
+            );
+
+            labelled.push((OTHER_TAKERS_LABEL.to_owned(), others));
+        }
+
+        for (label, taker) in labelled {
+            let taker_label = (TAKER_LABEL, label.as_str());
+
+            for (symbol, quantity) in &taker.volume {
+                set_volume(taker_label, *symbol, quantity.to_u64());
+            }
+            TAKER_POSITIONS_GAUGE
+                .with(&HashMap::from([taker_label]))
+                .set(taker.positions as i64);
+            TAKER_REALISED_PNL_GAUGE
+                .with(&HashMap::from([taker_label]))
+                .set(taker.realised_pnl_btc.as_sat());
+            TAKER_REALISED_FEES_GAUGE
+                .with(&HashMap::from([taker_label]))
+                .set(taker.realised_fees_btc.as_sat());
+
+            for (outcome, count) in [
+                (OUTCOME_COMPLETED_LABEL, taker.rollovers_completed),
+                (OUTCOME_FAILED_LABEL, taker.rollovers_failed),
+            ] {
+                TAKER_ROLLOVERS_GAUGE
+                    .with(&HashMap::from([taker_label, (OUTCOME_LABEL, outcome)]))
+                    .set(count as i64);
+            }
+
+            for (outcome, count) in [
+                (OUTCOME_ACCEPTED_LABEL, taker.collab_settlements_accepted),
+                (OUTCOME_REJECTED_LABEL, taker.collab_settlements_rejected),
+            ] {
+                TAKER_COLLAB_SETTLEMENTS_GAUGE
+                    .with(&HashMap::from([taker_label, (OUTCOME_LABEL, outcome)]))
+                    .set(count as i64);
+            }
+        }
+    }
+
+    fn set_volume(taker_label: (&str, &str), symbol: ContractSymbol, quantity: u64) {
+        TAKER_VOLUME_GAUGE
+            .with(&HashMap::from([
+                taker_label,
+                (SYMBOL_LABEL, symbol.to_string().as_str()),
+            ]))
+            .set(quantity as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregates_cfds_and_events_per_taker_ordered_by_volume() {
+        let small = PeerId::random();
+        let large = PeerId::random();
+
+        let cfds = [
+            Cfd::dummy(small, 100)
+                .closed(SignedAmount::from_sat(-1_000), SignedAmount::from_sat(50)),
+            Cfd::dummy(large, 300),
+            Cfd::dummy(large, 200)
+                .closed(SignedAmount::from_sat(5_000), SignedAmount::from_sat(-20)),
+            Cfd::dummy(large, 1_000).failed(),
+        ];
+        let event_counts = [
+            event_count(large, EventKind::ROLLOVER_COMPLETED_EVENT, 4),
+            event_count(large, EventKind::ROLLOVER_FAILED, 1),
+            event_count(large, EventKind::ROLLOVER_REJECTED, 1),
+            event_count(
+                small,
+                EventKind::COLLABORATIVE_SETTLEMENT_PROPOSAL_ACCEPTED,
+                3,
+            ),
+            event_count(small, EventKind::COLLABORATIVE_SETTLEMENT_REJECTED, 1),
+        ];
+
+        let analytics = analytics(
+            &cfds,
+            &event_counts,
+            &HashMap::new(),
+            OffsetDateTime::now_utc(),
+        );
+
+        assert_eq!(analytics.len(), 2);
+
+        let large = &analytics[0];
+        assert_eq!(large.volume[&ContractSymbol::BtcUsd], Contracts::new(500));
+        assert_eq!(large.positions, 2);
+        assert_eq!(large.realised_pnl_btc, SignedAmount::from_sat(5_000));
+        assert_eq!(large.realised_fees_btc, SignedAmount::from_sat(-20));
+        assert_eq!(large.rollovers_completed, 4);
+        assert_eq!(large.rollovers_failed, 2);
+        assert_eq!(large.collab_settlement_acceptance_rate, None);
+
+        let small = &analytics[1];
+        assert_eq!(small.positions, 1);
+        assert_eq!(small.realised_pnl_btc, SignedAmount::from_sat(-1_000));
+        assert_eq!(small.realised_fees_btc, SignedAmount::from_sat(50));
+        assert_eq!(small.collab_settlements_accepted, 3);
+        assert_eq!(small.collab_settlements_rejected, 1);
+        assert_eq!(small.collab_settlement_acceptance_rate, Some(0.75));
+    }
+
+    #[test]
+    fn uptime_includes_current_connection() {
+        let start = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let at = |seconds| start + time::Duration::seconds(seconds);
+
+        let mut connection = Connection::new(start);
+        connection.connected(start);
+        connection.dropped(at(30));
+        connection.connected(at(60));
+
+        assert_eq!(connection.uptime(at(100)), Some(0.7));
+
+        connection.dropped(at(100));
+
+        assert_eq!(connection.uptime(at(200)), Some(0.35));
+    }
+
+    #[test]
+    fn takers_without_cfds_are_reported_with_their_connection() {
+        let peer_id = PeerId::random();
+        let now = OffsetDateTime::now_utc();
+
+        let mut connection = Connection::new(now);
+        connection.connected(now);
+        connection.daemon_version = Some("0.7.0".to_owned());
+
+        let analytics = analytics(&[], &[], &HashMap::from([(peer_id, connection)]), now);
+
+        assert_eq!(analytics.len(), 1);
+        assert!(analytics[0].online);
+        assert_eq!(analytics[0].positions, 0);
+        assert_eq!(analytics[0].daemon_version.as_deref(), Some("0.7.0"));
+    }
+
+    impl Cfd {
+        fn dummy(counterparty: PeerId, quantity: u64) -> Self {
+            Self {
+                counterparty,
+                contract_symbol: ContractSymbol::BtcUsd,
+                quantity: Contracts::new(quantity),
+                opened: true,
+                realised: None,
+                version: 0,
+            }
+        }
+
+        fn closed(self, pnl: SignedAmount, fees: SignedAmount) -> Self {
+            Self {
+                realised: Some((pnl, fees)),
+                ..self
+            }
+        }
+
+        fn failed(self) -> Self {
+            Self {
+                opened: false,
+                ..self
+            }
+        }
+    }
+
+    fn event_count(counterparty: PeerId, name: &str, count: u64) -> CounterpartyEventCount {
+        CounterpartyEventCount {
+            counterparty,
+            name: name.to_owned(),
+            count,
+        }
+    }
+}
@@ -14,7 +14,7 @@ impl Connected {
     pub fn new(taker_id: Identity) -> Self {
         Self {
             taker_id,
-            timestamp: OffsetDateTime::now_utc(),
+            timestamp: tokio_extras::time::now_utc(),
         }
     }
 }
@@ -29,7 +29,7 @@ impl Position {
     pub fn new(taker_id: Identity) -> Self {
         Self {
             taker_id,
-            timestamp: OffsetDateTime::now_utc(),
+            timestamp: tokio_extras::time::now_utc(),
         }
     }
 }
@@ -1,24 +1,34 @@
 #![allow(clippy::let_unit_value)] // see: https://github.com/SergioBenitez/Rocket/issues/2211
 use crate::actor_system::ActorSystem;
+use crate::blocked_peers::BlocklistEntry;
+use crate::blocked_peers::BlocklistSource;
+use crate::exposure;
+use crate::liquidity;
+use crate::metrics::taker_analytics::TakerAnalytics;
+use crate::utxo_management;
 use anyhow::Result;
 use bdk::sled;
 use daemon::bdk::blockchain::ElectrumBlockchain;
+use daemon::block_explorer::BlockExplorer;
 use daemon::oracle;
-use daemon::projection::Cfd;
 use daemon::projection::CfdAction;
 use daemon::projection::FeedReceivers;
 use daemon::wallet;
 use http_api_problem::HttpApiProblem;
 use http_api_problem::StatusCode;
+use model::ContractType;
 use model::Contracts;
 use model::FundingRate;
 use model::Leverage;
 use model::LotSize;
 use model::OpeningFee;
 use model::OrderId;
+use model::PayoutGranularity;
 use model::Price;
 use model::TxFeeRate;
 use model::WalletInfo;
+use model::MAX_SETTLEMENT_INTERVAL;
+use model::MIN_SETTLEMENT_INTERVAL;
 use rocket::form::Form;
 use rocket::http::ContentType;
 use rocket::http::Status;
@@ -36,15 +46,30 @@ use rust_embed::RustEmbed;
 use rust_embed_rocket::EmbeddedFileExt;
 use serde::Deserialize;
 use serde::Serialize;
+use shared_bin::wallet::parse_outpoint;
+use shared_bin::wallet::parse_txid;
+use shared_bin::wallet::BumpFeeRequest;
+use shared_bin::wallet::UpdateUtxoRequest;
+use shared_bin::wallet::WithdrawRequest;
+use shared_bin::wallet::WithdrawResponse;
+use shared_bin::CfdDetail;
+use shared_bin::CfdEvents;
+use shared_bin::CfdPage;
+use shared_bin::CfdQuery;
+use shared_bin::LastEventId;
 use shared_bin::ToSseEvent;
 use std::borrow::Cow;
 use std::path::PathBuf;
+use std::sync::Arc;
+use time::Duration;
+use time::OffsetDateTime;
 use tokio::select;
 use tokio::sync::watch;
 use tracing::instrument;
 use uuid::Uuid;
+use xtra_libp2p::libp2p::PeerId;
 
-pub type Maker = ActorSystem<oracle::Actor, wallet::Actor<ElectrumBlockchain, sled::Tree>>;
+pub type Maker = Arc<ActorSystem<oracle::Actor, wallet::Actor<ElectrumBlockchain, sled::Tree>>>;
 
 #[allow(clippy::too_many_arguments)]
 #[rocket::get("/feed")]
@@ -52,17 +77,36 @@ pub type Maker = ActorSystem<oracle::Actor, wallet::Actor<ElectrumBlockchain, sl
 pub async fn maker_feed(
     rx: &State<FeedReceivers>,
     rx_wallet: &State<watch::Receiver<Option<WalletInfo>>>,
+    block_explorer: &State<BlockExplorer>,
+    rx_liquidity: &State<watch::Receiver<Option<liquidity::Liquidity>>>,
+    rx_exposure: &State<watch::Receiver<Option<exposure::ExposureReport>>>,
+    rx_utxo_management: &State<watch::Receiver<Option<utxo_management::Status>>>,
+    last_event_id: LastEventId,
     _user: User,
 ) -> EventStream![] {
     let rx = rx.inner();
-    let mut rx_cfds = rx.cfds.clone();
+    let (mut cfd_events, cfd_catch_up) =
+        CfdEvents::subscribe(rx.cfd_updates.clone(), last_event_id);
     let mut rx_wallet = rx_wallet.inner().clone();
+    let block_explorer = block_explorer.inner().clone();
+    let mut rx_liquidity = rx_liquidity.inner().clone();
+    let mut rx_exposure = rx_exposure.inner().clone();
+    let mut rx_utxo_management = rx_utxo_management.inner().clone();
     let mut rx_offers = rx.offers.clone();
     let mut rx_quote = rx.quote.clone();
 
     EventStream! {
         let wallet_info = rx_wallet.borrow().clone();
-        yield wallet_info.to_sse_event();
+        yield (&block_explorer, &wallet_info).to_sse_event();
+
+        let liquidity = *rx_liquidity.borrow();
+        yield Event::json(&liquidity).event("liquidity");
+
+        let exposure = rx_exposure.borrow().clone();
+        yield Event::json(&exposure).event("exposure");
+
+        let utxo_management = rx_utxo_management.borrow().clone();
+        yield Event::json(&utxo_management).event("utxo_management");
 
         let offers = rx_offers.borrow().clone();
         yield Event::json(&offers.btcusd_long).event("btcusd_long_offer");
@@ -74,16 +118,27 @@ pub async fn maker_feed(
         yield Event::json(&quote.get(&model::ContractSymbol::BtcUsd)).event("btcusd_quote");
         yield Event::json(&quote.get(&model::ContractSymbol::EthUsd)).event("ethusd_quote");
 
-        let cfds = rx_cfds.borrow().clone();
-        if let Some(cfds) = cfds {
-            yield cfds.to_sse_event()
+        for event in cfd_catch_up {
+            yield event;
         }
 
         loop{
             select! {
                 Ok(()) = rx_wallet.changed() => {
                     let wallet_info = rx_wallet.borrow().clone();
-                    yield wallet_info.to_sse_event();
+                    yield (&block_explorer, &wallet_info).to_sse_event();
+                },
+                Ok(()) = rx_liquidity.changed() => {
+                    let liquidity = *rx_liquidity.borrow();
+                    yield Event::json(&liquidity).event("liquidity");
+                },
+                Ok(()) = rx_exposure.changed() => {
+                    let exposure = rx_exposure.borrow().clone();
+                    yield Event::json(&exposure).event("exposure");
+                },
+                Ok(()) = rx_utxo_management.changed() => {
+                    let utxo_management = rx_utxo_management.borrow().clone();
+                    yield Event::json(&utxo_management).event("utxo_management");
                 },
                 Ok(()) = rx_offers.changed() => {
                     let offers = rx_offers.borrow().clone();
@@ -92,10 +147,9 @@ pub async fn maker_feed(
                     yield Event::json(&offers.ethusd_long).event("ethusd_long_offer");
                     yield Event::json(&offers.ethusd_short).event("ethusd_short_offer");
                 }
-                Ok(()) = rx_cfds.changed() => {
-                    let cfds = rx_cfds.borrow().clone();
-                    if let Some(cfds) = cfds {
-                        yield cfds.to_sse_event()
+                events = cfd_events.next() => {
+                    for event in events {
+                        yield event;
                     }
                 }
                 Ok(()) = rx_quote.changed() => {
@@ -127,6 +181,71 @@ pub struct CfdNewOfferParamsRequest {
     pub leverage_choices: Vec<Leverage>,
     #[serde(default = "default_lot_size")]
     pub lot_size: LotSize,
+    /// Settlement interval of the offered CFDs, defaults to 24 hours
+    #[serde(default)]
+    pub settlement_interval_hours: Option<u8>,
+    /// Offer dated futures expiring at this time instead of perpetual CFDs
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub expiry: Option<OffsetDateTime>,
+    /// Number of payouts per oracle event, defaults to 200
+    #[serde(default)]
+    pub n_payouts: Option<usize>,
+    /// Hours between the oracle events used for liquidation, defaults to 1 hour
+    #[serde(default)]
+    pub liquidation_interval_hours: Option<u8>,
+}
+
+impl CfdNewOfferParamsRequest {
+    fn settlement_interval(&self) -> Result<Option<Duration>, HttpApiProblem> {
+        let settlement_interval = match self.settlement_interval_hours {
+            None => return Ok(None),
+            Some(hours) => Duration::hours(hours.into()),
+        };
+
+        if settlement_interval < MIN_SETTLEMENT_INTERVAL
+            || settlement_interval > MAX_SETTLEMENT_INTERVAL
+        {
+            return Err(HttpApiProblem::new(StatusCode::BAD_REQUEST)
+                .title("Invalid settlement interval")
+                .detail(format!(
+                    "Settlement interval has to be between {} and {} hours",
+                    MIN_SETTLEMENT_INTERVAL.whole_hours(),
+                    MAX_SETTLEMENT_INTERVAL.whole_hours()
+                )));
+        }
+
+        Ok(Some(settlement_interval))
+    }
+
+    /// The contract type of the requested offers, validated against the current time `now`.
+    fn contract_type(&self, now: OffsetDateTime) -> Result<ContractType, HttpApiProblem> {
+        let expiry = match self.expiry {
+            None => return Ok(ContractType::Perpetual),
+            Some(expiry) => expiry,
+        };
+
+        ContractType::dated(expiry, now).map_err(|e| {
+            HttpApiProblem::new(StatusCode::BAD_REQUEST)
+                .title("Invalid expiry")
+                .detail(format!("{e:#}"))
+        })
+    }
+
+    fn granularity(&self) -> Result<PayoutGranularity, HttpApiProblem> {
+        let n_payouts = self
+            .n_payouts
+            .unwrap_or(PayoutGranularity::DEFAULT_N_PAYOUTS);
+        let liquidation_interval = self
+            .liquidation_interval_hours
+            .map(|hours| Duration::hours(hours.into()))
+            .unwrap_or(PayoutGranularity::DEFAULT_LIQUIDATION_INTERVAL);
+
+        PayoutGranularity::new(n_payouts, liquidation_interval).map_err(|e| {
+            HttpApiProblem::new(StatusCode::BAD_REQUEST)
+                .title("Invalid payout granularity")
+                .detail(format!("{e:#}"))
+        })
+    }
 }
 
 fn empty_leverage() -> Vec<Leverage> {
@@ -159,6 +278,9 @@ pub async fn put_offer_params(
             offer_params.leverage_choices.clone(),
             ContractSymbol::BtcUsd.into(),
             offer_params.lot_size,
+            offer_params.settlement_interval()?,
+            offer_params.contract_type(tokio_extras::time::now_utc())?,
+            offer_params.granularity()?,
         )
         .await
         .map_err(|e| {
@@ -224,6 +346,9 @@ pub async fn put_offer_params_for_symbol(
             offer_params.leverage_choices.clone(),
             symbol.into(),
             offer_params.lot_size,
+            offer_params.settlement_interval()?,
+            offer_params.contract_type(tokio_extras::time::now_utc())?,
+            offer_params.granularity()?,
         )
         .await
         .map_err(|e| {
@@ -236,11 +361,16 @@ pub async fn put_offer_params_for_symbol(
 }
 
 #[rocket::post("/cfd/<order_id>/<action>")]
-#[instrument(name = "POST /cfd/<order_id>/<action>", skip(maker, _user), err)]
+#[instrument(
+    name = "POST /cfd/<order_id>/<action>",
+    skip(maker, liquidity_actor, _user),
+    err
+)]
 pub async fn post_cfd_action(
     order_id: Uuid,
     action: String,
     maker: &State<Maker>,
+    liquidity_actor: &State<xtra::Address<liquidity::Actor>>,
     _user: User,
 ) -> Result<(), HttpApiProblem> {
     let order_id = OrderId::from(order_id);
@@ -248,6 +378,32 @@ pub async fn post_cfd_action(
         HttpApiProblem::new(StatusCode::BAD_REQUEST).detail(format!("Invalid action: {}", action))
     })?;
 
+    execute_cfd_action(maker, liquidity_actor, order_id, action).await
+}
+
+/// Execute an action on a CFD, shared between the HTTP and the WebSocket API.
+pub(crate) async fn execute_cfd_action(
+    maker: &Maker,
+    liquidity_actor: &xtra::Address<liquidity::Actor>,
+    order_id: OrderId,
+    action: CfdAction,
+) -> Result<(), HttpApiProblem> {
+    if action == CfdAction::AcceptOrder {
+        liquidity_actor
+            .send(liquidity::ReserveOrder { order_id })
+            .await
+            .map_err(|e| {
+                HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                    .title("Liquidity actor disconnected")
+                    .detail(e.to_string())
+            })?
+            .map_err(|e| {
+                HttpApiProblem::new(StatusCode::CONFLICT)
+                    .title("Cannot fund order")
+                    .detail(format!("{e:#}"))
+            })?;
+    }
+
     let result = match action {
         CfdAction::AcceptOrder => maker.accept_order(order_id).await,
         CfdAction::RejectOrder => maker.reject_order(order_id).await,
@@ -302,22 +458,236 @@ pub async fn put_sync_wallet(maker: &State<Maker>, _user: User) -> Result<(), Ht
     Ok(())
 }
 
-#[rocket::get("/cfds")]
+#[rocket::post("/withdraw", data = "<withdraw_request>")]
+#[instrument(name = "POST /withdraw", skip(maker, _user), err)]
+pub async fn post_withdraw_request(
+    withdraw_request: Json<WithdrawRequest>,
+    maker: &State<Maker>,
+    block_explorer: &State<BlockExplorer>,
+    _user: User,
+) -> Result<Json<WithdrawResponse>, HttpApiProblem> {
+    let txid = maker
+        .withdraw(
+            withdraw_request.amount(),
+            withdraw_request.address.clone(),
+            withdraw_request.fee()?,
+            withdraw_request.coins()?,
+        )
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not proceed with withdraw request")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(WithdrawResponse::new(txid, block_explorer)))
+}
+
+#[rocket::post("/withdraw/preview", data = "<withdraw_request>")]
+#[instrument(name = "POST /withdraw/preview", skip(maker, _user), err)]
+pub async fn post_withdraw_preview_request(
+    withdraw_request: Json<WithdrawRequest>,
+    maker: &State<Maker>,
+    _user: User,
+) -> Result<Json<wallet::WithdrawPreview>, HttpApiProblem> {
+    let preview = maker
+        .preview_withdraw(
+            withdraw_request.amount(),
+            withdraw_request.address.clone(),
+            withdraw_request.fee()?,
+            withdraw_request.coins()?,
+        )
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not preview withdraw request")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(preview))
+}
+
+#[rocket::post("/withdraw/<txid>/bump-fee", data = "<bump_fee_request>")]
+#[instrument(name = "POST /withdraw/<txid>/bump-fee", skip(maker, _user), err)]
+pub async fn post_bump_fee_request(
+    txid: &str,
+    bump_fee_request: Json<BumpFeeRequest>,
+    maker: &State<Maker>,
+    block_explorer: &State<BlockExplorer>,
+    _user: User,
+) -> Result<Json<WithdrawResponse>, HttpApiProblem> {
+    let txid = parse_txid(txid)?;
+
+    let replacement = maker
+        .bump_fee(txid, bump_fee_request.fee()?)
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not bump fee of withdrawal")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(WithdrawResponse::new(replacement, block_explorer)))
+}
+
+#[rocket::get("/wallet/fee-estimates")]
+#[instrument(name = "GET /wallet/fee-estimates", skip_all, err)]
+pub async fn get_fee_estimates(
+    maker: &State<Maker>,
+    _user: User,
+) -> Result<Json<Vec<wallet::FeeEstimate>>, HttpApiProblem> {
+    let fee_estimates = maker.estimate_fee_rates().await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not estimate fee rates")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(fee_estimates))
+}
+
+#[rocket::get("/wallet/utxos")]
+#[instrument(name = "GET /wallet/utxos", skip_all, err)]
+pub async fn get_utxos(
+    maker: &State<Maker>,
+    _user: User,
+) -> Result<Json<Vec<wallet::Utxo>>, HttpApiProblem> {
+    let utxos = maker.list_utxos().await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not list UTXOs")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(utxos))
+}
+
+#[rocket::put("/wallet/utxos/<outpoint>", data = "<update_utxo_request>")]
+#[instrument(name = "PUT /wallet/utxos/<outpoint>", skip(maker, _user), err)]
+pub async fn put_utxo(
+    outpoint: &str,
+    update_utxo_request: Json<UpdateUtxoRequest>,
+    maker: &State<Maker>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    let outpoint = parse_outpoint(outpoint)?;
+    let UpdateUtxoRequest { label, frozen } = update_utxo_request.into_inner();
+
+    maker
+        .update_utxo(outpoint, label, frozen)
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not update UTXO")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
+#[rocket::get("/cfds?<query..>")]
 #[instrument(name = "GET /cfds", skip_all, err)]
-pub async fn get_cfds<'r>(
+pub async fn get_cfds(
+    query: CfdQuery,
     rx: &State<FeedReceivers>,
     _user: User,
-) -> Result<Json<Vec<Cfd>>, HttpApiProblem> {
-    let rx = rx.inner();
-    let rx_cfds = rx.cfds.clone();
-    let cfds = rx_cfds.borrow().clone();
-
-    match cfds {
-        Some(cfds) => Ok(Json(cfds)),
-        None => Err(HttpApiProblem::new(StatusCode::SERVICE_UNAVAILABLE)
-            .title("CFDs not yet available")
-            .detail("CFDs are still being loaded from the database. Please retry later.")),
-    }
+) -> Result<Json<CfdPage>, HttpApiProblem> {
+    let cfds = rx.cfds.borrow().clone().ok_or_else(cfds_not_available)?;
+
+    Ok(Json(query.apply(cfds)?))
+}
+
+#[rocket::get("/cfds/<order_id>")]
+#[instrument(name = "GET /cfds/<order_id>", skip(rx, db, _user), err)]
+pub async fn get_cfd(
+    order_id: Uuid,
+    rx: &State<FeedReceivers>,
+    db: &State<sqlite_db::Connection>,
+    _user: User,
+) -> Result<Json<CfdDetail>, HttpApiProblem> {
+    let order_id = OrderId::from(order_id);
+
+    let cfd = rx
+        .cfds
+        .borrow()
+        .as_ref()
+        .ok_or_else(cfds_not_available)?
+        .iter()
+        .find(|cfd| cfd.order_id == order_id)
+        .cloned()
+        .ok_or_else(|| {
+            HttpApiProblem::new(StatusCode::NOT_FOUND)
+                .title("CFD not found")
+                .detail(format!("No CFD with order id {order_id}"))
+        })?;
+
+    let timeline = db.load_cfd_timeline(order_id).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Failed to load CFD events")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(CfdDetail::new(cfd, timeline)))
+}
+
+#[rocket::get("/exposure")]
+#[instrument(name = "GET /exposure", skip_all, err)]
+pub async fn get_exposure(
+    rx_exposure: &State<watch::Receiver<Option<exposure::ExposureReport>>>,
+    _user: User,
+) -> Result<Json<exposure::ExposureReport>, HttpApiProblem> {
+    let report = rx_exposure
+        .borrow()
+        .clone()
+        .ok_or_else(cfds_not_available)?;
+
+    Ok(Json(report))
+}
+
+/// Number of snapshots returned by default, a week of hourly snapshots
+const DEFAULT_EXPOSURE_HISTORY_LIMIT: u32 = 168;
+const MAX_EXPOSURE_HISTORY_LIMIT: u32 = 1000;
+
+/// Snapshots of the exposure report taken at or after `since` (unix timestamp), newest first
+#[rocket::get("/exposure/history?<since>&<limit>")]
+#[instrument(name = "GET /exposure/history", skip(db, _user), err)]
+pub async fn get_exposure_history(
+    since: Option<i64>,
+    limit: Option<u32>,
+    db: &State<sqlite_db::Connection>,
+    _user: User,
+) -> Result<Json<Vec<exposure::ExposureHistoryEntry>>, HttpApiProblem> {
+    let since = match since {
+        Some(since) => OffsetDateTime::from_unix_timestamp(since).map_err(|e| {
+            HttpApiProblem::new(StatusCode::BAD_REQUEST)
+                .title("Invalid timestamp")
+                .detail(format!("{e:#}"))
+        })?,
+        None => OffsetDateTime::UNIX_EPOCH,
+    };
+    let limit = limit
+        .unwrap_or(DEFAULT_EXPOSURE_HISTORY_LIMIT)
+        .min(MAX_EXPOSURE_HISTORY_LIMIT);
+
+    let snapshots = db
+        .load_exposure_snapshots(since, limit)
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not load exposure history")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(
+        snapshots
+            .into_iter()
+            .map(exposure::ExposureHistoryEntry::from)
+            .collect(),
+    ))
+}
+
+fn cfds_not_available() -> HttpApiProblem {
+    HttpApiProblem::new(StatusCode::SERVICE_UNAVAILABLE)
+        .title("CFDs not yet available")
+        .detail("CFDs are still being loaded from the database. Please retry later.")
 }
 
 // TODO: Use non-cookie auth for /metrics endpoint as Prometheus does not
@@ -360,6 +730,149 @@ pub async fn update_rollover_configuration(
     Ok(())
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockedPeerResponse {
+    peer_id: String,
+    reason: String,
+    #[serde(with = "time::serde::rfc3339")]
+    blocked_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    expires_at: Option<OffsetDateTime>,
+    /// Whether the peer was blocked via the API or is listed in the config file
+    source: BlockedPeerSource,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockedPeerSource {
+    Config,
+    Api,
+}
+
+impl From<BlocklistEntry> for BlockedPeerResponse {
+    fn from(entry: BlocklistEntry) -> Self {
+        let BlocklistEntry {
+            blocked_peer,
+            source,
+        } = entry;
+
+        Self {
+            peer_id: blocked_peer.peer_id.to_string(),
+            reason: blocked_peer.reason,
+            blocked_at: blocked_peer.blocked_at,
+            expires_at: blocked_peer.expires_at,
+            source: match source {
+                BlocklistSource::Config => BlockedPeerSource::Config,
+                BlocklistSource::Api => BlockedPeerSource::Api,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlockPeerRequest {
+    reason: String,
+    /// If not provided, the peer is blocked until it is explicitly unblocked.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    expires_at: Option<OffsetDateTime>,
+}
+
+fn parse_peer_id(peer_id: &str) -> Result<PeerId, HttpApiProblem> {
+    peer_id.parse().map_err(|e| {
+        HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Invalid peer id")
+            .detail(format!("{e:#}"))
+    })
+}
+
+#[rocket::get("/blocked-peers")]
+#[instrument(name = "GET /blocked-peers", skip_all, err)]
+pub async fn get_blocked_peers(
+    maker: &State<Maker>,
+    _user: User,
+) -> Result<Json<Vec<BlockedPeerResponse>>, HttpApiProblem> {
+    let blocked_peers = maker.blocked_peers().await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not load blocked peers")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(
+        blocked_peers
+            .into_iter()
+            .map(BlockedPeerResponse::from)
+            .collect(),
+    ))
+}
+
+#[rocket::put("/blocked-peers/<peer_id>", data = "<request>")]
+#[instrument(name = "PUT /blocked-peers/<peer_id>", skip(maker, _user), err)]
+pub async fn put_blocked_peer(
+    peer_id: &str,
+    request: Json<BlockPeerRequest>,
+    maker: &State<Maker>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    let peer_id = parse_peer_id(peer_id)?;
+    let BlockPeerRequest { reason, expires_at } = request.into_inner();
+
+    if matches!(expires_at, Some(expires_at) if expires_at <= tokio_extras::time::now_utc()) {
+        return Err(HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Invalid expiry")
+            .detail("Expiry must be in the future"));
+    }
+
+    maker
+        .block_peer(peer_id, reason, expires_at)
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not block peer")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
+#[rocket::delete("/blocked-peers/<peer_id>")]
+#[instrument(name = "DELETE /blocked-peers/<peer_id>", skip(maker, _user), err)]
+pub async fn delete_blocked_peer(
+    peer_id: &str,
+    maker: &State<Maker>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    let peer_id = parse_peer_id(peer_id)?;
+
+    let was_blocked = maker.unblock_peer(peer_id).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not unblock peer")
+            .detail(format!("{e:#}"))
+    })?;
+
+    if !was_blocked {
+        return Err(HttpApiProblem::new(StatusCode::NOT_FOUND)
+            .title("Peer not blocked")
+            .detail(format!("{peer_id} is not on the blocklist")));
+    }
+
+    Ok(())
+}
+
+#[rocket::get("/takers")]
+#[instrument(name = "GET /takers", skip_all, err)]
+pub async fn get_takers(
+    maker: &State<Maker>,
+    _user: User,
+) -> Result<Json<Vec<TakerAnalytics>>, HttpApiProblem> {
+    let analytics = maker.taker_analytics().await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not load taker analytics")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(analytics))
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct HealthCheck {
     daemon_version: String,
@@ -0,0 +1,121 @@
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use daemon::bdk::FeeRate;
+use daemon::wallet;
+use model::Timestamp;
+use serde::Serialize;
+use std::time::Duration;
+use tokio::sync::watch;
+use xtra::prelude::MessageChannel;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// How often the wallet is checked against the target UTXO distribution.
+const MANAGE_UTXOS_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// State of the UTXO management job as reported in the feed.
+#[derive(Debug, Clone, Serialize)]
+pub struct Status {
+    #[serde(flatten)]
+    pub distribution: wallet::UtxoDistribution,
+    pub max_sat_per_vb: f32,
+    /// Confirmed, spendable UTXOs of at least the target size as of the last check
+    pub usable_utxos: Option<usize>,
+    pub last_checked_at: Option<Timestamp>,
+    pub last_transaction: Option<wallet::UtxoManagementTx>,
+    pub last_error: Option<String>,
+}
+
+/// Periodically asks the wallet to split or consolidate UTXOs towards a target distribution.
+pub struct Actor {
+    wallet: MessageChannel<wallet::ManageUtxos, Result<wallet::UtxoManagementReport>>,
+    distribution: wallet::UtxoDistribution,
+    max_fee_rate: FeeRate,
+    status: Status,
+    feed: watch::Sender<Option<Status>>,
+}
+
+impl Actor {
+    pub fn new(
+        wallet: MessageChannel<wallet::ManageUtxos, Result<wallet::UtxoManagementReport>>,
+        distribution: wallet::UtxoDistribution,
+        max_fee_rate: FeeRate,
+    ) -> (Self, watch::Receiver<Option<Status>>) {
+        let status = Status {
+            distribution,
+            max_sat_per_vb: max_fee_rate.as_sat_vb(),
+            usable_utxos: None,
+            last_checked_at: None,
+            last_transaction: None,
+            last_error: None,
+        };
+        let (feed, receiver) = watch::channel(Some(status.clone()));
+
+        let actor = Self {
+            wallet,
+            distribution,
+            max_fee_rate,
+            status,
+            feed,
+        };
+
+        (actor, receiver)
+    }
+
+    async fn manage_utxos(&mut self) -> Result<wallet::UtxoManagementReport> {
+        self.wallet
+            .send(wallet::ManageUtxos {
+                distribution: self.distribution,
+                max_fee_rate: self.max_fee_rate,
+            })
+            .await
+            .context("Wallet actor disconnected")?
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we are alive");
+
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                MANAGE_UTXOS_INTERVAL,
+                || ManageUtxos,
+                xtras::IncludeSpan::Always,
+            ),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: ManageUtxos) {
+        self.status.last_checked_at = Some(Timestamp::now());
+
+        match self.manage_utxos().await {
+            Ok(report) => {
+                self.status.usable_utxos = Some(report.usable_utxos);
+                self.status.last_error = None;
+
+                if let Some(transaction) = report.transaction {
+                    self.status.last_transaction = Some(transaction);
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Failed to manage UTXOs: {e:#}");
+                self.status.last_error = Some(format!("{e:#}"));
+            }
+        }
+
+        let _ = self.feed.send(Some(self.status.clone()));
+    }
+}
+
+struct ManageUtxos;
@@ -0,0 +1,54 @@
+use crate::liquidity;
+use crate::routes::execute_cfd_action;
+use crate::routes::Maker;
+use async_trait::async_trait;
+use daemon::projection::CfdAction;
+use http_api_problem::HttpApiProblem;
+use model::OrderId;
+use serde::Deserialize;
+use serde_json::Value;
+use shared_bin::websocket::CommandHandler;
+
+/// Commands the maker accepts over the WebSocket API.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Command {
+    AcceptOrder { order_id: OrderId },
+    RejectOrder { order_id: OrderId },
+    AcceptSettlement { order_id: OrderId },
+    RejectSettlement { order_id: OrderId },
+    Commit { order_id: OrderId },
+}
+
+pub struct Commands {
+    maker: Maker,
+    liquidity_actor: xtra::Address<liquidity::Actor>,
+}
+
+impl Commands {
+    pub fn new(maker: Maker, liquidity_actor: xtra::Address<liquidity::Actor>) -> Self {
+        Self {
+            maker,
+            liquidity_actor,
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for Commands {
+    type Command = Command;
+
+    async fn handle(&self, command: Command) -> Result<Value, HttpApiProblem> {
+        let (order_id, action) = match command {
+            Command::AcceptOrder { order_id } => (order_id, CfdAction::AcceptOrder),
+            Command::RejectOrder { order_id } => (order_id, CfdAction::RejectOrder),
+            Command::AcceptSettlement { order_id } => (order_id, CfdAction::AcceptSettlement),
+            Command::RejectSettlement { order_id } => (order_id, CfdAction::RejectSettlement),
+            Command::Commit { order_id } => (order_id, CfdAction::Commit),
+        };
+
+        execute_cfd_action(&self.maker, &self.liquidity_actor, order_id, action).await?;
+
+        Ok(Value::Null)
+    }
+}
@@ -25,7 +25,9 @@ use crate::Position;
 use crate::Price;
 use crate::Timestamp;
 use crate::TxFeeRate;
-use crate::SETTLEMENT_INTERVAL;
+use crate::MAX_SETTLEMENT_INTERVAL;
+use crate::MAX_TIME_TO_EXPIRY;
+use crate::MIN_SETTLEMENT_INTERVAL;
 use anyhow::anyhow;
 use anyhow::bail;
 use anyhow::ensure;
@@ -69,7 +71,7 @@ pub const CET_TIMELOCK: u32 = 12;
 // TODO: Clean this up to be a separate type
 pub type OfferId = OrderId;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct OrderId(Uuid);
 
 impl Serialize for OrderId {
@@ -140,6 +142,149 @@ impl From<Origin> for Role {
     }
 }
 
+/// The kind of contract a CFD represents
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ContractType {
+    /// Kept open indefinitely by rolling over every settlement interval
+    Perpetual,
+    /// Settles once at the oracle price of `expiry` and is never rolled over
+    Dated {
+        #[serde(with = "time::serde::timestamp")]
+        expiry: OffsetDateTime,
+    },
+}
+
+impl ContractType {
+    /// Create a dated contract expiring at `expiry`
+    ///
+    /// The oracle attests to prices on the hour, so the expiry has to be on the hour as well. The
+    /// expiry has to be in the future, at most [`MAX_TIME_TO_EXPIRY`] from `now`.
+    pub fn dated(expiry: OffsetDateTime, now: OffsetDateTime) -> Result<Self> {
+        ensure!(
+            expiry.minute() == 0 && expiry.second() == 0 && expiry.nanosecond() == 0,
+            "Expiry {expiry} is not on the hour"
+        );
+        ensure!(expiry > now, "Expiry {expiry} is in the past");
+        ensure!(
+            expiry <= now + MAX_TIME_TO_EXPIRY,
+            "Expiry {expiry} is more than {} days away",
+            MAX_TIME_TO_EXPIRY.whole_days()
+        );
+
+        Ok(Self::Dated { expiry })
+    }
+
+    pub fn expiry(&self) -> Option<OffsetDateTime> {
+        match self {
+            ContractType::Perpetual => None,
+            ContractType::Dated { expiry } => Some(*expiry),
+        }
+    }
+
+    /// The number of hours of funding charged upfront when opening a position
+    ///
+    /// Perpetual CFDs pay funding for their whole settlement interval. Dated futures do not pay
+    /// funding, the cost of holding the position until expiry is priced into the offer.
+    pub fn initial_funding_hours(&self, settlement_interval: Duration) -> i64 {
+        match self {
+            ContractType::Perpetual => settlement_interval.whole_hours(),
+            ContractType::Dated { .. } => 0,
+        }
+    }
+}
+
+impl Default for ContractType {
+    fn default() -> Self {
+        ContractType::Perpetual
+    }
+}
+
+/// How finely the oracle events of a CFD resolve its payouts
+///
+/// The payout curve is discretised into `n_payouts` intervals and liquidations are checked at
+/// oracle events `liquidation_interval` apart. Every payout interval of every event requires one
+/// CET, so a finer granularity makes payouts and liquidations more precise at the cost of more
+/// CETs to build, sign and exchange during contract setup and rollover.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PayoutGranularity {
+    pub n_payouts: usize,
+    pub liquidation_interval: Duration,
+}
+
+impl PayoutGranularity {
+    pub const DEFAULT_N_PAYOUTS: usize = 200;
+    pub const MIN_N_PAYOUTS: usize = 10;
+    pub const MAX_N_PAYOUTS: usize = 1000;
+
+    pub const DEFAULT_LIQUIDATION_INTERVAL: Duration = Duration::HOUR;
+    pub const MAX_LIQUIDATION_INTERVAL: Duration = Duration::hours(24);
+
+    pub fn new(n_payouts: usize, liquidation_interval: Duration) -> Result<Self> {
+        let granularity = Self {
+            n_payouts,
+            liquidation_interval,
+        };
+        granularity.validate()?;
+
+        Ok(granularity)
+    }
+
+    /// Check that the granularity is within the bounds both parties support
+    ///
+    /// The oracle attests to prices on the hour, so the liquidation interval has to be a whole
+    /// number of hours.
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            (Self::MIN_N_PAYOUTS..=Self::MAX_N_PAYOUTS).contains(&self.n_payouts),
+            "Number of payouts has to be between {} and {}, got {}",
+            Self::MIN_N_PAYOUTS,
+            Self::MAX_N_PAYOUTS,
+            self.n_payouts
+        );
+        ensure!(
+            self.liquidation_interval >= Duration::HOUR
+                && self.liquidation_interval <= Self::MAX_LIQUIDATION_INTERVAL
+                && self.liquidation_interval.whole_seconds() % Duration::HOUR.whole_seconds() == 0,
+            "Liquidation interval has to be between 1 and {} whole hours, got {}",
+            Self::MAX_LIQUIDATION_INTERVAL.whole_hours(),
+            self.liquidation_interval
+        );
+
+        Ok(())
+    }
+
+    /// The oracle events a CFD settling at the event right after `end` is set up with
+    ///
+    /// Starts from the event right after `start` and includes an event every
+    /// `liquidation_interval`. The last event is the settlement event.
+    pub fn oracle_events(
+        &self,
+        start: OffsetDateTime,
+        end: OffsetDateTime,
+        contract_symbol: ContractSymbol,
+    ) -> Result<Vec<BitMexPriceEventId>> {
+        let mut event_ids =
+            olivia::spaced_hourly_events(start, end, self.liquidation_interval, contract_symbol)?;
+
+        let settlement_event_id = olivia::next_announcement_after(end, contract_symbol);
+        if event_ids.last() != Some(&settlement_event_id) {
+            event_ids.push(settlement_event_id);
+        }
+
+        Ok(event_ids)
+    }
+}
+
+impl Default for PayoutGranularity {
+    fn default() -> Self {
+        Self {
+            n_payouts: Self::DEFAULT_N_PAYOUTS,
+            liquidation_interval: Self::DEFAULT_LIQUIDATION_INTERVAL,
+        }
+    }
+}
+
 /// A concrete order created by a maker for a taker
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Offer {
@@ -162,8 +307,14 @@ pub struct Offer {
     pub creation_timestamp_maker: Timestamp,
 
     /// The duration that will be used for calculating the settlement timestamp
+    ///
+    /// For dated futures this covers the time until expiry.
     pub settlement_interval: Duration,
 
+    pub contract_type: ContractType,
+
+    pub granularity: PayoutGranularity,
+
     /// The id of the event to be used for price attestation
     ///
     /// The maker includes this into the Order based on the Oracle announcement to be used.
@@ -183,6 +334,8 @@ impl Offer {
         min_quantity: Contracts,
         max_quantity: Contracts,
         settlement_interval: Duration,
+        contract_type: ContractType,
+        granularity: PayoutGranularity,
         tx_fee_rate: TxFeeRate,
         funding_rate: FundingRate,
         opening_fee: OpeningFee,
@@ -190,10 +343,20 @@ impl Offer {
         contract_symbol: ContractSymbol,
         lot_size: LotSize,
     ) -> Self {
-        let oracle_event_id = olivia::next_announcement_after(
-            time::OffsetDateTime::now_utc() + settlement_interval,
-            contract_symbol,
-        );
+        let now = wall_clock::now_utc();
+
+        // Dated futures settle at the oracle event of their expiry, the settlement interval is
+        // extended to the expiry so that the refund timelock is derived from it
+        let (settlement_interval, oracle_event_id) = match contract_type {
+            ContractType::Perpetual => (
+                settlement_interval,
+                olivia::next_announcement_after(now + settlement_interval, contract_symbol),
+            ),
+            ContractType::Dated { expiry } => (
+                Duration::hours((expiry - now).whole_hours() + 1),
+                BitMexPriceEventId::with_20_digits(expiry, contract_symbol),
+            ),
+        };
 
         Offer {
             id: OfferId::default(),
@@ -205,6 +368,8 @@ impl Offer {
             position_maker,
             creation_timestamp_maker: Timestamp::now(),
             settlement_interval,
+            contract_type,
+            granularity,
             oracle_event_id,
             tx_fee_rate,
             funding_rate,
@@ -213,6 +378,9 @@ impl Offer {
         }
     }
 
+    /// Dated futures closer to their expiry than this are not offered anymore.
+    const MIN_TIME_TO_EXPIRY: Duration = Duration::HOUR;
+
     /// Defines when we consider an order to be outdated
     ///
     /// If the maker's offer creation timestamp is older than `OUTDATED_AFTER_MINS` minutes then we
@@ -223,7 +391,14 @@ impl Offer {
     ///
     /// This is used as a safety net to prevent the taker from taking an outdated order.
     pub fn is_safe_to_take(&self, now: OffsetDateTime) -> bool {
-        !self.is_creation_timestamp_outdated(now) && self.is_oracle_event_timestamp_sane(now)
+        let is_oracle_event_sane = match self.contract_type {
+            ContractType::Perpetual => self.is_oracle_event_timestamp_sane(now),
+            ContractType::Dated { expiry } => self.is_expiry_oracle_event_sane(expiry, now),
+        };
+
+        !self.is_creation_timestamp_outdated(now)
+            && is_oracle_event_sane
+            && self.granularity.validate().is_ok()
     }
 
     /// Check if the the maker's offer creation timestamp is outdated
@@ -237,16 +412,39 @@ impl Offer {
 
     /// Check the oracle event's timestamp for sanity
     ///
-    /// An id within [25h, 23h] from now is considered sane.
+    /// The settlement interval has to be within [`MIN_SETTLEMENT_INTERVAL`,
+    /// `MAX_SETTLEMENT_INTERVAL`]. An id within one hour of the settlement interval from now is
+    /// considered sane, i.e. [23h, 25h] for a settlement interval of 24h.
     fn is_oracle_event_timestamp_sane(&self, now: OffsetDateTime) -> bool {
+        if self.settlement_interval < MIN_SETTLEMENT_INTERVAL
+            || self.settlement_interval > MAX_SETTLEMENT_INTERVAL
+        {
+            return false;
+        }
+
         let event_id_timestamp = self.oracle_event_id.timestamp();
 
-        let settlement_interval_minus_one_hour = now + SETTLEMENT_INTERVAL - Duration::HOUR;
-        let settlement_interval_plus_one_hour = now + SETTLEMENT_INTERVAL + Duration::HOUR;
+        let settlement_interval_minus_one_hour = now + self.settlement_interval - Duration::HOUR;
+        let settlement_interval_plus_one_hour = now + self.settlement_interval + Duration::HOUR;
 
         event_id_timestamp >= settlement_interval_minus_one_hour
             && event_id_timestamp <= settlement_interval_plus_one_hour
     }
+
+    /// Check the oracle event of a dated future for sanity
+    ///
+    /// The event has to be the one of the expiry, the expiry has to be at least
+    /// `MIN_TIME_TO_EXPIRY` and at most [`MAX_TIME_TO_EXPIRY`] away and the settlement interval has
+    /// to cover it, otherwise the refund transaction could become valid before the oracle attests.
+    /// The settlement interval must not exceed [`MAX_TIME_TO_EXPIRY`] either, as it determines the
+    /// refund timelock.
+    fn is_expiry_oracle_event_sane(&self, expiry: OffsetDateTime, now: OffsetDateTime) -> bool {
+        self.oracle_event_id == BitMexPriceEventId::with_20_digits(expiry, self.contract_symbol)
+            && expiry >= now + Self::MIN_TIME_TO_EXPIRY
+            && expiry <= now + MAX_TIME_TO_EXPIRY
+            && expiry <= now + self.settlement_interval
+            && self.settlement_interval <= MAX_TIME_TO_EXPIRY
+    }
 }
 
 /// Proposed collaborative settlement
@@ -277,6 +475,8 @@ pub enum CannotRollover {
     Closed,
     #[error("Cannot rollover CFD without events")]
     NoEvents,
+    #[error("Dated futures are never rolled over")]
+    DatedFuture,
 }
 
 /// Reasons why we cannot collab close a CFD
@@ -446,6 +646,11 @@ impl EventKind {
     pub const CONTRACT_SETUP_STARTED: &'static str = "ContractSetupCompleted";
     pub const CONTRACT_SETUP_COMPLETED_EVENT: &'static str = "ContractSetupCompleted";
     pub const ROLLOVER_COMPLETED_EVENT: &'static str = "RolloverCompleted";
+    pub const ROLLOVER_REJECTED: &'static str = "RolloverRejected";
+    pub const ROLLOVER_FAILED: &'static str = "RolloverFailed";
+    pub const COLLABORATIVE_SETTLEMENT_PROPOSAL_ACCEPTED: &'static str =
+        "CollaborativeSettlementProposalAccepted";
+    pub const COLLABORATIVE_SETTLEMENT_REJECTED: &'static str = "CollaborativeSettlementRejected";
     pub const COLLABORATIVE_SETTLEMENT_CONFIRMED: &'static str = "CollaborativeSettlementConfirmed";
     pub const CET_CONFIRMED: &'static str = "CetConfirmed";
     pub const REFUND_CONFIRMED: &'static str = "RefundConfirmed";
@@ -508,6 +713,8 @@ pub struct Cfd {
     opening_fee: OpeningFee,
     initial_tx_fee_rate: TxFeeRate,
     contract_symbol: ContractSymbol,
+    contract_type: ContractType,
+    granularity: PayoutGranularity,
     // dynamic (based on events)
     fee_account: FeeAccount,
 
@@ -563,6 +770,8 @@ impl Cfd {
         initial_funding_rate: FundingRate,
         initial_tx_fee_rate: TxFeeRate,
         contract_symbol: ContractSymbol,
+        contract_type: ContractType,
+        granularity: PayoutGranularity,
     ) -> Self {
         let (long_leverage, short_leverage) =
             long_and_short_leverage(taker_leverage, role, position);
@@ -573,7 +782,7 @@ impl Cfd {
             long_leverage,
             short_leverage,
             initial_funding_rate,
-            SETTLEMENT_INTERVAL.whole_hours(),
+            contract_type.initial_funding_hours(settlement_interval),
             contract_symbol,
         )
         .expect("values from db to be sane");
@@ -595,6 +804,8 @@ impl Cfd {
             opening_fee,
             initial_tx_fee_rate,
             contract_symbol,
+            contract_type,
+            granularity,
             dlc: None,
             cet: None,
             commit_tx: None,
@@ -646,6 +857,8 @@ impl Cfd {
             offer.funding_rate,
             offer.tx_fee_rate,
             offer.contract_symbol,
+            offer.contract_type,
+            offer.granularity,
         )
     }
 
@@ -701,7 +914,7 @@ impl Cfd {
 
         let expiry_timestamp = dlc.settlement_event_id.timestamp();
         let time_until_expiry = expiry_timestamp - now;
-        if time_until_expiry > SETTLEMENT_INTERVAL - Duration::HOUR {
+        if time_until_expiry > self.settlement_interval - Duration::HOUR {
             return Err(CannotRollover::TooRecent);
         }
 
@@ -709,6 +922,10 @@ impl Cfd {
     }
 
     fn can_rollover(&self) -> Result<(), CannotRollover> {
+        if let ContractType::Dated { .. } = self.contract_type {
+            return Err(CannotRollover::DatedFuture);
+        }
+
         if self.is_closed() {
             return Err(CannotRollover::Closed);
         }
@@ -881,9 +1098,12 @@ impl Cfd {
             bail!("Can only accept proposal as a maker");
         }
 
-        let now = OffsetDateTime::now_utc();
-        let to_event_ids =
-            olivia::hourly_events(now, now + self.settlement_interval, self.contract_symbol)?;
+        let now = wall_clock::now_utc();
+        let to_event_ids = self.granularity.oracle_events(
+            now,
+            now + self.settlement_interval,
+            self.contract_symbol,
+        )?;
         let settlement_event_id = to_event_ids.last().context("Empty to_event_ids")?;
 
         // If a `from_event_id` was specified we use it, otherwise we use the
@@ -941,6 +1161,7 @@ impl Cfd {
                 tx_fee_rate,
                 rollover_fee_account,
                 funding_fee,
+                self.granularity.n_payouts,
             ),
             self.dlc.clone().context("No DLC present")?,
             self.position,
@@ -965,10 +1186,13 @@ impl Cfd {
 
         self.can_rollover()?;
 
-        let now = OffsetDateTime::now_utc();
+        let now = wall_clock::now_utc();
 
-        let to_event_ids =
-            olivia::hourly_events(now, now + self.settlement_interval, self.contract_symbol)?;
+        let to_event_ids = self.granularity.oracle_events(
+            now,
+            now + self.settlement_interval,
+            self.contract_symbol,
+        )?;
 
         ensure!(
             to_event_ids == maker_to_event_ids,
@@ -1006,6 +1230,7 @@ impl Cfd {
                 tx_fee_rate,
                 self.fee_account,
                 funding_fee,
+                self.granularity.n_payouts,
             ),
             self.dlc.clone().context("No DLC present")?,
             self.position,
@@ -1015,7 +1240,6 @@ impl Cfd {
     pub fn start_collab_settlement_taker(
         self,
         current_price: Price,
-        n_payouts: usize,
     ) -> Result<(CfdEvent, SettlementTransaction, SettlementProposal)> {
         ensure!(!self.is_in_collaborative_settlement());
         ensure!(self.role == Role::Taker);
@@ -1023,7 +1247,7 @@ impl Cfd {
             .context("Cannot collaboratively settle")?;
 
         let (collab_settlement_tx, proposal) =
-            self.make_proposal(current_price, n_payouts, InverseMaxPrice::OliviaMax)?;
+            self.make_proposal(current_price, InverseMaxPrice::OliviaMax)?;
 
         Ok((
             CfdEvent::new(
@@ -1043,12 +1267,10 @@ impl Cfd {
     pub fn start_collab_settlement_maker_olivia_max(
         self,
         current_price: Price,
-        n_payouts: usize,
         proposed_settlement_transaction: &Transaction,
     ) -> Result<(CfdEvent, SettlementTransaction, SettlementProposal)> {
         self.start_collab_settlement_maker(
             current_price,
-            n_payouts,
             proposed_settlement_transaction,
             InverseMaxPrice::OliviaMax,
         )
@@ -1062,12 +1284,10 @@ impl Cfd {
     pub fn start_collab_settlement_maker_double_initial(
         self,
         current_price: Price,
-        n_payouts: usize,
         proposed_settlement_transaction: &Transaction,
     ) -> Result<(CfdEvent, SettlementTransaction, SettlementProposal)> {
         self.start_collab_settlement_maker(
             current_price,
-            n_payouts,
             proposed_settlement_transaction,
             InverseMaxPrice::DoubleOfInitial,
         )
@@ -1076,7 +1296,6 @@ impl Cfd {
     fn start_collab_settlement_maker(
         self,
         current_price: Price,
-        n_payouts: usize,
         proposed_settlement_transaction: &Transaction,
         inverse_max_price_config: InverseMaxPrice,
     ) -> Result<(CfdEvent, SettlementTransaction, SettlementProposal)> {
@@ -1086,7 +1305,7 @@ impl Cfd {
             .context("Cannot collaboratively settle")?;
 
         let (settlement_tx, proposal) =
-            self.make_proposal(current_price, n_payouts, inverse_max_price_config)?;
+            self.make_proposal(current_price, inverse_max_price_config)?;
 
         let local_settlement_transaction = settlement_tx.unsigned_transaction();
 
@@ -1108,7 +1327,6 @@ impl Cfd {
     fn make_proposal(
         self,
         current_price: Price,
-        n_payouts: usize,
         inverse_max_price_config: InverseMaxPrice,
     ) -> Result<(SettlementTransaction, SettlementProposal)> {
         let payouts = match self.contract_symbol {
@@ -1117,7 +1335,7 @@ impl Cfd {
                 self.initial_price,
                 self.quantity,
                 (self.long_leverage, self.short_leverage),
-                n_payouts,
+                self.granularity.n_payouts,
                 self.fee_account.settle(),
                 inverse_max_price_config,
             )?,
@@ -1126,7 +1344,7 @@ impl Cfd {
                 self.initial_price.to_u64(),
                 self.quantity.to_u64(),
                 (self.long_leverage, self.short_leverage),
-                n_payouts,
+                self.granularity.n_payouts,
                 ETHUSD_MULTIPLIER,
                 self.fee_account.settle(),
             )?,
@@ -1479,6 +1697,14 @@ impl Cfd {
         self.contract_symbol
     }
 
+    pub fn contract_type(&self) -> ContractType {
+        self.contract_type
+    }
+
+    pub fn granularity(&self) -> PayoutGranularity {
+        self.granularity
+    }
+
     pub fn opening_fee(&self) -> OpeningFee {
         self.opening_fee
     }
@@ -1515,7 +1741,7 @@ impl Cfd {
         if !hours_left.is_positive() {
             tracing::warn!("Rolling over a contract that can be settled non-collaboratively");
 
-            return Ok(SETTLEMENT_INTERVAL.whole_hours() as u64);
+            return Ok(self.settlement_interval.whole_hours() as u64);
         }
 
         let to_settlement_time = to_event_id.timestamp();
@@ -2443,6 +2669,8 @@ impl CollaborativeSettlement {
 #[cfg(test)]
 mod tests {
     use crate::Percent;
+    use crate::DEFAULT_SETTLEMENT_INTERVAL;
+    use crate::FUNDING_INTERVAL;
 
     use super::*;
     use bdk::bitcoin;
@@ -2977,6 +3205,37 @@ mod tests {
         assert_eq!(cannot_roll_over, CannotRollover::TooRecent)
     }
 
+    #[test]
+    fn given_dated_cfd_then_no_rollover() {
+        let expiry = datetime!(2021-12-31 08:00:00).assume_utc();
+        let offer = Offer::dummy_btc_usd_short().with_dated_expiry(expiry, Duration::days(30));
+        let cfd =
+            Cfd::taker_long_from_order(offer, Contracts::new(1000), Leverage::TWO).dummy_open(
+                BitMexPriceEventId::with_20_digits(expiry, ContractSymbol::BtcUsd),
+            );
+
+        let cannot_roll_over = cfd
+            .can_auto_rollover_taker(datetime!(2021-12-31 07:30:00).assume_utc())
+            .unwrap_err();
+
+        assert_eq!(cannot_roll_over, CannotRollover::DatedFuture)
+    }
+
+    #[test]
+    fn given_dated_cfd_then_no_initial_funding_fee() {
+        let offer = Offer::dummy_btc_usd_short()
+            .with_funding_rate(FundingRate::new(dec!(0.0005)).unwrap())
+            .with_dated_expiry(
+                datetime!(2021-12-31 08:00:00).assume_utc(),
+                Duration::days(30),
+            );
+
+        let taker_long = Cfd::taker_long_from_order(offer, Contracts::new(1000), Leverage::TWO);
+
+        // The dummy offer does not charge an opening fee
+        assert_eq!(taker_long.fee_account.balance(), SignedAmount::ZERO);
+    }
+
     #[test]
     fn given_cfd_was_renewed_less_than_1h_ago_then_no_rollover() {
         // --|----|-------------------------------------------------|--> time
@@ -2997,6 +3256,97 @@ mod tests {
         assert_eq!(cannot_roll_over, CannotRollover::TooRecent)
     }
 
+    #[test]
+    fn given_8h_settlement_interval_then_rollover_within_7h_of_expiry() {
+        let offer = Offer::dummy_btc_usd_short().with_settlement_interval(Duration::hours(8));
+        let cfd = Cfd::taker_long_from_order(offer, Contracts::new(1000), Leverage::TWO)
+            .dummy_open(BitMexPriceEventId::with_20_digits(
+                datetime!(2021-11-18 18:00:00).assume_utc(),
+                ContractSymbol::BtcUsd,
+            ));
+
+        let cannot_roll_over = cfd
+            .can_auto_rollover_taker(datetime!(2021-11-18 10:59:59).assume_utc())
+            .unwrap_err();
+        assert_eq!(cannot_roll_over, CannotRollover::TooRecent);
+
+        let result = cfd.can_auto_rollover_taker(datetime!(2021-11-18 11:00:00).assume_utc());
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn given_8h_settlement_interval_then_initial_funding_fee_covers_8h() {
+        let funding_rate = FundingRate::new(dec!(0.0003)).unwrap();
+        let offer_24h = Offer::dummy_btc_usd_short().with_funding_rate(funding_rate);
+        let offer_8h = offer_24h
+            .clone()
+            .with_settlement_interval(Duration::hours(8));
+
+        let taker_24h = Cfd::taker_long_from_order(offer_24h, Contracts::new(1000), Leverage::TWO);
+        let taker_8h = Cfd::taker_long_from_order(offer_8h, Contracts::new(1000), Leverage::TWO);
+
+        assert_eq!(
+            taker_8h.fee_account.balance() * 3,
+            taker_24h.fee_account.balance()
+        );
+    }
+
+    #[test]
+    fn given_4h_liquidation_interval_then_every_4th_event_and_settlement_event() {
+        let granularity = PayoutGranularity::new(100, Duration::hours(4)).unwrap();
+        let now = datetime!(2021-11-18 09:40:00).assume_utc();
+
+        let event_timestamps = granularity
+            .oracle_events(now, now + Duration::hours(10), ContractSymbol::BtcUsd)
+            .unwrap()
+            .iter()
+            .map(BitMexPriceEventId::timestamp)
+            .collect_vec();
+
+        assert_eq!(
+            event_timestamps,
+            vec![
+                datetime!(2021-11-18 10:00:00).assume_utc(),
+                datetime!(2021-11-18 14:00:00).assume_utc(),
+                datetime!(2021-11-18 18:00:00).assume_utc(),
+                datetime!(2021-11-18 20:00:00).assume_utc(),
+            ]
+        );
+    }
+
+    #[test]
+    fn given_default_granularity_then_hourly_events() {
+        let now = datetime!(2021-11-18 09:40:00).assume_utc();
+        let end = now + DEFAULT_SETTLEMENT_INTERVAL;
+
+        let event_ids = PayoutGranularity::default()
+            .oracle_events(now, end, ContractSymbol::BtcUsd)
+            .unwrap();
+
+        assert_eq!(
+            event_ids,
+            olivia::hourly_events(now, end, ContractSymbol::BtcUsd).unwrap()
+        );
+    }
+
+    #[test]
+    fn given_granularity_out_of_bounds_then_rejected() {
+        assert!(PayoutGranularity::new(5, Duration::HOUR).is_err());
+        assert!(PayoutGranularity::new(2000, Duration::HOUR).is_err());
+        assert!(PayoutGranularity::new(200, Duration::minutes(90)).is_err());
+        assert!(PayoutGranularity::new(200, Duration::hours(25)).is_err());
+        assert!(PayoutGranularity::new(50, Duration::hours(6)).is_ok());
+    }
+
+    #[test]
+    fn given_offer_with_invalid_granularity_then_not_safe_to_take() {
+        let mut offer = Offer::dummy_btc_usd_short();
+        assert!(offer.is_safe_to_take(OffsetDateTime::now_utc()));
+
+        offer.granularity.n_payouts = 1;
+        assert!(!offer.is_safe_to_take(OffsetDateTime::now_utc()));
+    }
+
     #[test]
     fn given_cfd_not_locked_then_no_rollover() {
         let cfd = Cfd::dummy_not_open_yet();
@@ -3039,7 +3389,7 @@ mod tests {
             Leverage::ONE,
             Leverage::ONE,
             funding_rate,
-            SETTLEMENT_INTERVAL.whole_hours(),
+            FUNDING_INTERVAL.whole_hours(),
             ContractSymbol::BtcUsd,
         )
         .unwrap();
@@ -3308,7 +3658,7 @@ mod tests {
         // Extract unsigned tx to be able to trigger collab settlement in the maker
         let unsigned_tx = taker_long
             .clone()
-            .start_collab_settlement_taker(price, N_PAYOUTS)
+            .start_collab_settlement_taker(price)
             .unwrap()
             .1
             .unsigned_transaction()
@@ -3321,10 +3671,9 @@ mod tests {
             .with_lock(taker_keys, maker_keys)
             .dummy_commit();
 
-        let result_taker = taker_long.start_collab_settlement_taker(price, N_PAYOUTS);
+        let result_taker = taker_long.start_collab_settlement_taker(price);
         let result_maker = maker_short.start_collab_settlement_maker(
             Price::dummy(),
-            N_PAYOUTS,
             &unsigned_tx,
             InverseMaxPrice::OliviaMax,
         );
@@ -3529,7 +3878,7 @@ mod tests {
     fn given_settlement_within_24_hours_when_calculating_hours_to_extend_based_on_event_then_return_expected_hours(
     ) {
         for now in common_time_boundaries() {
-            let settlement_interval = SETTLEMENT_INTERVAL.whole_hours();
+            let settlement_interval = DEFAULT_SETTLEMENT_INTERVAL.whole_hours();
 
             let to_event_id = BitMexPriceEventId::with_20_digits(
                 now + settlement_interval.hours(),
@@ -3585,7 +3934,7 @@ mod tests {
                         event_id_1_hour_ago
                     )
                     .unwrap(),
-                SETTLEMENT_INTERVAL.whole_hours() as u64,
+                DEFAULT_SETTLEMENT_INTERVAL.whole_hours() as u64,
                 "Failed with now {}",
                 now
             );
@@ -3598,7 +3947,7 @@ mod tests {
                         event_id_1_hour_ago
                     )
                     .unwrap(),
-                SETTLEMENT_INTERVAL.whole_hours() as u64,
+                DEFAULT_SETTLEMENT_INTERVAL.whole_hours() as u64,
                 "Failed with now {}",
                 now
             );
@@ -3705,7 +4054,7 @@ mod tests {
                 leverage,
                 leverage,
                 funding_rate,
-                SETTLEMENT_INTERVAL.whole_hours(),
+                FUNDING_INTERVAL.whole_hours(),
                 ContractSymbol::BtcUsd,
             )
                 .unwrap();
@@ -3727,7 +4076,7 @@ mod tests {
 
             let total_balance_when_collected_hourly = fee_account_one_hour
                 .balance()
-                .checked_mul(SETTLEMENT_INTERVAL.whole_hours())
+                .checked_mul(FUNDING_INTERVAL.whole_hours())
                 .unwrap();
             let total_balance_when_collected_for_whole_interval = fee_account_whole_interval
                 .balance();
@@ -3874,6 +4223,142 @@ mod tests {
         assert!(!sane, "an oracle event id that is outdated got accepted")
     }
 
+    #[test]
+    fn given_8h_settlement_interval_and_oracle_event_id_8h_in_the_future_then_sane_to_take() {
+        let contract_symbol = ContractSymbol::BtcUsd;
+        let order = Offer::dummy_short(contract_symbol)
+            .with_settlement_interval(Duration::hours(8))
+            .with_oracle_event_id(BitMexPriceEventId::with_20_digits(
+                datetime!(2021-11-18 18:00:00).assume_utc(),
+                contract_symbol,
+            ));
+
+        let sane =
+            order.is_oracle_event_timestamp_sane(datetime!(2021-11-18 10:00:00).assume_utc());
+        assert!(sane);
+
+        let sane =
+            order.is_oracle_event_timestamp_sane(datetime!(2021-11-17 18:00:00).assume_utc());
+        assert!(
+            !sane,
+            "an oracle event id matching the default interval got accepted"
+        )
+    }
+
+    #[test]
+    fn given_settlement_interval_longer_than_max_then_not_sane_to_take() {
+        let contract_symbol = ContractSymbol::BtcUsd;
+        let now = datetime!(2021-11-18 10:00:00).assume_utc();
+        let settlement_interval = MAX_SETTLEMENT_INTERVAL + Duration::HOUR;
+        let order = Offer::dummy_short(contract_symbol)
+            .with_settlement_interval(settlement_interval)
+            .with_oracle_event_id(BitMexPriceEventId::with_20_digits(
+                now + settlement_interval,
+                contract_symbol,
+            ));
+
+        let sane = order.is_oracle_event_timestamp_sane(now);
+        assert!(
+            !sane,
+            "a settlement interval above the maximum got accepted"
+        )
+    }
+
+    #[test]
+    fn given_dated_offer_with_expiry_event_then_sane_to_take() {
+        let expiry = datetime!(2021-12-31 08:00:00).assume_utc();
+        let offer = Offer::dummy_btc_usd_short().with_dated_expiry(expiry, Duration::days(44));
+
+        let sane =
+            offer.is_expiry_oracle_event_sane(expiry, datetime!(2021-11-18 10:00:00).assume_utc());
+        assert!(sane)
+    }
+
+    #[test]
+    fn given_dated_offer_with_other_event_then_not_sane_to_take() {
+        let expiry = datetime!(2021-12-31 08:00:00).assume_utc();
+        let offer = Offer::dummy_btc_usd_short()
+            .with_dated_expiry(expiry, Duration::days(44))
+            .with_oracle_event_id(BitMexPriceEventId::with_20_digits(
+                datetime!(2021-11-19 10:00:00).assume_utc(),
+                ContractSymbol::BtcUsd,
+            ));
+
+        let sane =
+            offer.is_expiry_oracle_event_sane(expiry, datetime!(2021-11-18 10:00:00).assume_utc());
+        assert!(
+            !sane,
+            "an oracle event id other than the expiry got accepted"
+        )
+    }
+
+    #[test]
+    fn given_dated_offer_close_to_expiry_then_not_sane_to_take() {
+        let expiry = datetime!(2021-12-31 08:00:00).assume_utc();
+        let offer = Offer::dummy_btc_usd_short().with_dated_expiry(expiry, Duration::days(44));
+
+        let sane =
+            offer.is_expiry_oracle_event_sane(expiry, datetime!(2021-12-31 07:30:00).assume_utc());
+        assert!(!sane, "a dated offer about to expire got accepted")
+    }
+
+    #[test]
+    fn given_dated_offer_with_settlement_interval_short_of_expiry_then_not_sane_to_take() {
+        let expiry = datetime!(2021-12-31 08:00:00).assume_utc();
+        let offer = Offer::dummy_btc_usd_short().with_dated_expiry(expiry, Duration::days(1));
+
+        let sane =
+            offer.is_expiry_oracle_event_sane(expiry, datetime!(2021-11-18 10:00:00).assume_utc());
+        assert!(
+            !sane,
+            "a dated offer whose refund timelock precedes the expiry got accepted"
+        )
+    }
+
+    #[test]
+    fn given_dated_offer_expiring_after_max_time_to_expiry_then_not_sane_to_take() {
+        let now = datetime!(2021-11-18 10:00:00).assume_utc();
+        let expiry = now + MAX_TIME_TO_EXPIRY + Duration::HOUR;
+        let offer = Offer::dummy_btc_usd_short()
+            .with_dated_expiry(expiry, MAX_TIME_TO_EXPIRY + Duration::HOUR);
+
+        let sane = offer.is_expiry_oracle_event_sane(expiry, now);
+        assert!(
+            !sane,
+            "a dated offer expiring after the maximum time to expiry got accepted"
+        )
+    }
+
+    #[test]
+    fn given_dated_offer_with_settlement_interval_above_max_time_to_expiry_then_not_sane_to_take() {
+        let now = datetime!(2021-11-18 10:00:00).assume_utc();
+        let expiry = datetime!(2021-12-31 08:00:00).assume_utc();
+        let offer = Offer::dummy_btc_usd_short().with_dated_expiry(expiry, Duration::days(365));
+
+        let sane = offer.is_expiry_oracle_event_sane(expiry, now);
+        assert!(
+            !sane,
+            "a dated offer whose refund timelock is too far in the future got accepted"
+        )
+    }
+
+    #[test]
+    fn dated_contract_type_requires_expiry_on_the_hour() {
+        let now = datetime!(2021-11-18 10:00:00).assume_utc();
+
+        assert!(ContractType::dated(datetime!(2021-12-31 08:00:00).assume_utc(), now).is_ok());
+        assert!(ContractType::dated(datetime!(2021-12-31 08:30:00).assume_utc(), now).is_err());
+    }
+
+    #[test]
+    fn dated_contract_type_requires_expiry_within_max_time_to_expiry() {
+        let now = datetime!(2021-11-18 10:00:00).assume_utc();
+
+        assert!(ContractType::dated(now + MAX_TIME_TO_EXPIRY, now).is_ok());
+        assert!(ContractType::dated(now + MAX_TIME_TO_EXPIRY + Duration::HOUR, now).is_err());
+        assert!(ContractType::dated(now, now).is_err());
+    }
+
     impl CfdEvent {
         fn dummy_open(event_id: BitMexPriceEventId) -> Vec<Self> {
             vec![
@@ -4122,16 +4607,13 @@ mod tests {
         ) {
             let mut events = Vec::new();
 
-            let (propose, settlement_transaction, settlement_proposal) = self
-                .clone()
-                .start_collab_settlement_taker(price, N_PAYOUTS)
-                .unwrap();
+            let (propose, settlement_transaction, settlement_proposal) =
+                self.clone().start_collab_settlement_taker(price).unwrap();
             events.push(propose);
 
             let (_, maker_transaction, _) = maker_cfd
                 .start_collab_settlement_maker(
                     price,
-                    N_PAYOUTS,
                     settlement_transaction.unsigned_transaction(),
                     InverseMaxPrice::OliviaMax,
                 )
@@ -4167,12 +4649,7 @@ mod tests {
 
             let (incoming_settlement, transaction, _) = self
                 .clone()
-                .start_collab_settlement_maker(
-                    price,
-                    N_PAYOUTS,
-                    taker_unsigned_tx,
-                    InverseMaxPrice::OliviaMax,
-                )
+                .start_collab_settlement_maker(price, taker_unsigned_tx, InverseMaxPrice::OliviaMax)
                 .unwrap();
             events.push(incoming_settlement);
 
@@ -4271,6 +4748,8 @@ mod tests {
                 Contracts::new(100),
                 Contracts::new(1000),
                 time::Duration::hours(24),
+                ContractType::Perpetual,
+                PayoutGranularity::default(),
                 TxFeeRate::default(),
                 FundingRate::default(),
                 OpeningFee::default(),
@@ -4294,6 +4773,26 @@ mod tests {
             self
         }
 
+        fn with_settlement_interval(mut self, settlement_interval: Duration) -> Self {
+            self.settlement_interval = settlement_interval;
+            self.oracle_event_id = olivia::next_announcement_after(
+                OffsetDateTime::now_utc() + settlement_interval,
+                self.contract_symbol,
+            );
+            self
+        }
+
+        fn with_dated_expiry(
+            mut self,
+            expiry: OffsetDateTime,
+            settlement_interval: Duration,
+        ) -> Self {
+            self.contract_type = ContractType::Dated { expiry };
+            self.settlement_interval = settlement_interval;
+            self.oracle_event_id = BitMexPriceEventId::with_20_digits(expiry, self.contract_symbol);
+            self
+        }
+
         fn with_creation_timestamp(mut self, creation_timestamp: Timestamp) -> Self {
             self.creation_timestamp_maker = creation_timestamp;
             self
@@ -4516,8 +5015,6 @@ mod tests {
     /// party.
     const TX_FEE_COLLAB_SETTLEMENT: u64 = 85;
 
-    const N_PAYOUTS: usize = 200;
-
     fn new_keypair() -> (SecretKey, PublicKey) {
         let (sk, pk) = keypair::new(&mut thread_rng());
         (sk, pk)
@@ -8,6 +8,7 @@ use bdk::bitcoin::Denomination;
 use bdk::bitcoin::Network;
 use bdk::bitcoin::SignedAmount;
 use bdk::bitcoin::Txid;
+use bdk::BlockTime;
 use bdk::TransactionDetails;
 use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
@@ -24,8 +25,6 @@ use std::ops::Div;
 use std::ops::Mul;
 use std::ops::Sub;
 use std::str;
-use std::time::SystemTime;
-use std::time::UNIX_EPOCH;
 use strum_macros::Display;
 use strum_macros::EnumIter;
 use time::OffsetDateTime;
@@ -48,12 +47,32 @@ pub use rollover::BaseDlcParams;
 pub use rollover::RolloverParams;
 pub use transaction_ext::TransactionExt;
 
-/// The time-to-live of a CFD after it is first created or rolled
-/// over.
+/// The default time-to-live of a CFD after it is first created or
+/// rolled over.
 ///
-/// This variable determines what oracle event ID will be associated
-/// with the non-collaborative settlement of the CFD.
-pub const SETTLEMENT_INTERVAL: time::Duration = time::Duration::hours(24);
+/// The settlement interval is chosen by the maker per offer and
+/// stored with the CFD. It determines what oracle event ID will be
+/// associated with the non-collaborative settlement of the CFD. This
+/// default is used if the maker does not specify one.
+pub const DEFAULT_SETTLEMENT_INTERVAL: time::Duration = time::Duration::hours(24);
+
+/// The shortest settlement interval a maker can offer.
+pub const MIN_SETTLEMENT_INTERVAL: time::Duration = time::Duration::hours(2);
+
+/// The longest settlement interval a maker can offer.
+pub const MAX_SETTLEMENT_INTERVAL: time::Duration = time::Duration::days(7);
+
+/// The furthest into the future a dated future can expire.
+///
+/// The refund timelock of a dated future is past its expiry, this bounds how long the funds of an
+/// unresolved dated future can be locked up.
+pub const MAX_TIME_TO_EXPIRY: time::Duration = time::Duration::days(90);
+
+/// The period a [`FundingRate`] applies to.
+///
+/// Funding rates are independent of the settlement interval of a
+/// CFD, funding is charged pro-rata for the hours a CFD is extended.
+pub const FUNDING_INTERVAL: time::Duration = time::Duration::hours(24);
 
 /// Represents "quantity" or "contract size" in Cfd terms
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
@@ -458,6 +477,28 @@ pub struct WalletInfo {
     pub address: Address,
     pub last_updated_at: Timestamp,
     pub transactions: Vec<TransactionDetails>,
+    pub withdrawals: Vec<Withdrawal>,
+}
+
+/// A withdrawal from the wallet to an external address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Withdrawal {
+    pub txid: Txid,
+    pub address: Address,
+    /// Amount received by `address`
+    pub amount: Amount,
+    pub fee: Amount,
+    pub created_at: Timestamp,
+    pub confirmation_time: Option<BlockTime>,
+    /// The transaction which replaced this withdrawal by bumping its fee
+    pub replaced_by: Option<Txid>,
+}
+
+impl Withdrawal {
+    /// Whether the fee of this withdrawal can still be bumped
+    pub fn is_pending(&self) -> bool {
+        self.confirmation_time.is_none() && self.replaced_by.is_none()
+    }
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
@@ -469,14 +510,7 @@ impl Timestamp {
     }
 
     pub fn now() -> Self {
-        let seconds: i64 = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("time not to go backwards")
-            .as_secs()
-            .try_into()
-            .expect("seconds of system time to fit into i64");
-
-        Self(seconds)
+        Self(wall_clock::now_utc().unix_timestamp())
     }
 
     pub fn seconds(&self) -> i64 {
@@ -489,7 +523,7 @@ impl Timestamp {
     }
 }
 
-/// Funding rate per SETTLEMENT_INTERVAL
+/// Funding rate per FUNDING_INTERVAL
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FundingRate(Decimal);
 
@@ -615,14 +649,14 @@ impl FundingFee {
             calculate_margin(contract_symbol, price, quantity, short_leverage)
         };
 
-        let fraction_of_funding_period =
-            if hours_to_charge as i64 == SETTLEMENT_INTERVAL.whole_hours() {
-                Decimal::ONE
-            } else {
-                Decimal::from(hours_to_charge)
-                    .checked_div(Decimal::from(SETTLEMENT_INTERVAL.whole_hours()))
-                    .context("can't establish a fraction")?
-            };
+        let fraction_of_funding_period = if hours_to_charge as i64 == FUNDING_INTERVAL.whole_hours()
+        {
+            Decimal::ONE
+        } else {
+            Decimal::from(hours_to_charge)
+                .checked_div(Decimal::from(FUNDING_INTERVAL.whole_hours()))
+                .context("can't establish a fraction")?
+        };
 
         let funding_fee = Decimal::from(margin.as_sat())
             * funding_rate.to_decimal().abs()
@@ -979,6 +1013,8 @@ pub struct FailedCfd {
     pub fees: Fees,
     pub kind: FailedKind,
     pub creation_timestamp: Timestamp,
+    /// Time of the event that failed the CFD
+    pub closing_timestamp: Timestamp,
     pub contract_symbol: ContractSymbol,
 }
 
@@ -1034,6 +1070,8 @@ pub struct ClosedCfd {
     pub lock: Lock,
     pub settlement: Settlement,
     pub creation_timestamp: Timestamp,
+    /// Time of the event that closed the CFD
+    pub closing_timestamp: Timestamp,
     pub contract_symbol: ContractSymbol,
 }
 
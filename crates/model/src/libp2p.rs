@@ -3,7 +3,7 @@ use serde::Serialize;
 use std::fmt;
 use std::str::FromStr;
 
-#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct PeerId(libp2p_core::PeerId);
 
 impl fmt::Debug for PeerId {
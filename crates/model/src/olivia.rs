@@ -102,7 +102,7 @@ impl BitMexPriceEventId {
     ///
     /// We can't be sure about it because our local clock might be off from the oracle's clock.
     pub fn has_likely_occurred(&self) -> bool {
-        let now = OffsetDateTime::now_utc();
+        let now = wall_clock::now_utc();
 
         now > self.timestamp + Duration::minutes(1)
     }
@@ -193,10 +193,24 @@ pub fn hourly_events(
     start: OffsetDateTime,
     end: OffsetDateTime,
     index: impl Into<IndexPrice>,
+) -> Result<Vec<BitMexPriceEventId>> {
+    spaced_hourly_events(start, end, Duration::HOUR, index)
+}
+
+/// Produce a list of events `interval` apart, starting at the _next_
+/// hour after `start` up to the _next_ hour after `end`.
+///
+/// The event of the next hour after `end` is only included if it is a
+/// whole number of intervals after the first event.
+pub fn spaced_hourly_events(
+    start: OffsetDateTime,
+    end: OffsetDateTime,
+    interval: Duration,
+    index: impl Into<IndexPrice>,
 ) -> Result<Vec<BitMexPriceEventId>> {
     let start_adjusted = ceil_to_next_hour(start);
     let end_adjusted = ceil_to_next_hour(end);
-    let announcements = spaced_events(start_adjusted, end_adjusted, Duration::HOUR, index)?;
+    let announcements = spaced_events(start_adjusted, end_adjusted, interval, index)?;
 
     Ok(announcements)
 }
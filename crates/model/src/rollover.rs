@@ -31,6 +31,8 @@ pub struct RolloverParams {
     pub fee_rate: TxFeeRate,
     pub fee_account: FeeAccount,
     pub current_fee: FundingFee,
+    /// Number of payout intervals of the payout curve per oracle event
+    pub n_payouts: usize,
 }
 
 impl RolloverParams {
@@ -44,6 +46,7 @@ impl RolloverParams {
         fee_rate: TxFeeRate,
         fee_account: FeeAccount,
         current_fee: FundingFee,
+        n_payouts: usize,
     ) -> Self {
         Self {
             price,
@@ -54,6 +57,7 @@ impl RolloverParams {
             fee_rate,
             fee_account,
             current_fee,
+            n_payouts,
         }
     }
 
@@ -0,0 +1,111 @@
+use daemon::projection::CfdChange;
+use daemon::projection::CfdSync;
+use daemon::projection::CfdUpdate;
+use daemon::projection::CfdUpdates;
+use rocket::request::FromRequest;
+use rocket::request::Outcome;
+use rocket::response::stream::Event;
+use rocket::Request;
+use serde_json::json;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+/// The `Last-Event-ID` header sent by an `EventSource` when reconnecting
+///
+/// The CFD events carry their sequence number as event ID, which allows resuming the CFD feed
+/// after a reconnect instead of starting over with a snapshot.
+pub struct LastEventId(pub Option<u64>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LastEventId {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let last_event_id = request
+            .headers()
+            .get_one("Last-Event-ID")
+            .and_then(|id| id.parse().ok());
+
+        Outcome::Success(LastEventId(last_event_id))
+    }
+}
+
+/// Turns the CFD updates into SSE events
+///
+/// - `cfds`: all CFDs, only sent when the feed cannot be resumed
+/// - `cfd`: a single CFD that was added or changed
+/// - `cfd_removed`: the order ID of a CFD that is no longer part of the feed
+pub struct CfdEvents {
+    cfd_updates: CfdUpdates,
+    rx: broadcast::Receiver<CfdUpdate>,
+    last_sequence: Option<u64>,
+}
+
+impl CfdEvents {
+    /// Subscribe to the CFD updates, returning the events to catch up with the feed
+    pub fn subscribe(cfd_updates: CfdUpdates, last_event_id: LastEventId) -> (Self, Vec<Event>) {
+        let subscription = cfd_updates.subscribe(last_event_id.0);
+        let mut cfd_events = Self {
+            cfd_updates,
+            rx: subscription.updates,
+            last_sequence: last_event_id.0,
+        };
+        let events = cfd_events.catch_up(subscription.sync);
+
+        (cfd_events, events)
+    }
+
+    /// Wait for the next CFD events
+    ///
+    /// This is cancellation safe and can be used in `select!`.
+    pub async fn next(&mut self) -> Vec<Event> {
+        match self.rx.recv().await {
+            Ok(update) => {
+                self.last_sequence = Some(update.sequence);
+
+                vec![to_sse_event(update)]
+            }
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::debug!(%skipped, "CFD events lagged behind, resuming");
+
+                self.resubscribe()
+            }
+            Err(RecvError::Closed) => futures::future::pending().await,
+        }
+    }
+
+    fn resubscribe(&mut self) -> Vec<Event> {
+        let subscription = self.cfd_updates.subscribe(self.last_sequence);
+        self.rx = subscription.updates;
+
+        self.catch_up(subscription.sync)
+    }
+
+    fn catch_up(&mut self, sync: CfdSync) -> Vec<Event> {
+        match sync {
+            CfdSync::Snapshot { sequence, cfds } => {
+                self.last_sequence = Some(sequence);
+
+                vec![Event::json(&cfds).event("cfds").id(sequence.to_string())]
+            }
+            CfdSync::Resume(updates) => {
+                if let Some(update) = updates.last() {
+                    self.last_sequence = Some(update.sequence);
+                }
+
+                updates.into_iter().map(to_sse_event).collect()
+            }
+        }
+    }
+}
+
+fn to_sse_event(update: CfdUpdate) -> Event {
+    let event = match update.change {
+        CfdChange::Upsert(cfd) => Event::json(&cfd).event("cfd"),
+        CfdChange::Remove(order_id) => {
+            Event::json(&json!({ "order_id": order_id })).event("cfd_removed")
+        }
+    };
+
+    event.id(update.sequence.to_string())
+}
@@ -0,0 +1,551 @@
+use daemon::projection::Cfd;
+use daemon::projection::CfdState;
+use http_api_problem::HttpApiProblem;
+use http_api_problem::StatusCode;
+use model::libp2p::PeerId;
+use model::ContractSymbol;
+use model::Contracts;
+use model::Identity;
+use model::OrderId;
+use model::Position;
+use model::Timestamp;
+use rocket::FromForm;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use sqlite_db::event_log::TimelineEvent;
+use std::cmp::Ordering;
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+/// Query parameters of `GET /cfds`
+///
+/// States, symbols and positions are given as they are serialized in the feed (e.g. `Open`,
+/// `BtcUsd`, `Long`), timestamps as unix timestamps in seconds.
+#[derive(Debug, Default, FromForm)]
+pub struct CfdQuery {
+    /// Only CFDs in one of these states, can be repeated
+    state: Vec<String>,
+    symbol: Option<String>,
+    position: Option<String>,
+    /// Peer ID or legacy network identity of the counterparty
+    counterparty: Option<String>,
+    opened_after: Option<i64>,
+    opened_before: Option<i64>,
+    /// Only CFDs that were closed (or failed) in the given range, excludes open CFDs
+    closed_after: Option<i64>,
+    closed_before: Option<i64>,
+    /// `opened_at` (default), `closed_at`, `expiry` or `quantity`
+    sort: Option<String>,
+    /// `desc` (default) or `asc`
+    order: Option<String>,
+    /// Number of CFDs per page, defaults to 50, at most 500
+    limit: Option<usize>,
+    /// The `next_cursor` of the previous page
+    cursor: Option<String>,
+}
+
+/// A page of the CFDs matching a [`CfdQuery`]
+#[derive(Debug, Serialize)]
+pub struct CfdPage {
+    pub cfds: Vec<Cfd>,
+    /// Pass as `cursor` to get the next page, `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Position after the last CFD of a page, encoded as URL-safe base64 of JSON.
+///
+/// The cursor captures the sort value of the last CFD rather than referring to the CFD itself, so
+/// that the next page continues at the same position even if that CFD changes or disappears in
+/// between.
+#[derive(Debug, Serialize, Deserialize)]
+struct Cursor {
+    value: SortValue,
+    order_id: OrderId,
+    descending: bool,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("cursor to serialize");
+
+        base64::encode_config(json, base64::URL_SAFE_NO_PAD)
+    }
+
+    /// Decode a cursor, rejecting it if it was issued for a different sort or order.
+    fn decode(cursor: &str, sort: SortKey, descending: bool) -> Result<Self, HttpApiProblem> {
+        let cursor = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD)
+            .ok()
+            .and_then(|json| serde_json::from_slice::<Self>(&json).ok())
+            .ok_or_else(|| bad_request("Invalid cursor", format!("Malformed cursor `{cursor}`")))?;
+
+        if cursor.value.key() != sort || cursor.descending != descending {
+            return Err(bad_request(
+                "Invalid cursor",
+                "Cursor was issued for a different `sort` or `order`".to_owned(),
+            ));
+        }
+
+        Ok(cursor)
+    }
+}
+
+impl CfdQuery {
+    /// Filter, sort and paginate the given CFDs.
+    pub fn apply(self, cfds: Vec<Cfd>) -> Result<CfdPage, HttpApiProblem> {
+        let states = self
+            .state
+            .iter()
+            .map(|state| parse::<CfdState>("state", state))
+            .collect::<Result<Vec<_>, _>>()?;
+        let symbol = self
+            .symbol
+            .as_deref()
+            .map(|symbol| parse::<ContractSymbol>("symbol", symbol))
+            .transpose()?;
+        let position = self
+            .position
+            .as_deref()
+            .map(|position| parse::<Position>("position", position))
+            .transpose()?;
+        let counterparty = self
+            .counterparty
+            .as_deref()
+            .map(Counterparty::parse)
+            .transpose()?;
+        let sort = self
+            .sort
+            .as_deref()
+            .map(SortKey::parse)
+            .transpose()?
+            .unwrap_or(SortKey::OpenedAt);
+        let descending = match self.order.as_deref() {
+            None | Some("desc") => true,
+            Some("asc") => false,
+            Some(other) => {
+                return Err(bad_request(
+                    "Invalid order",
+                    format!("Expected `asc` or `desc`, got `{other}`"),
+                ))
+            }
+        };
+        let limit = match self.limit {
+            None => DEFAULT_LIMIT,
+            Some(limit) if (1..=MAX_LIMIT).contains(&limit) => limit,
+            Some(limit) => {
+                return Err(bad_request(
+                    "Invalid limit",
+                    format!("Limit has to be between 1 and {MAX_LIMIT}, got {limit}"),
+                ))
+            }
+        };
+
+        let cursor = self
+            .cursor
+            .as_deref()
+            .map(|cursor| Cursor::decode(cursor, sort, descending))
+            .transpose()?;
+
+        let compare = |a: &(SortValue, OrderId), b: &(SortValue, OrderId)| {
+            let ordering =
+                a.0.partial_cmp(&b.0)
+                    .unwrap_or(Ordering::Equal)
+                    .then(a.1.cmp(&b.1));
+
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        };
+        let key = |cfd: &Cfd| (sort.value(cfd), cfd.order_id);
+
+        let mut cfds = cfds
+            .into_iter()
+            .filter(|cfd| states.is_empty() || states.contains(&cfd.state))
+            .filter(|cfd| symbol.map_or(true, |symbol| cfd.contract_symbol == symbol))
+            .filter(|cfd| position.map_or(true, |position| cfd.position == position))
+            .filter(|cfd| {
+                counterparty
+                    .as_ref()
+                    .map_or(true, |counterparty| counterparty.matches(cfd))
+            })
+            .filter(|cfd| {
+                in_range(
+                    Some(cfd.creation_timestamp()),
+                    self.opened_after,
+                    self.opened_before,
+                )
+            })
+            .filter(|cfd| {
+                in_range(
+                    cfd.closing_timestamp(),
+                    self.closed_after,
+                    self.closed_before,
+                )
+            })
+            .filter(|cfd| {
+                cursor.as_ref().map_or(true, |cursor| {
+                    compare(&key(cfd), &(cursor.value, cursor.order_id)) == Ordering::Greater
+                })
+            })
+            .collect::<Vec<_>>();
+
+        cfds.sort_by(|a, b| compare(&key(a), &key(b)));
+
+        let next_cursor = if cfds.len() > limit {
+            cfds.truncate(limit);
+            cfds.last().map(|cfd| {
+                Cursor {
+                    value: sort.value(cfd),
+                    order_id: cfd.order_id,
+                    descending,
+                }
+                .encode()
+            })
+        } else {
+            None
+        };
+
+        Ok(CfdPage { cfds, next_cursor })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    OpenedAt,
+    ClosedAt,
+    Expiry,
+    Quantity,
+}
+
+impl SortKey {
+    fn parse(sort: &str) -> Result<Self, HttpApiProblem> {
+        match sort {
+            "opened_at" => Ok(SortKey::OpenedAt),
+            "closed_at" => Ok(SortKey::ClosedAt),
+            "expiry" => Ok(SortKey::Expiry),
+            "quantity" => Ok(SortKey::Quantity),
+            other => Err(bad_request(
+                "Invalid sort",
+                format!("Expected `opened_at`, `closed_at`, `expiry` or `quantity`, got `{other}`"),
+            )),
+        }
+    }
+
+    fn value(&self, cfd: &Cfd) -> SortValue {
+        match self {
+            SortKey::OpenedAt => SortValue::OpenedAt(cfd.creation_timestamp()),
+            SortKey::ClosedAt => SortValue::ClosedAt(cfd.closing_timestamp()),
+            SortKey::Expiry => {
+                SortValue::Expiry(cfd.expiry_timestamp.map(|expiry| expiry.unix_timestamp()))
+            }
+            SortKey::Quantity => SortValue::Quantity(cfd.quantity),
+        }
+    }
+}
+
+/// The value of a CFD that it is sorted by
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+enum SortValue {
+    OpenedAt(Timestamp),
+    ClosedAt(Option<Timestamp>),
+    /// Unix timestamp of the expiry
+    Expiry(Option<i64>),
+    Quantity(Contracts),
+}
+
+impl SortValue {
+    fn key(&self) -> SortKey {
+        match self {
+            SortValue::OpenedAt(_) => SortKey::OpenedAt,
+            SortValue::ClosedAt(_) => SortKey::ClosedAt,
+            SortValue::Expiry(_) => SortKey::Expiry,
+            SortValue::Quantity(_) => SortKey::Quantity,
+        }
+    }
+}
+
+enum Counterparty {
+    PeerId(PeerId),
+    Identity(Identity),
+}
+
+impl Counterparty {
+    fn parse(counterparty: &str) -> Result<Self, HttpApiProblem> {
+        if let Ok(peer_id) = counterparty.parse() {
+            return Ok(Counterparty::PeerId(peer_id));
+        }
+
+        counterparty
+            .parse()
+            .map(Counterparty::Identity)
+            .map_err(|_| {
+                bad_request(
+                    "Invalid counterparty",
+                    format!("`{counterparty}` is neither a peer ID nor a network identity"),
+                )
+            })
+    }
+
+    fn matches(&self, cfd: &Cfd) -> bool {
+        match self {
+            Counterparty::PeerId(peer_id) => cfd.counterparty == *peer_id,
+            Counterparty::Identity(identity) => cfd.counterparty_network_identity() == *identity,
+        }
+    }
+}
+
+/// A CFD with everything we know about it
+#[derive(Debug, Serialize)]
+pub struct CfdDetail {
+    #[serde(flatten)]
+    pub cfd: Cfd,
+    pub opened_at: Timestamp,
+    pub closed_at: Option<Timestamp>,
+    /// The events of the CFD, oldest first
+    pub timeline: Vec<TimelineEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimelineEntry {
+    pub event: String,
+    pub timestamp: Timestamp,
+}
+
+impl CfdDetail {
+    pub fn new(cfd: Cfd, timeline: Vec<TimelineEvent>) -> Self {
+        Self {
+            opened_at: cfd.creation_timestamp(),
+            closed_at: cfd.closing_timestamp(),
+            cfd,
+            timeline: timeline
+                .into_iter()
+                .map(|TimelineEvent { name, timestamp }| TimelineEntry {
+                    event: name,
+                    timestamp,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Whether the timestamp is strictly within the given bounds, unbounded if neither is given.
+fn in_range(timestamp: Option<Timestamp>, after: Option<i64>, before: Option<i64>) -> bool {
+    if after.is_none() && before.is_none() {
+        return true;
+    }
+
+    let timestamp = match timestamp {
+        Some(timestamp) => timestamp.seconds(),
+        None => return false,
+    };
+
+    after.map_or(true, |after| timestamp > after)
+        && before.map_or(true, |before| timestamp < before)
+}
+
+/// Parse a query parameter the same way it is deserialized from JSON.
+fn parse<T>(name: &str, value: &str) -> Result<T, HttpApiProblem>
+where
+    T: DeserializeOwned,
+{
+    serde_json::from_value(Value::String(value.to_owned())).map_err(|_| {
+        bad_request(
+            &format!("Invalid {name}"),
+            format!("Unknown {name} `{value}`"),
+        )
+    })
+}
+
+fn bad_request(title: &str, detail: String) -> HttpApiProblem {
+    HttpApiProblem::new(StatusCode::BAD_REQUEST)
+        .title(title)
+        .detail(detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use daemon::bdk::bitcoin::Amount;
+    use daemon::block_explorer::BlockExplorer;
+    use model::ContractType;
+    use model::Contracts;
+    use model::FundingRate;
+    use model::Leverage;
+    use model::OfferId;
+    use model::OpeningFee;
+    use model::PayoutGranularity;
+    use model::Role;
+    use model::TxFeeRate;
+
+    #[tokio::test]
+    async fn paginates_filtered_cfds_with_cursor() {
+        let cfds = cfds(&[
+            (Position::Long, 100),
+            (Position::Short, 200),
+            (Position::Long, 300),
+            (Position::Long, 400),
+        ])
+        .await;
+        let query = || CfdQuery {
+            position: Some("Long".to_owned()),
+            sort: Some("quantity".to_owned()),
+            order: Some("asc".to_owned()),
+            limit: Some(2),
+            ..CfdQuery::default()
+        };
+
+        let first_page = query().apply(cfds.clone()).unwrap();
+        let second_page = CfdQuery {
+            cursor: first_page.next_cursor.clone(),
+            ..query()
+        }
+        .apply(cfds)
+        .unwrap();
+
+        assert_eq!(quantities(&first_page), vec![100, 300]);
+        assert!(first_page.next_cursor.is_some());
+        assert_eq!(quantities(&second_page), vec![400]);
+        assert_eq!(second_page.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn cursor_keeps_position_when_last_cfd_of_page_changes() {
+        let mut cfds = cfds(&[
+            (Position::Long, 100),
+            (Position::Long, 200),
+            (Position::Long, 300),
+        ])
+        .await;
+        let query = || CfdQuery {
+            sort: Some("quantity".to_owned()),
+            order: Some("asc".to_owned()),
+            limit: Some(1),
+            ..CfdQuery::default()
+        };
+
+        let first_page = query().apply(cfds.clone()).unwrap();
+        cfds[0].quantity = Contracts::new(250);
+        let second_page = CfdQuery {
+            cursor: first_page.next_cursor.clone(),
+            ..query()
+        }
+        .apply(cfds.clone())
+        .unwrap();
+        cfds.retain(|cfd| cfd.order_id != second_page.cfds[0].order_id);
+        let third_page = CfdQuery {
+            cursor: second_page.next_cursor.clone(),
+            ..query()
+        }
+        .apply(cfds)
+        .unwrap();
+
+        assert_eq!(quantities(&first_page), vec![100]);
+        assert_eq!(quantities(&second_page), vec![200]);
+        assert_eq!(quantities(&third_page), vec![250]);
+    }
+
+    #[tokio::test]
+    async fn rejects_cursor_of_different_order() {
+        let cfds = cfds(&[(Position::Long, 100), (Position::Long, 200)]).await;
+
+        let page = CfdQuery {
+            limit: Some(1),
+            ..CfdQuery::default()
+        }
+        .apply(cfds.clone())
+        .unwrap();
+        let problem = CfdQuery {
+            order: Some("asc".to_owned()),
+            cursor: page.next_cursor,
+            ..CfdQuery::default()
+        }
+        .apply(cfds)
+        .unwrap_err();
+
+        assert_eq!(problem.status, Some(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        let invalid_queries = [
+            CfdQuery {
+                state: vec!["Unknown".to_owned()],
+                ..CfdQuery::default()
+            },
+            CfdQuery {
+                sort: Some("profit".to_owned()),
+                ..CfdQuery::default()
+            },
+            CfdQuery {
+                limit: Some(0),
+                ..CfdQuery::default()
+            },
+            CfdQuery {
+                cursor: Some(OrderId::default().to_string()),
+                ..CfdQuery::default()
+            },
+        ];
+
+        for query in invalid_queries {
+            let problem = query.apply(Vec::new()).unwrap_err();
+
+            assert_eq!(problem.status, Some(StatusCode::BAD_REQUEST));
+        }
+    }
+
+    #[test]
+    fn open_cfds_are_not_in_any_closing_range() {
+        assert!(in_range(None, None, None));
+        assert!(!in_range(None, Some(0), None));
+        assert!(in_range(Some(Timestamp::new(10)), Some(9), Some(11)));
+        assert!(!in_range(Some(Timestamp::new(10)), Some(10), None));
+    }
+
+    async fn cfds(cfds: &[(Position, u64)]) -> Vec<Cfd> {
+        let db = sqlite_db::memory().await.unwrap();
+        let mut loaded = Vec::new();
+
+        for (position, quantity) in cfds {
+            let cfd = model::Cfd::new(
+                OrderId::default(),
+                OfferId::default(),
+                *position,
+                "60000".parse().unwrap(),
+                Leverage::TWO,
+                time::Duration::hours(24),
+                Role::Taker,
+                Contracts::new(*quantity),
+                "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"
+                    .parse()
+                    .unwrap(),
+                Some(PeerId::random()),
+                OpeningFee::new(Amount::from_sat(2000)),
+                FundingRate::default(),
+                TxFeeRate::default(),
+                ContractSymbol::BtcUsd,
+                ContractType::Perpetual,
+                PayoutGranularity::default(),
+            );
+            db.insert_cfd(&cfd).await.unwrap();
+
+            loaded.push(
+                db.load_open_cfd::<Cfd>(cfd.id(), BlockExplorer::None)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        loaded
+    }
+
+    fn quantities(page: &CfdPage) -> Vec<u64> {
+        page.cfds
+            .iter()
+            .map(|cfd| cfd.quantity.to_string().parse().unwrap())
+            .collect()
+    }
+}
@@ -1,11 +1,21 @@
 use crate::MAINNET_ELECTRUM;
 use crate::TESTNET_ELECTRUM;
+use anyhow::Context;
+use anyhow::Result;
+use clap::Args;
 use clap::Parser;
 use clap::Subcommand;
 use daemon::bdk::bitcoin;
 use daemon::bdk::bitcoin::Address;
 use daemon::bdk::bitcoin::Amount;
+use daemon::block_explorer::BlockExplorer;
+use daemon::notifications;
+use daemon::wallet::Descriptors;
+use daemon::wallet::ScriptType;
+use model::Role;
 use std::path::PathBuf;
+use std::time::Duration;
+use tokio_extras::Tasks;
 
 #[derive(Parser, Clone)]
 pub enum Network {
@@ -73,6 +83,169 @@ pub enum Withdraw {
     },
 }
 
+/// Output descriptors to use for the wallet instead of the key derived from the seed.
+#[derive(Args, Clone, Debug)]
+pub struct WalletDescriptors {
+    /// Output descriptor of the receive addresses, e.g. `wpkh(<xprv>/84'/0'/0'/0/*)`. Only segwit
+    /// descriptors are supported.
+    #[clap(long, conflicts_with = "wallet_backup")]
+    pub wallet_descriptor: Option<String>,
+
+    /// Output descriptor of the change addresses. If not provided, change is sent to receive
+    /// addresses.
+    #[clap(long, requires = "wallet_descriptor")]
+    pub wallet_change_descriptor: Option<String>,
+
+    /// Import the active descriptors of a descriptor wallet from a backup, i.e. the output of
+    /// Bitcoin Core's `listdescriptors true`.
+    #[clap(long)]
+    pub wallet_backup: Option<PathBuf>,
+
+    /// Script type of the descriptors imported from the backup: wpkh, sh-wpkh or tr.
+    #[clap(long, default_value = "wpkh")]
+    pub wallet_backup_script_type: ScriptType,
+}
+
+impl WalletDescriptors {
+    /// The descriptors given on the command line or imported from a backup, if any.
+    pub fn load(&self) -> Result<Option<Descriptors>> {
+        if let Some(path) = &self.wallet_backup {
+            let backup = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read wallet backup {}", path.display()))?;
+            let descriptors = Descriptors::from_backup(&backup, self.wallet_backup_script_type)?;
+
+            return Ok(Some(descriptors));
+        }
+
+        let descriptors = self.wallet_descriptor.clone().map(|receive| Descriptors {
+            receive,
+            change: self.wallet_change_descriptor.clone(),
+        });
+
+        Ok(descriptors)
+    }
+}
+
+impl Default for WalletDescriptors {
+    fn default() -> Self {
+        Self {
+            wallet_descriptor: None,
+            wallet_change_descriptor: None,
+            wallet_backup: None,
+            wallet_backup_script_type: ScriptType::Wpkh,
+        }
+    }
+}
+
+/// The block explorer transactions are linked to in the UI and API responses.
+#[derive(Args, Clone, Debug, Default)]
+pub struct BlockExplorerOpts {
+    /// URL template for links to transactions, e.g. "https://mempool.space/tx/{txid}".
+    ///
+    /// Use "none" to not link transactions at all. Defaults to mempool.space for the network and
+    /// no links on regtest.
+    #[clap(long)]
+    pub block_explorer: Option<String>,
+
+    /// URL template for links to transaction outputs, containing `{txid}` and `{vout}`, e.g.
+    /// "https://mempool.space/tx/{txid}:{vout}". Outputs are linked like their transaction if not
+    /// provided.
+    #[clap(long, requires = "block_explorer")]
+    pub block_explorer_output: Option<String>,
+}
+
+impl BlockExplorerOpts {
+    pub fn block_explorer(&self, network: bitcoin::Network) -> Result<BlockExplorer> {
+        match &self.block_explorer {
+            Some(tx) => BlockExplorer::new(tx, self.block_explorer_output.as_deref()),
+            None => Ok(BlockExplorer::default_for(network)),
+        }
+    }
+}
+
+/// Webhooks notified about key events of CFDs.
+#[derive(Args, Clone, Debug, Default)]
+pub struct NotificationOpts {
+    /// URL to post notifications about CFD events to. Can be given multiple times.
+    #[clap(long = "notification-webhook", requires = "notification_secret")]
+    pub notification_webhooks: Vec<String>,
+
+    /// Secret notifications are signed with, using HMAC-SHA256 over the body.
+    #[clap(long)]
+    pub notification_secret: Option<String>,
+}
+
+impl NotificationOpts {
+    /// The notifications outbox, if any webhook is configured.
+    pub fn outbox(
+        &self,
+        db: sqlite_db::Connection,
+        role: Role,
+        tasks: &mut Tasks,
+    ) -> Result<Option<notifications::Outbox>> {
+        if self.notification_webhooks.is_empty() {
+            return Ok(None);
+        }
+
+        let secret = self
+            .notification_secret
+            .clone()
+            .context("Notification webhooks require --notification-secret")?;
+
+        Ok(Some(notifications::Outbox::new(
+            db,
+            role,
+            self.notification_webhooks.clone(),
+            secret,
+            tasks,
+        )))
+    }
+}
+
+/// Export of the Prometheus metrics to an OpenTelemetry collector.
+#[derive(Args, Clone, Debug)]
+pub struct MetricsExportOpts {
+    /// If enabled, metrics are exported to the OTEL collector at `--collector-endpoint`, next to
+    /// the traces.
+    #[clap(long)]
+    pub export_metrics: bool,
+
+    /// OTEL collector endpoint to export metrics to, if different from `--collector-endpoint`.
+    ///
+    /// Implies `--export-metrics`.
+    #[clap(long)]
+    pub metrics_collector_endpoint: Option<String>,
+
+    /// Interval in seconds at which metrics are exported.
+    #[clap(long, default_value = "60")]
+    pub metrics_export_interval_secs: u64,
+}
+
+impl MetricsExportOpts {
+    /// The endpoint to export metrics to, if the export is enabled.
+    pub fn endpoint(&self, collector_endpoint: &str) -> Option<String> {
+        match &self.metrics_collector_endpoint {
+            Some(endpoint) => Some(endpoint.clone()),
+            None if self.export_metrics => Some(collector_endpoint.to_owned()),
+            None => None,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.metrics_export_interval_secs)
+    }
+}
+
+impl Default for MetricsExportOpts {
+    fn default() -> Self {
+        Self {
+            export_metrics: false,
+            metrics_collector_endpoint: None,
+            metrics_export_interval_secs: 60,
+        }
+    }
+}
+
 impl Network {
     pub fn electrum(&self) -> &str {
         match self {
@@ -1,9 +1,16 @@
 pub mod catchers;
+mod cfd_feed;
+mod cfd_query;
 pub mod cli;
 pub mod fairings;
 pub mod logger;
+pub mod metrics_export;
 mod to_sse_event;
+pub mod wallet;
+pub mod websocket;
 
+pub use crate::cfd_feed::*;
+pub use crate::cfd_query::*;
 pub use crate::to_sse_event::*;
 
 pub const MAINNET_ELECTRUM: &str = "ssl://blockstream.info:700";
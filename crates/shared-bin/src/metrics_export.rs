@@ -0,0 +1,327 @@
+//! Push the Prometheus metrics to an OpenTelemetry collector.
+//!
+//! The metrics served on the Prometheus scrape endpoint are observed through asynchronous
+//! instruments of an OTLP metrics pipeline, which exports them to the collector. Counters are
+//! exported as cumulative sums and gauges as gauges. The OpenTelemetry API has no asynchronous
+//! histograms, histograms are thus exported as their `_bucket`, `_sum` and `_count` sums, like on
+//! the scrape endpoint. Every metric carries the resource attributes of the daemon, e.g. its role
+//! and network.
+
+use anyhow::Context;
+use anyhow::Result;
+use daemon::bdk::bitcoin;
+use model::Role;
+use opentelemetry::metrics::Meter;
+use opentelemetry::metrics::MeterProvider;
+use opentelemetry::sdk::export::metrics::aggregation::cumulative_temporality_selector;
+use opentelemetry::sdk::metrics::selectors;
+use opentelemetry::sdk::Resource;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use prometheus::proto::MetricFamily;
+use prometheus::proto::MetricType;
+use std::time::Duration;
+
+/// Timeout for a single export to the collector.
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The resource attributes the metrics of a daemon are exported with.
+pub fn resource(service_name: &str, role: Role, network: bitcoin::Network) -> Resource {
+    let role = match role {
+        Role::Maker => "maker",
+        Role::Taker => "taker",
+    };
+
+    Resource::new([
+        KeyValue::new("service.name", service_name.to_owned()),
+        KeyValue::new("service.version", daemon::version()),
+        KeyValue::new("role", role),
+        KeyValue::new("network", network.to_string()),
+    ])
+}
+
+/// Export the metrics of the default Prometheus registry to the collector at `endpoint` every
+/// `interval`.
+///
+/// Failed exports are reported to the OpenTelemetry error handler and retried at the next
+/// interval.
+pub fn export(endpoint: String, interval: Duration, resource: Resource) -> Result<()> {
+    anyhow::ensure!(
+        !interval.is_zero(),
+        "Metrics export interval must not be zero"
+    );
+
+    let controller = opentelemetry_otlp::new_pipeline()
+        .metrics(
+            selectors::simple::inexpensive(),
+            cumulative_temporality_selector(),
+            opentelemetry::runtime::Tokio,
+        )
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint)
+                .with_timeout(EXPORT_TIMEOUT),
+        )
+        .with_period(interval)
+        .with_resource(resource)
+        .build()
+        .context("Failed to initialise OTLP metrics exporter")?;
+
+    let meter = controller.versioned_meter("prometheus", None, None);
+    let instruments = meter.clone();
+    meter
+        .register_callback(move |cx| {
+            for observation in observations(&prometheus::gather()) {
+                observation.observe(&instruments, cx);
+            }
+        })
+        .context("Failed to register Prometheus metrics")?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Counter,
+    Gauge,
+}
+
+/// A value of a Prometheus metric, as observed by an asynchronous instrument.
+#[derive(Debug, Clone, PartialEq)]
+struct Observation {
+    name: String,
+    description: String,
+    kind: Kind,
+    value: f64,
+    attributes: Vec<KeyValue>,
+}
+
+impl Observation {
+    /// Observe the value through the instrument of the same name.
+    ///
+    /// Instruments are identified by their name and kind, observing through a new instrument on
+    /// every collection thus reports to the same metric.
+    fn observe(&self, meter: &Meter, cx: &opentelemetry::Context) {
+        match self.kind {
+            Kind::Counter => meter
+                .f64_observable_counter(self.name.clone())
+                .with_description(self.description.clone())
+                .init()
+                .observe(cx, self.value, &self.attributes),
+            Kind::Gauge => meter
+                .f64_observable_gauge(self.name.clone())
+                .with_description(self.description.clone())
+                .init()
+                .observe(cx, self.value, &self.attributes),
+        }
+    }
+}
+
+/// Flatten Prometheus metric families into the observations of asynchronous instruments.
+///
+/// Summaries, which we do not use, are skipped.
+fn observations(families: &[MetricFamily]) -> Vec<Observation> {
+    let mut observations = Vec::new();
+
+    for family in families {
+        let name = family.get_name();
+        let observation = |suffix: &str, kind, value, attributes| Observation {
+            name: format!("{name}{suffix}"),
+            description: family.get_help().to_owned(),
+            kind,
+            value,
+            attributes,
+        };
+
+        for metric in family.get_metric() {
+            match family.get_field_type() {
+                MetricType::COUNTER => observations.push(observation(
+                    "",
+                    Kind::Counter,
+                    metric.get_counter().get_value(),
+                    attributes(metric),
+                )),
+                MetricType::GAUGE => observations.push(observation(
+                    "",
+                    Kind::Gauge,
+                    metric.get_gauge().get_value(),
+                    attributes(metric),
+                )),
+                MetricType::UNTYPED => observations.push(observation(
+                    "",
+                    Kind::Gauge,
+                    metric.get_untyped().get_value(),
+                    attributes(metric),
+                )),
+                MetricType::HISTOGRAM => {
+                    let histogram = metric.get_histogram();
+
+                    // Prometheus buckets are cumulative and exclude the `+Inf` bucket
+                    let buckets = histogram
+                        .get_bucket()
+                        .iter()
+                        .filter(|bucket| !bucket.get_upper_bound().is_infinite())
+                        .map(|bucket| {
+                            (
+                                bucket.get_upper_bound().to_string(),
+                                bucket.get_cumulative_count(),
+                            )
+                        })
+                        .chain([("+Inf".to_owned(), histogram.get_sample_count())]);
+                    for (le, count) in buckets {
+                        let mut attributes = attributes(metric);
+                        attributes.push(KeyValue::new("le", le));
+
+                        observations.push(observation(
+                            "_bucket",
+                            Kind::Counter,
+                            count as f64,
+                            attributes,
+                        ));
+                    }
+
+                    observations.push(observation(
+                        "_sum",
+                        Kind::Counter,
+                        histogram.get_sample_sum(),
+                        attributes(metric),
+                    ));
+                    observations.push(observation(
+                        "_count",
+                        Kind::Counter,
+                        histogram.get_sample_count() as f64,
+                        attributes(metric),
+                    ));
+                }
+                MetricType::SUMMARY => {
+                    tracing::trace!(%name, "Not exporting summary");
+                    break;
+                }
+            }
+        }
+    }
+
+    observations
+}
+
+fn attributes(metric: &prometheus::proto::Metric) -> Vec<KeyValue> {
+    metric
+        .get_label()
+        .iter()
+        .map(|label| KeyValue::new(label.get_name().to_owned(), label.get_value().to_owned()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::Key;
+    use opentelemetry::Value;
+    use prometheus::HistogramOpts;
+    use prometheus::IntCounterVec;
+    use prometheus::IntGauge;
+    use prometheus::Opts;
+    use prometheus::Registry;
+
+    #[test]
+    fn counter_is_observed_as_counter() {
+        let registry = Registry::new();
+        let counter =
+            IntCounterVec::new(Opts::new("orders_total", "Orders placed"), &["side"]).unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        counter.with_label_values(&["buy"]).inc_by(3);
+
+        assert_eq!(
+            observations(&registry.gather()),
+            vec![Observation {
+                name: "orders_total".to_owned(),
+                description: "Orders placed".to_owned(),
+                kind: Kind::Counter,
+                value: 3.0,
+                attributes: vec![KeyValue::new("side", "buy")],
+            }]
+        );
+    }
+
+    #[test]
+    fn gauge_is_observed_as_gauge() {
+        let registry = Registry::new();
+        let gauge = IntGauge::new("open_cfds", "Open CFDs").unwrap();
+        registry.register(Box::new(gauge.clone())).unwrap();
+        gauge.set(5);
+
+        let observations = observations(&registry.gather());
+
+        assert_eq!(observations[0].kind, Kind::Gauge);
+        assert_eq!(observations[0].value, 5.0);
+    }
+
+    #[test]
+    fn histogram_is_observed_as_cumulative_buckets_sum_and_count() {
+        let registry = Registry::new();
+        let histogram = prometheus::Histogram::with_opts(
+            HistogramOpts::new("duration_seconds", "Duration").buckets(vec![1.0, 2.0]),
+        )
+        .unwrap();
+        registry.register(Box::new(histogram.clone())).unwrap();
+        histogram.observe(0.5);
+        histogram.observe(0.75);
+        histogram.observe(1.5);
+        histogram.observe(3.0);
+
+        let observations = observations(&registry.gather());
+
+        assert!(observations
+            .iter()
+            .all(|observation| observation.kind == Kind::Counter));
+        assert_eq!(
+            observations
+                .iter()
+                .map(|observation| (
+                    observation.name.as_str(),
+                    observation.attributes.clone(),
+                    observation.value
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "duration_seconds_bucket",
+                    vec![KeyValue::new("le", "1")],
+                    2.0
+                ),
+                (
+                    "duration_seconds_bucket",
+                    vec![KeyValue::new("le", "2")],
+                    3.0
+                ),
+                (
+                    "duration_seconds_bucket",
+                    vec![KeyValue::new("le", "+Inf")],
+                    4.0
+                ),
+                ("duration_seconds_sum", vec![], 5.75),
+                ("duration_seconds_count", vec![], 4.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn resource_carries_daemon_attributes() {
+        let resource = resource("maker", Role::Maker, bitcoin::Network::Testnet);
+
+        assert_eq!(
+            resource.get(Key::new("service.name")),
+            Some(Value::from("maker"))
+        );
+        assert_eq!(resource.get(Key::new("role")), Some(Value::from("maker")));
+        assert_eq!(
+            resource.get(Key::new("network")),
+            Some(Value::from("testnet"))
+        );
+        assert_eq!(
+            resource.get(Key::new("service.version")),
+            Some(Value::from(daemon::version()))
+        );
+    }
+}
@@ -1,12 +1,11 @@
 use daemon::bdk::bitcoin::Amount;
-use daemon::bdk::bitcoin::Network;
 use daemon::bdk::bitcoin::Txid;
 use daemon::bdk::BlockTime;
+use daemon::block_explorer::BlockExplorer;
 use daemon::identify;
 use daemon::listen_protocols::does_maker_satisfy_taker_needs;
-use daemon::listen_protocols::REQUIRED_MAKER_LISTEN_PROTOCOLS;
+use daemon::listen_protocols::TAKER_DIALED_PROTOCOLS;
 use daemon::online_status;
-use daemon::projection::Cfd;
 use model::Timestamp;
 use rocket::response::stream::Event;
 use serde::Serialize;
@@ -16,12 +15,6 @@ pub trait ToSseEvent {
     fn to_sse_event(&self) -> Event;
 }
 
-impl ToSseEvent for Vec<Cfd> {
-    fn to_sse_event(&self) -> Event {
-        Event::json(&self).event("cfds")
-    }
-}
-
 #[derive(Debug, Clone, Serialize)]
 pub struct WalletInfo {
     #[serde(with = "daemon::bdk::bitcoin::util::amount::serde::as_btc")]
@@ -29,6 +22,37 @@ pub struct WalletInfo {
     address: String,
     last_updated_at: Timestamp,
     transactions: Vec<TransactionDetails>,
+    withdrawals: Vec<Withdrawal>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Withdrawal {
+    pub txid: Txid,
+    pub address: String,
+    #[serde(with = "daemon::bdk::bitcoin::util::amount::serde::as_btc")]
+    pub amount: Amount,
+    #[serde(with = "daemon::bdk::bitcoin::util::amount::serde::as_btc")]
+    pub fee: Amount,
+    pub created_at: Timestamp,
+    pub confirmation_time: Option<BlockTime>,
+    pub replaced_by: Option<Txid>,
+    /// Whether the fee of the withdrawal can still be bumped
+    pub pending: bool,
+}
+
+impl From<&model::Withdrawal> for Withdrawal {
+    fn from(withdrawal: &model::Withdrawal) -> Self {
+        Self {
+            txid: withdrawal.txid,
+            address: withdrawal.address.to_string(),
+            amount: withdrawal.amount,
+            fee: withdrawal.fee,
+            created_at: withdrawal.created_at,
+            confirmation_time: withdrawal.confirmation_time.clone(),
+            replaced_by: withdrawal.replaced_by,
+            pending: withdrawal.is_pending(),
+        }
+    }
 }
 
 #[derive(Serialize, Debug, Clone, PartialEq, Eq, Default)]
@@ -42,41 +66,46 @@ pub struct TransactionDetails {
     pub link: Option<String>,
 }
 
-impl From<(Network, &daemon::bdk::TransactionDetails)> for TransactionDetails {
-    fn from((network, tx): (Network, &daemon::bdk::TransactionDetails)) -> Self {
-        let txid = tx.txid;
-        let link = match network {
-            Network::Bitcoin => Some(format!("https://mempool.space/tx/{txid}")),
-            Network::Testnet => Some(format!("https://mempool.space/testnet/tx/{txid}")),
-            Network::Signet => Some(format!("https://mempool.space/signet/tx/{txid}")),
-            Network::Regtest => None,
-        };
+impl From<(&BlockExplorer, &daemon::bdk::TransactionDetails)> for TransactionDetails {
+    fn from((block_explorer, tx): (&BlockExplorer, &daemon::bdk::TransactionDetails)) -> Self {
         Self {
-            txid,
+            txid: tx.txid,
             received: Amount::from_sat(tx.received),
             sent: Amount::from_sat(tx.sent),
             confirmation_time: tx.confirmation_time.clone(),
-            link,
+            link: block_explorer.tx_url(tx.txid),
         }
     }
 }
 
-impl ToSseEvent for Option<model::WalletInfo> {
-    fn to_sse_event(&self) -> Event {
-        let wallet_info = self.as_ref().map(|wallet_info| {
-            let transaction_details = wallet_info
-                .transactions
+impl From<(&BlockExplorer, &model::WalletInfo)> for WalletInfo {
+    fn from((block_explorer, wallet_info): (&BlockExplorer, &model::WalletInfo)) -> Self {
+        let transaction_details = wallet_info
+            .transactions
+            .iter()
+            .map(|tx| (block_explorer, tx).into())
+            .collect();
+
+        Self {
+            balance: wallet_info.balance,
+            address: wallet_info.address.to_string(),
+            last_updated_at: wallet_info.last_updated_at,
+            transactions: transaction_details,
+            withdrawals: wallet_info
+                .withdrawals
                 .iter()
-                .map(|tx| (wallet_info.network, tx).into())
-                .collect();
-
-            WalletInfo {
-                balance: wallet_info.balance,
-                address: wallet_info.address.to_string(),
-                last_updated_at: wallet_info.last_updated_at,
-                transactions: transaction_details,
-            }
-        });
+                .map(Withdrawal::from)
+                .collect(),
+        }
+    }
+}
+
+impl ToSseEvent for (&BlockExplorer, &Option<model::WalletInfo>) {
+    fn to_sse_event(&self) -> Event {
+        let (block_explorer, wallet_info) = *self;
+        let wallet_info = wallet_info
+            .as_ref()
+            .map(|wallet_info| WalletInfo::from((block_explorer, wallet_info)));
 
         Event::json(&wallet_info).event("wallet")
     }
@@ -113,10 +142,7 @@ pub struct MakerCompatibility {
 impl MakerCompatibility {
     pub fn new(peer_info: &Option<identify::PeerInfo>) -> Self {
         let unsupported_protocols = peer_info.as_ref().map(|peer_info| {
-            match does_maker_satisfy_taker_needs(
-                &peer_info.protocols,
-                REQUIRED_MAKER_LISTEN_PROTOCOLS,
-            ) {
+            match does_maker_satisfy_taker_needs(&peer_info.protocols, &TAKER_DIALED_PROTOCOLS) {
                 Ok(_) => HashSet::new(),
                 Err(missing_protocols) => missing_protocols,
             }
@@ -0,0 +1,122 @@
+use daemon::bdk::bitcoin::Address;
+use daemon::bdk::bitcoin::Amount;
+use daemon::bdk::bitcoin::OutPoint;
+use daemon::bdk::bitcoin::Txid;
+use daemon::bdk::FeeRate;
+use daemon::block_explorer::BlockExplorer;
+use daemon::wallet::FeePreset;
+use daemon::wallet::WithdrawFee;
+use http_api_problem::HttpApiProblem;
+use http_api_problem::StatusCode;
+use serde::Deserialize;
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Request to withdraw from the wallet or to preview such a withdrawal
+#[derive(Debug, Clone, Deserialize)]
+pub struct WithdrawRequest {
+    pub address: Address,
+    /// All selected coins are spent if the amount is zero
+    #[serde(with = "daemon::bdk::bitcoin::util::amount::serde::as_btc")]
+    pub amount: Amount,
+    /// Fee rate in sat/vB
+    #[serde(default)]
+    pub fee: Option<f32>,
+    /// Fee rate estimated for the given preset, takes precedence over `fee`
+    #[serde(default)]
+    pub fee_preset: Option<FeePreset>,
+    /// Outpoints (`txid:vout`) of the coins to spend
+    ///
+    /// If empty, coin selection picks from all coins that are not frozen.
+    #[serde(default)]
+    pub coins: Vec<String>,
+}
+
+impl WithdrawRequest {
+    pub fn amount(&self) -> Option<Amount> {
+        (self.amount != Amount::ZERO).then(|| self.amount)
+    }
+
+    pub fn fee(&self) -> Result<WithdrawFee, HttpApiProblem> {
+        withdraw_fee(self.fee_preset, self.fee)
+    }
+
+    pub fn coins(&self) -> Result<Vec<OutPoint>, HttpApiProblem> {
+        self.coins
+            .iter()
+            .map(|outpoint| parse_outpoint(outpoint))
+            .collect()
+    }
+}
+
+/// Request to replace an unconfirmed withdrawal with one paying a higher fee
+#[derive(Debug, Clone, Deserialize)]
+pub struct BumpFeeRequest {
+    /// Fee rate in sat/vB
+    #[serde(default)]
+    pub fee: Option<f32>,
+    /// Fee rate estimated for the given preset, takes precedence over `fee`
+    #[serde(default)]
+    pub fee_preset: Option<FeePreset>,
+}
+
+impl BumpFeeRequest {
+    pub fn fee(&self) -> Result<WithdrawFee, HttpApiProblem> {
+        withdraw_fee(self.fee_preset, self.fee)
+    }
+}
+
+/// The transaction published for a withdrawal or fee bump
+#[derive(Debug, Clone, Serialize)]
+pub struct WithdrawResponse {
+    pub txid: Txid,
+    /// Link to the transaction on the block explorer, if configured
+    pub url: Option<String>,
+}
+
+impl WithdrawResponse {
+    pub fn new(txid: Txid, block_explorer: &BlockExplorer) -> Self {
+        Self {
+            txid,
+            url: block_explorer.tx_url(txid),
+        }
+    }
+}
+
+/// Request to replace the label and freeze state of a UTXO
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateUtxoRequest {
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub frozen: bool,
+}
+
+fn withdraw_fee(
+    fee_preset: Option<FeePreset>,
+    fee: Option<f32>,
+) -> Result<WithdrawFee, HttpApiProblem> {
+    match (fee_preset, fee) {
+        (Some(preset), _) => Ok(WithdrawFee::Preset(preset)),
+        (None, Some(fee)) => Ok(WithdrawFee::Rate(FeeRate::from_sat_per_vb(fee))),
+        (None, None) => Err(HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Missing fee")
+            .detail("Either `fee` or `fee_preset` has to be specified")),
+    }
+}
+
+pub fn parse_txid(txid: &str) -> Result<Txid, HttpApiProblem> {
+    Txid::from_str(txid).map_err(|e| {
+        HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Invalid txid")
+            .detail(format!("{txid} is not a valid txid: {e}"))
+    })
+}
+
+pub fn parse_outpoint(outpoint: &str) -> Result<OutPoint, HttpApiProblem> {
+    OutPoint::from_str(outpoint).map_err(|e| {
+        HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Invalid outpoint")
+            .detail(format!("{outpoint} is not a valid outpoint: {e}"))
+    })
+}
@@ -0,0 +1,552 @@
+//! Bidirectional WebSocket API for bots.
+//!
+//! Rocket does not support connection upgrades, hence the WebSocket API is served on its own
+//! address. Clients authenticate with the password of the web interface, subscribe to the topics
+//! they are interested in and send commands over the same connection. Every request carries an
+//! `id` which is echoed in the `ack` or `error` response to correlate the two.
+//!
+//! After subscribing to a topic the client receives a `snapshot` of it, followed by `update`s.
+//! Changes to CFDs are sent as `cfd_updated` and `cfd_removed` messages instead of the full list.
+//! These carry a `sequence` number; after reconnecting, a client can pass the last one it has seen
+//! as `after_sequence` when subscribing to `cfds` to only receive the changes it missed.
+
+use crate::WalletInfo;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use daemon::block_explorer::BlockExplorer;
+use daemon::projection::Cfd;
+use daemon::projection::CfdChange;
+use daemon::projection::CfdSync;
+use daemon::projection::CfdUpdate;
+use daemon::projection::CfdUpdates;
+use daemon::projection::FeedReceivers;
+use daemon::projection::LatestQuotes;
+use daemon::projection::MakerOffers;
+use futures::stream::FuturesUnordered;
+use futures::SinkExt;
+use futures::StreamExt;
+use http_api_problem::HttpApiProblem;
+use http_api_problem::StatusCode;
+use model::OrderId;
+use rocket_cookie_auth::user::verify_password;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::select;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+use tokio_tungstenite::tungstenite::Message;
+
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Executes the commands a client sends over the WebSocket.
+#[async_trait]
+pub trait CommandHandler: Send + Sync + 'static {
+    type Command: DeserializeOwned + Send;
+
+    /// Execute the command, the returned value is sent back in the `ack` message.
+    async fn handle(&self, command: Self::Command) -> Result<Value, HttpApiProblem>;
+}
+
+/// The feeds a client can subscribe to.
+#[derive(Clone)]
+pub struct Feeds {
+    pub cfds: CfdUpdates,
+    pub offers: watch::Receiver<MakerOffers>,
+    pub quotes: watch::Receiver<LatestQuotes>,
+    pub wallet: watch::Receiver<Option<model::WalletInfo>>,
+    pub block_explorer: BlockExplorer,
+}
+
+impl Feeds {
+    pub fn new(
+        feed_receivers: &FeedReceivers,
+        wallet: watch::Receiver<Option<model::WalletInfo>>,
+        block_explorer: BlockExplorer,
+    ) -> Self {
+        Self {
+            cfds: feed_receivers.cfd_updates.clone(),
+            offers: feed_receivers.offers.clone(),
+            quotes: feed_receivers.quote.clone(),
+            wallet,
+            block_explorer,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Topic {
+    Cfds,
+    Offers,
+    Quotes,
+    Wallet,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage<C> {
+    Authenticate {
+        id: u64,
+        password: String,
+    },
+    Subscribe {
+        id: u64,
+        topics: Vec<Topic>,
+        /// Resume the `cfds` topic after this sequence number instead of starting with a snapshot
+        after_sequence: Option<u64>,
+    },
+    Unsubscribe {
+        id: u64,
+        topics: Vec<Topic>,
+    },
+    Command {
+        id: u64,
+        command: C,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    /// The request with the given id succeeded
+    Ack {
+        id: u64,
+        result: Value,
+    },
+    /// The request with the given id failed, or could not be parsed at all
+    Error {
+        id: Option<u64>,
+        error: HttpApiProblem,
+    },
+    /// Full state of a topic, sent once after subscribing
+    Snapshot {
+        topic: Topic,
+        data: Value,
+        /// Sequence number the `cfds` snapshot was taken at
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sequence: Option<u64>,
+    },
+    /// Latest state of a topic other than `cfds`
+    Update {
+        topic: Topic,
+        data: Value,
+    },
+    CfdUpdated {
+        sequence: u64,
+        cfd: Box<Cfd>,
+    },
+    CfdRemoved {
+        sequence: u64,
+        order_id: OrderId,
+    },
+}
+
+impl ServerMessage {
+    fn to_message(&self) -> Result<Message> {
+        let json = serde_json::to_string(self).context("Failed to serialize message")?;
+
+        Ok(Message::Text(json))
+    }
+}
+
+/// Serve the WebSocket API on the given address until the returned future is dropped.
+pub async fn serve<H>(
+    address: SocketAddr,
+    auth: Box<dyn rocket_cookie_auth::Database>,
+    feeds: Feeds,
+    handler: H,
+) -> Result<()>
+where
+    H: CommandHandler,
+{
+    let listener = TcpListener::bind(address)
+        .await
+        .with_context(|| format!("Failed to listen on {address}"))?;
+    tracing::info!(target: "ws", endpoint = %format!("ws://{address}"), "WebSocket API is ready");
+
+    let auth = Arc::<dyn rocket_cookie_auth::Database>::from(auth);
+    let handler = Arc::new(handler);
+
+    // Connections are driven by the server itself, they are dropped as soon as they are closed and
+    // cancelled together with the server
+    let mut connections = FuturesUnordered::new();
+
+    loop {
+        let accepted = select! {
+            accepted = listener.accept() => accepted,
+            Some(()) = connections.next(), if !connections.is_empty() => continue,
+        };
+        let (stream, peer) = match accepted {
+            Ok(connection) => connection,
+            Err(e) => {
+                tracing::warn!(target: "ws", "Failed to accept connection: {e:#}");
+                continue;
+            }
+        };
+
+        let connection = Connection {
+            auth: auth.clone(),
+            handler: handler.clone(),
+            feeds: feeds.clone(),
+            authenticated: false,
+            topics: HashSet::new(),
+            cfd_updates: None,
+            cfd_sequence: None,
+        };
+
+        connections.push(async move {
+            if let Err(e) = connection.run(stream).await {
+                tracing::debug!(target: "ws", %peer, "WebSocket connection closed: {e:#}");
+            }
+        });
+    }
+}
+
+struct Connection<H> {
+    auth: Arc<dyn rocket_cookie_auth::Database>,
+    handler: Arc<H>,
+    feeds: Feeds,
+    authenticated: bool,
+    topics: HashSet<Topic>,
+    /// Only present while subscribed to [`Topic::Cfds`]
+    cfd_updates: Option<broadcast::Receiver<CfdUpdate>>,
+    /// Sequence number of the last CFD update sent to the client
+    cfd_sequence: Option<u64>,
+}
+
+impl<H> Connection<H>
+where
+    H: CommandHandler,
+{
+    async fn run(mut self, stream: TcpStream) -> Result<()> {
+        let websocket = tokio_tungstenite::accept_async(stream)
+            .await
+            .context("WebSocket handshake failed")?;
+        let (mut sink, mut stream) = websocket.split();
+
+        let mut ping = tokio::time::interval(PING_INTERVAL);
+
+        loop {
+            let messages = select! {
+                message = stream.next() => {
+                    let text = match message.transpose()? {
+                        None | Some(Message::Close(_)) => return Ok(()),
+                        Some(Message::Text(text)) => text,
+                        // Pings are answered by tungstenite
+                        Some(_) => continue,
+                    };
+
+                    self.handle_request(&text).await
+                }
+                update = next_cfd_update(&mut self.cfd_updates) => {
+                    match update {
+                        Some(update) => vec![self.cfd_update(update)],
+                        // Lagged behind, catch up from the last update we have sent
+                        None => self.subscribe_cfds(self.cfd_sequence)?,
+                    }
+                }
+                Ok(()) = self.feeds.offers.changed(), if self.is_subscribed(Topic::Offers) => {
+                    vec![self.update(Topic::Offers)?]
+                }
+                Ok(()) = self.feeds.quotes.changed(), if self.is_subscribed(Topic::Quotes) => {
+                    vec![self.update(Topic::Quotes)?]
+                }
+                Ok(()) = self.feeds.wallet.changed(), if self.is_subscribed(Topic::Wallet) => {
+                    vec![self.update(Topic::Wallet)?]
+                }
+                _ = ping.tick() => {
+                    sink.send(Message::Ping(Vec::new())).await?;
+                    continue;
+                }
+            };
+
+            for message in messages {
+                sink.send(message.to_message()?).await?;
+            }
+        }
+    }
+
+    fn is_subscribed(&self, topic: Topic) -> bool {
+        self.topics.contains(&topic)
+    }
+
+    async fn handle_request(&mut self, text: &str) -> Vec<ServerMessage> {
+        let request = match serde_json::from_str::<Value>(text) {
+            Ok(request) => request,
+            Err(e) => {
+                return vec![ServerMessage::Error {
+                    id: None,
+                    error: bad_request("Invalid JSON", e),
+                }]
+            }
+        };
+        let id = request.get("id").and_then(Value::as_u64);
+
+        let message = match serde_json::from_value::<ClientMessage<H::Command>>(request) {
+            Ok(message) => message,
+            Err(e) => {
+                return vec![ServerMessage::Error {
+                    id,
+                    error: bad_request("Invalid request", e),
+                }]
+            }
+        };
+
+        match message {
+            ClientMessage::Authenticate { id, password } => {
+                match self.authenticate(&password).await {
+                    Ok(()) => vec![ack(id)],
+                    Err(error) => vec![ServerMessage::Error {
+                        id: Some(id),
+                        error,
+                    }],
+                }
+            }
+            _ if !self.authenticated => vec![ServerMessage::Error {
+                id,
+                error: HttpApiProblem::new(StatusCode::UNAUTHORIZED)
+                    .title("Not authenticated")
+                    .detail("Send an `authenticate` request first"),
+            }],
+            ClientMessage::Subscribe {
+                id,
+                topics,
+                after_sequence,
+            } => {
+                let mut messages = vec![ack(id)];
+
+                for topic in topics {
+                    if !self.topics.insert(topic) {
+                        continue;
+                    }
+
+                    let catch_up = match topic {
+                        Topic::Cfds => self.subscribe_cfds(after_sequence),
+                        topic => self.snapshot(topic).map(|snapshot| vec![snapshot]),
+                    };
+
+                    match catch_up {
+                        Ok(catch_up) => messages.extend(catch_up),
+                        Err(e) => {
+                            tracing::error!(target: "ws", ?topic, "Failed to serialize: {e:#}")
+                        }
+                    }
+                }
+
+                messages
+            }
+            ClientMessage::Unsubscribe { id, topics } => {
+                for topic in topics {
+                    self.topics.remove(&topic);
+
+                    if topic == Topic::Cfds {
+                        self.cfd_updates = None;
+                        self.cfd_sequence = None;
+                    }
+                }
+
+                vec![ack(id)]
+            }
+            ClientMessage::Command { id, command } => match self.handler.handle(command).await {
+                Ok(result) => vec![ServerMessage::Ack { id, result }],
+                Err(error) => vec![ServerMessage::Error {
+                    id: Some(id),
+                    error,
+                }],
+            },
+        }
+    }
+
+    async fn authenticate(&mut self, password: &str) -> Result<(), HttpApiProblem> {
+        let unauthorized = || HttpApiProblem::new(StatusCode::UNAUTHORIZED).title("Wrong password");
+
+        let user = self
+            .auth
+            .load_user()
+            .await
+            .map_err(|e| {
+                HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                    .title("Could not load user")
+                    .detail(format!("{e:#}"))
+            })?
+            .ok_or_else(unauthorized)?;
+
+        if !verify_password(&user.password, password).unwrap_or(false) {
+            return Err(unauthorized());
+        }
+
+        self.authenticated = true;
+
+        Ok(())
+    }
+
+    fn snapshot(&mut self, topic: Topic) -> Result<ServerMessage> {
+        Ok(ServerMessage::Snapshot {
+            topic,
+            data: self.latest(topic)?,
+            sequence: None,
+        })
+    }
+
+    /// (Re-)subscribe to the CFD updates, resuming after the given sequence number if possible.
+    fn subscribe_cfds(&mut self, after_sequence: Option<u64>) -> Result<Vec<ServerMessage>> {
+        let subscription = self.feeds.cfds.subscribe(after_sequence);
+        self.cfd_updates = Some(subscription.updates);
+
+        let messages = match subscription.sync {
+            CfdSync::Snapshot { sequence, cfds } => {
+                self.cfd_sequence = Some(sequence);
+
+                vec![ServerMessage::Snapshot {
+                    topic: Topic::Cfds,
+                    data: serde_json::to_value(cfds)?,
+                    sequence: Some(sequence),
+                }]
+            }
+            CfdSync::Resume(updates) => updates
+                .into_iter()
+                .map(|update| self.cfd_update(update))
+                .collect(),
+        };
+
+        Ok(messages)
+    }
+
+    fn cfd_update(&mut self, update: CfdUpdate) -> ServerMessage {
+        let sequence = update.sequence;
+        self.cfd_sequence = Some(sequence);
+
+        match update.change {
+            CfdChange::Upsert(cfd) => ServerMessage::CfdUpdated { sequence, cfd },
+            CfdChange::Remove(order_id) => ServerMessage::CfdRemoved { sequence, order_id },
+        }
+    }
+
+    fn update(&mut self, topic: Topic) -> Result<ServerMessage> {
+        Ok(ServerMessage::Update {
+            topic,
+            data: self.latest(topic)?,
+        })
+    }
+
+    fn latest(&mut self, topic: Topic) -> Result<Value> {
+        let value = match topic {
+            Topic::Cfds => unreachable!("CFDs are sent as individual updates"),
+            Topic::Offers => serde_json::to_value(&*self.feeds.offers.borrow_and_update())?,
+            Topic::Quotes => serde_json::to_value(&*self.feeds.quotes.borrow_and_update())?,
+            Topic::Wallet => {
+                let block_explorer = &self.feeds.block_explorer;
+                let wallet_info = self
+                    .feeds
+                    .wallet
+                    .borrow_and_update()
+                    .as_ref()
+                    .map(|wallet_info| WalletInfo::from((block_explorer, wallet_info)));
+
+                serde_json::to_value(wallet_info)?
+            }
+        };
+
+        Ok(value)
+    }
+}
+
+/// Wait for the next CFD update, `None` if the receiver lagged behind.
+///
+/// Never resolves if the client is not subscribed to [`Topic::Cfds`].
+async fn next_cfd_update(
+    updates: &mut Option<broadcast::Receiver<CfdUpdate>>,
+) -> Option<CfdUpdate> {
+    let updates = match updates {
+        Some(updates) => updates,
+        None => return futures::future::pending().await,
+    };
+
+    match updates.recv().await {
+        Ok(update) => Some(update),
+        Err(RecvError::Lagged(_)) => None,
+        Err(RecvError::Closed) => futures::future::pending().await,
+    }
+}
+
+fn ack(id: u64) -> ServerMessage {
+    ServerMessage::Ack {
+        id,
+        result: Value::Null,
+    }
+}
+
+fn bad_request(title: &str, e: serde_json::Error) -> HttpApiProblem {
+    HttpApiProblem::new(StatusCode::BAD_REQUEST)
+        .title(title)
+        .detail(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(tag = "action", rename_all = "snake_case")]
+    enum DummyCommand {
+        Commit { order_id: OrderId },
+    }
+
+    #[test]
+    fn deserialize_command_request() {
+        let order_id = OrderId::default();
+        let request = format!(
+            r#"{{"type":"command","id":7,"command":{{"action":"commit","order_id":"{order_id}"}}}}"#
+        );
+
+        let message = serde_json::from_str::<ClientMessage<DummyCommand>>(&request).unwrap();
+
+        match message {
+            ClientMessage::Command { id, command } => {
+                assert_eq!(id, 7);
+                assert_eq!(command, DummyCommand::Commit { order_id });
+            }
+            other => panic!("unexpected message {other:?}"),
+        }
+    }
+
+    #[test]
+    fn deserialize_subscribe_request() {
+        let request = r#"{"type":"subscribe","id":1,"topics":["cfds","wallet"]}"#;
+
+        let message = serde_json::from_str::<ClientMessage<DummyCommand>>(request).unwrap();
+
+        match message {
+            ClientMessage::Subscribe {
+                id,
+                topics,
+                after_sequence,
+            } => {
+                assert_eq!(id, 1);
+                assert_eq!(topics, vec![Topic::Cfds, Topic::Wallet]);
+                assert_eq!(after_sequence, None);
+            }
+            other => panic!("unexpected message {other:?}"),
+        }
+    }
+
+    #[test]
+    fn serialize_ack_with_type_tag() {
+        let json = serde_json::to_value(&ack(3)).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({ "type": "ack", "id": 3, "result": null })
+        );
+    }
+}
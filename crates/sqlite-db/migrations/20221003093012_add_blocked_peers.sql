@@ -0,0 +1,11 @@
+-- Peers the maker refuses to connect with.
+--
+-- If `expires_at` is NULL the peer is blocked until it is removed from
+-- the table.
+CREATE TABLE IF NOT EXISTS blocked_peers (
+    id integer PRIMARY KEY autoincrement,
+    peer_id text UNIQUE NOT NULL,
+    reason text NOT NULL,
+    blocked_at integer NOT NULL,
+    expires_at integer
+);
@@ -0,0 +1,4 @@
+-- Expiry of dated futures as unix timestamp.
+--
+-- NULL for perpetual CFDs, which are kept open by rolling over.
+ALTER TABLE cfds ADD COLUMN contract_expiry integer;
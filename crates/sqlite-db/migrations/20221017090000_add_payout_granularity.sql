@@ -0,0 +1,6 @@
+-- Granularity of the oracle events a CFD is set up and rolled over with.
+--
+-- CFDs created before the granularity was negotiated per offer use 200 payouts and hourly
+-- liquidation events.
+ALTER TABLE cfds ADD COLUMN n_payouts integer not null default 200;
+ALTER TABLE cfds ADD COLUMN liquidation_interval_hours integer not null default 1;
@@ -0,0 +1,26 @@
+-- Periodic snapshots of the maker's profit and loss and exposure.
+--
+-- Amounts are stored in satoshis. `unrealised_pnl` and `net_exposure` are
+-- NULL if the quote of a symbol with open positions was not known.
+CREATE TABLE IF NOT EXISTS exposure_snapshots (
+    id integer PRIMARY KEY autoincrement,
+    timestamp integer NOT NULL,
+    realised_pnl integer NOT NULL,
+    unrealised_pnl integer,
+    accrued_fees integer NOT NULL,
+    margin_at_risk integer NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS exposure_snapshots_timestamp ON exposure_snapshots (timestamp);
+
+CREATE TABLE IF NOT EXISTS exposure_snapshot_symbols (
+    id integer PRIMARY KEY autoincrement,
+    snapshot_id integer NOT NULL,
+    contract_symbol text NOT NULL,
+    price text,
+    long_contracts text NOT NULL,
+    short_contracts text NOT NULL,
+    net_exposure integer,
+    unrealised_pnl integer,
+    FOREIGN KEY (snapshot_id) REFERENCES exposure_snapshots (id)
+);
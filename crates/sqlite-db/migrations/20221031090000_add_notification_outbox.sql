@@ -0,0 +1,14 @@
+-- Notifications about CFD events which are yet to be delivered to a
+-- webhook.
+--
+-- A notification is deleted once it was delivered or given up on.
+-- `next_attempt_at` is a unix timestamp.
+CREATE TABLE IF NOT EXISTS notification_outbox (
+    id integer PRIMARY KEY autoincrement,
+    webhook text NOT NULL,
+    payload text NOT NULL,
+    attempts integer NOT NULL DEFAULT 0,
+    next_attempt_at integer NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS notification_outbox_next_attempt_at ON notification_outbox (next_attempt_at);
@@ -0,0 +1,18 @@
+-- Hedge orders of the maker on an exchange.
+--
+-- Every CFD has at most one hedge of each kind: `Open` offsets opening
+-- the CFD and `Close` unwinds it again. `exchange_order_id` is set once
+-- the order was placed, until then it is retried at `next_attempt_at`,
+-- a unix timestamp.
+CREATE TABLE IF NOT EXISTS hedges (
+    id integer PRIMARY KEY autoincrement,
+    order_id text NOT NULL,
+    kind text NOT NULL,
+    contract_symbol text NOT NULL,
+    position text NOT NULL,
+    quantity text NOT NULL,
+    exchange_order_id text,
+    attempts integer NOT NULL DEFAULT 0,
+    next_attempt_at integer NOT NULL,
+    UNIQUE (order_id, kind)
+);
@@ -0,0 +1,9 @@
+-- Reverse submarine swaps of the taker.
+--
+-- `data` is the JSON-encoded state of the swap, including the preimage
+-- and the key needed to claim its on-chain funds. Swaps are never
+-- deleted, such that they can be claimed after a restart.
+CREATE TABLE IF NOT EXISTS swaps (
+    id text PRIMARY KEY NOT NULL,
+    data text NOT NULL
+);
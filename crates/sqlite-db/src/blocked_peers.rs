@@ -0,0 +1,175 @@
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use model::libp2p::PeerId;
+use time::OffsetDateTime;
+
+/// A peer which the maker refuses to connect with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockedPeer {
+    pub peer_id: PeerId,
+    pub reason: String,
+    pub blocked_at: OffsetDateTime,
+    /// Point in time after which the peer is no longer blocked.
+    ///
+    /// If `None`, the peer is blocked until it is explicitly unblocked.
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+impl BlockedPeer {
+    pub fn is_expired(&self, now: OffsetDateTime) -> bool {
+        matches!(self.expires_at, Some(expires_at) if expires_at <= now)
+    }
+}
+
+impl Connection {
+    /// Add a peer to the blocklist.
+    ///
+    /// If the peer is already blocked, its reason and expiry are replaced.
+    pub async fn insert_blocked_peer(&self, blocked_peer: BlockedPeer) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        let peer_id = models::PeerId::from(blocked_peer.peer_id);
+        let blocked_at = blocked_peer.blocked_at.unix_timestamp();
+        let expires_at = blocked_peer
+            .expires_at
+            .map(|expires_at| expires_at.unix_timestamp());
+
+        sqlx::query!(
+            r#"
+            INSERT INTO blocked_peers
+            (
+                peer_id,
+                reason,
+                blocked_at,
+                expires_at
+            )
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT(peer_id) DO UPDATE SET
+                reason = $2,
+                blocked_at = $3,
+                expires_at = $4
+            "#,
+            peer_id,
+            blocked_peer.reason,
+            blocked_at,
+            expires_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Remove a peer from the blocklist.
+    ///
+    /// Returns `false` if the peer was not blocked.
+    pub async fn delete_blocked_peer(&self, peer_id: PeerId) -> Result<bool> {
+        let mut conn = self.inner.acquire().await?;
+
+        let peer_id = models::PeerId::from(peer_id);
+
+        let query_result = sqlx::query!(
+            r#"
+            DELETE FROM blocked_peers
+            WHERE peer_id = $1
+            "#,
+            peer_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(query_result.rows_affected() > 0)
+    }
+
+    /// Load all entries of the blocklist, including expired ones.
+    pub async fn load_blocked_peers(&self) -> Result<Vec<BlockedPeer>> {
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                peer_id as "peer_id: models::PeerId",
+                reason,
+                blocked_at,
+                expires_at
+            FROM
+                blocked_peers
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(BlockedPeer {
+                    peer_id: row.peer_id.into(),
+                    reason: row.reason,
+                    blocked_at: OffsetDateTime::from_unix_timestamp(row.blocked_at)?,
+                    expires_at: row
+                        .expires_at
+                        .map(OffsetDateTime::from_unix_timestamp)
+                        .transpose()?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+
+    #[tokio::test]
+    async fn given_blocked_peer_when_blocking_again_then_entry_is_replaced() {
+        let db = memory().await.unwrap();
+
+        let peer_id = PeerId::random();
+
+        db.insert_blocked_peer(dummy_blocked_peer(peer_id, "spam", None))
+            .await
+            .unwrap();
+        let expires_at = OffsetDateTime::from_unix_timestamp(100).unwrap();
+        db.insert_blocked_peer(dummy_blocked_peer(peer_id, "abuse", Some(expires_at)))
+            .await
+            .unwrap();
+
+        let blocked_peers = db.load_blocked_peers().await.unwrap();
+
+        assert_eq!(
+            blocked_peers,
+            vec![dummy_blocked_peer(peer_id, "abuse", Some(expires_at))]
+        );
+    }
+
+    #[tokio::test]
+    async fn given_blocked_peer_when_deleting_then_not_loaded() {
+        let db = memory().await.unwrap();
+
+        let peer_id = PeerId::random();
+        db.insert_blocked_peer(dummy_blocked_peer(peer_id, "spam", None))
+            .await
+            .unwrap();
+
+        let deleted = db.delete_blocked_peer(peer_id).await.unwrap();
+        let deleted_again = db.delete_blocked_peer(peer_id).await.unwrap();
+
+        assert!(deleted);
+        assert!(!deleted_again);
+        assert!(db.load_blocked_peers().await.unwrap().is_empty());
+    }
+
+    fn dummy_blocked_peer(
+        peer_id: PeerId,
+        reason: &str,
+        expires_at: Option<OffsetDateTime>,
+    ) -> BlockedPeer {
+        BlockedPeer {
+            peer_id,
+            reason: reason.to_owned(),
+            blocked_at: OffsetDateTime::from_unix_timestamp(1).unwrap(),
+            expires_at,
+        }
+    }
+}
@@ -47,7 +47,6 @@ use model::Price;
 use model::Role;
 use model::Settlement;
 use model::Timestamp;
-use model::SETTLEMENT_INTERVAL;
 use models::Payout;
 use models::Vout;
 use sqlx::Acquire;
@@ -161,6 +160,7 @@ impl Connection {
         };
 
         let creation_timestamp = load_creation_timestamp(&mut conn, id).await?;
+        let closing_timestamp = load_closing_timestamp(&mut conn, id).await?;
 
         let cfd = ClosedCfd {
             id,
@@ -180,13 +180,14 @@ impl Connection {
             },
             settlement,
             creation_timestamp,
+            closing_timestamp,
             contract_symbol: cfd.contract_symbol.into(),
         };
 
         Ok(C::new_closed(args, cfd))
     }
 
-    pub(crate) async fn load_closed_cfd_ids(&self) -> Result<Vec<OrderId>> {
+    pub async fn load_closed_cfd_ids(&self) -> Result<Vec<OrderId>> {
         let mut conn = self.inner.acquire().await?;
 
         let ids = sqlx::query!(
@@ -242,7 +243,7 @@ impl ClosedCfdInputAggregate {
             position,
             initial_price,
             taker_leverage,
-            settlement_interval: _,
+            settlement_interval,
             quantity,
             counterparty_network_identity,
             counterparty_peer_id,
@@ -250,6 +251,7 @@ impl ClosedCfdInputAggregate {
             opening_fee,
             initial_funding_rate,
             contract_symbol,
+            contract_type,
             ..
         } = cfd;
         let n_contracts = quantity.to_u64();
@@ -265,7 +267,7 @@ impl ClosedCfdInputAggregate {
                 long_leverage,
                 short_leverage,
                 initial_funding_rate,
-                SETTLEMENT_INTERVAL.whole_hours(),
+                contract_type.initial_funding_hours(settlement_interval),
                 contract_symbol,
             )
             .expect("values from db to be sane")
@@ -990,6 +992,35 @@ async fn load_creation_timestamp(conn: &mut SqliteConnection, id: OrderId) -> Re
     Ok(Timestamp::new(row.created_at))
 }
 
+/// Obtain the time at which the closed CFD was closed, according to
+/// the `event_log` table.
+///
+/// We use the timestamp of the last event for a particular CFD `id`
+/// in the `event_log` table.
+async fn load_closing_timestamp(conn: &mut SqliteConnection, id: OrderId) -> Result<Timestamp> {
+    let id = models::OrderId::from(id);
+
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            event_log.created_at as "created_at!: i64"
+        FROM
+            event_log
+        JOIN
+            closed_cfds on closed_cfds.id = event_log.cfd_id
+        WHERE
+            closed_cfds.order_id = $1
+        ORDER BY event_log.created_at DESC
+        LIMIT 1
+        "#,
+        id,
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(Timestamp::new(row.created_at))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -998,12 +1029,14 @@ mod tests {
     use model::libp2p::PeerId;
     use model::Cfd;
     use model::ContractSymbol;
+    use model::ContractType;
     use model::Contracts;
     use model::EventKind;
     use model::FundingRate;
     use model::OfferId;
     use model::OpeningFee;
     use model::Payout;
+    use model::PayoutGranularity;
     use model::Price;
     use model::Timestamp;
     use model::TxFeeRate;
@@ -1310,6 +1343,8 @@ mod tests {
             FundingRate::default(),
             TxFeeRate::default(),
             ContractSymbol::BtcUsd,
+            ContractType::Perpetual,
+            PayoutGranularity::default(),
         );
 
         let contract_setup_completed =
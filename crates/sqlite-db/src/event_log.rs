@@ -1,5 +1,11 @@
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use model::libp2p::PeerId;
 use model::CfdEvent;
 use model::EventKind;
+use model::OrderId;
+use model::Timestamp;
 
 pub(super) struct EventLog(pub Vec<EventLogEntry>);
 
@@ -28,3 +34,191 @@ impl From<&CfdEvent> for EventLogEntry {
         Self { name, created_at }
     }
 }
+
+/// An entry of the event timeline of a CFD.
+///
+/// Only the name of the events is kept once a CFD is closed or failed, hence the timeline does not
+/// include the event data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineEvent {
+    pub name: String,
+    pub timestamp: Timestamp,
+}
+
+/// The number of events with a particular name across all CFDs with a counterparty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CounterpartyEventCount {
+    pub counterparty: PeerId,
+    pub name: String,
+    pub count: u64,
+}
+
+impl Connection {
+    /// Load the event timeline of a CFD, regardless of whether it is open, closed or failed.
+    ///
+    /// Returns an empty timeline if the CFD does not exist.
+    pub async fn load_cfd_timeline(&self, id: OrderId) -> Result<Vec<TimelineEvent>> {
+        let mut conn = self.inner.acquire().await?;
+        let id = models::OrderId::from(id);
+
+        let events = sqlx::query!(
+            r#"
+            SELECT
+                events.name,
+                events.created_at as "created_at: models::Timestamp"
+            FROM
+                events
+            JOIN
+                cfds on cfds.id = events.cfd_id
+            WHERE
+                cfds.order_id = $1
+            ORDER BY events.id ASC
+            "#,
+            id
+        )
+        .fetch_all(&mut conn)
+        .await?
+        .into_iter()
+        .map(|row| TimelineEvent {
+            name: row.name,
+            timestamp: row.created_at.into(),
+        })
+        .collect::<Vec<_>>();
+
+        if !events.is_empty() {
+            return Ok(events);
+        }
+
+        let closed = sqlx::query!(
+            r#"
+            SELECT
+                event_log.name,
+                event_log.created_at
+            FROM
+                event_log
+            JOIN
+                closed_cfds on closed_cfds.id = event_log.cfd_id
+            WHERE
+                closed_cfds.order_id = $1
+            ORDER BY event_log.id ASC
+            "#,
+            id
+        )
+        .fetch_all(&mut conn)
+        .await?
+        .into_iter()
+        .map(|row| TimelineEvent {
+            name: row.name,
+            timestamp: Timestamp::new(row.created_at),
+        })
+        .collect::<Vec<_>>();
+
+        if !closed.is_empty() {
+            return Ok(closed);
+        }
+
+        let failed = sqlx::query!(
+            r#"
+            SELECT
+                event_log_failed.name,
+                event_log_failed.created_at
+            FROM
+                event_log_failed
+            JOIN
+                failed_cfds on failed_cfds.id = event_log_failed.cfd_id
+            WHERE
+                failed_cfds.order_id = $1
+            ORDER BY event_log_failed.id ASC
+            "#,
+            id
+        )
+        .fetch_all(&mut conn)
+        .await?
+        .into_iter()
+        .map(|row| TimelineEvent {
+            name: row.name,
+            timestamp: Timestamp::new(row.created_at),
+        })
+        .collect();
+
+        Ok(failed)
+    }
+
+    /// Count the events of all open, closed and failed CFDs by counterparty and event name.
+    ///
+    /// The same counterparty and event name can appear up to three times, once for every table.
+    pub async fn load_event_counts_by_counterparty(&self) -> Result<Vec<CounterpartyEventCount>> {
+        let mut conn = self.inner.acquire().await?;
+
+        let open = sqlx::query!(
+            r#"
+            SELECT
+                cfds.counterparty_peer_id as "counterparty_peer_id: models::PeerId",
+                events.name,
+                COUNT(*) as "count!: i64"
+            FROM
+                events
+            JOIN
+                cfds on cfds.id = events.cfd_id
+            GROUP BY
+                cfds.counterparty_peer_id, events.name
+            "#
+        )
+        .fetch_all(&mut conn)
+        .await?
+        .into_iter()
+        .map(|row| (row.counterparty_peer_id, row.name, row.count));
+
+        let closed = sqlx::query!(
+            r#"
+            SELECT
+                closed_cfds.counterparty_peer_id as "counterparty_peer_id: models::PeerId",
+                event_log.name,
+                COUNT(*) as "count!: i64"
+            FROM
+                event_log
+            JOIN
+                closed_cfds on closed_cfds.id = event_log.cfd_id
+            GROUP BY
+                closed_cfds.counterparty_peer_id, event_log.name
+            "#
+        )
+        .fetch_all(&mut conn)
+        .await?
+        .into_iter()
+        .map(|row| (row.counterparty_peer_id, row.name, row.count));
+
+        let failed = sqlx::query!(
+            r#"
+            SELECT
+                failed_cfds.counterparty_peer_id as "counterparty_peer_id: models::PeerId",
+                event_log_failed.name,
+                COUNT(*) as "count!: i64"
+            FROM
+                event_log_failed
+            JOIN
+                failed_cfds on failed_cfds.id = event_log_failed.cfd_id
+            GROUP BY
+                failed_cfds.counterparty_peer_id, event_log_failed.name
+            "#
+        )
+        .fetch_all(&mut conn)
+        .await?
+        .into_iter()
+        .map(|row| (row.counterparty_peer_id, row.name, row.count));
+
+        let counts = open
+            .chain(closed)
+            .chain(failed)
+            .map(|(counterparty, name, count)| {
+                Ok(CounterpartyEventCount {
+                    counterparty: counterparty.into(),
+                    name,
+                    count: u64::try_from(count)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(counts)
+    }
+}
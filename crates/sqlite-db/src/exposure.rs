@@ -0,0 +1,255 @@
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::SignedAmount;
+use model::ContractSymbol;
+use model::Contracts;
+use model::Price;
+use sqlx::Acquire;
+use time::OffsetDateTime;
+
+/// Snapshot of the maker's profit and loss and exposure at a point in time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExposureSnapshot {
+    pub timestamp: OffsetDateTime,
+    pub realised_pnl: SignedAmount,
+    /// `None` if the quote of a symbol with open positions was not known
+    pub unrealised_pnl: Option<SignedAmount>,
+    pub accrued_fees: SignedAmount,
+    pub margin_at_risk: Amount,
+    pub symbols: Vec<SymbolExposureSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolExposureSnapshot {
+    pub contract_symbol: ContractSymbol,
+    pub price: Option<Price>,
+    pub long_contracts: Contracts,
+    pub short_contracts: Contracts,
+    pub net_exposure: Option<SignedAmount>,
+    pub unrealised_pnl: Option<SignedAmount>,
+}
+
+impl Connection {
+    pub async fn insert_exposure_snapshot(&self, snapshot: ExposureSnapshot) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+        let mut db_tx = conn.begin().await?;
+
+        let timestamp = snapshot.timestamp.unix_timestamp();
+        let realised_pnl = snapshot.realised_pnl.as_sat();
+        let unrealised_pnl = snapshot.unrealised_pnl.map(|pnl| pnl.as_sat());
+        let accrued_fees = snapshot.accrued_fees.as_sat();
+        let margin_at_risk = snapshot.margin_at_risk.as_sat() as i64;
+
+        let snapshot_id = sqlx::query!(
+            r#"
+            INSERT INTO exposure_snapshots
+            (
+                timestamp,
+                realised_pnl,
+                unrealised_pnl,
+                accrued_fees,
+                margin_at_risk
+            )
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            timestamp,
+            realised_pnl,
+            unrealised_pnl,
+            accrued_fees,
+            margin_at_risk,
+        )
+        .execute(&mut db_tx)
+        .await?
+        .last_insert_rowid();
+
+        for symbol in snapshot.symbols {
+            let contract_symbol = models::ContractSymbol::from(symbol.contract_symbol);
+            let price = symbol.price.map(models::Price::from);
+            let long_contracts = models::Contracts::from(symbol.long_contracts);
+            let short_contracts = models::Contracts::from(symbol.short_contracts);
+            let net_exposure = symbol.net_exposure.map(|exposure| exposure.as_sat());
+            let unrealised_pnl = symbol.unrealised_pnl.map(|pnl| pnl.as_sat());
+
+            sqlx::query!(
+                r#"
+                INSERT INTO exposure_snapshot_symbols
+                (
+                    snapshot_id,
+                    contract_symbol,
+                    price,
+                    long_contracts,
+                    short_contracts,
+                    net_exposure,
+                    unrealised_pnl
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+                snapshot_id,
+                contract_symbol,
+                price,
+                long_contracts,
+                short_contracts,
+                net_exposure,
+                unrealised_pnl,
+            )
+            .execute(&mut db_tx)
+            .await?;
+        }
+
+        db_tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Load the latest snapshots taken at or after `since`, newest first.
+    pub async fn load_exposure_snapshots(
+        &self,
+        since: OffsetDateTime,
+        limit: u32,
+    ) -> Result<Vec<ExposureSnapshot>> {
+        let mut conn = self.inner.acquire().await?;
+
+        let since = since.unix_timestamp();
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                id as "id!: i64",
+                timestamp,
+                realised_pnl,
+                unrealised_pnl,
+                accrued_fees,
+                margin_at_risk
+            FROM
+                exposure_snapshots
+            WHERE
+                timestamp >= $1
+            ORDER BY timestamp DESC
+            LIMIT $2
+            "#,
+            since,
+            limit,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut snapshots = Vec::with_capacity(rows.len());
+        for row in rows {
+            let symbols = sqlx::query!(
+                r#"
+                SELECT
+                    contract_symbol as "contract_symbol: models::ContractSymbol",
+                    price as "price: models::Price",
+                    long_contracts as "long_contracts: models::Contracts",
+                    short_contracts as "short_contracts: models::Contracts",
+                    net_exposure,
+                    unrealised_pnl
+                FROM
+                    exposure_snapshot_symbols
+                WHERE
+                    snapshot_id = $1
+                ORDER BY id
+                "#,
+                row.id,
+            )
+            .fetch_all(&mut *conn)
+            .await?
+            .into_iter()
+            .map(|symbol| {
+                Ok(SymbolExposureSnapshot {
+                    contract_symbol: symbol.contract_symbol.into(),
+                    price: symbol.price.map(Price::from),
+                    long_contracts: symbol.long_contracts.try_into()?,
+                    short_contracts: symbol.short_contracts.try_into()?,
+                    net_exposure: symbol.net_exposure.map(SignedAmount::from_sat),
+                    unrealised_pnl: symbol.unrealised_pnl.map(SignedAmount::from_sat),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+            snapshots.push(ExposureSnapshot {
+                timestamp: OffsetDateTime::from_unix_timestamp(row.timestamp)?,
+                realised_pnl: SignedAmount::from_sat(row.realised_pnl),
+                unrealised_pnl: row.unrealised_pnl.map(SignedAmount::from_sat),
+                accrued_fees: SignedAmount::from_sat(row.accrued_fees),
+                margin_at_risk: Amount::from_sat(u64::try_from(row.margin_at_risk)?),
+                symbols,
+            });
+        }
+
+        Ok(snapshots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn given_snapshots_when_loading_since_then_newer_snapshots_are_loaded_newest_first() {
+        let db = memory().await.unwrap();
+
+        for timestamp in [100, 200, 300] {
+            db.insert_exposure_snapshot(dummy_snapshot(timestamp))
+                .await
+                .unwrap();
+        }
+
+        let snapshots = db
+            .load_exposure_snapshots(OffsetDateTime::from_unix_timestamp(200).unwrap(), 10)
+            .await
+            .unwrap();
+
+        assert_eq!(snapshots, vec![dummy_snapshot(300), dummy_snapshot(200)]);
+    }
+
+    #[tokio::test]
+    async fn given_snapshots_when_loading_with_limit_then_only_latest_snapshots_are_loaded() {
+        let db = memory().await.unwrap();
+
+        for timestamp in [100, 200, 300] {
+            db.insert_exposure_snapshot(dummy_snapshot(timestamp))
+                .await
+                .unwrap();
+        }
+
+        let snapshots = db
+            .load_exposure_snapshots(OffsetDateTime::UNIX_EPOCH, 1)
+            .await
+            .unwrap();
+
+        assert_eq!(snapshots, vec![dummy_snapshot(300)]);
+    }
+
+    fn dummy_snapshot(timestamp: i64) -> ExposureSnapshot {
+        ExposureSnapshot {
+            timestamp: OffsetDateTime::from_unix_timestamp(timestamp).unwrap(),
+            realised_pnl: SignedAmount::from_sat(-1_000),
+            unrealised_pnl: None,
+            accrued_fees: SignedAmount::from_sat(500),
+            margin_at_risk: Amount::from_sat(20_000),
+            symbols: vec![
+                SymbolExposureSnapshot {
+                    contract_symbol: ContractSymbol::BtcUsd,
+                    price: Some(Price::from_str("20000").unwrap()),
+                    long_contracts: Contracts::new(300),
+                    short_contracts: Contracts::new(100),
+                    net_exposure: Some(SignedAmount::from_sat(1_000_000)),
+                    unrealised_pnl: Some(SignedAmount::from_sat(2_000)),
+                },
+                SymbolExposureSnapshot {
+                    contract_symbol: ContractSymbol::EthUsd,
+                    price: None,
+                    long_contracts: Contracts::ZERO,
+                    short_contracts: Contracts::new(100),
+                    net_exposure: None,
+                    unrealised_pnl: None,
+                },
+            ],
+        }
+    }
+}
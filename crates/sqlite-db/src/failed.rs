@@ -115,6 +115,7 @@ impl Connection {
         .await?;
 
         let creation_timestamp = load_creation_timestamp(&mut conn, id).await?;
+        let closing_timestamp = load_closing_timestamp(&mut conn, id).await?;
 
         let cfd = FailedCfd {
             id,
@@ -129,13 +130,14 @@ impl Connection {
             fees: cfd.fees.into(),
             kind: cfd.kind.into(),
             creation_timestamp,
+            closing_timestamp,
             contract_symbol: cfd.contract_symbol.into(),
         };
 
         Ok(C::new_failed(args, cfd))
     }
 
-    pub(crate) async fn load_failed_cfd_ids(&self) -> Result<Vec<OrderId>> {
+    pub async fn load_failed_cfd_ids(&self) -> Result<Vec<OrderId>> {
         let mut conn = self.inner.acquire().await?;
 
         let ids = sqlx::query!(
@@ -181,7 +183,8 @@ async fn insert_failed_cfd(
             long_leverage,
             short_leverage,
             cfd.initial_funding_rate,
-            cfd.settlement_interval.whole_hours(),
+            cfd.contract_type
+                .initial_funding_hours(cfd.settlement_interval),
             cfd.contract_symbol,
         )
         .expect("values from db to be sane");
@@ -316,9 +319,39 @@ async fn load_creation_timestamp(conn: &mut SqliteConnection, id: OrderId) -> Re
     Ok(Timestamp::new(row.created_at))
 }
 
+/// Obtain the time at which the failed CFD was marked as failed, according to
+/// the `event_log_failed` table.
+///
+/// We use the timestamp of the last event for a particular CFD `id`
+/// in the `event_log_failed` table.
+async fn load_closing_timestamp(conn: &mut SqliteConnection, id: OrderId) -> Result<Timestamp> {
+    let id = models::OrderId::from(id);
+
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            event_log_failed.created_at as "created_at!: i64"
+        FROM
+            event_log_failed
+        JOIN
+            failed_cfds on failed_cfds.id = event_log_failed.cfd_id
+        WHERE
+            failed_cfds.order_id = $1
+        ORDER BY event_log_failed.created_at DESC
+        LIMIT 1
+        "#,
+        id,
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(Timestamp::new(row.created_at))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::event_log::TimelineEvent;
     use crate::memory;
     use crate::tests::dummy_cfd;
     use crate::tests::lock_confirmed;
@@ -433,6 +466,57 @@ mod tests {
         assert_eq!(creation_timestamp, contract_setup_started_timestamp);
     }
 
+    #[tokio::test]
+    async fn given_contract_setup_failed_when_move_cfds_to_failed_table_then_timeline_and_closing_timestamp_are_kept(
+    ) {
+        let db = memory().await.unwrap();
+        let mut conn = db.inner.acquire().await.unwrap();
+
+        let cfd = dummy_cfd();
+        let id = cfd.id();
+
+        db.insert_cfd(&cfd).await.unwrap();
+
+        let contract_setup_started_timestamp = Timestamp::new(1);
+        db.append_event(CfdEvent {
+            timestamp: contract_setup_started_timestamp,
+            id,
+            event: EventKind::ContractSetupStarted,
+        })
+        .await
+        .unwrap();
+
+        let contract_setup_failed_timestamp = Timestamp::new(2);
+        db.append_event(CfdEvent {
+            timestamp: contract_setup_failed_timestamp,
+            id,
+            event: EventKind::ContractSetupFailed,
+        })
+        .await
+        .unwrap();
+
+        let expected_timeline = vec![
+            TimelineEvent {
+                name: "ContractSetupStarted".to_owned(),
+                timestamp: contract_setup_started_timestamp,
+            },
+            TimelineEvent {
+                name: "ContractSetupFailed".to_owned(),
+                timestamp: contract_setup_failed_timestamp,
+            },
+        ];
+        let open_timeline = db.load_cfd_timeline(id).await.unwrap();
+
+        db.move_to_failed_cfds().await.unwrap();
+
+        let failed_timeline = db.load_cfd_timeline(id).await.unwrap();
+        let closing_timestamp = load_closing_timestamp(&mut *conn, id).await.unwrap();
+
+        assert_eq!(open_timeline, expected_timeline);
+        assert_eq!(failed_timeline, expected_timeline);
+        assert_eq!(closing_timestamp, contract_setup_failed_timestamp);
+    }
+
     #[derive(Debug, Clone)]
     struct DummyAggregate;
 
@@ -0,0 +1,352 @@
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use model::ContractSymbol;
+use model::Contracts;
+use model::OrderId;
+use model::Position;
+use time::OffsetDateTime;
+
+/// Whether a hedge offsets opening a CFD or unwinds it again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+pub enum HedgeKind {
+    Open,
+    Close,
+}
+
+/// A hedge order which is yet to be placed on the exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingHedge {
+    pub id: i64,
+    pub order_id: OrderId,
+    pub kind: HedgeKind,
+    pub contract_symbol: ContractSymbol,
+    /// Our position in the hedged CFD
+    pub position: Position,
+    pub quantity: Contracts,
+    /// The number of failed attempts to place the order
+    pub attempts: u32,
+}
+
+impl Connection {
+    /// Queue the hedge for opening a CFD for immediate placement.
+    ///
+    /// Returns `false` if the CFD was already hedged.
+    pub async fn insert_opening_hedge(
+        &self,
+        order_id: OrderId,
+        contract_symbol: ContractSymbol,
+        position: Position,
+        quantity: Contracts,
+        now: OffsetDateTime,
+    ) -> Result<bool> {
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(order_id);
+        let contract_symbol = models::ContractSymbol::from(contract_symbol);
+        let position = models::Position::from(position);
+        let quantity = models::Contracts::from(quantity);
+        let next_attempt_at = now.unix_timestamp();
+
+        let inserted = sqlx::query!(
+            r#"
+            INSERT OR IGNORE INTO hedges
+            (
+                order_id,
+                kind,
+                contract_symbol,
+                position,
+                quantity,
+                next_attempt_at
+            )
+            VALUES ($1, 'Open', $2, $3, $4, $5)
+            "#,
+            order_id,
+            contract_symbol,
+            position,
+            quantity,
+            next_attempt_at,
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+
+        Ok(inserted == 1)
+    }
+
+    /// Queue the hedge unwinding the hedge for opening a CFD for immediate placement.
+    ///
+    /// Returns `false` if the CFD was never hedged or its hedge was already unwound.
+    pub async fn insert_closing_hedge(
+        &self,
+        order_id: OrderId,
+        now: OffsetDateTime,
+    ) -> Result<bool> {
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(order_id);
+        let next_attempt_at = now.unix_timestamp();
+
+        let inserted = sqlx::query!(
+            r#"
+            INSERT OR IGNORE INTO hedges
+            (
+                order_id,
+                kind,
+                contract_symbol,
+                position,
+                quantity,
+                next_attempt_at
+            )
+            SELECT
+                order_id,
+                'Close',
+                contract_symbol,
+                position,
+                quantity,
+                $2
+            FROM
+                hedges
+            WHERE
+                order_id = $1 AND kind = 'Open'
+            "#,
+            order_id,
+            next_attempt_at,
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+
+        Ok(inserted == 1)
+    }
+
+    /// Load up to `limit` hedges which are due for placement at `now`, oldest first.
+    ///
+    /// The hedge closing a CFD is only due once the hedge opening it was placed.
+    pub async fn load_due_hedges(
+        &self,
+        now: OffsetDateTime,
+        limit: u32,
+    ) -> Result<Vec<PendingHedge>> {
+        let mut conn = self.inner.acquire().await?;
+
+        let now = now.unix_timestamp();
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                id as "id!: i64",
+                order_id as "order_id: models::OrderId",
+                kind as "kind: HedgeKind",
+                contract_symbol as "contract_symbol: models::ContractSymbol",
+                position as "position: models::Position",
+                quantity as "quantity: models::Contracts",
+                attempts
+            FROM
+                hedges
+            WHERE
+                exchange_order_id IS NULL
+                AND next_attempt_at <= $1
+                AND NOT (
+                    kind = 'Close' AND EXISTS (
+                        SELECT 1 FROM hedges AS opening
+                        WHERE
+                            opening.order_id = hedges.order_id
+                            AND opening.kind = 'Open'
+                            AND opening.exchange_order_id IS NULL
+                    )
+                )
+            ORDER BY id
+            LIMIT $2
+            "#,
+            now,
+            limit,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(PendingHedge {
+                    id: row.id,
+                    order_id: row.order_id.into(),
+                    kind: row.kind,
+                    contract_symbol: row.contract_symbol.into(),
+                    position: row.position.into(),
+                    quantity: row.quantity.try_into()?,
+                    attempts: u32::try_from(row.attempts)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Record that a hedge was placed on the exchange under `exchange_order_id`.
+    pub async fn mark_hedge_placed(&self, id: i64, exchange_order_id: &str) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE hedges
+            SET
+                exchange_order_id = $2
+            WHERE id = $1
+            "#,
+            id,
+            exchange_order_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record a failed attempt to place a hedge and schedule the next one.
+    pub async fn reschedule_hedge(
+        &self,
+        id: i64,
+        attempts: u32,
+        next_attempt_at: OffsetDateTime,
+    ) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        let next_attempt_at = next_attempt_at.unix_timestamp();
+
+        sqlx::query!(
+            r#"
+            UPDATE hedges
+            SET
+                attempts = $2,
+                next_attempt_at = $3
+            WHERE id = $1
+            "#,
+            id,
+            attempts,
+            next_attempt_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+
+    #[tokio::test]
+    async fn given_cfd_hedged_twice_then_only_one_hedge_is_pending() {
+        let db = memory().await.unwrap();
+        let order_id = OrderId::default();
+
+        assert!(insert_opening_hedge(&db, order_id).await);
+        assert!(!insert_opening_hedge(&db, order_id).await);
+
+        let pending = db.load_due_hedges(timestamp(100), 10).await.unwrap();
+
+        assert_eq!(
+            pending,
+            vec![PendingHedge {
+                id: pending[0].id,
+                order_id,
+                kind: HedgeKind::Open,
+                contract_symbol: ContractSymbol::BtcUsd,
+                position: Position::Short,
+                quantity: Contracts::new(100),
+                attempts: 0,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn given_cfd_without_hedge_then_it_is_not_unwound() {
+        let db = memory().await.unwrap();
+
+        let inserted = db
+            .insert_closing_hedge(OrderId::default(), timestamp(100))
+            .await
+            .unwrap();
+
+        assert!(!inserted);
+        assert!(db
+            .load_due_hedges(timestamp(100), 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn given_placed_hedge_when_closing_twice_then_it_is_unwound_once() {
+        let db = memory().await.unwrap();
+        let order_id = OrderId::default();
+        insert_opening_hedge(&db, order_id).await;
+        let id = db.load_due_hedges(timestamp(100), 10).await.unwrap()[0].id;
+        db.mark_hedge_placed(id, "exchange-order").await.unwrap();
+
+        assert!(db
+            .insert_closing_hedge(order_id, timestamp(100))
+            .await
+            .unwrap());
+        assert!(!db
+            .insert_closing_hedge(order_id, timestamp(100))
+            .await
+            .unwrap());
+
+        let pending = db.load_due_hedges(timestamp(100), 10).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].kind, HedgeKind::Close);
+        assert_eq!(pending[0].position, Position::Short);
+        assert_eq!(pending[0].quantity, Contracts::new(100));
+    }
+
+    #[tokio::test]
+    async fn given_unplaced_opening_hedge_then_closing_hedge_is_not_due() {
+        let db = memory().await.unwrap();
+        let order_id = OrderId::default();
+        insert_opening_hedge(&db, order_id).await;
+        db.insert_closing_hedge(order_id, timestamp(100))
+            .await
+            .unwrap();
+
+        let pending = db.load_due_hedges(timestamp(100), 10).await.unwrap();
+
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].kind, HedgeKind::Open);
+    }
+
+    #[tokio::test]
+    async fn given_rescheduled_hedge_then_it_is_only_due_at_next_attempt() {
+        let db = memory().await.unwrap();
+        insert_opening_hedge(&db, OrderId::default()).await;
+        let id = db.load_due_hedges(timestamp(100), 10).await.unwrap()[0].id;
+
+        db.reschedule_hedge(id, 1, timestamp(200)).await.unwrap();
+
+        assert!(db
+            .load_due_hedges(timestamp(199), 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            db.load_due_hedges(timestamp(200), 10).await.unwrap()[0].attempts,
+            1
+        );
+    }
+
+    async fn insert_opening_hedge(db: &Connection, order_id: OrderId) -> bool {
+        db.insert_opening_hedge(
+            order_id,
+            ContractSymbol::BtcUsd,
+            Position::Short,
+            Contracts::new(100),
+            timestamp(100),
+        )
+        .await
+        .unwrap()
+    }
+
+    fn timestamp(seconds: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(seconds).unwrap()
+    }
+}
@@ -20,6 +20,8 @@ impl crate::CfdAggregate for model::Cfd {
             initial_funding_rate,
             initial_tx_fee_rate,
             contract_symbol,
+            contract_type,
+            granularity,
         }: crate::Cfd,
     ) -> Self {
         model::Cfd::new(
@@ -37,6 +39,8 @@ impl crate::CfdAggregate for model::Cfd {
             initial_funding_rate,
             initial_tx_fee_rate,
             contract_symbol,
+            contract_type,
+            granularity,
         )
     }
 
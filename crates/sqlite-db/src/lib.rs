@@ -10,6 +10,7 @@ use futures::Stream;
 use model::libp2p::PeerId;
 use model::CfdEvent;
 use model::ContractSymbol;
+use model::ContractType;
 use model::Contracts;
 use model::EventKind;
 use model::FundingRate;
@@ -18,6 +19,7 @@ use model::Leverage;
 use model::OfferId;
 use model::OpeningFee;
 use model::OrderId;
+use model::PayoutGranularity;
 use model::Position;
 use model::Price;
 use model::Role;
@@ -33,17 +35,23 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 use time::Duration;
+use time::OffsetDateTime;
 
 pub use closed::*;
 pub use failed::*;
 use model::EventKind::RolloverCompleted;
 
+pub mod blocked_peers;
 pub mod closed;
 pub mod event_log;
+pub mod exposure;
 pub mod failed;
+pub mod hedges;
 mod impls;
 mod models;
+pub mod notification_outbox;
 mod rollover;
+mod swaps;
 pub mod time_to_first_position;
 pub mod user;
 
@@ -128,6 +136,19 @@ pub fn connect(
     .boxed()
 }
 
+/// Opens the SQLite database at the given path without write access.
+///
+/// No migrations are applied, hence the database has to be on the schema of this version. Meant for
+/// inspecting the database of a daemon, possibly while it is running.
+pub async fn connect_read_only(path: PathBuf) -> Result<Connection> {
+    let pool =
+        SqlitePool::connect_with(SqliteConnectOptions::new().filename(&path).read_only(true))
+            .await
+            .with_context(|| format!("Failed to open database at {}", path.display()))?;
+
+    Ok(Connection::new(pool))
+}
+
 pub async fn memory() -> Result<Connection> {
     // Note: Every :memory: database is distinct from every other. So, opening two database
     // connections each with the filename ":memory:" will create two independent in-memory
@@ -168,6 +189,11 @@ impl Connection {
         let tx_fee_rate = models::TxFeeRate::from(cfd.initial_tx_fee_rate());
         let counterparty_peer_id = cfd.counterparty_peer_id().map(models::PeerId::from);
         let contract_symbol = models::ContractSymbol::from(cfd.contract_symbol());
+        let contract_expiry = cfd
+            .contract_type()
+            .expiry()
+            .map(|expiry| expiry.unix_timestamp());
+        let granularity = cfd.granularity();
 
         let query_result = sqlx::query(
             r#"
@@ -185,8 +211,11 @@ impl Connection {
             opening_fee,
             initial_funding_rate,
             initial_tx_fee_rate,
-            contract_symbol
-        ) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)"#,
+            contract_symbol,
+            contract_expiry,
+            n_payouts,
+            liquidation_interval_hours
+        ) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)"#,
         )
         .bind(&order_id)
         .bind(&offer_id)
@@ -209,6 +238,9 @@ impl Connection {
         .bind(&initial_funding_rate)
         .bind(&tx_fee_rate)
         .bind(&contract_symbol)
+        .bind(&contract_expiry)
+        .bind(&(granularity.n_payouts as i64))
+        .bind(&granularity.liquidation_interval.whole_hours())
         .execute(&mut conn)
         .await?;
 
@@ -224,65 +256,16 @@ impl Connection {
     /// To make handling of `None` events more ergonomic, you can pass anything in here that
     /// implements `Into<Option>` event.
     pub async fn append_event(&self, event: impl Into<Option<CfdEvent>>) -> Result<()> {
-        let mut conn = self.inner.acquire().await?;
-        let mut db_tx = conn.begin().await?;
-
         let event = match event.into() {
             Some(event) => event,
             None => return Ok(()),
         };
 
-        let (event_name, event_data) = event.event.to_json();
-
-        let order_id = models::OrderId::from(event.id);
-        let timestamp = models::Timestamp::from(event.timestamp);
-        let query_result = sqlx::query(
-            r##"
-        insert into events (
-            cfd_id,
-            name,
-            data,
-            created_at
-        ) values (
-            (select id from cfds where cfds.order_id = $1),
-            $2, $3, $4
-        )"##,
-        )
-        .bind(&order_id)
-        .bind(&event_name)
-        .bind(&event_data)
-        .bind(&timestamp)
-        .execute(&mut db_tx)
-        .await?;
+        let mut conn = self.inner.acquire().await?;
+        let mut db_tx = conn.begin().await?;
 
-        if query_result.rows_affected() != 1 {
-            bail!("failed to insert event");
-        }
-
-        match event.event {
-            // if we have a rollover completed event we store it additionally in its own table
-            RolloverCompleted {
-                dlc: Some(dlc),
-                funding_fee,
-                complete_fee,
-            } => {
-                rollover::overwrite(
-                    &mut db_tx,
-                    query_result.last_insert_rowid(),
-                    order_id,
-                    dlc,
-                    funding_fee,
-                    complete_fee,
-                )
-                .await?;
-            }
-            RolloverCompleted { dlc: None, .. } => {
-                tracing::error!(
-                    "Invalid RolloverCompleted event: Trying to insert a RolloverCompleted event without a DLC"
-                )
-            }
-            _ => {}
-        }
+        let order_id = event.id;
+        let event_name = insert_event(&mut db_tx, event).await?;
 
         db_tx.commit().await?;
 
@@ -336,6 +319,21 @@ impl Connection {
         Ok(cfd)
     }
 
+    /// Load a CFD together with all of its events, without applying them.
+    ///
+    /// Only CFDs which are still in the `cfds` table have an event log, the events of closed and
+    /// failed CFDs are dropped once they are moved to their own tables.
+    pub async fn load_cfd_with_events(&self, id: OrderId) -> Result<(Cfd, Vec<CfdEvent>), Error> {
+        let mut conn = self.inner.acquire().await?;
+
+        let cfd = load_cfd_row(&mut conn, id).await?;
+        let events = load_cfd_events(&mut conn, id, 0)
+            .await
+            .with_context(|| format!("Could not load events for CFD {id}"))?;
+
+        Ok((cfd, events))
+    }
+
     pub fn load_all_cfds<'a, C>(
         &'a self,
         args: C::CtorArgs,
@@ -526,6 +524,8 @@ pub struct Cfd {
     pub initial_funding_rate: FundingRate,
     pub initial_tx_fee_rate: TxFeeRate,
     pub contract_symbol: ContractSymbol,
+    pub contract_type: ContractType,
+    pub granularity: PayoutGranularity,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -563,6 +563,63 @@ pub trait CfdAggregate: Clone + Send + Sync + 'static {
     fn version(&self) -> u32;
 }
 
+/// Insert an event into the `events` table, returning the name of the event.
+pub(crate) async fn insert_event(conn: &mut SqliteConnection, event: CfdEvent) -> Result<String> {
+    let (event_name, event_data) = event.event.to_json();
+
+    let order_id = models::OrderId::from(event.id);
+    let timestamp = models::Timestamp::from(event.timestamp);
+    let query_result = sqlx::query(
+        r##"
+        insert into events (
+            cfd_id,
+            name,
+            data,
+            created_at
+        ) values (
+            (select id from cfds where cfds.order_id = $1),
+            $2, $3, $4
+        )"##,
+    )
+    .bind(&order_id)
+    .bind(&event_name)
+    .bind(&event_data)
+    .bind(&timestamp)
+    .execute(&mut *conn)
+    .await?;
+
+    if query_result.rows_affected() != 1 {
+        bail!("failed to insert event");
+    }
+
+    match event.event {
+        // if we have a rollover completed event we store it additionally in its own table
+        RolloverCompleted {
+            dlc: Some(dlc),
+            funding_fee,
+            complete_fee,
+        } => {
+            rollover::overwrite(
+                &mut *conn,
+                query_result.last_insert_rowid(),
+                order_id,
+                dlc,
+                funding_fee,
+                complete_fee,
+            )
+            .await?;
+        }
+        RolloverCompleted { dlc: None, .. } => {
+            tracing::error!(
+                "Invalid RolloverCompleted event: Trying to insert a RolloverCompleted event without a DLC"
+            )
+        }
+        _ => {}
+    }
+
+    Ok(event_name)
+}
+
 async fn load_cfd_row(conn: &mut SqliteConnection, id: OrderId) -> Result<Cfd, Error> {
     let id = models::OrderId::from(id);
 
@@ -583,7 +640,10 @@ async fn load_cfd_row(conn: &mut SqliteConnection, id: OrderId) -> Result<Cfd, E
                 opening_fee as "opening_fee: models::OpeningFee",
                 initial_funding_rate as "initial_funding_rate: models::FundingRate",
                 initial_tx_fee_rate as "initial_tx_fee_rate: models::TxFeeRate",
-                contract_symbol as "contract_symbol: models::ContractSymbol"
+                contract_symbol as "contract_symbol: models::ContractSymbol",
+                contract_expiry,
+                n_payouts,
+                liquidation_interval_hours
             from
                 cfds
             where
@@ -605,6 +665,19 @@ async fn load_cfd_row(conn: &mut SqliteConnection, id: OrderId) -> Result<Cfd, E
         Some(cfd_row.counterparty_peer_id.into())
     };
 
+    let contract_type = match cfd_row.contract_expiry {
+        Some(expiry) => ContractType::Dated {
+            expiry: OffsetDateTime::from_unix_timestamp(expiry)
+                .context("Invalid contract expiry")?,
+        },
+        None => ContractType::Perpetual,
+    };
+
+    let granularity = PayoutGranularity {
+        n_payouts: usize::try_from(cfd_row.n_payouts).context("Invalid number of payouts")?,
+        liquidation_interval: Duration::hours(cfd_row.liquidation_interval_hours),
+    };
+
     Ok(Cfd {
         id: cfd_row.order_id.into(),
         offer_id: cfd_row.offer_id.into(),
@@ -620,6 +693,8 @@ async fn load_cfd_row(conn: &mut SqliteConnection, id: OrderId) -> Result<Cfd, E
         initial_funding_rate: cfd_row.initial_funding_rate.into(),
         initial_tx_fee_rate: cfd_row.initial_tx_fee_rate.into(),
         contract_symbol: cfd_row.contract_symbol.into(),
+        contract_type,
+        granularity,
     })
 }
 
@@ -804,6 +879,8 @@ mod tests {
             initial_funding_rate,
             initial_tx_fee_rate,
             contract_symbol,
+            contract_type,
+            granularity,
         } = load_cfd_row(&mut *conn, cfd.id()).await.unwrap();
 
         assert_eq!(cfd.id(), id);
@@ -823,6 +900,76 @@ mod tests {
         assert_eq!(cfd.initial_funding_rate(), initial_funding_rate);
         assert_eq!(cfd.initial_tx_fee_rate(), initial_tx_fee_rate);
         assert_eq!(cfd.contract_symbol(), contract_symbol);
+        assert_eq!(cfd.contract_type(), contract_type);
+        assert_eq!(cfd.granularity(), granularity);
+    }
+
+    #[tokio::test]
+    async fn test_insert_and_load_dated_cfd() {
+        let db = memory().await.unwrap();
+        let mut conn = db.inner.acquire().await.unwrap();
+
+        let contract_type = ContractType::Dated {
+            expiry: time::macros::datetime!(2022-12-30 08:00:00).assume_utc(),
+        };
+        let cfd = Cfd::new(
+            OrderId::default(),
+            OfferId::default(),
+            Position::Long,
+            Price::new(dec!(60_000)).unwrap(),
+            Leverage::TWO,
+            Duration::hours(24 * 90),
+            Role::Taker,
+            Contracts::new(1_000),
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"
+                .parse()
+                .unwrap(),
+            Some(PeerId::random()),
+            OpeningFee::new(Amount::from_sat(2000)),
+            FundingRate::default(),
+            TxFeeRate::default(),
+            ContractSymbol::BtcUsd,
+            contract_type,
+            PayoutGranularity::default(),
+        );
+        db.insert_cfd(&cfd).await.unwrap();
+
+        let loaded = load_cfd_row(&mut *conn, cfd.id()).await.unwrap();
+
+        assert_eq!(loaded.contract_type, contract_type);
+    }
+
+    #[tokio::test]
+    async fn test_insert_and_load_cfd_with_custom_granularity() {
+        let db = memory().await.unwrap();
+        let mut conn = db.inner.acquire().await.unwrap();
+
+        let granularity = PayoutGranularity::new(50, Duration::hours(4)).unwrap();
+        let cfd = Cfd::new(
+            OrderId::default(),
+            OfferId::default(),
+            Position::Long,
+            Price::new(dec!(60_000)).unwrap(),
+            Leverage::TWO,
+            Duration::hours(24),
+            Role::Taker,
+            Contracts::new(1_000),
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"
+                .parse()
+                .unwrap(),
+            Some(PeerId::random()),
+            OpeningFee::new(Amount::from_sat(2000)),
+            FundingRate::default(),
+            TxFeeRate::default(),
+            ContractSymbol::BtcUsd,
+            ContractType::Perpetual,
+            granularity,
+        );
+        db.insert_cfd(&cfd).await.unwrap();
+
+        let loaded = load_cfd_row(&mut *conn, cfd.id()).await.unwrap();
+
+        assert_eq!(loaded.granularity, granularity);
     }
 
     #[tokio::test]
@@ -858,6 +1005,41 @@ mod tests {
         assert_eq!(events, vec![event1, event2])
     }
 
+    #[tokio::test]
+    async fn given_cfd_with_events_when_loading_with_events_then_row_and_all_events_loaded() {
+        let db = memory().await.unwrap();
+
+        let cfd = dummy_cfd();
+        db.insert_cfd(&cfd).await.unwrap();
+
+        let event1 = CfdEvent {
+            timestamp: Timestamp::now(),
+            id: cfd.id(),
+            event: EventKind::ContractSetupStarted,
+        };
+        let event2 = CfdEvent {
+            timestamp: Timestamp::now(),
+            id: cfd.id(),
+            event: EventKind::ContractSetupFailed,
+        };
+        db.append_event(event1.clone()).await.unwrap();
+        db.append_event(event2.clone()).await.unwrap();
+
+        let (loaded, events) = db.load_cfd_with_events(cfd.id()).await.unwrap();
+
+        assert_eq!(loaded.id, cfd.id());
+        assert_eq!(events, vec![event1, event2]);
+    }
+
+    #[tokio::test]
+    async fn given_unknown_cfd_when_loading_with_events_then_not_found() {
+        let db = memory().await.unwrap();
+
+        let result = db.load_cfd_with_events(OrderId::default()).await;
+
+        assert!(matches!(result, Err(Error::OpenCfdNotFound)));
+    }
+
     #[tokio::test]
     async fn given_insert_cfd_with_peer_id_then_peer_id_loaded() {
         let db = memory().await.unwrap();
@@ -956,6 +1138,8 @@ mod tests {
             FundingRate::default(),
             TxFeeRate::default(),
             ContractSymbol::BtcUsd,
+            ContractType::Perpetual,
+            PayoutGranularity::default(),
         )
     }
 
@@ -975,6 +1159,8 @@ mod tests {
             FundingRate::default(),
             TxFeeRate::default(),
             ContractSymbol::BtcUsd,
+            ContractType::Perpetual,
+            PayoutGranularity::default(),
         )
     }
 
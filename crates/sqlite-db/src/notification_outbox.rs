@@ -0,0 +1,315 @@
+use crate::insert_event;
+use crate::Connection;
+use anyhow::Result;
+use model::CfdEvent;
+use sqlx::Acquire;
+use sqlx::SqliteConnection;
+use time::OffsetDateTime;
+
+/// A notification waiting to be delivered to a webhook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingNotification {
+    pub id: i64,
+    pub webhook: String,
+    /// The JSON body of the notification
+    pub payload: String,
+    /// The number of failed attempts to deliver the notification
+    pub attempts: u32,
+}
+
+impl Connection {
+    /// Queue a notification for immediate delivery to each of the `webhooks`.
+    pub async fn insert_notifications(
+        &self,
+        webhooks: &[String],
+        payload: &str,
+        now: OffsetDateTime,
+    ) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+        let mut db_tx = conn.begin().await?;
+
+        insert_notifications(&mut db_tx, webhooks, payload, now).await?;
+
+        db_tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Append an event and queue the notification about it in the same transaction.
+    ///
+    /// The notification is thus neither lost if we stop right after appending the event, nor
+    /// queued for an event which failed to be appended.
+    pub async fn append_event_with_notification(
+        &self,
+        event: CfdEvent,
+        webhooks: &[String],
+        payload: &str,
+        now: OffsetDateTime,
+    ) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+        let mut db_tx = conn.begin().await?;
+
+        let order_id = event.id;
+        let event_name = insert_event(&mut db_tx, event).await?;
+        insert_notifications(&mut db_tx, webhooks, payload, now).await?;
+
+        db_tx.commit().await?;
+
+        tracing::info!(event = %event_name, %order_id, "Appended event to database");
+
+        Ok(())
+    }
+
+    /// Load up to `limit` notifications which are due for delivery at `now`, oldest first.
+    pub async fn load_due_notifications(
+        &self,
+        now: OffsetDateTime,
+        limit: u32,
+    ) -> Result<Vec<PendingNotification>> {
+        let mut conn = self.inner.acquire().await?;
+
+        let now = now.unix_timestamp();
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                id as "id!: i64",
+                webhook,
+                payload,
+                attempts
+            FROM
+                notification_outbox
+            WHERE
+                next_attempt_at <= $1
+            ORDER BY id
+            LIMIT $2
+            "#,
+            now,
+            limit,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(PendingNotification {
+                    id: row.id,
+                    webhook: row.webhook,
+                    payload: row.payload,
+                    attempts: u32::try_from(row.attempts)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Remove a notification from the outbox after it was delivered or given up on.
+    pub async fn delete_notification(&self, id: i64) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM notification_outbox
+            WHERE id = $1
+            "#,
+            id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record a failed delivery attempt and schedule the next one.
+    pub async fn reschedule_notification(
+        &self,
+        id: i64,
+        attempts: u32,
+        next_attempt_at: OffsetDateTime,
+    ) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        let next_attempt_at = next_attempt_at.unix_timestamp();
+
+        sqlx::query!(
+            r#"
+            UPDATE notification_outbox
+            SET
+                attempts = $2,
+                next_attempt_at = $3
+            WHERE id = $1
+            "#,
+            id,
+            attempts,
+            next_attempt_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+async fn insert_notifications(
+    conn: &mut SqliteConnection,
+    webhooks: &[String],
+    payload: &str,
+    now: OffsetDateTime,
+) -> Result<()> {
+    let next_attempt_at = now.unix_timestamp();
+
+    for webhook in webhooks {
+        sqlx::query!(
+            r#"
+            INSERT INTO notification_outbox
+            (
+                webhook,
+                payload,
+                next_attempt_at
+            )
+            VALUES ($1, $2, $3)
+            "#,
+            webhook,
+            payload,
+            next_attempt_at,
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_cfd_events;
+    use crate::memory;
+    use crate::tests::dummy_cfd;
+    use crate::tests::lock_confirmed;
+
+    #[tokio::test]
+    async fn given_notification_for_two_webhooks_then_one_is_pending_per_webhook() {
+        let db = memory().await.unwrap();
+
+        db.insert_notifications(&dummy_webhooks(), "{}", timestamp(100))
+            .await
+            .unwrap();
+
+        let pending = db.load_due_notifications(timestamp(100), 10).await.unwrap();
+
+        assert_eq!(
+            pending
+                .iter()
+                .map(|notification| notification.webhook.as_str())
+                .collect::<Vec<_>>(),
+            vec!["https://a.example", "https://b.example"]
+        );
+    }
+
+    #[tokio::test]
+    async fn given_rescheduled_notification_then_it_is_only_due_at_next_attempt() {
+        let db = memory().await.unwrap();
+        db.insert_notifications(&dummy_webhooks()[..1], "{}", timestamp(100))
+            .await
+            .unwrap();
+        let id = db.load_due_notifications(timestamp(100), 10).await.unwrap()[0].id;
+
+        db.reschedule_notification(id, 1, timestamp(200))
+            .await
+            .unwrap();
+
+        assert!(db
+            .load_due_notifications(timestamp(199), 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            db.load_due_notifications(timestamp(200), 10).await.unwrap(),
+            vec![PendingNotification {
+                id,
+                webhook: "https://a.example".to_owned(),
+                payload: "{}".to_owned(),
+                attempts: 1,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn given_deleted_notification_then_it_is_not_pending() {
+        let db = memory().await.unwrap();
+        db.insert_notifications(&dummy_webhooks()[..1], "{}", timestamp(100))
+            .await
+            .unwrap();
+        let id = db.load_due_notifications(timestamp(100), 10).await.unwrap()[0].id;
+
+        db.delete_notification(id).await.unwrap();
+
+        assert!(db
+            .load_due_notifications(timestamp(100), 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn given_event_with_notification_then_both_are_stored() {
+        let db = memory().await.unwrap();
+        let cfd = dummy_cfd();
+        db.insert_cfd(&cfd).await.unwrap();
+
+        let event = lock_confirmed(&cfd);
+        db.append_event_with_notification(
+            event.clone(),
+            &dummy_webhooks()[..1],
+            "{}",
+            timestamp(100),
+        )
+        .await
+        .unwrap();
+
+        let mut conn = db.inner.acquire().await.unwrap();
+        assert_eq!(
+            load_cfd_events(&mut *conn, cfd.id(), 0).await.unwrap(),
+            vec![event]
+        );
+        assert_eq!(
+            db.load_due_notifications(timestamp(100), 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn given_event_of_unknown_cfd_then_notification_is_not_queued() {
+        let db = memory().await.unwrap();
+
+        let result = db
+            .append_event_with_notification(
+                lock_confirmed(&dummy_cfd()),
+                &dummy_webhooks()[..1],
+                "{}",
+                timestamp(100),
+            )
+            .await;
+
+        assert!(result.is_err());
+        assert!(db
+            .load_due_notifications(timestamp(100), 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    fn dummy_webhooks() -> Vec<String> {
+        vec![
+            "https://a.example".to_owned(),
+            "https://b.example".to_owned(),
+        ]
+    }
+
+    fn timestamp(seconds: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(seconds).unwrap()
+    }
+}
@@ -19,6 +19,7 @@ mod tests {
     use model::CfdEvent;
     use model::CompleteFee;
     use model::ContractSymbol;
+    use model::ContractType;
     use model::Contracts;
     use model::Dlc;
     use model::EventKind;
@@ -28,6 +29,7 @@ mod tests {
     use model::OfferId;
     use model::OpeningFee;
     use model::OrderId;
+    use model::PayoutGranularity;
     use model::Position;
     use model::Price;
     use model::Role;
@@ -57,6 +59,8 @@ mod tests {
             FundingRate::default(),
             TxFeeRate::default(),
             ContractSymbol::BtcUsd,
+            ContractType::Perpetual,
+            PayoutGranularity::default(),
         )
     }
 
@@ -0,0 +1,85 @@
+use crate::Connection;
+use anyhow::Result;
+
+impl Connection {
+    /// Insert a swap with its JSON-encoded state.
+    pub async fn insert_swap(&self, id: &str, data: &str) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO swaps (id, data) VALUES ($1, $2)
+            "#,
+            id,
+            data,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Replace the JSON-encoded state of a swap.
+    pub async fn update_swap(&self, id: &str, data: &str) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        let updated = sqlx::query!(
+            r#"
+            UPDATE swaps SET data = $2 WHERE id = $1
+            "#,
+            id,
+            data,
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+
+        anyhow::ensure!(updated == 1, "Unknown swap {id}");
+
+        Ok(())
+    }
+
+    /// Load the JSON-encoded state of all swaps, oldest first.
+    pub async fn load_swaps(&self) -> Result<Vec<String>> {
+        let mut conn = self.inner.acquire().await?;
+
+        let swaps = sqlx::query!(
+            r#"
+            SELECT data FROM swaps ORDER BY rowid
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|row| row.data)
+        .collect();
+
+        Ok(swaps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::memory;
+
+    #[tokio::test]
+    async fn updated_swaps_are_loaded_in_insertion_order() {
+        let db = memory().await.unwrap();
+
+        db.insert_swap("b", "{}").await.unwrap();
+        db.insert_swap("a", "{}").await.unwrap();
+        db.update_swap("b", r#"{"claimed":true}"#).await.unwrap();
+
+        assert_eq!(
+            db.load_swaps().await.unwrap(),
+            vec![r#"{"claimed":true}"#.to_owned(), "{}".to_owned()]
+        );
+    }
+
+    #[tokio::test]
+    async fn updating_unknown_swap_fails() {
+        let db = memory().await.unwrap();
+
+        assert!(db.update_swap("a", "{}").await.is_err());
+    }
+}
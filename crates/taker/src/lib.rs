@@ -4,9 +4,11 @@ use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use clap::Parser;
+use daemon::alerts;
 use daemon::bdk::bitcoin;
 use daemon::bdk::FeeRate;
 use daemon::libp2p_utils::create_connect_tcp_multiaddr;
+use daemon::libp2p_utils::tcp_transport;
 use daemon::monitor;
 use daemon::oracle;
 use daemon::projection;
@@ -14,25 +16,29 @@ use daemon::seed::AppSeed;
 use daemon::seed::RandomSeed;
 use daemon::seed::Seed;
 use daemon::seed::ThreadSafeSeed;
+use daemon::swap;
 use daemon::wallet;
 use daemon::wallet::TAKER_WALLET_ID;
 use daemon::Environment;
 use daemon::TakerActorSystem;
-use daemon::N_PAYOUTS;
 use libp2p_core::PeerId;
 use model::olivia;
 use model::Identity;
 use model::Role;
-use model::SETTLEMENT_INTERVAL;
 use rocket::async_trait;
 use rocket_cookie_auth::users::Users;
 use shared_bin::catchers::default_catchers;
+use shared_bin::cli::BlockExplorerOpts;
+use shared_bin::cli::MetricsExportOpts;
 use shared_bin::cli::Network;
+use shared_bin::cli::NotificationOpts;
+use shared_bin::cli::WalletDescriptors;
 use shared_bin::cli::Withdraw;
 use shared_bin::fairings;
 use shared_bin::logger;
 use shared_bin::logger::LevelFilter;
 use shared_bin::logger::LOCAL_COLLECTOR_ENDPOINT;
+use shared_bin::metrics_export;
 use shared_bin::MAINNET_ELECTRUM;
 use shared_bin::TESTNET_ELECTRUM;
 use std::convert::Infallible;
@@ -44,11 +50,14 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::watch;
 use tokio_extras::Tasks;
+use xtra::Actor as _;
 use xtras::supervisor::always_restart;
 use xtras::supervisor::Supervisor;
 
 mod routes;
+mod websocket;
 
 pub const ANNOUNCEMENT_LOOKAHEAD: time::Duration = time::Duration::hours(24);
 
@@ -60,6 +69,8 @@ const TESTNET_MAKER: &str = "testnet.itchysats.network:10000";
 const TESTNET_MAKER_ID: &str = "69a42aa90da8b065b9532b62bff940a3ba07dbbb11d4482c7db83a7e049a9f1e";
 const TESTNET_MAKER_PEER_ID: &str = "12D3KooWEsK2X8Tp24XtyWh7DM65VfwXtNH2cmfs2JsWmkmwKbV1";
 
+const DEFAULT_LIQUIDATION_ALERTS: [&str; 2] = ["10%", "5%"];
+
 #[derive(Debug)]
 pub struct Password(String);
 
@@ -107,6 +118,12 @@ pub struct Opts {
     #[clap(long, default_value = "127.0.0.1:8000")]
     http_address: SocketAddr,
 
+    /// The address to serve the WebSocket API on, e.g. "127.0.0.1:8002".
+    ///
+    /// The WebSocket API is disabled if not specified.
+    #[clap(long)]
+    ws_address: Option<SocketAddr>,
+
     /// Where to permanently store data, defaults to the current working directory.
     #[clap(long)]
     data_dir: Option<PathBuf>,
@@ -170,6 +187,36 @@ pub struct Opts {
     #[clap(short, long)]
     pub wallet_xprv: Option<ExtendedPrivKey>,
 
+    #[clap(flatten)]
+    pub wallet_descriptors: WalletDescriptors,
+
+    #[clap(flatten)]
+    pub block_explorer: BlockExplorerOpts,
+
+    #[clap(flatten)]
+    pub notifications: NotificationOpts,
+
+    #[clap(flatten)]
+    pub metrics_export: MetricsExportOpts,
+
+    /// URL of a swap server to fund the wallet with Lightning payments.
+    #[clap(long)]
+    pub swap_server: Option<String>,
+
+    /// Warn once the price comes within this distance of the liquidation price of a CFD, e.g.
+    /// "10%". Can be given multiple times.
+    #[clap(long = "liquidation-alert", default_values = &DEFAULT_LIQUIDATION_ALERTS)]
+    pub liquidation_alerts: Vec<alerts::Distance>,
+
+    /// Warn if the maker is offline this many hours before a CFD settles instead of being rolled
+    /// over.
+    #[clap(long, default_value = "3")]
+    pub maker_offline_alert_hours: u32,
+
+    /// Run this command for every alert, passing the alert as JSON in its only argument.
+    #[clap(long)]
+    pub alert_command: Option<PathBuf>,
+
     /// If enabled, the log will be printed to {service_name}.log in the data dir
     #[clap(long)]
     pub log_to_file: bool,
@@ -194,6 +241,7 @@ impl Opts {
             maker_id: Some(maker_id),
             maker_peer_id: Some(maker_peer_id),
             http_address: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), port),
+            ws_address: None,
             data_dir: Some(PathBuf::from(data_dir)),
             json: false,
             json_span_list: false,
@@ -208,6 +256,17 @@ impl Opts {
             network: Some(network.into()),
             app_seed: None,
             wallet_xprv: None,
+            wallet_descriptors: WalletDescriptors::default(),
+            block_explorer: BlockExplorerOpts::default(),
+            notifications: NotificationOpts::default(),
+            metrics_export: MetricsExportOpts::default(),
+            swap_server: None,
+            liquidation_alerts: DEFAULT_LIQUIDATION_ALERTS
+                .iter()
+                .map(|distance| alerts::Distance::from_str(distance))
+                .collect::<Result<_>>()?,
+            maker_offline_alert_hours: 3,
+            alert_command: None,
             log_to_file: true,
         })
     }
@@ -341,15 +400,10 @@ pub async fn run(opts: Opts) -> Result<()> {
     )
     .context("initialize logger")?;
     tracing::info!("Running version: {}", daemon::version());
-    let settlement_interval_hours = SETTLEMENT_INTERVAL.whole_hours();
-
-    tracing::info!(
-        "CFDs created with this release will settle after {settlement_interval_hours} hours"
-    );
-
     let maker_identity = Identity::new(maker_id);
 
     let bitcoin_network = network.bitcoin_network();
+    let block_explorer = opts.block_explorer.block_explorer(bitcoin_network)?;
 
     let seed: Arc<ThreadSafeSeed> = match opts.app_seed {
         Some(seed_bytes) => Arc::new(AppSeed::from(seed_bytes)),
@@ -358,23 +412,36 @@ pub async fn run(opts: Opts) -> Result<()> {
 
     let identities = seed.derive_identities();
 
-    let ext_priv_key = match opts.wallet_xprv {
-        Some(wallet_xprv) => {
+    let wallet_keys = match (opts.wallet_descriptors.load()?, opts.wallet_xprv) {
+        (Some(_), Some(_)) => bail!("Wallet descriptors cannot be combined with a private key"),
+        (Some(descriptors), None) => wallet::WalletKeys::Descriptors {
+            descriptors,
+            signer: None,
+        },
+        (None, Some(wallet_xprv)) => {
             if wallet_xprv.network != bitcoin_network {
                 let network = wallet_xprv.network;
                 bail!("Invalid private key provided. Was '{network}' but should have been '{bitcoin_network}'");
             }
-            wallet_xprv
+            wallet::WalletKeys::Hot(wallet_xprv)
         }
-        None => seed.derive_extended_priv_key(bitcoin_network)?,
+        (None, None) => wallet::WalletKeys::Hot(seed.derive_extended_priv_key(bitcoin_network)?),
     };
 
     let mut tasks = Tasks::default();
 
+    if let Some(endpoint) = opts.metrics_export.endpoint(&opts.collector_endpoint) {
+        metrics_export::export(
+            endpoint,
+            opts.metrics_export.interval(),
+            metrics_export::resource(&opts.service_name, Role::Taker, bitcoin_network),
+        )?;
+    }
+
     let mut wallet_dir = data_dir.clone();
     wallet_dir.push(TAKER_WALLET_ID);
     let (wallet, wallet_feed_receiver) =
-        wallet::Actor::spawn(network.electrum(), ext_priv_key, wallet_dir)?;
+        wallet::Actor::spawn(network.electrum(), bitcoin_network, wallet_keys, wallet_dir)?;
 
     if let Some(Withdraw::Withdraw {
         amount,
@@ -386,9 +453,13 @@ pub async fn run(opts: Opts) -> Result<()> {
             .send(wallet::Withdraw {
                 amount: *amount,
                 address: address.clone(),
-                fee: fee.map(FeeRate::from_sat_per_vb),
+                fee: fee
+                    .map(FeeRate::from_sat_per_vb)
+                    .map(wallet::WithdrawFee::Rate),
+                coins: Vec::new(),
             })
-            .await??;
+            .await?
+            .await?;
 
         return Ok(());
     }
@@ -444,10 +515,11 @@ pub async fn run(opts: Opts) -> Result<()> {
     let (supervisor, projection_actor) = Supervisor::new({
         let db = db.clone();
         let price_feed = price_feed_actor.clone();
+        let block_explorer = block_explorer.clone();
         move || {
             projection::Actor::new(
                 db.clone(),
-                bitcoin_network,
+                block_explorer.clone(),
                 price_feed.clone().into(),
                 Role::Taker,
                 feed_senders.clone(),
@@ -456,6 +528,27 @@ pub async fn run(opts: Opts) -> Result<()> {
     });
     tasks.add(supervisor.run_log_summary());
 
+    let (swap_actor, swap_feed_receiver) = match &opts.swap_server {
+        Some(url) => {
+            tracing::info!(%url, "Funding via Lightning swaps enabled");
+
+            let (swap_actor, swap_feed_receiver) = swap::Actor::new(
+                db.clone(),
+                Arc::new(swap::HttpSwapService::new(url)?),
+                Arc::new(swap::Electrum::new(network.electrum())?),
+                wallet.clone().into(),
+            );
+            let swap_actor = swap_actor.create(None).spawn(&mut tasks);
+
+            (Some(swap_actor), swap_feed_receiver)
+        }
+        None => (None, watch::channel(Vec::new()).1),
+    };
+
+    let notifications = opts
+        .notifications
+        .outbox(db.clone(), Role::Taker, &mut tasks)?;
+
     let taker = TakerActorSystem::new(
         db.clone(),
         wallet.clone(),
@@ -467,13 +560,36 @@ pub async fn run(opts: Opts) -> Result<()> {
             monitor::Actor::new(db.clone(), electrum, executor)
         },
         price_feed_actor,
-        N_PAYOUTS,
         Duration::from_secs(10),
         projection_actor.clone(),
         maker_identity,
         maker_multiaddr,
+        Box::new(tcp_transport),
         environment,
+        notifications.clone(),
     )?;
+    let taker = Arc::new(taker);
+
+    let mut alert_sinks: Vec<Box<dyn alerts::NotificationSink>> = Vec::new();
+    if let Some(outbox) = notifications {
+        alert_sinks.push(Box::new(alerts::Webhook::new(outbox)));
+    }
+    if let Some(command) = &opts.alert_command {
+        alert_sinks.push(Box::new(alerts::Command::new(command.clone())));
+    }
+    let (alerts_actor, alerts_feed_receiver) = alerts::Actor::new(
+        alerts::Config {
+            liquidation_distances: opts.liquidation_alerts.clone(),
+            maker_offline_before_expiry: time::Duration::hours(
+                opts.maker_offline_alert_hours.into(),
+            ),
+        },
+        feed_receivers.cfds.clone(),
+        feed_receivers.quote.clone(),
+        taker.maker_online_status_feed_receiver.clone(),
+        alert_sinks,
+    );
+    let _alerts_actor = alerts_actor.create(None).spawn(&mut tasks);
 
     if let Some(password) = opts.password {
         db.clone()
@@ -486,11 +602,33 @@ pub async fn run(opts: Opts) -> Result<()> {
     let rocket_auth_db_connection = RocketAuthDbConnection::new(db.clone());
     let users = Users::new(Box::new(rocket_auth_db_connection));
 
+    if let Some(ws_address) = opts.ws_address {
+        let feeds = shared_bin::websocket::Feeds::new(
+            &feed_receivers,
+            wallet_feed_receiver.clone(),
+            block_explorer.clone(),
+        );
+
+        tasks.add_fallible(
+            shared_bin::websocket::serve(
+                ws_address,
+                Box::new(RocketAuthDbConnection::new(db.clone())),
+                feeds,
+                websocket::Commands::new(taker.clone()),
+            ),
+            |e| async move { tracing::error!("WebSocket API stopped: {e:#}") },
+        );
+    }
+
     let mission_success = rocket::custom(figment)
         .manage(feed_receivers)
         .manage(wallet_feed_receiver)
+        .manage(swap_actor)
+        .manage(swap_feed_receiver)
+        .manage(alerts_feed_receiver)
         .manage(identity_info)
-        .manage(bitcoin_network)
+        .manage(block_explorer)
+        .manage(db.clone())
         .manage(taker.maker_online_status_feed_receiver.clone())
         .manage(taker.identify_info_feed_receiver.clone())
         .manage(taker)
@@ -501,7 +639,15 @@ pub async fn run(opts: Opts) -> Result<()> {
                 routes::post_order_request,
                 routes::get_health_check,
                 routes::post_cfd_action,
+                routes::get_cfds,
+                routes::get_cfd,
                 routes::post_withdraw_request,
+                routes::post_withdraw_preview_request,
+                routes::post_bump_fee_request,
+                routes::get_fee_estimates,
+                routes::get_utxos,
+                routes::put_utxo,
+                routes::post_swap,
                 routes::get_metrics,
                 routes::put_sync_wallet,
                 routes::get_version,
@@ -1,23 +1,25 @@
 #![allow(clippy::let_unit_value)]
 // see: https://github.com/SergioBenitez/Rocket/issues/2211
+use daemon::alerts::Alert;
 use daemon::bdk;
 use daemon::bdk::bitcoin::Amount;
-use daemon::bdk::bitcoin::Network;
 use daemon::bdk::blockchain::ElectrumBlockchain;
 use daemon::bdk::sled;
+use daemon::block_explorer::BlockExplorer;
 use daemon::identify;
 use daemon::online_status::ConnectionStatus;
 use daemon::oracle;
-use daemon::projection;
 use daemon::projection::CfdAction;
 use daemon::projection::FeedReceivers;
 use daemon::seed::ThreadSafeSeed;
+use daemon::swap;
 use daemon::wallet;
 use daemon::TakerActorSystem;
 use http_api_problem::HttpApiProblem;
 use http_api_problem::StatusCode;
 use model::Contracts;
 use model::Leverage;
+use model::OfferId;
 use model::OrderId;
 use model::Price;
 use model::Timestamp;
@@ -41,6 +43,17 @@ use rust_embed::RustEmbed;
 use rust_embed_rocket::EmbeddedFileExt;
 use serde::Deserialize;
 use serde::Serialize;
+use shared_bin::wallet::parse_outpoint;
+use shared_bin::wallet::parse_txid;
+use shared_bin::wallet::BumpFeeRequest;
+use shared_bin::wallet::UpdateUtxoRequest;
+use shared_bin::wallet::WithdrawRequest;
+use shared_bin::wallet::WithdrawResponse;
+use shared_bin::CfdDetail;
+use shared_bin::CfdEvents;
+use shared_bin::CfdPage;
+use shared_bin::CfdQuery;
+use shared_bin::LastEventId;
 use shared_bin::ToSseEvent;
 use std::borrow::Cow;
 use std::path::PathBuf;
@@ -49,10 +62,12 @@ use tokio::select;
 use tokio::sync::watch;
 use tracing::instrument;
 
-type Taker = TakerActorSystem<
-    oracle::Actor,
-    wallet::Actor<ElectrumBlockchain, sled::Tree>,
-    xtra_bitmex_price_feed::Actor,
+pub(crate) type Taker = Arc<
+    TakerActorSystem<
+        oracle::Actor,
+        wallet::Actor<ElectrumBlockchain, sled::Tree>,
+        xtra_bitmex_price_feed::Actor,
+    >,
 >;
 
 const HEARTBEAT_INTERVAL_SECS: u64 = 5;
@@ -65,23 +80,32 @@ pub struct IdentityInfo {
     pub(crate) taker_peer_id: String,
 }
 
+#[allow(clippy::too_many_arguments)]
 #[rocket::get("/feed")]
 #[instrument(name = "GET /feed", skip_all)]
 pub async fn feed(
     rx: &State<FeedReceivers>,
     rx_wallet: &State<watch::Receiver<Option<WalletInfo>>>,
+    block_explorer: &State<BlockExplorer>,
     rx_maker_status: &State<watch::Receiver<ConnectionStatus>>,
     rx_maker_identity: &State<watch::Receiver<Option<identify::PeerInfo>>>,
+    rx_swaps: &State<watch::Receiver<Vec<swap::Swap>>>,
+    rx_alerts: &State<watch::Receiver<Vec<Alert>>>,
     identity_info: &State<IdentityInfo>,
+    last_event_id: LastEventId,
     _user: User,
 ) -> EventStream![] {
     let rx = rx.inner();
-    let mut rx_cfds = rx.cfds.clone();
+    let (mut cfd_events, cfd_catch_up) =
+        CfdEvents::subscribe(rx.cfd_updates.clone(), last_event_id);
     let mut rx_offers = rx.offers.clone();
 
     let mut rx_wallet = rx_wallet.inner().clone();
+    let block_explorer = block_explorer.inner().clone();
     let mut rx_maker_status = rx_maker_status.inner().clone();
     let mut rx_maker_identity = rx_maker_identity.inner().clone();
+    let mut rx_swaps = rx_swaps.inner().clone();
+    let mut rx_alerts = rx_alerts.inner().clone();
     let identity = identity_info.inner().clone();
     let mut heartbeat =
         tokio::time::interval(std::time::Duration::from_secs(HEARTBEAT_INTERVAL_SECS));
@@ -89,7 +113,7 @@ pub async fn feed(
     EventStream! {
 
         let wallet_info = rx_wallet.borrow().clone();
-        yield wallet_info.to_sse_event();
+        yield (&block_explorer, &wallet_info).to_sse_event();
 
         let maker_status = rx_maker_status.borrow().clone();
         yield maker_status.to_sse_event();
@@ -99,22 +123,27 @@ pub async fn feed(
 
         yield Event::json(&identity).event("identity");
 
+        let swaps = rx_swaps.borrow().clone();
+        yield Event::json(&swaps).event("swaps");
+
+        let alerts = rx_alerts.borrow().clone();
+        yield Event::json(&alerts).event("alerts");
+
         let offers = rx_offers.borrow().clone();
         yield Event::json(&offers.btcusd_long).event("btcusd_long_offer");
         yield Event::json(&offers.btcusd_short).event("btcusd_short_offer");
         yield Event::json(&offers.ethusd_long).event("ethusd_long_offer");
         yield Event::json(&offers.ethusd_short).event("ethusd_short_offer");
 
-        let cfds = rx_cfds.borrow().clone();
-        if let Some(cfds) = cfds {
-            yield cfds.to_sse_event()
+        for event in cfd_catch_up {
+            yield event;
         }
 
         loop{
             select! {
                 Ok(()) = rx_wallet.changed() => {
                     let wallet_info = rx_wallet.borrow().clone();
-                    yield wallet_info.to_sse_event();
+                    yield (&block_explorer, &wallet_info).to_sse_event();
                 },
                 Ok(()) = rx_maker_status.changed() => {
                     let maker_status = rx_maker_status.borrow().clone();
@@ -131,10 +160,17 @@ pub async fn feed(
                     yield Event::json(&offers.ethusd_long).event("ethusd_long_offer");
                     yield Event::json(&offers.ethusd_short).event("ethusd_short_offer");
                 }
-                Ok(()) = rx_cfds.changed() => {
-                    let cfds = rx_cfds.borrow().clone();
-                    if let Some(cfds) = cfds {
-                        yield cfds.to_sse_event()
+                Ok(()) = rx_swaps.changed() => {
+                    let swaps = rx_swaps.borrow().clone();
+                    yield Event::json(&swaps).event("swaps");
+                }
+                Ok(()) = rx_alerts.changed() => {
+                    let alerts = rx_alerts.borrow().clone();
+                    yield Event::json(&alerts).event("alerts");
+                }
+                events = cfd_events.next() => {
+                    for event in events {
+                        yield event;
                     }
                 }
                 _ = heartbeat.tick() => {
@@ -174,20 +210,83 @@ pub async fn post_order_request(
     taker: &State<Taker>,
     _user: User,
 ) -> Result<(), HttpApiProblem> {
+    place_order(
+        taker,
+        cfd_order_request.order_id,
+        cfd_order_request.quantity,
+        cfd_order_request.leverage,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Take the given offer, shared between the HTTP and the WebSocket API.
+pub(crate) async fn place_order(
+    taker: &Taker,
+    offer_id: OfferId,
+    quantity: Contracts,
+    leverage: Leverage,
+) -> Result<OrderId, HttpApiProblem> {
     taker
-        .place_order(
-            cfd_order_request.order_id,
-            cfd_order_request.quantity,
-            cfd_order_request.leverage,
-        )
+        .place_order(offer_id, quantity, leverage)
         .await
         .map_err(|e| {
             HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
                 .title("Order request failed")
                 .detail(format!("{e:#}"))
+        })
+}
+
+#[rocket::get("/cfds?<query..>")]
+#[instrument(name = "GET /cfds", skip_all, err)]
+pub async fn get_cfds(
+    query: CfdQuery,
+    rx: &State<FeedReceivers>,
+    _user: User,
+) -> Result<Json<CfdPage>, HttpApiProblem> {
+    let cfds = rx.cfds.borrow().clone().ok_or_else(cfds_not_available)?;
+
+    Ok(Json(query.apply(cfds)?))
+}
+
+#[rocket::get("/cfds/<order_id>")]
+#[instrument(name = "GET /cfds/<order_id>", skip(rx, db, _user), err)]
+pub async fn get_cfd(
+    order_id: Uuid,
+    rx: &State<FeedReceivers>,
+    db: &State<sqlite_db::Connection>,
+    _user: User,
+) -> Result<Json<CfdDetail>, HttpApiProblem> {
+    let order_id = OrderId::from(order_id);
+
+    let cfd = rx
+        .cfds
+        .borrow()
+        .as_ref()
+        .ok_or_else(cfds_not_available)?
+        .iter()
+        .find(|cfd| cfd.order_id == order_id)
+        .cloned()
+        .ok_or_else(|| {
+            HttpApiProblem::new(StatusCode::NOT_FOUND)
+                .title("CFD not found")
+                .detail(format!("No CFD with order id {order_id}"))
         })?;
 
-    Ok(())
+    let timeline = db.load_cfd_timeline(order_id).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Failed to load CFD events")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(CfdDetail::new(cfd, timeline)))
+}
+
+fn cfds_not_available() -> HttpApiProblem {
+    HttpApiProblem::new(StatusCode::SERVICE_UNAVAILABLE)
+        .title("CFDs not yet available")
+        .detail("CFDs are still being loaded from the database. Please retry later.")
 }
 
 #[rocket::post("/cfd/<order_id>/<action>")]
@@ -203,6 +302,15 @@ pub async fn post_cfd_action(
         HttpApiProblem::new(StatusCode::BAD_REQUEST).detail(format!("Invalid action: {}", action))
     })?;
 
+    execute_cfd_action(taker, order_id, action).await
+}
+
+/// Execute an action on a CFD, shared between the HTTP and the WebSocket API.
+pub(crate) async fn execute_cfd_action(
+    taker: &Taker,
+    order_id: OrderId,
+    action: CfdAction,
+) -> Result<(), HttpApiProblem> {
     let result = match action {
         CfdAction::AcceptOrder
         | CfdAction::RejectOrder
@@ -267,30 +375,20 @@ pub fn index<'r>(_paths: PathBuf) -> impl Responder<'r, 'static> {
     Ok::<(ContentType, Cow<[u8]>), Status>((ContentType::HTML, asset.data))
 }
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct WithdrawRequest {
-    address: bdk::bitcoin::Address,
-    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
-    amount: Amount,
-    fee: f32,
-}
-
 #[rocket::post("/withdraw", data = "<withdraw_request>")]
 #[instrument(name = "POST /withdraw", skip(taker, _user), err)]
 pub async fn post_withdraw_request(
     withdraw_request: Json<WithdrawRequest>,
     taker: &State<Taker>,
-    network: &State<Network>,
+    block_explorer: &State<BlockExplorer>,
     _user: User,
-) -> Result<String, HttpApiProblem> {
-    let amount =
-        (withdraw_request.amount != bdk::bitcoin::Amount::ZERO).then(|| withdraw_request.amount);
-
+) -> Result<Json<WithdrawResponse>, HttpApiProblem> {
     let txid = taker
         .withdraw(
-            amount,
+            withdraw_request.amount(),
             withdraw_request.address.clone(),
-            bdk::FeeRate::from_sat_per_vb(withdraw_request.fee),
+            withdraw_request.fee()?,
+            withdraw_request.coins()?,
         )
         .await
         .map_err(|e| {
@@ -299,7 +397,145 @@ pub async fn post_withdraw_request(
                 .detail(format!("{e:#}"))
         })?;
 
-    Ok(projection::to_mempool_url(txid, *network.inner()))
+    Ok(Json(WithdrawResponse::new(txid, block_explorer)))
+}
+
+#[rocket::post("/withdraw/preview", data = "<withdraw_request>")]
+#[instrument(name = "POST /withdraw/preview", skip(taker, _user), err)]
+pub async fn post_withdraw_preview_request(
+    withdraw_request: Json<WithdrawRequest>,
+    taker: &State<Taker>,
+    _user: User,
+) -> Result<Json<wallet::WithdrawPreview>, HttpApiProblem> {
+    let preview = taker
+        .preview_withdraw(
+            withdraw_request.amount(),
+            withdraw_request.address.clone(),
+            withdraw_request.fee()?,
+            withdraw_request.coins()?,
+        )
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not preview withdraw request")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(preview))
+}
+
+#[rocket::post("/withdraw/<txid>/bump-fee", data = "<bump_fee_request>")]
+#[instrument(name = "POST /withdraw/<txid>/bump-fee", skip(taker, _user), err)]
+pub async fn post_bump_fee_request(
+    txid: &str,
+    bump_fee_request: Json<BumpFeeRequest>,
+    taker: &State<Taker>,
+    block_explorer: &State<BlockExplorer>,
+    _user: User,
+) -> Result<Json<WithdrawResponse>, HttpApiProblem> {
+    let txid = parse_txid(txid)?;
+
+    let replacement = taker
+        .bump_fee(txid, bump_fee_request.fee()?)
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not bump fee of withdrawal")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(WithdrawResponse::new(replacement, block_explorer)))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SwapRequest {
+    #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
+    pub amount: Amount,
+}
+
+#[rocket::post("/swaps", data = "<swap_request>")]
+#[instrument(name = "POST /swaps", skip(swap_actor, _user), err)]
+pub async fn post_swap(
+    swap_request: Json<SwapRequest>,
+    swap_actor: &State<Option<xtra::Address<swap::Actor>>>,
+    _user: User,
+) -> Result<Json<swap::Swap>, HttpApiProblem> {
+    let swap_actor = swap_actor.inner().as_ref().ok_or_else(|| {
+        HttpApiProblem::new(StatusCode::NOT_FOUND)
+            .title("Swaps not enabled")
+            .detail("Start the taker with `--swap-server` to fund the wallet via Lightning")
+    })?;
+
+    let swap = swap_actor
+        .send(swap::CreateSwap {
+            amount: swap_request.amount,
+        })
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Swap actor disconnected")
+                .detail(e.to_string())
+        })?
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not create swap")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(swap))
+}
+
+#[rocket::get("/wallet/fee-estimates")]
+#[instrument(name = "GET /wallet/fee-estimates", skip_all, err)]
+pub async fn get_fee_estimates(
+    taker: &State<Taker>,
+    _user: User,
+) -> Result<Json<Vec<wallet::FeeEstimate>>, HttpApiProblem> {
+    let fee_estimates = taker.estimate_fee_rates().await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not estimate fee rates")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(fee_estimates))
+}
+
+#[rocket::get("/wallet/utxos")]
+#[instrument(name = "GET /wallet/utxos", skip_all, err)]
+pub async fn get_utxos(
+    taker: &State<Taker>,
+    _user: User,
+) -> Result<Json<Vec<wallet::Utxo>>, HttpApiProblem> {
+    let utxos = taker.list_utxos().await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not list UTXOs")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(utxos))
+}
+
+#[rocket::put("/wallet/utxos/<outpoint>", data = "<update_utxo_request>")]
+#[instrument(name = "PUT /wallet/utxos/<outpoint>", skip(taker, _user), err)]
+pub async fn put_utxo(
+    outpoint: &str,
+    update_utxo_request: Json<UpdateUtxoRequest>,
+    taker: &State<Taker>,
+    _user: User,
+) -> Result<(), HttpApiProblem> {
+    let outpoint = parse_outpoint(outpoint)?;
+    let UpdateUtxoRequest { label, frozen } = update_utxo_request.into_inner();
+
+    taker
+        .update_utxo(outpoint, label, frozen)
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not update UTXO")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
 }
 
 // TODO: Use non-cookie auth for /metrics endpoint as Prometheus does not
@@ -0,0 +1,71 @@
+use crate::routes::execute_cfd_action;
+use crate::routes::place_order;
+use crate::routes::Taker;
+use daemon::projection::CfdAction;
+use http_api_problem::HttpApiProblem;
+use model::Contracts;
+use model::Leverage;
+use model::OfferId;
+use model::OrderId;
+use rocket::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::Value;
+use shared_bin::websocket::CommandHandler;
+
+/// Commands the taker accepts over the WebSocket API.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Command {
+    /// Take an offer, acknowledged with the id of the new order
+    PlaceOrder {
+        offer_id: OfferId,
+        quantity: Contracts,
+        leverage: Leverage,
+    },
+    Settle {
+        order_id: OrderId,
+    },
+    Commit {
+        order_id: OrderId,
+    },
+}
+
+pub struct Commands {
+    taker: Taker,
+}
+
+impl Commands {
+    pub fn new(taker: Taker) -> Self {
+        Self { taker }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for Commands {
+    type Command = Command;
+
+    async fn handle(&self, command: Command) -> Result<Value, HttpApiProblem> {
+        match command {
+            Command::PlaceOrder {
+                offer_id,
+                quantity,
+                leverage,
+            } => {
+                let order_id = place_order(&self.taker, offer_id, quantity, leverage).await?;
+
+                Ok(json!({ "order_id": order_id }))
+            }
+            Command::Settle { order_id } => {
+                execute_cfd_action(&self.taker, order_id, CfdAction::Settle).await?;
+
+                Ok(Value::Null)
+            }
+            Command::Commit { order_id } => {
+                execute_cfd_action(&self.taker, order_id, CfdAction::Commit).await?;
+
+                Ok(Value::Null)
+            }
+        }
+    }
+}
@@ -1,9 +1,12 @@
+use std::cell::Cell;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::Context;
 use std::task::Poll;
 use std::time::Duration;
+use time::OffsetDateTime;
 use tokio::time::error::Elapsed;
+use tokio::time::Instant;
 use tokio::time::Timeout as TokioTimeout;
 use tracing::field;
 use tracing::Span;
@@ -20,6 +23,44 @@ pub async fn sleep_silent(duration: Duration) {
     tokio::time::sleep(duration).await
 }
 
+thread_local! {
+    static SIMULATED_CLOCK: Cell<Option<(OffsetDateTime, Instant)>> = Cell::new(None);
+}
+
+/// The current wall-clock time in UTC.
+///
+/// Use this instead of [`OffsetDateTime::now_utc`] wherever behaviour depends on the passing of
+/// time, so that it can be driven by a simulated clock in tests. Unless a simulated clock was
+/// started on the current thread, this is the system time. Crates without a runtime read the same
+/// time through [`wall_clock::now_utc`].
+pub fn now_utc() -> OffsetDateTime {
+    wall_clock::now_utc()
+}
+
+fn simulated_now_utc() -> OffsetDateTime {
+    match SIMULATED_CLOCK.with(Cell::get) {
+        Some((start, started_at)) => start + Instant::now().duration_since(started_at),
+        None => OffsetDateTime::now_utc(),
+    }
+}
+
+/// Derive the wall-clock time returned by [`now_utc`] from tokio's clock, starting at `start`.
+///
+/// Together with a paused tokio clock (`tokio::time::pause`), wall-clock time, [`sleep`] and
+/// [`timeout`] then all advance in lockstep with tokio's virtual time, which makes time-dependent
+/// behaviour deterministic. The simulated clock is local to the current thread, hence it should be
+/// used with a single-threaded runtime.
+pub fn start_simulated_clock(start: OffsetDateTime) {
+    SIMULATED_CLOCK.with(|clock| clock.set(Some((start, Instant::now()))));
+    wall_clock::set_source(simulated_now_utc);
+}
+
+/// Go back to using the system time in [`now_utc`] on the current thread.
+pub fn stop_simulated_clock() {
+    SIMULATED_CLOCK.with(|clock| clock.set(None));
+    wall_clock::reset_source();
+}
+
 /// Limit the future's time of execution to a certain duration, cancelling it and returning
 /// an error if time runs out. This is instrumented, unlike `tokio::time::timeout`. The
 /// `child_span` function constructs the span for the child future from the span of the parent
@@ -113,3 +154,20 @@ where
         poll
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn simulated_clock_advances_with_tokio_time() {
+        let start = OffsetDateTime::from_unix_timestamp(1_000_000).unwrap();
+        start_simulated_clock(start);
+
+        sleep_silent(Duration::from_secs(3600)).await;
+
+        assert_eq!(now_utc(), start + Duration::from_secs(3600));
+
+        stop_simulated_clock();
+    }
+}
@@ -0,0 +1,50 @@
+//! The wall-clock time, as read by code that must not depend on an async runtime (e.g. `model`).
+//!
+//! By default this is the system time. A simulated clock, such as the one of `tokio-extras`, can
+//! take its place on the current thread through [`set_source`].
+
+use std::cell::Cell;
+use time::OffsetDateTime;
+
+thread_local! {
+    static SOURCE: Cell<Option<fn() -> OffsetDateTime>> = Cell::new(None);
+}
+
+/// The current wall-clock time in UTC.
+///
+/// Use this instead of [`OffsetDateTime::now_utc`] wherever behaviour depends on the passing of
+/// time, so that it can be driven by a simulated clock in tests.
+pub fn now_utc() -> OffsetDateTime {
+    match SOURCE.with(Cell::get) {
+        Some(now_utc) => now_utc(),
+        None => OffsetDateTime::now_utc(),
+    }
+}
+
+/// Read the wall-clock time of the current thread from `source` instead of the system time.
+pub fn set_source(source: fn() -> OffsetDateTime) {
+    SOURCE.with(|clock| clock.set(Some(source)));
+}
+
+/// Go back to using the system time on the current thread.
+pub fn reset_source() {
+    SOURCE.with(|clock| clock.set(None));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_replaces_system_time_until_reset() {
+        fn epoch() -> OffsetDateTime {
+            OffsetDateTime::UNIX_EPOCH
+        }
+
+        set_source(epoch);
+        assert_eq!(now_utc(), OffsetDateTime::UNIX_EPOCH);
+
+        reset_source();
+        assert!(now_utc() > OffsetDateTime::UNIX_EPOCH);
+    }
+}
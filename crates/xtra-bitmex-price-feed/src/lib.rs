@@ -204,7 +204,7 @@ impl Quote {
     }
 
     pub fn is_older_than(&self, duration: time::Duration) -> bool {
-        let required_quote_timestamp = (OffsetDateTime::now_utc() - duration).unix_timestamp();
+        let required_quote_timestamp = (tokio_extras::time::now_utc() - duration).unix_timestamp();
 
         self.timestamp.unix_timestamp() < required_quote_timestamp
     }
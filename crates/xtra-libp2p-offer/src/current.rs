@@ -2,4 +2,4 @@ pub mod maker;
 mod protocol;
 pub mod taker;
 
-pub const PROTOCOL: &str = "/itchysats/offer/2.0.0";
+pub const PROTOCOL: &str = "/itchysats/offer/3.0.0";
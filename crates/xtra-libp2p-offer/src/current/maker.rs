@@ -1,8 +1,12 @@
+use crate::current;
 use crate::current::protocol;
-use crate::current::PROTOCOL;
+use crate::deprecated;
+use crate::previous;
+use crate::PROTOCOLS;
 use async_trait::async_trait;
 use model::ContractSymbol;
 use model::Position;
+use nonempty::NonEmpty;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::time::Duration;
@@ -30,6 +34,10 @@ impl Actor {
         }
     }
 
+    /// Send offers to a taker over the latest version of the offer protocol it supports.
+    ///
+    /// Takers on the latest version receive the given `offers`, takers on older versions only the
+    /// offers they know how to handle.
     #[tracing::instrument(name = "Broadcast offers to taker", skip(self, offers, ctx))]
     async fn send_offers(
         &self,
@@ -38,14 +46,28 @@ impl Actor {
         ctx: &mut xtra::Context<Self>,
     ) {
         let endpoint = self.endpoint.clone();
+        let latest_offers = self.current_offers.to_vec();
 
         let task = async move {
-            let stream = endpoint
-                .send(OpenSubstream::single_protocol(peer_id, PROTOCOL))
+            let (negotiated, stream) = endpoint
+                .send(OpenSubstream::versioned(peer_id, PROTOCOLS))
                 .await??
                 .await?;
 
-            protocol::send(stream, offers.into()).await?;
+            match negotiated {
+                current::PROTOCOL => protocol::send(stream, offers.into()).await?,
+                previous::PROTOCOL => {
+                    previous::protocol::send(stream, previous::protocol::Offers::new(offers))
+                        .await?
+                }
+                deprecated::PROTOCOL => {
+                    let offers = NonEmpty::from_vec(latest_offers)
+                        .and_then(deprecated::protocol::MakerOffers::new);
+
+                    deprecated::protocol::send(stream, offers).await?
+                }
+                _ => anyhow::bail!("Unsupported offer protocol {negotiated}"),
+            }
 
             anyhow::Ok(())
         };
@@ -8,12 +8,14 @@ use futures::SinkExt;
 use futures::StreamExt;
 use model::olivia::BitMexPriceEventId;
 use model::ContractSymbol;
+use model::ContractType;
 use model::Contracts;
 use model::FundingRate;
 use model::Leverage;
 use model::LotSize;
 use model::OfferId;
 use model::OpeningFee;
+use model::PayoutGranularity;
 use model::Position;
 use model::Price;
 use model::Timestamp;
@@ -61,6 +63,8 @@ pub(crate) struct Offer {
     leverage_choices: Vec<Leverage>,
     creation_timestamp_maker: Timestamp,
     settlement_interval: Duration,
+    contract_type: ContractType,
+    granularity: PayoutGranularity,
     oracle_event_id: BitMexPriceEventId,
     tx_fee_rate: TxFeeRate,
     funding_rate: FundingRate,
@@ -80,6 +84,8 @@ impl From<model::Offer> for Offer {
             leverage_choices: offer.leverage_choices,
             creation_timestamp_maker: offer.creation_timestamp_maker,
             settlement_interval: offer.settlement_interval,
+            contract_type: offer.contract_type,
+            granularity: offer.granularity,
             oracle_event_id: offer.oracle_event_id,
             tx_fee_rate: offer.tx_fee_rate,
             funding_rate: offer.funding_rate,
@@ -101,6 +107,8 @@ impl From<Offer> for model::Offer {
             leverage_choices: offer.leverage_choices,
             creation_timestamp_maker: offer.creation_timestamp_maker,
             settlement_interval: offer.settlement_interval,
+            contract_type: offer.contract_type,
+            granularity: offer.granularity,
             oracle_event_id: offer.oracle_event_id,
             tx_fee_rate: offer.tx_fee_rate,
             funding_rate: offer.funding_rate,
@@ -142,7 +150,7 @@ pub(crate) enum ReceiveError {
 
 static MESSAGES_SENT: conquer_once::Lazy<prometheus::IntCounter> = conquer_once::Lazy::new(|| {
     prometheus::register_int_counter!(
-        "offer_messages_sent_total_v2",
+        "offer_messages_sent_total_v3",
         "The number of offer messages sent over the libp2p connection.",
     )
     .unwrap()
@@ -1,4 +1,3 @@
-pub mod maker;
 pub mod protocol;
 
 pub const PROTOCOL: &str = "/itchysats/offer/1.0.0";
@@ -5,16 +5,19 @@ use futures::AsyncWriteExt;
 use futures::SinkExt;
 use model::olivia::BitMexPriceEventId;
 use model::ContractSymbol;
+use model::ContractType;
 use model::Contracts;
 use model::FundingRate;
 use model::Leverage;
 use model::OfferId;
 use model::OpeningFee;
 use model::Origin;
+use model::PayoutGranularity;
 use model::Position;
 use model::Price;
 use model::Timestamp;
 use model::TxFeeRate;
+use model::DEFAULT_SETTLEMENT_INTERVAL;
 use nonempty::NonEmpty;
 use serde::Deserialize;
 use serde::Serialize;
@@ -91,17 +94,23 @@ impl MakerOffers {
         // field is redundant across offers
         let tx_fee_rate = offers.first().tx_fee_rate;
 
-        // This version of the protocol caters to takers that only support BTCUSD CFDs
-        let mut offers = offers
-            .iter()
-            .filter(|offer| offer.contract_symbol == ContractSymbol::BtcUsd);
+        // This version of the protocol caters to takers that only support perpetual BTCUSD CFDs
+        // with the default settlement interval and granularity
+        let find = |position| {
+            offers
+                .iter()
+                .find(|offer| {
+                    offer.contract_symbol == ContractSymbol::BtcUsd
+                        && offer.contract_type == ContractType::Perpetual
+                        && offer.settlement_interval == DEFAULT_SETTLEMENT_INTERVAL
+                        && offer.granularity == PayoutGranularity::default()
+                        && offer.position_maker == position
+                })
+                .map(|offer| Offer::from(offer.clone()))
+        };
 
-        let long = offers.find_map(|offer| {
-            (offer.position_maker == Position::Long).then(|| Offer::from(offer.clone()))
-        })?;
-        let short = offers.find_map(|offer| {
-            (offer.position_maker == Position::Short).then(|| Offer::from(offer.clone()))
-        })?;
+        let long = find(Position::Long)?;
+        let short = find(Position::Short)?;
 
         let funding_rate_long = long.funding_rate;
         let funding_rate_short = short.funding_rate;
@@ -123,3 +132,24 @@ static MESSAGES_SENT: conquer_once::Lazy<prometheus::IntCounter> = conquer_once:
     )
     .unwrap()
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::dummy_offers;
+
+    #[test]
+    fn offers_are_sent_regardless_of_order() {
+        let mut offers = dummy_offers();
+        for offer in offers.iter_mut() {
+            offer.settlement_interval = DEFAULT_SETTLEMENT_INTERVAL;
+        }
+        offers.reverse();
+        assert_eq!(offers[0].position_maker, Position::Short);
+
+        let sent = MakerOffers::new(NonEmpty::from_vec(offers.clone()).unwrap()).unwrap();
+
+        assert_eq!(sent.long, Some(Offer::from(offers[1].clone())));
+        assert_eq!(sent.short, Some(Offer::from(offers[0].clone())));
+    }
+}
@@ -1,8 +1,17 @@
+use xtra_libp2p::VersionedProtocol;
+
 mod current;
 pub mod deprecated;
+pub mod previous;
 
 pub use current::*;
 
+/// All supported versions of the offer protocol, ordered from latest to oldest.
+pub const PROTOCOLS: VersionedProtocol = VersionedProtocol::new(
+    "/itchysats/offer",
+    &[current::PROTOCOL, previous::PROTOCOL, deprecated::PROTOCOL],
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -11,10 +20,12 @@ mod tests {
     use futures::Future;
     use model::olivia::BitMexPriceEventId;
     use model::ContractSymbol;
+    use model::ContractType;
     use model::Contracts;
     use model::FundingRate;
     use model::Leverage;
     use model::LotSize;
+    use model::PayoutGranularity;
     use model::Position;
     use model::Price;
     use model::Timestamp;
@@ -268,6 +279,8 @@ mod tests {
             leverage_choices: vec![Leverage::TWO],
             creation_timestamp_maker: Timestamp::now(),
             settlement_interval: time::Duration::hours(24),
+            contract_type: ContractType::Perpetual,
+            granularity: PayoutGranularity::default(),
             oracle_event_id: BitMexPriceEventId::with_20_digits(
                 datetime!(2021-10-04 22:00:00).assume_utc(),
                 contract_symbol,
@@ -0,0 +1,3 @@
+pub mod protocol;
+
+pub const PROTOCOL: &str = "/itchysats/offer/2.0.0";
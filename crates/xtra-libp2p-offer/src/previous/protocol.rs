@@ -0,0 +1,132 @@
+use asynchronous_codec::FramedWrite;
+use asynchronous_codec::JsonCodec;
+use asynchronous_codec::JsonCodecError;
+use futures::AsyncWriteExt;
+use futures::SinkExt;
+use model::olivia::BitMexPriceEventId;
+use model::ContractSymbol;
+use model::ContractType;
+use model::Contracts;
+use model::FundingRate;
+use model::Leverage;
+use model::LotSize;
+use model::OfferId;
+use model::OpeningFee;
+use model::PayoutGranularity;
+use model::Position;
+use model::Price;
+use model::Timestamp;
+use model::TxFeeRate;
+use serde::Deserialize;
+use serde::Serialize;
+use time::Duration;
+
+pub(crate) async fn send<S>(sink: S, offers: Offers) -> Result<(), JsonCodecError>
+where
+    S: AsyncWriteExt + Unpin,
+{
+    let mut framed = FramedWrite::new(sink, JsonCodec::<Offers, ()>::new());
+    framed.send(offers).await?;
+    MESSAGES_SENT.inc();
+
+    Ok(())
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub(crate) struct Offers(Vec<Offer>);
+
+impl Offers {
+    pub(crate) fn new(offers: Vec<model::Offer>) -> Self {
+        // This version of the protocol caters to takers that only support perpetual CFDs with the
+        // default granularity
+        let offers = offers
+            .into_iter()
+            .filter(|offer| {
+                offer.contract_type == ContractType::Perpetual
+                    && offer.granularity == PayoutGranularity::default()
+            })
+            .map(Offer::from)
+            .collect();
+
+        Self(offers)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct Offer {
+    id: OfferId,
+    contract_symbol: ContractSymbol,
+    position_maker: Position,
+    price: Price,
+    min_quantity: Contracts,
+    max_quantity: Contracts,
+    leverage_choices: Vec<Leverage>,
+    creation_timestamp_maker: Timestamp,
+    settlement_interval: Duration,
+    oracle_event_id: BitMexPriceEventId,
+    tx_fee_rate: TxFeeRate,
+    funding_rate: FundingRate,
+    opening_fee: OpeningFee,
+    lot_size: LotSize,
+}
+
+impl From<model::Offer> for Offer {
+    fn from(offer: model::Offer) -> Self {
+        Self {
+            id: offer.id,
+            contract_symbol: offer.contract_symbol,
+            position_maker: offer.position_maker,
+            price: offer.price,
+            min_quantity: offer.min_quantity,
+            max_quantity: offer.max_quantity,
+            leverage_choices: offer.leverage_choices,
+            creation_timestamp_maker: offer.creation_timestamp_maker,
+            settlement_interval: offer.settlement_interval,
+            oracle_event_id: offer.oracle_event_id,
+            tx_fee_rate: offer.tx_fee_rate,
+            funding_rate: offer.funding_rate,
+            opening_fee: offer.opening_fee,
+            lot_size: offer.lot_size,
+        }
+    }
+}
+
+static MESSAGES_SENT: conquer_once::Lazy<prometheus::IntCounter> = conquer_once::Lazy::new(|| {
+    prometheus::register_int_counter!(
+        "offer_messages_sent_total_v2",
+        "The number of offer messages sent over the libp2p connection.",
+    )
+    .unwrap()
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::dummy_offers;
+    use time::macros::datetime;
+
+    #[test]
+    fn dated_offers_are_not_sent() {
+        let mut offers = dummy_offers();
+        offers[1].contract_type = ContractType::Dated {
+            expiry: datetime!(2021-10-08 20:00:00).assume_utc(),
+        };
+
+        let Offers(sent) = Offers::new(offers.clone());
+
+        assert_eq!(sent, vec![Offer::from(offers[0].clone())]);
+    }
+
+    #[test]
+    fn offers_with_non_default_granularity_are_not_sent() {
+        let mut offers = dummy_offers();
+        offers[1].granularity = PayoutGranularity {
+            n_payouts: 50,
+            ..PayoutGranularity::default()
+        };
+
+        let Offers(sent) = Offers::new(offers.clone());
+
+        assert_eq!(sent, vec![Offer::from(offers[0].clone())]);
+    }
+}
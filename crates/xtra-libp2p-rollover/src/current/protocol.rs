@@ -319,7 +319,6 @@ pub(crate) async fn build_own_cfd_transactions(
     announcements: Vec<olivia::Announcement>,
     oracle_pk: XOnlyPublicKey,
     our_position: Position,
-    n_payouts: usize,
     complete_fee: model::CompleteFee,
     punish_params: PunishParams,
     role: Role,
@@ -339,7 +338,7 @@ pub(crate) async fn build_own_cfd_transactions(
                 rollover_params.long_leverage,
                 rollover_params.short_leverage,
             ),
-            n_payouts,
+            rollover_params.n_payouts,
             complete_fee,
         )?,
         ContractSymbol::EthUsd => Payouts::new_quanto(
@@ -350,7 +349,7 @@ pub(crate) async fn build_own_cfd_transactions(
                 rollover_params.long_leverage,
                 rollover_params.short_leverage,
             ),
-            n_payouts,
+            rollover_params.n_payouts,
             ETHUSD_MULTIPLIER,
             complete_fee,
         )?,
@@ -1,5 +1,6 @@
 use crate::current;
 use crate::current::protocol::*;
+use crate::deprecated;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
@@ -34,7 +35,6 @@ pub struct Actor<E, O> {
     endpoint: Address<Endpoint>,
     oracle_pk: XOnlyPublicKey,
     oracle: O,
-    n_payouts: usize,
     executor: E,
 }
 
@@ -63,33 +63,28 @@ impl<E, O> Actor<E, O> {
         executor: E,
         oracle_pk: XOnlyPublicKey,
         get_announcement: O,
-        n_payouts: usize,
     ) -> Self {
         Self {
             endpoint,
             executor,
             oracle: get_announcement,
             oracle_pk,
-            n_payouts,
         }
     }
 }
 
 impl<E, O> Actor<E, O> {
-    async fn open_substream(&self, peer_id: PeerId) -> Result<Substream> {
-        let substream = self
+    async fn open_substream(&self, peer_id: PeerId) -> Result<(&'static str, Substream)> {
+        let (protocol, substream) = self
             .endpoint
-            .send(OpenSubstream::single_protocol(
-                peer_id.inner(),
-                current::PROTOCOL,
-            ))
+            .send(OpenSubstream::versioned(peer_id.inner(), crate::PROTOCOLS))
             .await
             .context("Endpoint is disconnected")?
             .context("No connection to peer")?
             .await
             .context("Failed to open substream")?;
 
-        Ok(substream)
+        Ok((protocol, substream))
     }
 }
 
@@ -97,7 +92,7 @@ impl<E, O> Actor<E, O> {
 impl<E, O> Actor<E, O>
 where
     E: ExecuteOnCfd + Clone + Send + Sync + 'static,
-    O: GetAnnouncements + Clone + Send + Sync + 'static,
+    O: GetAnnouncements + deprecated::protocol::GetAnnouncements + Clone + Send + Sync + 'static,
 {
     pub async fn handle(&mut self, msg: ProposeRollover, ctx: &mut xtra::Context<Self>) {
         let ProposeRollover {
@@ -107,12 +102,12 @@ where
             from_settlement_event_id,
         } = msg;
 
-        let substream = match self
+        let (protocol, substream) = match self
             .open_substream(maker_peer_id)
             .await
             .context("Failed to start rollover")
         {
-            Ok(substream) => substream,
+            Ok(negotiated) => negotiated,
             Err(e) => {
                 emit_failed(order_id, e, &self.executor).await;
                 return;
@@ -125,236 +120,34 @@ where
                 let executor = self.executor.clone();
                 let oracle = self.oracle.clone();
                 let oracle_pk = self.oracle_pk;
-                let n_payouts = self.n_payouts;
                 async move {
-                    let mut framed = asynchronous_codec::Framed::new(
-                        substream,
-                        asynchronous_codec::JsonCodec::<DialerMessage, ListenerMessage>::new(),
-                    );
-
-                    let contract_symbol = executor
-                        .execute(order_id, |cfd| {
-                            let event = cfd.start_rollover_taker()?;
-                            let contract_symbol = cfd.contract_symbol();
-
-                            Ok((event, contract_symbol))
-                        })
-                        .await?;
-
-                    framed
-                        .send(DialerMessage::Propose(Propose {
-                            order_id,
-                            timestamp: Timestamp::now(),
-                            from_commit_txid,
-                        }))
-                        .await
-                        .context("Failed to send Msg0")?;
-
-                    match framed
-                        .next()
-                        .timeout(DECISION_TIMEOUT, || {
-                            tracing::debug_span!("receive decision")
-                        })
-                        .await
-                        .with_context(|| {
-                            format!(
-                                "Maker did not accept/reject within {} seconds.",
-                                DECISION_TIMEOUT.as_secs()
-                            )
-                        })?
-                        .context("End of stream while receiving rollover decision from maker")?
-                        .context("Failed to decode rollover decision from maker")?
-                        .into_decision()?
-                    {
-                        Decision::Confirm(Confirm {
-                            order_id,
-                            oracle_event_ids,
-                            tx_fee_rate,
-                            funding_rate,
-                            complete_fee,
-                        }) => {
-                            let (rollover_params, dlc, position) = executor
-                                .execute(order_id, |cfd| {
-                                    cfd.handle_rollover_accepted_taker(
-                                        tx_fee_rate,
-                                        funding_rate,
-                                        &oracle_event_ids,
-                                        from_settlement_event_id,
-                                    )
-                                })
-                                .await?;
-
-                            let announcements = oracle
-                                .get_announcements(oracle_event_ids)
-                                .await
-                                .context("Failed to get announcement")?;
-                            let settlement_event_id =
-                                announcements.last().context("Empty to_event_ids")?.id;
-
-                            tracing::info!(%order_id, "Rollover proposal got accepted");
-
-                            let funding_fee = *rollover_params.funding_fee();
-                            let complete_fee_before_rollover =
-                                rollover_params.complete_fee_before_rollover();
-                            let our_role = Role::Taker;
-                            let our_position = position;
-
-                            let (rev_sk, rev_pk) = keypair::new(&mut rand::thread_rng());
-                            let (publish_sk, publish_pk) = keypair::new(&mut rand::thread_rng());
-
-                            framed
-                                .send(DialerMessage::RolloverMsg(Box::new(RolloverMsg::Msg0(
-                                    RolloverMsg0 {
-                                        revocation_pk: rev_pk,
-                                        publish_pk,
-                                    },
-                                ))))
-                                .await
-                                .context("Failed to send Msg0")?;
-
-                            fn next_rollover_span() -> tracing::Span {
-                                tracing::debug_span!("next rollover message")
-                            }
-
-                            let msg0 = framed
-                                .next()
-                                .timeout(ROLLOVER_MSG_TIMEOUT, next_rollover_span)
-                                .await
-                                .with_context(|| {
-                                    format!(
-                                        "Expected Msg0 within {} seconds",
-                                        ROLLOVER_MSG_TIMEOUT.as_secs()
-                                    )
-                                })?
-                                .context("Empty stream instead of Msg0")?
-                                .context("Unable to decode listener Msg0")?
-                                .into_rollover_msg()?
-                                .try_into_msg0()?;
-
-                            let punish_params = PunishParams::new(
-                                msg0.revocation_pk,
-                                rev_pk,
-                                msg0.publish_pk,
-                                publish_pk,
-                            );
-
-                            let own_cfd_txs = build_own_cfd_transactions(
-                                &dlc,
-                                rollover_params,
-                                announcements.clone(),
+                    match protocol {
+                        current::PROTOCOL => {
+                            dialer(
+                                substream,
+                                executor,
+                                oracle,
                                 oracle_pk,
-                                our_position,
-                                n_payouts,
-                                complete_fee.into(),
-                                punish_params,
-                                Role::Taker,
-                                contract_symbol,
+                                order_id,
+                                from_commit_txid,
+                                from_settlement_event_id,
                             )
-                            .await?;
-
-                            framed
-                                .send(DialerMessage::RolloverMsg(Box::new(RolloverMsg::Msg1(
-                                    RolloverMsg1::from(own_cfd_txs.clone()),
-                                ))))
-                                .await
-                                .context("Failed to send Msg1")?;
-
-                            let msg1 = framed
-                                .next()
-                                .timeout(ROLLOVER_MSG_TIMEOUT, next_rollover_span)
-                                .await
-                                .with_context(|| {
-                                    format!(
-                                        "Expected Msg1 within {} seconds",
-                                        ROLLOVER_MSG_TIMEOUT.as_secs()
-                                    )
-                                })?
-                                .context("Empty stream instead of Msg1")?
-                                .context("Unable to decode listener Msg1")?
-                                .into_rollover_msg()?
-                                .try_into_msg1()?;
-
-                            let commit_desc = build_commit_descriptor(
-                                dlc.identity_counterparty,
-                                dlc.identity_pk(),
-                                punish_params,
-                            );
-                            let (cets, refund_tx) = build_and_verify_cets_and_refund(
-                                &dlc,
+                            .await
+                        }
+                        deprecated::PROTOCOL => {
+                            deprecated::taker::dialer(
+                                substream,
+                                executor,
+                                oracle,
                                 oracle_pk,
-                                publish_pk,
-                                our_role,
-                                &own_cfd_txs,
-                                &commit_desc,
-                                &msg1,
-                            )
-                            .await?;
-
-                            // reveal revocation secrets to the counterparty
-                            framed
-                                .send(DialerMessage::RolloverMsg(Box::new(RolloverMsg::Msg2(
-                                    RolloverMsg2 {
-                                        revocation_sk: dlc.revocation,
-                                    },
-                                ))))
-                                .await
-                                .context("Failed to send Msg2")?;
-
-                            let msg2 = framed
-                                .next()
-                                .timeout(ROLLOVER_MSG_TIMEOUT, next_rollover_span)
-                                .await
-                                .with_context(|| {
-                                    format!(
-                                        "Expected Msg2 within {} seconds",
-                                        ROLLOVER_MSG_TIMEOUT.as_secs()
-                                    )
-                                })?
-                                .context("Empty stream instead of Msg2")?
-                                .context("Unable to decode listener Msg2")?
-                                .into_rollover_msg()?
-                                .try_into_msg2()?;
-
-                            let revocation_sk_theirs = msg2.revocation_sk;
-                            let revoked_commits = dlc
-                                .base_dlc_params_from_latest(complete_fee_before_rollover)
-                                .revoke_base_commit_tx(revocation_sk_theirs)
-                                .context("Maker sent invalid revocation sk")?;
-
-                            let dlc = Dlc {
-                                identity: dlc.identity,
-                                identity_counterparty: dlc.identity_counterparty,
-                                revocation: rev_sk,
-                                revocation_pk_counterparty: punish_params.maker.revocation_pk,
-                                publish: publish_sk,
-                                publish_pk_counterparty: punish_params.maker.publish_pk,
-                                maker_address: dlc.maker_address,
-                                taker_address: dlc.taker_address,
-                                lock: dlc.lock.clone(),
-                                commit: (own_cfd_txs.commit.0.clone(), msg1.commit, commit_desc),
-                                cets,
-                                refund: (refund_tx, msg1.refund),
-                                maker_lock_amount: dlc.maker_lock_amount,
-                                taker_lock_amount: dlc.taker_lock_amount,
-                                revoked_commit: revoked_commits,
-                                settlement_event_id,
-                                refund_timelock: rollover_params.refund_timelock,
-                            };
-
-                            emit_completed(
                                 order_id,
-                                dlc,
-                                funding_fee,
-                                complete_fee.into(),
-                                &executor,
+                                from_commit_txid,
+                                from_settlement_event_id,
                             )
-                            .await;
-                        }
-                        Decision::Reject(_) => {
-                            emit_rejected(order_id, &executor).await;
+                            .await
                         }
+                        _ => anyhow::bail!("Unsupported rollover protocol {protocol}"),
                     }
-                    Ok(())
                 }
             },
             {
@@ -366,3 +159,233 @@ where
         );
     }
 }
+
+/// Run the dialer side of the rollover protocol over a negotiated `substream`.
+pub(crate) async fn dialer<E, O>(
+    substream: Substream,
+    executor: E,
+    oracle: O,
+    oracle_pk: XOnlyPublicKey,
+    order_id: OrderId,
+    from_commit_txid: Txid,
+    from_settlement_event_id: BitMexPriceEventId,
+) -> Result<()>
+where
+    E: ExecuteOnCfd,
+    O: GetAnnouncements,
+{
+    let mut framed = asynchronous_codec::Framed::new(
+        substream,
+        asynchronous_codec::JsonCodec::<DialerMessage, ListenerMessage>::new(),
+    );
+
+    let contract_symbol = executor
+        .execute(order_id, |cfd| {
+            let event = cfd.start_rollover_taker()?;
+            let contract_symbol = cfd.contract_symbol();
+
+            Ok((event, contract_symbol))
+        })
+        .await?;
+
+    framed
+        .send(DialerMessage::Propose(Propose {
+            order_id,
+            timestamp: Timestamp::now(),
+            from_commit_txid,
+        }))
+        .await
+        .context("Failed to send Msg0")?;
+
+    match framed
+        .next()
+        .timeout(DECISION_TIMEOUT, || {
+            tracing::debug_span!("receive decision")
+        })
+        .await
+        .with_context(|| {
+            format!(
+                "Maker did not accept/reject within {} seconds.",
+                DECISION_TIMEOUT.as_secs()
+            )
+        })?
+        .context("End of stream while receiving rollover decision from maker")?
+        .context("Failed to decode rollover decision from maker")?
+        .into_decision()?
+    {
+        Decision::Confirm(Confirm {
+            order_id,
+            oracle_event_ids,
+            tx_fee_rate,
+            funding_rate,
+            complete_fee,
+        }) => {
+            let (rollover_params, dlc, position) = executor
+                .execute(order_id, |cfd| {
+                    cfd.handle_rollover_accepted_taker(
+                        tx_fee_rate,
+                        funding_rate,
+                        &oracle_event_ids,
+                        from_settlement_event_id,
+                    )
+                })
+                .await?;
+
+            let announcements = oracle
+                .get_announcements(oracle_event_ids)
+                .await
+                .context("Failed to get announcement")?;
+            let settlement_event_id = announcements.last().context("Empty to_event_ids")?.id;
+
+            tracing::info!(%order_id, "Rollover proposal got accepted");
+
+            let funding_fee = *rollover_params.funding_fee();
+            let complete_fee_before_rollover = rollover_params.complete_fee_before_rollover();
+            let our_role = Role::Taker;
+            let our_position = position;
+
+            let (rev_sk, rev_pk) = keypair::new(&mut rand::thread_rng());
+            let (publish_sk, publish_pk) = keypair::new(&mut rand::thread_rng());
+
+            framed
+                .send(DialerMessage::RolloverMsg(Box::new(RolloverMsg::Msg0(
+                    RolloverMsg0 {
+                        revocation_pk: rev_pk,
+                        publish_pk,
+                    },
+                ))))
+                .await
+                .context("Failed to send Msg0")?;
+
+            fn next_rollover_span() -> tracing::Span {
+                tracing::debug_span!("next rollover message")
+            }
+
+            let msg0 = framed
+                .next()
+                .timeout(ROLLOVER_MSG_TIMEOUT, next_rollover_span)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Expected Msg0 within {} seconds",
+                        ROLLOVER_MSG_TIMEOUT.as_secs()
+                    )
+                })?
+                .context("Empty stream instead of Msg0")?
+                .context("Unable to decode listener Msg0")?
+                .into_rollover_msg()?
+                .try_into_msg0()?;
+
+            let punish_params =
+                PunishParams::new(msg0.revocation_pk, rev_pk, msg0.publish_pk, publish_pk);
+
+            let own_cfd_txs = build_own_cfd_transactions(
+                &dlc,
+                rollover_params,
+                announcements.clone(),
+                oracle_pk,
+                our_position,
+                complete_fee.into(),
+                punish_params,
+                Role::Taker,
+                contract_symbol,
+            )
+            .await?;
+
+            framed
+                .send(DialerMessage::RolloverMsg(Box::new(RolloverMsg::Msg1(
+                    RolloverMsg1::from(own_cfd_txs.clone()),
+                ))))
+                .await
+                .context("Failed to send Msg1")?;
+
+            let msg1 = framed
+                .next()
+                .timeout(ROLLOVER_MSG_TIMEOUT, next_rollover_span)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Expected Msg1 within {} seconds",
+                        ROLLOVER_MSG_TIMEOUT.as_secs()
+                    )
+                })?
+                .context("Empty stream instead of Msg1")?
+                .context("Unable to decode listener Msg1")?
+                .into_rollover_msg()?
+                .try_into_msg1()?;
+
+            let commit_desc = build_commit_descriptor(
+                dlc.identity_counterparty,
+                dlc.identity_pk(),
+                punish_params,
+            );
+            let (cets, refund_tx) = build_and_verify_cets_and_refund(
+                &dlc,
+                oracle_pk,
+                publish_pk,
+                our_role,
+                &own_cfd_txs,
+                &commit_desc,
+                &msg1,
+            )
+            .await?;
+
+            // reveal revocation secrets to the counterparty
+            framed
+                .send(DialerMessage::RolloverMsg(Box::new(RolloverMsg::Msg2(
+                    RolloverMsg2 {
+                        revocation_sk: dlc.revocation,
+                    },
+                ))))
+                .await
+                .context("Failed to send Msg2")?;
+
+            let msg2 = framed
+                .next()
+                .timeout(ROLLOVER_MSG_TIMEOUT, next_rollover_span)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Expected Msg2 within {} seconds",
+                        ROLLOVER_MSG_TIMEOUT.as_secs()
+                    )
+                })?
+                .context("Empty stream instead of Msg2")?
+                .context("Unable to decode listener Msg2")?
+                .into_rollover_msg()?
+                .try_into_msg2()?;
+
+            let revocation_sk_theirs = msg2.revocation_sk;
+            let revoked_commits = dlc
+                .base_dlc_params_from_latest(complete_fee_before_rollover)
+                .revoke_base_commit_tx(revocation_sk_theirs)
+                .context("Maker sent invalid revocation sk")?;
+
+            let dlc = Dlc {
+                identity: dlc.identity,
+                identity_counterparty: dlc.identity_counterparty,
+                revocation: rev_sk,
+                revocation_pk_counterparty: punish_params.maker.revocation_pk,
+                publish: publish_sk,
+                publish_pk_counterparty: punish_params.maker.publish_pk,
+                maker_address: dlc.maker_address,
+                taker_address: dlc.taker_address,
+                lock: dlc.lock.clone(),
+                commit: (own_cfd_txs.commit.0.clone(), msg1.commit, commit_desc),
+                cets,
+                refund: (refund_tx, msg1.refund),
+                maker_lock_amount: dlc.maker_lock_amount,
+                taker_lock_amount: dlc.taker_lock_amount,
+                revoked_commit: revoked_commits,
+                settlement_event_id,
+                refund_timelock: rollover_params.refund_timelock,
+            };
+
+            emit_completed(order_id, dlc, funding_fee, complete_fee.into(), &executor).await;
+        }
+        Decision::Reject(_) => {
+            emit_rejected(order_id, &executor).await;
+        }
+    }
+    Ok(())
+}
@@ -25,24 +25,16 @@ use xtra_productivity::xtra_productivity;
 pub struct Actor<E, O, R> {
     oracle_pk: XOnlyPublicKey,
     oracle: O,
-    n_payouts: usize,
     executor: E,
     rates: R,
     is_accepting_rollovers: bool,
 }
 
 impl<E, O, R> Actor<E, O, R> {
-    pub fn new(
-        executor: E,
-        oracle_pk: XOnlyPublicKey,
-        oracle: O,
-        rates: R,
-        n_payouts: usize,
-    ) -> Self {
+    pub fn new(executor: E, oracle_pk: XOnlyPublicKey, oracle: O, rates: R) -> Self {
         Self {
             oracle_pk,
             oracle,
-            n_payouts,
             executor,
             rates,
             is_accepting_rollovers: true,
@@ -177,7 +169,6 @@ where
             let oracle = self.oracle.clone();
             let rates = self.rates.clone();
             let oracle_pk = self.oracle_pk;
-            let n_payouts = self.n_payouts;
             async move {
                 let Rates {
                     funding_rate_long,
@@ -273,7 +264,6 @@ where
                     announcements.clone(),
                     oracle_pk,
                     our_position,
-                    n_payouts,
                     complete_fee,
                     punish_params,
                     Role::Maker,
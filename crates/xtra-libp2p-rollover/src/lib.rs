@@ -1,4 +1,12 @@
+use xtra_libp2p::VersionedProtocol;
+
 mod current;
 pub mod deprecated;
 
 pub use current::*;
+
+/// All supported versions of the rollover protocol, ordered from latest to oldest.
+pub const PROTOCOLS: VersionedProtocol = VersionedProtocol::new(
+    "/itchysats/rollover",
+    &[current::PROTOCOL, deprecated::PROTOCOL],
+);
@@ -2,6 +2,7 @@ use crate::multiaddress_ext::MultiaddrExt as _;
 use crate::upgrade;
 use crate::Connection;
 use crate::Substream;
+use crate::VersionedProtocol;
 use anyhow::bail;
 use anyhow::Context as _;
 use anyhow::Result;
@@ -61,7 +62,7 @@ pub struct Endpoint {
     inbound_substream_channels: HashMap<&'static str, MessageChannel<NewInboundSubstream, ()>>,
     listen_addresses: HashSet<Multiaddr>,
     inflight_connections: HashSet<PeerId>,
-    blocked_peers: Arc<HashSet<PeerId>>,
+    blocked_peers: HashSet<PeerId>,
     connection_timeout: Duration,
     subscribers: Subscribers,
     peer_listen_protocols: HashMap<PeerId, HashSet<String>>,
@@ -109,6 +110,8 @@ impl OpenSubstream<Multiple> {
     /// Specifying multiple protocols can useful to maintain backwards-compatibility. An endpoint
     /// can attempt to first establish a substream with a new protocol and falling back to older
     /// versions in case the new version is not supported.
+    ///
+    /// Protocols which the peer is known not to listen for are skipped.
     pub fn multiple_protocols(peer_id: PeerId, protocols: Vec<&'static str>) -> Self {
         tracing::trace!(
             %peer_id, ?protocols, "Open substream (multi protocol) with"
@@ -119,6 +122,15 @@ impl OpenSubstream<Multiple> {
             marker_num_protocols: PhantomData,
         }
     }
+
+    /// Constructs [`OpenSubstream`] for a [`VersionedProtocol`].
+    ///
+    /// The highest version of the protocol that the peer listens for is negotiated. If we don't
+    /// know the peer's listen protocols (yet), the versions are tried in order, starting with the
+    /// latest.
+    pub fn versioned(peer_id: PeerId, protocol: VersionedProtocol) -> Self {
+        Self::multiple_protocols(peer_id, protocol.versions().to_vec())
+    }
 }
 
 /// Connect to the given [`Multiaddr`].
@@ -132,6 +144,17 @@ pub struct Connect(pub Multiaddr);
 #[derive(Clone, Copy, Debug)]
 pub struct Disconnect(pub PeerId);
 
+/// Block the given peer.
+///
+/// Any future connection from or to this peer will be rejected. If we are currently connected to
+/// the peer, the connection is dropped.
+#[derive(Clone, Copy, Debug)]
+pub struct BlockPeer(pub PeerId);
+
+/// Unblock the given peer, allowing it to connect again.
+#[derive(Clone, Copy, Debug)]
+pub struct UnblockPeer(pub PeerId);
+
 /// Listen on the provided [`Multiaddr`].
 ///
 /// For this to work, the [`Endpoint`] needs to be constructed with a compatible transport.
@@ -220,6 +243,9 @@ impl Endpoint {
     ///
     /// The provided substream handlers are actors that will be given the fully-negotiated
     /// substreams whenever a peer opens a new substream for the provided protocol.
+    ///
+    /// The `blocked_peers` are the initial set of peers we refuse connections with. The set can be
+    /// changed at runtime by sending [`BlockPeer`] and [`UnblockPeer`].
     pub fn new<T, const N: usize>(
         transport: Box<dyn Fn() -> T + Send + 'static>,
         identity: Keypair,
@@ -260,13 +286,38 @@ impl Endpoint {
             controls: HashMap::default(),
             listen_addresses: HashSet::default(),
             inflight_connections: HashSet::default(),
-            blocked_peers,
+            blocked_peers: blocked_peers.as_ref().clone(),
             connection_timeout,
             subscribers,
             peer_listen_protocols: HashMap::default(),
         }
     }
 
+    /// Filter the given protocols down to the ones the peer listens for, preserving their order.
+    ///
+    /// Without information about the peer's listen protocols, all protocols are returned.
+    fn protocols_supported_by_peer(
+        &self,
+        peer_id: PeerId,
+        protocols: Vec<&'static str>,
+    ) -> Result<Vec<&'static str>, Error> {
+        let listen_protocols = match self.peer_listen_protocols.get(&peer_id) {
+            Some(listen_protocols) => listen_protocols,
+            None => return Ok(protocols),
+        };
+
+        let supported = protocols
+            .into_iter()
+            .filter(|protocol| listen_protocols.contains(*protocol))
+            .collect::<Vec<_>>();
+
+        if supported.is_empty() {
+            return Err(Error::ProtocolNotSupportedByPeer);
+        }
+
+        Ok(supported)
+    }
+
     fn does_peer_listen_for(&self, peer_id: PeerId, protocols: &[&str]) -> Result<(), Error> {
         let listen_protocols = match self.peer_listen_protocols.get(&peer_id) {
             Some(listen_protocols) => listen_protocols,
@@ -347,6 +398,15 @@ impl Endpoint {
             worker,
         } = msg;
 
+        if self.blocked_peers.contains(&peer_id) {
+            tracing::trace!(
+                target: "blocked_peers",
+                peer_id = %peer_id, // Weird but required
+                "Blocked peer from connecting"
+            );
+            return; // Dropping the connection closes it
+        }
+
         let mut tasks = Tasks::default();
         tasks.add(worker);
         tasks.add_fallible(
@@ -490,6 +550,27 @@ impl Endpoint {
             .await;
     }
 
+    async fn handle(&mut self, msg: BlockPeer, ctx: &mut Context<Self>) {
+        let peer_id = msg.0;
+
+        if !self.blocked_peers.insert(peer_id) {
+            return;
+        }
+
+        tracing::info!(%peer_id, "Blocked peer");
+
+        self.drop_connection(&ctx.address().expect("self to be alive"), &peer_id)
+            .await;
+    }
+
+    async fn handle(&mut self, msg: UnblockPeer) {
+        let peer_id = msg.0;
+
+        if self.blocked_peers.remove(&peer_id) {
+            tracing::info!(%peer_id, "Unblocked peer");
+        }
+    }
+
     async fn handle(&mut self, msg: ListenOn, ctx: &mut Context<Self>) {
         let this = ctx.address().expect("we are alive");
         let listen_address = msg.0.clone();
@@ -499,7 +580,6 @@ impl Endpoint {
         tokio_extras::spawn_fallible::<_, _, _, (), _, _, _>(
             &this.clone(),
             {
-                let blocked_peers = self.blocked_peers.clone();
                 let this = this.clone();
                 let listen_address = listen_address.clone();
 
@@ -523,7 +603,6 @@ impl Endpoint {
                                 remote_addr,
                                 ..
                             }) => {
-                                let blocked_peers = blocked_peers.clone();
                                 let this = this.clone();
                                 tasks.add_fallible(
                                     async move {
@@ -537,15 +616,6 @@ impl Endpoint {
                                                 }
                                             })?;
 
-                                        if blocked_peers.contains(&peer_id) {
-                                            tracing::trace!(
-                                                target: "blocked_peers",
-                                                peer_id = %peer_id, // Weird but required
-                                                "Blocked peer from connecting"
-                                            );
-                                            return Ok(()); // Skip this peer
-                                        }
-
                                         this.send_async_next(NewConnection {
                                             peer_id,
                                             control,
@@ -642,10 +712,11 @@ impl Endpoint {
         Error,
     > {
         let peer = msg.peer_id;
-        let protocols = msg.protocols;
 
         let (control, _) = self.controls.get(&peer).ok_or(Error::NoConnection(peer))?;
 
+        let protocols = self.protocols_supported_by_peer(peer, msg.protocols)?;
+
         let fut = {
             let connection_timeout = self.connection_timeout;
             let control = control.clone();
@@ -1,3 +1,4 @@
+pub use crate::endpoint::BlockPeer;
 pub use crate::endpoint::Connect;
 pub use crate::endpoint::ConnectionStats;
 pub use crate::endpoint::Disconnect;
@@ -9,7 +10,9 @@ pub use crate::endpoint::Multiple;
 pub use crate::endpoint::NewInboundSubstream;
 pub use crate::endpoint::OpenSubstream;
 pub use crate::endpoint::Single;
+pub use crate::endpoint::UnblockPeer;
 pub use crate::substream::Substream;
+pub use crate::versioned_protocol::VersionedProtocol;
 pub use libp2p_core as libp2p;
 pub use multistream_select::NegotiationError;
 
@@ -25,6 +28,7 @@ pub mod multiaddress_ext;
 mod substream;
 mod upgrade;
 mod verify_peer_id;
+pub mod versioned_protocol;
 
 type Connection = (
     PeerId,
@@ -1,3 +1,4 @@
+use crate::versioned_protocol::split_version;
 use conquer_once::Lazy;
 use futures::ready;
 use futures::AsyncRead;
@@ -67,6 +68,16 @@ impl Substream {
         };
         let labels = HashMap::from([(PROTOCOL_LABEL, protocol), (ROLE_LABEL, role)]);
 
+        if let Some((name, version)) = split_version(protocol) {
+            SUBSTREAM_PROTOCOL_VERSIONS_COUNTER
+                .with(&HashMap::from([
+                    (PROTOCOL_LABEL, name),
+                    (VERSION_LABEL, version),
+                    (ROLE_LABEL, role),
+                ]))
+                .inc();
+        }
+
         Self {
             inner,
             _timer: SUBSTREAM_DURATION_HISTOGRAM.with(&labels).start_timer(),
@@ -145,6 +156,9 @@ const PROTOCOL_LABEL: &str = "protocol";
 /// The role of substream in the protocol: dialer or listener.
 const ROLE_LABEL: &str = "role";
 
+/// The version of the protocol that was negotiated for the substream.
+const VERSION_LABEL: &str = "version";
+
 static SUBSTREAM_DURATION_HISTOGRAM: Lazy<prometheus::HistogramVec> = Lazy::new(|| {
     prometheus::register_histogram_vec!(
         "substream_duration_seconds",
@@ -172,3 +186,12 @@ static SUBSTREAM_BYTES_WRITTEN_COUNTER: Lazy<prometheus::IntCounterVec> = Lazy::
     )
     .unwrap()
 });
+
+static SUBSTREAM_PROTOCOL_VERSIONS_COUNTER: Lazy<prometheus::IntCounterVec> = Lazy::new(|| {
+    prometheus::register_int_counter_vec!(
+        "substream_protocol_versions_total",
+        "The total number of substreams opened, segregated by protocol and negotiated version.",
+        &[PROTOCOL_LABEL, VERSION_LABEL, ROLE_LABEL]
+    )
+    .unwrap()
+});
@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+
+/// A protocol that is available in multiple versions.
+///
+/// Every version is a fully-qualified protocol identifier of the form `{name}/{version}`, e.g.
+/// `/itchysats/rollover/3.0.0`. Versions are ordered by preference, i.e. the first version is the
+/// latest one and all subsequent versions are deprecated.
+///
+/// Declaring a protocol like this allows a dialer to pick the highest version that both parties
+/// support (see [`OpenSubstream::versioned`](crate::OpenSubstream::versioned)). A version can be
+/// retired by removing it from the list, once the `substream_protocol_versions_total` metric
+/// shows that it is no longer in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VersionedProtocol {
+    name: &'static str,
+    versions: &'static [&'static str],
+}
+
+impl VersionedProtocol {
+    /// Declare a protocol with the given versions, ordered from latest to oldest.
+    ///
+    /// Panics if no version is given or if a version does not start with the protocol name.
+    pub const fn new(name: &'static str, versions: &'static [&'static str]) -> Self {
+        assert!(!versions.is_empty(), "At least one version is required");
+
+        let mut i = 0;
+        while i < versions.len() {
+            assert!(
+                is_version_of(name, versions[i]),
+                "Version must be prefixed with the protocol name"
+            );
+            i += 1;
+        }
+
+        Self { name, versions }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The latest version of the protocol.
+    pub const fn latest(&self) -> &'static str {
+        self.versions[0]
+    }
+
+    /// All versions of the protocol, ordered from latest to oldest.
+    pub const fn versions(&self) -> &'static [&'static str] {
+        self.versions
+    }
+
+    /// All versions of the protocol except for the latest one, ordered from latest to oldest.
+    pub fn deprecated(&self) -> &'static [&'static str] {
+        let versions = self.versions;
+        &versions[1..]
+    }
+
+    pub fn is_deprecated(&self, version: &str) -> bool {
+        self.deprecated().contains(&version)
+    }
+
+    /// Select the highest version that is contained in the given listen protocols of a peer.
+    pub fn select(&self, listen_protocols: &HashSet<String>) -> Option<&'static str> {
+        self.versions
+            .iter()
+            .copied()
+            .find(|version| listen_protocols.contains(*version))
+    }
+}
+
+/// Split a protocol identifier into its name and version.
+///
+/// By convention, the version is the last segment of the identifier, e.g. `3.0.0` for
+/// `/itchysats/rollover/3.0.0`. Returns `None` if the last segment does not look like a version.
+pub fn split_version(protocol: &str) -> Option<(&str, &str)> {
+    let (name, version) = protocol.rsplit_once('/')?;
+
+    let is_version = !name.is_empty()
+        && !version.is_empty()
+        && version.chars().all(|c| c.is_ascii_digit() || c == '.');
+
+    is_version.then(|| (name, version))
+}
+
+const fn is_version_of(name: &str, version: &str) -> bool {
+    let name = name.as_bytes();
+    let version = version.as_bytes();
+
+    if version.len() <= name.len() + 1 {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < name.len() {
+        if name[i] != version[i] {
+            return false;
+        }
+        i += 1;
+    }
+
+    version[name.len()] == b'/'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROTOCOL: VersionedProtocol = VersionedProtocol::new(
+        "/itchysats/rollover",
+        &[
+            "/itchysats/rollover/3.0.0",
+            "/itchysats/rollover/2.0.0",
+            "/itchysats/rollover/1.0.0",
+        ],
+    );
+
+    #[test]
+    fn given_peer_supports_several_versions_then_select_latest_common() {
+        let listen_protocols = HashSet::from([
+            "/itchysats/rollover/1.0.0".to_string(),
+            "/itchysats/rollover/2.0.0".to_string(),
+            "/itchysats/offer/2.0.0".to_string(),
+        ]);
+
+        let selected = PROTOCOL.select(&listen_protocols);
+
+        assert_eq!(selected, Some("/itchysats/rollover/2.0.0"));
+    }
+
+    #[test]
+    fn given_peer_supports_no_version_then_select_none() {
+        let listen_protocols = HashSet::from(["/itchysats/offer/2.0.0".to_string()]);
+
+        let selected = PROTOCOL.select(&listen_protocols);
+
+        assert_eq!(selected, None);
+    }
+
+    #[test]
+    fn all_but_latest_version_are_deprecated() {
+        assert_eq!(PROTOCOL.latest(), "/itchysats/rollover/3.0.0");
+        assert_eq!(
+            PROTOCOL.deprecated(),
+            &["/itchysats/rollover/2.0.0", "/itchysats/rollover/1.0.0"]
+        );
+        assert!(!PROTOCOL.is_deprecated("/itchysats/rollover/3.0.0"));
+        assert!(PROTOCOL.is_deprecated("/itchysats/rollover/1.0.0"));
+    }
+
+    #[test]
+    fn split_version_of_protocol_identifier() {
+        assert_eq!(
+            split_version("/itchysats/rollover/3.0.0"),
+            Some(("/itchysats/rollover", "3.0.0"))
+        );
+        assert_eq!(
+            split_version("/ipfs/ping/1.0.0"),
+            Some(("/ipfs/ping", "1.0.0"))
+        );
+        assert_eq!(split_version("/itchysats/rollover"), None);
+        assert_eq!(split_version("no-version"), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn version_must_be_prefixed_with_name() {
+        VersionedProtocol::new("/itchysats/rollover", &["/itchysats/offer/1.0.0"]);
+    }
+}
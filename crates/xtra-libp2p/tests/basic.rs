@@ -20,12 +20,14 @@ use xtra::Context;
 use xtra_libp2p::endpoint;
 use xtra_libp2p::endpoint::RegisterListenProtocols;
 use xtra_libp2p::libp2p::PeerId;
+use xtra_libp2p::BlockPeer;
 use xtra_libp2p::Connect;
 use xtra_libp2p::Disconnect;
 use xtra_libp2p::GetConnectionStats;
 use xtra_libp2p::ListenOn;
 use xtra_libp2p::NewInboundSubstream;
 use xtra_libp2p::OpenSubstream;
+use xtra_libp2p::UnblockPeer;
 use xtra_productivity::xtra_productivity;
 
 mod util;
@@ -108,6 +110,48 @@ async fn blocked_peers_cannot_connect() {
     assert!(bob_to_alice.is_err());
 }
 
+#[tokio::test]
+async fn blocking_connected_peer_drops_connection() {
+    let (alice, bob, _) = alice_and_bob([], []).await;
+
+    alice.endpoint.send(BlockPeer(bob.peer_id)).await.unwrap();
+
+    let alice_stats = alice.endpoint.send(GetConnectionStats).await.unwrap();
+
+    assert_eq!(alice_stats.connected_peers, HashSet::from([]));
+}
+
+#[tokio::test]
+async fn unblocked_peer_can_connect() {
+    let bob = make_node([]);
+    let alice = make_node_with_blocklist([], Arc::new(HashSet::from([bob.peer_id])));
+
+    alice.endpoint.send(UnblockPeer(bob.peer_id)).await.unwrap();
+
+    let port = rand::random::<u16>();
+    let alice_listen = format!("/memory/{port}").parse::<Multiaddr>().unwrap();
+    alice
+        .endpoint
+        .send(ListenOn(alice_listen.clone()))
+        .await
+        .unwrap();
+
+    let alice_peer_id = &alice.peer_id;
+    bob.endpoint
+        .send(Connect(
+            format!("/memory/{port}/p2p/{alice_peer_id}")
+                .parse()
+                .unwrap(),
+        ))
+        .await
+        .unwrap()
+        .unwrap();
+
+    let alice_stats = alice.endpoint.send(GetConnectionStats).await.unwrap();
+
+    assert_eq!(alice_stats.connected_peers, HashSet::from([bob.peer_id]));
+}
+
 #[tokio::test]
 async fn after_connect_see_each_other_as_connected() {
     let (alice, bob, _) = alice_and_bob([], []).await;
@@ -27,9 +27,9 @@ import { CfdTable } from "./components/cfdtables/CfdTable";
 import CurrencyInputField from "./components/CurrencyInputField";
 import CurrentPrice from "./components/CurrentPrice";
 import createErrorToast from "./components/ErrorToast";
-import useLatestEvent from "./components/Hooks";
+import useLatestEvent, { useCfds } from "./components/Hooks";
 import OrderTile from "./components/OrderTile";
-import { Cfd, intoCfd, MakerOffer, PriceInfo, StateGroupKey, WalletInfo } from "./components/Types";
+import { MakerOffer, PriceInfo, StateGroupKey, WalletInfo } from "./components/Types";
 import Wallet from "./components/Wallet";
 import { CfdNewOfferParamsPayload, putCfdNewOfferParamsRequest, triggerWalletSync } from "./MakerClient";
 
@@ -75,7 +75,7 @@ export default function App() {
 
     let [leverages, setLeverages] = useState(["1", "2", "3"]);
 
-    const cfdsOrUndefined = useLatestEvent<Cfd[]>(source, "cfds", intoCfd);
+    const cfdsOrUndefined = useCfds(source);
     let cfds = cfdsOrUndefined ? cfdsOrUndefined! : [];
 
     const btcUsdLongOffer = useLatestEvent<MakerOffer>(
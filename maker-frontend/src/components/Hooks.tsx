@@ -1,5 +1,6 @@
 import { useState } from "react";
 import { useEventSourceListener } from "react-sse-hooks";
+import { Cfd, intoCfd } from "./Types";
 
 export default function useLatestEvent<T,>(
     source: EventSource,
@@ -26,3 +27,70 @@ export default function useLatestEvent<T,>(
 
     return state;
 }
+
+/**
+ * Keeps track of the CFDs of the feed.
+ *
+ * The feed sends all CFDs in a `cfds` event once and afterwards only the CFDs that changed (`cfd`)
+ * or are no longer part of the feed (`cfd_removed`).
+ */
+export function useCfds(source: EventSource): Cfd[] | null {
+    const [cfds, setCfds] = useState<Cfd[] | null>(null);
+
+    useEventSourceListener<Cfd[] | null>(
+        {
+            source: source,
+            startOnInit: true,
+            event: {
+                name: "cfds",
+                listener: ({ event }) => {
+                    // @ts-ignore - yes, there is a data field on event
+                    setCfds(JSON.parse(event.data, intoCfd));
+                },
+            },
+        },
+        [source],
+    );
+
+    useEventSourceListener<Cfd[] | null>(
+        {
+            source: source,
+            startOnInit: true,
+            event: {
+                name: "cfd",
+                listener: ({ event }) => {
+                    // @ts-ignore - yes, there is a data field on event
+                    const cfd: Cfd = JSON.parse(event.data, intoCfd);
+                    setCfds((cfds) => upsert(cfds || [], cfd));
+                },
+            },
+        },
+        [source],
+    );
+
+    useEventSourceListener<Cfd[] | null>(
+        {
+            source: source,
+            startOnInit: true,
+            event: {
+                name: "cfd_removed",
+                listener: ({ event }) => {
+                    // @ts-ignore - yes, there is a data field on event
+                    const { order_id } = JSON.parse(event.data);
+                    setCfds((cfds) => (cfds || []).filter((cfd) => cfd.order_id !== order_id));
+                },
+            },
+        },
+        [source],
+    );
+
+    return cfds;
+}
+
+// New CFDs go first, the feed is ordered by creation time, newest first
+function upsert(cfds: Cfd[], cfd: Cfd): Cfd[] {
+    if (cfds.some((existing) => existing.order_id === cfd.order_id)) {
+        return cfds.map((existing) => existing.order_id === cfd.order_id ? cfd : existing);
+    }
+    return [cfd, ...cfds];
+}
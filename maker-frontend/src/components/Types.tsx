@@ -71,7 +71,9 @@ export interface CfdDetails {
 
 export interface Tx {
     label: string;
-    url: string;
+    txid: string;
+    // null if the daemon does not link to a block explorer
+    url: string | null;
 }
 
 export class State {
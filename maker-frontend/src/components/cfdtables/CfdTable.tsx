@@ -98,8 +98,16 @@ export function CfdTable(
                 Header: "Details",
                 accessor: ({ details, expiry_timestamp, order_id }) => {
                     const txs = details.tx_url_list.map((tx) => {
+                        if (!tx.url) {
+                            return (
+                                <Text key={tx.txid} title={tx.txid}>
+                                    {tx.label + " transaction"}
+                                </Text>
+                            );
+                        }
+
                         return (
-                            <Link href={tx.url} key={tx.url} isExternal>
+                            <Link href={tx.url} key={tx.txid} isExternal>
                                 {tx.label + " transaction"}
                                 <ExternalLinkIcon mx="2px" />
                             </Link>
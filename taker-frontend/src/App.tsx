@@ -15,16 +15,15 @@ import { Wallet } from "./components/Wallet";
 import { fetchDaemonVersion, fetchGithubVersion } from "./fetchVersion";
 import {
     BXBTData,
-    Cfd,
     ConnectionStatus,
     IdentityInfo,
-    intoCfd,
     intoMakerOffer,
     LeverageDetails,
     MakerCompatibility,
     MakerOffer,
     WalletInfo,
 } from "./types";
+import useCfds from "./useCfds";
 import { useEventSource } from "./useEventSource";
 import useLatestEvent from "./useLatestEvent";
 
@@ -173,7 +172,7 @@ export const App = () => {
         return Number.parseFloat(Number(n).toFixed(digits));
     }
 
-    const cfdsOrUndefined = useLatestEvent<Cfd[]>(source, "cfds", intoCfd);
+    const cfdsOrUndefined = useCfds(source);
     let cfds = cfdsOrUndefined ? cfdsOrUndefined! : [];
     const connectedToMakerOrUndefined = useLatestEvent<ConnectionStatus>(source, "maker_status");
     const makerCompatibilityOrUndefined = useLatestEvent<MakerCompatibility>(source, "maker_compatibility");
@@ -14,7 +14,6 @@ import {
     PopoverTrigger,
     SimpleGrid,
     Skeleton,
-    Spinner,
     Table,
     Tbody,
     Td,
@@ -352,11 +351,15 @@ const TxIcon = ({ tx }: TxIconProps) => {
 
     if (!tx) {
         return <ExternalLinkIcon boxSize={5} color={color} />;
-    } else if (tx && !tx.url) {
-        return <Spinner mx="2px" color={color} speed="1.65s" />;
+    } else if (!tx.url) {
+        return (
+            <Tooltip label={tx.txid} placement="top">
+                <ExternalLinkIcon boxSize={5} color={color} />
+            </Tooltip>
+        );
     } else {
         return (
-            <Link href={tx.url!} isExternal>
+            <Link href={tx.url} isExternal>
                 <ExternalLinkIcon boxSize={5} color={color} />
             </Link>
         );
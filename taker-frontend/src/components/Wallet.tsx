@@ -35,7 +35,7 @@ import { QRCodeCanvas } from "qrcode.react";
 import * as React from "react";
 import { useState } from "react";
 import { BsArrowDownRightCircle, BsArrowUpRightCircle } from "react-icons/all";
-import { Transaction, WalletInfo, WithdrawRequest } from "../types";
+import { Transaction, WalletInfo, WithdrawRequest, WithdrawResponse } from "../types";
 import usePostRequest from "../usePostRequest";
 import Timestamp from "./Timestamp";
 
@@ -56,20 +56,27 @@ export default function Wallet(
     const [withdrawAmount, setWithdrawAmount] = useState(0);
     const [fee, setFee] = useState(1);
     const [withdrawAddress, setWithdrawAddress] = useState("");
-    const [runWithdraw, isWithdrawing] = usePostRequest<WithdrawRequest, string>("/api/withdraw", (url) => {
-        window.open(url, "_blank");
-        toast({
-            title: "Withdraw successful",
-            description: (
-                <Link href={url} isExternal>
-                    {url}
-                </Link>
-            ),
-            status: "info",
-            duration: 10000,
-            isClosable: true,
-        });
-    });
+    const [runWithdraw, isWithdrawing] = usePostRequest<WithdrawRequest, WithdrawResponse>(
+        "/api/withdraw",
+        ({ txid, url }) => {
+            if (url) {
+                window.open(url, "_blank");
+            }
+            toast({
+                title: "Withdraw successful",
+                description: url
+                    ? (
+                        <Link href={url} isExternal>
+                            {url}
+                        </Link>
+                    )
+                    : txid,
+                status: "info",
+                duration: 10000,
+                isClosable: true,
+            });
+        },
+    );
 
     let [{ status: walletSyncing }, { execute: syncWallet }] = useAsync(
         async () => {
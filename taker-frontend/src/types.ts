@@ -109,7 +109,9 @@ export interface CfdDetails {
 
 export interface Tx {
     label: TxLabel;
-    url: string;
+    txid: string;
+    // null if the daemon does not link to a block explorer
+    url: string | null;
 }
 
 export enum TxLabel {
@@ -292,6 +294,12 @@ export interface WithdrawRequest {
     fee: number;
 }
 
+export interface WithdrawResponse {
+    txid: string;
+    // null if the daemon does not link to a block explorer
+    url: string | null;
+}
+
 export interface ConnectionStatus {
     online: boolean;
 }
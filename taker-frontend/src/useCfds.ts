@@ -0,0 +1,49 @@
+import { useEffect, useState } from "react";
+import { Cfd, intoCfd } from "./types";
+import { EventSourceEvent } from "./useLatestEvent";
+
+/**
+ * Keeps track of the CFDs of the feed.
+ *
+ * The feed sends all CFDs in a `cfds` event once and afterwards only the CFDs that changed (`cfd`)
+ * or are no longer part of the feed (`cfd_removed`).
+ */
+export default function useCfds(source: EventSource | null): Cfd[] | null {
+    const [cfds, setCfds] = useState<Cfd[] | null>(null);
+
+    useEffect(() => {
+        if (source) {
+            const onSnapshot = (event: Event) => {
+                setCfds(JSON.parse((event as EventSourceEvent).data, intoCfd));
+            };
+            const onUpsert = (event: Event) => {
+                const cfd: Cfd = JSON.parse((event as EventSourceEvent).data, intoCfd);
+                setCfds((cfds) => upsert(cfds || [], cfd));
+            };
+            const onRemove = (event: Event) => {
+                const { order_id } = JSON.parse((event as EventSourceEvent).data);
+                setCfds((cfds) => (cfds || []).filter((cfd) => cfd.order_id !== order_id));
+            };
+
+            source.addEventListener("cfds", onSnapshot);
+            source.addEventListener("cfd", onUpsert);
+            source.addEventListener("cfd_removed", onRemove);
+            return () => {
+                source.removeEventListener("cfds", onSnapshot);
+                source.removeEventListener("cfd", onUpsert);
+                source.removeEventListener("cfd_removed", onRemove);
+            };
+        }
+        return undefined;
+    }, [source]);
+
+    return cfds;
+}
+
+// New CFDs go first, the feed is ordered by creation time, newest first
+function upsert(cfds: Cfd[], cfd: Cfd): Cfd[] {
+    if (cfds.some((existing) => existing.order_id === cfd.order_id)) {
+        return cfds.map((existing) => existing.order_id === cfd.order_id ? cfd : existing);
+    }
+    return [cfd, ...cfds];
+}